@@ -17,12 +17,171 @@
  * under the License.
  */
 
-pub type NodeId = usize;
+use core::fmt;
+use core::iter::Sum;
+use core::ops::{Add, AddAssign, Sub};
+
+use serde::{Deserialize, Serialize};
+
+/// Defines a wrapper around `usize` that can be converted from and to `usize` (so numeric
+/// literals can be used via `.into()`), but cannot be compared or mixed with `usize` or the other
+/// wrappers.
+macro_rules! usize_newtype {
+    ($(#[$attr:meta])* $name:ident) => {
+        $(#[$attr])*
+        #[derive(
+            Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+        )]
+        #[serde(transparent)]
+        pub struct $name(pub usize);
+
+        impl From<usize> for $name {
+            fn from(value: usize) -> Self {
+                $name(value)
+            }
+        }
+
+        impl From<$name> for usize {
+            fn from(value: $name) -> Self {
+                value.0
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Display::fmt(&self.0, f)
+            }
+        }
+
+        // formatted like a plain number, so e.g. lists of nodes stay readable
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                fmt::Debug::fmt(&self.0, f)
+            }
+        }
+    };
+}
+
+usize_newtype!(
+    /// The id of a node, i.e. a number between zero and the number of nodes of the graph. This is
+    /// a separate type (and not just `usize`) so node ids cannot be mixed up with weights by
+    /// accident, e.g. when calling `InputGraph::add_edge`.
+    NodeId
+);
+
+usize_newtype!(
+    /// The weight of an edge or path, see `WEIGHT_MAX` for infinite weights. Just like
+    /// `add_weights`, `a + b` saturates, so infinite weights stay infinite, while `a - b` is the
+    /// usual subtraction that must not underflow (see `saturating_sub`).
+    Weight
+);
+
+impl Weight {
+    /// Subtracts the given weight, but returns zero instead of underflowing
+    pub fn saturating_sub(self, other: Weight) -> Weight {
+        Weight(self.0.saturating_sub(other.0))
+    }
+}
+
+impl Add for Weight {
+    type Output = Weight;
+
+    fn add(self, other: Weight) -> Weight {
+        add_weights(self, other)
+    }
+}
+
+impl AddAssign for Weight {
+    fn add_assign(&mut self, other: Weight) {
+        *self = add_weights(*self, other);
+    }
+}
+
+impl Sub for Weight {
+    type Output = Weight;
+
+    fn sub(self, other: Weight) -> Weight {
+        Weight(self.0 - other.0)
+    }
+}
+
+impl Sum for Weight {
+    fn sum<I: Iterator<Item = Weight>>(iter: I) -> Weight {
+        iter.fold(WEIGHT_ZERO, add_weights)
+    }
+}
+
 pub type EdgeId = usize;
-pub type Weight = usize;
 pub type Length = f64;
+pub type EdgeData = u64;
+/// A point in time given in the same unit as the weights, e.g. seconds since midnight
+pub type TimeOfDay = Weight;
+/// The cell of a node in a partition of the graph, see `prepare_partitioned`
+pub type CellId = usize;
+
+pub const INVALID_NODE: NodeId = NodeId(core::usize::MAX);
+pub const INVALID_EDGE: EdgeId = core::usize::MAX;
+pub const WEIGHT_MAX: Weight = Weight(core::usize::MAX);
+pub const WEIGHT_ZERO: Weight = Weight(0);
+pub const INVALID_EDGE_DATA: EdgeData = u64::MAX;
+
+/// Returns true if the given weight is `WEIGHT_MAX`, which stands for an infinite weight, e.g.
+/// the weight of a node that cannot be reached. All smaller weights are finite, so the largest
+/// finite weight is `WEIGHT_MAX - 1`.
+pub fn is_infinite(weight: Weight) -> bool {
+    weight == WEIGHT_MAX
+}
+
+/// Adds two weights. The sum is infinite (`WEIGHT_MAX`) if one of the weights is infinite or if
+/// the sum of the weights is not smaller than `WEIGHT_MAX`, so this never overflows. This is
+/// also what `a + b` does.
+pub fn add_weights(a: Weight, b: Weight) -> Weight {
+    Weight(a.0.saturating_add(b.0))
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::format;
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn convert_and_compare() {
+        let node: NodeId = 3.into();
+        assert_eq!(NodeId(3), node);
+        assert_eq!(3, usize::from(node));
+        assert!(node == NodeId(3));
+        assert!(node < NodeId(4));
+        let nodes = ['a', 'b', 'c', 'd'];
+        assert_eq!('d', nodes[node.0]);
+        assert_eq!("[3, 5]", format!("{:?}", vec![node, NodeId(5)]));
+    }
 
-pub const INVALID_NODE: NodeId = std::usize::MAX;
-pub const INVALID_EDGE: EdgeId = std::usize::MAX;
-pub const WEIGHT_MAX: Weight = std::usize::MAX;
-pub const WEIGHT_ZERO: Weight = 0;
+    #[test]
+    fn weight_arithmetic() {
+        let mut weight = Weight(5) + Weight(3);
+        assert_eq!(Weight(8), weight);
+        weight += Weight(2);
+        assert_eq!(Weight(10), weight);
+        assert_eq!(Weight(4), weight - Weight(6));
+        assert_eq!(WEIGHT_ZERO, Weight(3).saturating_sub(Weight(4)));
+        assert_eq!(
+            Weight(6),
+            vec![Weight(1), Weight(2), Weight(3)]
+                .into_iter()
+                .sum::<Weight>()
+        );
+        assert_eq!(WEIGHT_MAX, add_weights(WEIGHT_MAX - Weight(1), Weight(2)));
+        // addition saturates as well
+        assert_eq!(WEIGHT_MAX, WEIGHT_MAX + Weight(1));
+        assert_eq!(WEIGHT_MAX, WEIGHT_MAX - Weight(1) + Weight(2));
+        let mut weight = WEIGHT_MAX;
+        weight += Weight(3);
+        assert_eq!(WEIGHT_MAX, weight);
+        assert_eq!(
+            WEIGHT_MAX,
+            vec![WEIGHT_MAX, Weight(1)].into_iter().sum::<Weight>()
+        );
+    }
+}
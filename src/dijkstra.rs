@@ -20,7 +20,7 @@
 use std::collections::BinaryHeap;
 
 use crate::constants::Weight;
-use crate::constants::{NodeId, INVALID_NODE, WEIGHT_MAX};
+use crate::constants::{add_weights, NodeId, INVALID_NODE, WEIGHT_MAX, WEIGHT_ZERO};
 use crate::heap_item::HeapItem;
 use crate::preparation_graph::PreparationGraph;
 use crate::shortest_path::ShortestPath;
@@ -33,13 +33,20 @@ pub struct Dijkstra {
     heap: BinaryHeap<HeapItem>,
 }
 
-/// Dijkstra's algorithm using pre-allocated memory for the shortest path tree. Currently only used
-/// to test the correctness of the path_calculator implementation. Providing a flexible Dijkstra
-/// implementation that works for arbitrary weight functions and that runs on the fast_graph
-/// datastructure might be useful, but this was not the intention here.
+/// Dijkstra's algorithm using pre-allocated memory for the shortest path tree. It is used to
+/// verify the correctness of the path_calculator implementation and can serve as a baseline for
+/// benchmarks, e.g. using the original edges of a prepared graph obtained with
+/// `FastGraph::to_preparation_graph`. Providing a flexible Dijkstra implementation that works for
+/// arbitrary weight functions and that runs on the fast_graph datastructure might be useful, but
+/// this was not the intention here.
 impl Dijkstra {
     pub fn new(num_nodes: usize) -> Self {
-        let heap = BinaryHeap::new();
+        Dijkstra::with_capacity(num_nodes, 0)
+    }
+
+    /// Like `new`, but the heap can hold `heap_capacity` items before it needs to grow
+    pub fn with_capacity(num_nodes: usize, heap_capacity: usize) -> Self {
+        let heap = BinaryHeap::with_capacity(heap_capacity);
         Dijkstra {
             num_nodes,
             data: (0..num_nodes).map(|_i| Data::new()).collect(),
@@ -48,7 +55,6 @@ impl Dijkstra {
         }
     }
 
-    #[allow(dead_code)]
     pub fn calc_path(
         &mut self,
         graph: &PreparationGraph,
@@ -63,8 +69,8 @@ impl Dijkstra {
     fn init(&mut self, start: NodeId) {
         self.heap.clear();
         self.valid_flags.invalidate_all();
-        self.update_node(start, 0, INVALID_NODE);
-        self.heap.push(HeapItem::new(0, start));
+        self.update_node(start, WEIGHT_ZERO, INVALID_NODE);
+        self.heap.push(HeapItem::new(WEIGHT_ZERO, start));
     }
 
     fn do_calc_path(&mut self, graph: &PreparationGraph, start: NodeId, end: NodeId) {
@@ -86,16 +92,16 @@ impl Dijkstra {
                 // filter out duplicate heap items here
                 continue;
             }
-            for i in 0..graph.out_edges[curr.node_id].len() {
-                let adj = graph.out_edges[curr.node_id][i].adj_node;
-                let edge_weight = graph.out_edges[curr.node_id][i].weight;
-                let weight = curr.weight + edge_weight;
+            for i in 0..graph.out_edges[curr.node_id.0].len() {
+                let adj = graph.out_edges[curr.node_id.0][i].adj_node;
+                let edge_weight = graph.out_edges[curr.node_id.0][i].weight;
+                let weight = add_weights(curr.weight, edge_weight);
                 if weight < self.get_weight(adj) {
                     self.update_node(adj, weight, curr.node_id);
                     self.heap.push(HeapItem::new(weight, adj));
                 }
             }
-            self.data[curr.node_id].settled = true;
+            self.data[curr.node_id.0].settled = true;
             if curr.node_id == end {
                 break;
             }
@@ -106,34 +112,34 @@ impl Dijkstra {
         if start == end {
             return Some(ShortestPath::singular(start));
         }
-        if !self.valid_flags.is_valid(end) || !self.data[end].settled {
+        if !self.valid_flags.is_valid(end) || !self.data[end.0].settled {
             return None;
         }
         let mut path = Vec::new();
         let mut node = end;
-        while self.data[node].parent != INVALID_NODE {
+        while self.data[node.0].parent != INVALID_NODE {
             path.push(node);
-            node = self.data[node].parent;
+            node = self.data[node.0].parent;
         }
         path.push(start);
         path = path.iter().rev().cloned().collect();
-        Some(ShortestPath::new(start, end, self.data[end].weight, path))
+        Some(ShortestPath::new(start, end, self.data[end.0].weight, path))
     }
 
     fn update_node(&mut self, node: NodeId, weight: Weight, parent: NodeId) {
         self.valid_flags.set_valid(node);
-        self.data[node].settled = false;
-        self.data[node].weight = weight;
-        self.data[node].parent = parent;
+        self.data[node.0].settled = false;
+        self.data[node.0].weight = weight;
+        self.data[node.0].parent = parent;
     }
 
     fn is_settled(&self, node: NodeId) -> bool {
-        self.valid_flags.is_valid(node) && self.data[node].settled
+        self.valid_flags.is_valid(node) && self.data[node.0].settled
     }
 
     fn get_weight(&self, node: NodeId) -> Weight {
         if self.valid_flags.is_valid(node) {
-            self.data[node].weight
+            self.data[node.0].weight
         } else {
             WEIGHT_MAX
         }
@@ -169,24 +175,52 @@ mod tests {
         // |         |  \ |
         // 1 -> 2 -> 3 -> 4
         let mut g = PreparationGraph::new(10);
-        g.add_edge(0, 1, 1, 1.0);
-        g.add_edge(1, 2, 1, 1.0);
-        g.add_edge(2, 3, 1, 1.0);
-        g.add_edge(3, 4, 20, 20.0);
-        g.add_edge(0, 5, 5, 5.0);
-        g.add_edge(5, 6, 1, 1.0);
-        g.add_edge(6, 4, 20, 20.0);
-        g.add_edge(6, 3, 20, 20.0);
-        g.add_edge(5, 7, 5, 5.0);
-        g.add_edge(7, 8, 1, 1.0);
-        g.add_edge(8, 9, 1, 1.0);
-        g.add_edge(9, 4, 1, 1.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(20), 20.0);
+        g.add_edge(NodeId(0), NodeId(5), Weight(5), 5.0);
+        g.add_edge(NodeId(5), NodeId(6), Weight(1), 1.0);
+        g.add_edge(NodeId(6), NodeId(4), Weight(20), 20.0);
+        g.add_edge(NodeId(6), NodeId(3), Weight(20), 20.0);
+        g.add_edge(NodeId(5), NodeId(7), Weight(5), 5.0);
+        g.add_edge(NodeId(7), NodeId(8), Weight(1), 1.0);
+        g.add_edge(NodeId(8), NodeId(9), Weight(1), 1.0);
+        g.add_edge(NodeId(9), NodeId(4), Weight(1), 1.0);
         let mut d = Dijkstra::new(g.get_num_nodes());
-        assert_no_path(&mut d, &g, 4, 0);
-        assert_path(&mut d, &g, 4, 4, 0, vec![4]);
-        assert_path(&mut d, &g, 6, 3, 20, vec![6, 3]);
-        assert_path(&mut d, &g, 1, 4, 22, vec![1, 2, 3, 4]);
-        assert_path(&mut d, &g, 0, 4, 13, vec![0, 5, 7, 8, 9, 4]);
+        assert_no_path(&mut d, &g, NodeId(4), NodeId(0));
+        assert_path(&mut d, &g, NodeId(4), NodeId(4), Weight(0), vec![NodeId(4)]);
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(6),
+            NodeId(3),
+            Weight(20),
+            vec![NodeId(6), NodeId(3)],
+        );
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(1),
+            NodeId(4),
+            Weight(22),
+            vec![NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+        );
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(0),
+            NodeId(4),
+            Weight(13),
+            vec![
+                NodeId(0),
+                NodeId(5),
+                NodeId(7),
+                NodeId(8),
+                NodeId(9),
+                NodeId(4),
+            ],
+        );
     }
 
     #[test]
@@ -195,12 +229,19 @@ mod tests {
         // |    |
         // 2 -> 3
         let mut g = PreparationGraph::new(4);
-        g.add_edge(0, 1, 10, 10.0);
-        g.add_edge(0, 2, 1, 1.0);
-        g.add_edge(2, 3, 1, 1.0);
-        g.add_edge(3, 1, 1, 1.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(10), 10.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(1), Weight(1), 1.0);
         let mut d = Dijkstra::new(g.get_num_nodes());
-        assert_path(&mut d, &g, 0, 1, 3, vec![0, 2, 3, 1]);
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(0),
+            NodeId(1),
+            Weight(3),
+            vec![NodeId(0), NodeId(2), NodeId(3), NodeId(1)],
+        );
     }
 
     #[test]
@@ -213,25 +254,88 @@ mod tests {
         //            \
         //             8 -> 9 -> 10
         let mut g = PreparationGraph::new(11);
-        g.add_edge(0, 1, 1, 1.0);
-        g.add_edge(1, 2, 1, 1.0);
-        g.add_edge(1, 3, 1, 1.0);
-        g.add_edge(3, 4, 1, 1.0);
-        g.add_edge(3, 6, 1, 1.0);
-        g.add_edge(6, 7, 1, 1.0);
-        g.add_edge(3, 5, 1, 1.0);
-        g.add_edge(3, 8, 1, 1.0);
-        g.add_edge(8, 9, 1, 1.0);
-        g.add_edge(9, 10, 1, 1.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(6), Weight(1), 1.0);
+        g.add_edge(NodeId(6), NodeId(7), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(5), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(8), Weight(1), 1.0);
+        g.add_edge(NodeId(8), NodeId(9), Weight(1), 1.0);
+        g.add_edge(NodeId(9), NodeId(10), Weight(1), 1.0);
         let mut d = Dijkstra::new(g.get_num_nodes());
-        assert_path(&mut d, &g, 0, 1, 1, vec![0, 1]);
-        assert_path(&mut d, &g, 0, 2, 2, vec![0, 1, 2]);
-        assert_path(&mut d, &g, 0, 4, 3, vec![0, 1, 3, 4]);
-        assert_path(&mut d, &g, 0, 3, 2, vec![0, 1, 3]);
-        assert_path(&mut d, &g, 0, 7, 4, vec![0, 1, 3, 6, 7]);
-        assert_path(&mut d, &g, 0, 5, 3, vec![0, 1, 3, 5]);
-        assert_path(&mut d, &g, 0, 10, 5, vec![0, 1, 3, 8, 9, 10]);
-        assert_path(&mut d, &g, 3, 10, 3, vec![3, 8, 9, 10]);
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(0),
+            NodeId(1),
+            Weight(1),
+            vec![NodeId(0), NodeId(1)],
+        );
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(0),
+            NodeId(2),
+            Weight(2),
+            vec![NodeId(0), NodeId(1), NodeId(2)],
+        );
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(0),
+            NodeId(4),
+            Weight(3),
+            vec![NodeId(0), NodeId(1), NodeId(3), NodeId(4)],
+        );
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(0),
+            NodeId(3),
+            Weight(2),
+            vec![NodeId(0), NodeId(1), NodeId(3)],
+        );
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(0),
+            NodeId(7),
+            Weight(4),
+            vec![NodeId(0), NodeId(1), NodeId(3), NodeId(6), NodeId(7)],
+        );
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(0),
+            NodeId(5),
+            Weight(3),
+            vec![NodeId(0), NodeId(1), NodeId(3), NodeId(5)],
+        );
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(0),
+            NodeId(10),
+            Weight(5),
+            vec![
+                NodeId(0),
+                NodeId(1),
+                NodeId(3),
+                NodeId(8),
+                NodeId(9),
+                NodeId(10),
+            ],
+        );
+        assert_path(
+            &mut d,
+            &g,
+            NodeId(3),
+            NodeId(10),
+            Weight(3),
+            vec![NodeId(3), NodeId(8), NodeId(9), NodeId(10)],
+        );
     }
 
     fn assert_no_path(
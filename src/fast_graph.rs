@@ -17,13 +17,44 @@
  * under the License.
  */
 
+use alloc::format;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use std::fs::File;
+#[cfg(feature = "std")]
+use std::io::{self, BufReader, BufWriter, Read, Write};
+#[cfg(feature = "std")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "std")]
+use bincode::Options;
+use serde::de::Error;
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
+use serde::Serializer;
 
-use crate::constants::{EdgeId, Length, NodeId, Weight, INVALID_EDGE};
+use crate::constants::{add_weights, is_infinite};
+use crate::constants::{
+    EdgeData, EdgeId, Length, NodeId, Weight, INVALID_EDGE, INVALID_EDGE_DATA, INVALID_NODE,
+};
+#[cfg(feature = "std")]
+use crate::fast_graph32;
+#[cfg(feature = "std")]
+use crate::input_graph::InputGraph;
+use crate::path_calculator::PathCalculator;
+#[cfg(feature = "std")]
+use crate::preparation_graph::PreparationGraph;
+#[cfg(feature = "std")]
+use crate::spatial_index::GridIndex;
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FastGraph {
+    /// written first, so a graph serialized in a different format is rejected before we try to
+    /// read any of the other fields
+    format_version: FormatVersion,
     num_nodes: usize,
     pub ranks: Vec<usize>,
     pub edges_fwd: Vec<FastGraphEdge>,
@@ -31,28 +62,726 @@ pub struct FastGraph {
 
     pub edges_bwd: Vec<FastGraphEdge>,
     pub first_edge_ids_bwd: Vec<EdgeId>,
+
+    /// the (lat, lon) coordinates of the nodes, empty if they were not set
+    #[serde(default)]
+    coordinates: Vec<(f64, f64)>,
+    /// built on first use by `nearest_node`
+    #[cfg(feature = "std")]
+    #[serde(skip)]
+    spatial_index: OnceLock<GridIndex>,
+    /// the weight scale of the input graph, see `InputGraph::set_weight_scale`
+    weight_scale: f64,
+    /// why each node got its rank, empty unless `Params::rank_explanations` was enabled, see
+    /// `explain_rank`
+    #[serde(default)]
+    rank_explanations: Vec<Option<RankExplanation>>,
+    /// the original edges that were replaced by a shortcut with a smaller weight during the
+    /// preparation, see `get_replaced_edges`
+    #[serde(default)]
+    replaced_edges: Vec<FastGraphEdge>,
+    /// built on first use by `get_down_edges`
+    #[cfg(feature = "std")]
+    #[serde(skip)]
+    down_edges: OnceLock<DownEdges>,
 }
 
 impl FastGraph {
+    /// The version of the serialized form of a `FastGraph`. It is increased whenever the
+    /// serialized form changes and deserializing a graph that was written with a different
+    /// version fails with an error.
+    pub const FORMAT_VERSION: u32 = 5;
+
+    /// The first bytes of the data written by `serialize_into_buf`
+    pub const BYTES_MAGIC: [u8; 4] = *b"FPGB";
+
+    /// The number of bytes of the header written by `serialize_into_buf`
+    const HEADER_BYTES: usize = 56;
+
+    /// The number of bytes of each edge written by `serialize_into_buf`
+    const EDGE_BYTES: usize = 64;
+
+    /// The first bytes of a file written by `save_ordering`
+    #[cfg(feature = "std")]
+    pub const ORDERING_FILE_MAGIC: [u8; 4] = *b"FPNO";
+
     pub fn new(num_nodes: usize) -> Self {
         FastGraph {
+            format_version: FormatVersion,
             ranks: vec![0; num_nodes],
             num_nodes,
             edges_fwd: vec![],
             first_edge_ids_fwd: vec![0; num_nodes + 1],
             edges_bwd: vec![],
             first_edge_ids_bwd: vec![0; num_nodes + 1],
+            coordinates: vec![],
+            #[cfg(feature = "std")]
+            spatial_index: OnceLock::new(),
+            weight_scale: 1.0,
+            rank_explanations: vec![],
+            replaced_edges: vec![],
+            #[cfg(feature = "std")]
+            down_edges: OnceLock::new(),
+        }
+    }
+
+    /// Sets the (lat, lon) coordinates of the nodes, the coordinates of node i must be given at
+    /// index i. They are not needed for the shortest path calculations, but allow finding the
+    /// node closest to a location using `nearest_node`. Note that the coordinates are not part of
+    /// the 32-bit representation (`FastGraph32`).
+    pub fn set_coordinates(&mut self, coordinates: Vec<(f64, f64)>) -> Result<(), String> {
+        if coordinates.len() != self.num_nodes {
+            return Err(format!(
+                "The number of coordinates ({}) must be equal to the number of nodes ({})",
+                coordinates.len(),
+                self.num_nodes
+            ));
         }
+        self.coordinates = coordinates;
+        #[cfg(feature = "std")]
+        {
+            self.spatial_index = OnceLock::new();
+        }
+        Ok(())
+    }
+
+    pub fn get_coordinates(&self) -> &Vec<(f64, f64)> {
+        &self.coordinates
+    }
+
+    /// Returns the node closest to the given location, or `None` if no coordinates were set. The
+    /// spatial index used for this is built on the first call, which takes O(nodes) time.
+    #[cfg(feature = "std")]
+    pub fn nearest_node(&self, lat: f64, lon: f64) -> Option<NodeId> {
+        if self.coordinates.is_empty() {
+            return None;
+        }
+        self.spatial_index
+            .get_or_init(|| GridIndex::new(&self.coordinates))
+            .find_nearest(lat, lon)
+    }
+
+    /// Returns the factor the weights of the input graph were scaled with, see
+    /// `InputGraph::set_weight_scale`. Note that the weight scale is not part of the 32-bit
+    /// representation (`FastGraph32`), so it is 1 for graphs read from it.
+    pub fn get_weight_scale(&self) -> f64 {
+        self.weight_scale
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn set_weight_scale(&mut self, weight_scale: f64) {
+        self.weight_scale = weight_scale;
     }
 
     pub fn get_node_ordering(&self) -> Vec<NodeId> {
-        let mut ordering = vec![0; self.ranks.len()];
+        let mut ordering = vec![INVALID_NODE; self.ranks.len()];
         for i in 0..self.ranks.len() {
-            ordering[self.ranks[i]] = i;
+            ordering[self.ranks[i]] = NodeId(i);
         }
         ordering
     }
 
+    /// Releases the excess capacity of the internal vectors, which may be left over from growing
+    /// them while the graph was built or modified (e.g. by `add_node_and_edges`). This does not
+    /// change the graph, but for large graphs it can reduce the memory usage noticeably.
+    pub fn shrink_to_fit(&mut self) {
+        self.ranks.shrink_to_fit();
+        self.edges_fwd.shrink_to_fit();
+        self.first_edge_ids_fwd.shrink_to_fit();
+        self.edges_bwd.shrink_to_fit();
+        self.first_edge_ids_bwd.shrink_to_fit();
+        self.coordinates.shrink_to_fit();
+        self.rank_explanations.shrink_to_fit();
+        self.replaced_edges.shrink_to_fit();
+    }
+
+    /// Returns the edges of the input graph that are not stored as original edges of this graph,
+    /// because a shortcut with a smaller weight between the same nodes replaced them during the
+    /// preparation. The base node of these edges is their source and the adjacent node is their
+    /// target. They are never part of a shortest path, so the usual queries do not need them, but
+    /// the queries with additional constraints (like `PathCalculator::calc_path_max_hops` or
+    /// `PathCalculator::calc_path_avoiding`) do, because they might have to use an edge that is
+    /// not on a shortest path. Together with the original edges of this graph they form the
+    /// input graph, see `to_input_graph`.
+    pub fn get_replaced_edges(&self) -> &[FastGraphEdge] {
+        &self.replaced_edges
+    }
+
+    pub(crate) fn set_replaced_edges(&mut self, replaced_edges: Vec<FastGraphEdge>) {
+        self.replaced_edges = replaced_edges;
+    }
+
+    /// Returns the values that determined the contraction priority of the given node when it was
+    /// contracted, and thereby its rank, e.g. to understand why a node ended up high in the
+    /// hierarchy. The explanations are only recorded if `Params::rank_explanations` was enabled
+    /// for the preparation, otherwise, and for nodes that were not contracted by priority (core
+    /// nodes, nodes added by `add_node_and_edges` or nodes whose initial priority was given to
+    /// `prepare_with_initial_priorities` and never updated), this returns `None`. Like the
+    /// coordinates the explanations are not part of `serialize_into_buf` and `FastGraph32`.
+    pub fn explain_rank(&self, node: NodeId) -> Option<RankExplanation> {
+        self.rank_explanations.get(node.0).copied().flatten()
+    }
+
+    /// Enables recording the rank explanations, see `explain_rank`
+    #[cfg(feature = "std")]
+    pub(crate) fn init_rank_explanations(&mut self) {
+        self.rank_explanations = vec![None; self.num_nodes];
+    }
+
+    #[cfg(feature = "std")]
+    pub(crate) fn set_rank_explanation(&mut self, node: NodeId, explanation: RankExplanation) {
+        self.rank_explanations[node.0] = Some(explanation);
+    }
+
+    /// Returns the node with the highest rank on the shortest path from `source` to `target` along
+    /// with its rank, or `None` if there is no such path. This is the apex of the path in the
+    /// hierarchy, i.e. the node where the forward and backward searches of the query meet. It is
+    /// meant for debugging the hierarchy: a query whose apex has a low rank compared to the
+    /// number of nodes needs long upward searches before they meet, which hints at a poor node
+    /// ordering. This allocates a new `PathCalculator` for every call, so it should not be used
+    /// for a large number of queries.
+    pub fn highest_ranked_on_path(
+        &self,
+        source: NodeId,
+        target: NodeId,
+    ) -> Option<(NodeId, usize)> {
+        let mut calc = PathCalculator::new(self.num_nodes);
+        let path = calc.calc_path(self, source, target)?;
+        path.get_nodes()
+            .iter()
+            .map(|&node| (node, self.ranks[node.0]))
+            .max_by_key(|&(_, rank)| rank)
+    }
+
+    /// Writes the node ordering (see `get_node_ordering`) to the given file, so it can be read
+    /// using `load_ordering` and passed to `prepare_with_order` later, e.g. on another machine.
+    /// The file is binary and all numbers are little-endian:
+    /// * the four bytes `FPNO` (`ORDERING_FILE_MAGIC`)
+    /// * the number of nodes as u64
+    /// * the nodes as u64, starting with the one with the lowest rank
+    #[cfg(feature = "std")]
+    pub fn save_ordering(&self, filename: &str) -> io::Result<()> {
+        let mut f = BufWriter::new(File::create(filename)?);
+        f.write_all(&FastGraph::ORDERING_FILE_MAGIC)?;
+        f.write_all(&(self.num_nodes as u64).to_le_bytes())?;
+        for node in self.get_node_ordering() {
+            f.write_all(&(node.0 as u64).to_le_bytes())?;
+        }
+        f.flush()
+    }
+
+    /// Reads a graph from the given reader that was serialized by bincode in its 32bit
+    /// representation, e.g. using `bincode::serialize_into(writer, &FastGraph32::new(&graph))` or
+    /// `serialize_32`. The values are converted to `usize` while they are read, so neither the
+    /// 32bit graph nor the file content is ever kept in memory as a whole. The reader is not
+    /// buffered, so for files wrapping them in a `BufReader` is much faster. Errors of the reader
+    /// are returned as they are (e.g. `UnexpectedEof` for truncated data) and other errors get the
+    /// kind `InvalidData`.
+    #[cfg(feature = "std")]
+    pub fn load_streaming<R: Read>(reader: R) -> io::Result<FastGraph> {
+        let options = bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes();
+        let mut deserializer = bincode::Deserializer::with_reader(reader, options);
+        fast_graph32::deserialize_to_usize(&mut deserializer)
+            .map_err(|e| FastGraph::to_io_error(*e))
+    }
+
+    /// Writes this graph to the given file, serialized using bincode and compressed using zstd
+    /// with the given compression level (1 to 22, or 0 for the default level 3). Prepared graphs
+    /// compress well, e.g. the graph of the Bremen test map shrinks to less than a fifth of its
+    /// size with the default level. Higher levels compress better, but take longer, while the
+    /// time for `load_compressed` is about the same for all levels.
+    #[cfg(feature = "compression")]
+    pub fn save_compressed(&self, filename: &str, level: i32) -> io::Result<()> {
+        let mut encoder = zstd::Encoder::new(BufWriter::new(File::create(filename)?), level)?;
+        bincode::serialize_into(&mut encoder, self).map_err(|e| FastGraph::to_io_error(*e))?;
+        encoder.finish()?.flush()
+    }
+
+    /// Reads a graph written by `save_compressed`. Returns an error with kind `InvalidData` if the
+    /// file does not contain a compressed graph or the graph has a different format version (see
+    /// `FORMAT_VERSION`).
+    #[cfg(feature = "compression")]
+    pub fn load_compressed(filename: &str) -> io::Result<FastGraph> {
+        let decoder = zstd::Decoder::new(File::open(filename)?)?;
+        bincode::deserialize_from(decoder).map_err(|e| FastGraph::to_io_error(*e))
+    }
+
+    /// Keeps the errors of the underlying reader or writer, all other errors mean the data is
+    /// invalid
+    #[cfg(feature = "std")]
+    fn to_io_error(e: bincode::ErrorKind) -> io::Error {
+        match e {
+            bincode::ErrorKind::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::InvalidData, e),
+        }
+    }
+
+    /// Reads a node ordering written by `save_ordering`. Returns an error with kind
+    /// `InvalidData` if the file does not start with `ORDERING_FILE_MAGIC`, if the number of
+    /// nodes in the file does not match the number of nodes stored in its header or if it
+    /// contains a node id that is not smaller than the number of nodes.
+    #[cfg(feature = "std")]
+    pub fn load_ordering(filename: &str) -> io::Result<Vec<NodeId>> {
+        let mut f = BufReader::new(File::open(filename)?);
+        let mut magic = [0; 4];
+        f.read_exact(&mut magic)?;
+        if magic != FastGraph::ORDERING_FILE_MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "The file does not contain a node ordering",
+            ));
+        }
+        let mut buf = [0; 8];
+        f.read_exact(&mut buf)?;
+        let num_nodes = u64::from_le_bytes(buf) as usize;
+        let mut bytes = vec![];
+        f.read_to_end(&mut bytes)?;
+        if bytes.len() % 8 != 0 || bytes.len() / 8 != num_nodes {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "The node ordering should contain {} nodes, but the file contains {} bytes of \
+                     node ids",
+                    num_nodes,
+                    bytes.len()
+                ),
+            ));
+        }
+        let mut ordering = Vec::with_capacity(num_nodes);
+        for chunk in bytes.chunks_exact(8) {
+            buf.copy_from_slice(chunk);
+            let node = NodeId(u64::from_le_bytes(buf) as usize);
+            if node.0 >= num_nodes {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "The node ordering contains the invalid node id {}, node ids must be in \
+                         [0, {})",
+                        node, num_nodes
+                    ),
+                ));
+            }
+            ordering.push(node);
+        }
+        Ok(ordering)
+    }
+
+    /// Appends this graph to the given buffer, such that it can be read using `from_bytes`, e.g.
+    /// to store it in a memory-mapped file. Unlike the serde serialization this uses a fixed
+    /// layout, where all numbers are little-endian and each of the arrays starts at a multiple of
+    /// eight bytes (relative to the start of the data) and can be located without reading the
+    /// previous ones:
+    /// * the four bytes `FPGB` (`BYTES_MAGIC`) and `FORMAT_VERSION` as u32
+    /// * the number of nodes, out-edges, in-edges, replaced edges (see `get_replaced_edges`) and
+    ///   coordinates as u64, followed by the weight scale as f64
+    /// * the ranks of the nodes as u64
+    /// * the first out-edge ids and first in-edge ids as u64, one more than there are nodes
+    /// * the out-edges, in-edges and replaced edges, 64 bytes each: the base node, adjacent node,
+    ///   weight, length (f64), replaced in-edge, replaced out-edge, data and base edge count
+    /// * the (lat, lon) coordinates as pairs of f64
+    pub fn serialize_into_buf(&self, buf: &mut Vec<u8>) {
+        let num_bytes = FastGraph::get_num_bytes(
+            self.num_nodes,
+            self.edges_fwd.len(),
+            self.edges_bwd.len(),
+            self.replaced_edges.len(),
+            self.coordinates.len(),
+        )
+        .expect("The graph is too large to be serialized");
+        buf.reserve(num_bytes);
+        buf.extend_from_slice(&FastGraph::BYTES_MAGIC);
+        buf.extend_from_slice(&FastGraph::FORMAT_VERSION.to_le_bytes());
+        for count in [
+            self.num_nodes,
+            self.edges_fwd.len(),
+            self.edges_bwd.len(),
+            self.replaced_edges.len(),
+            self.coordinates.len(),
+        ] {
+            write_usize(buf, count);
+        }
+        buf.extend_from_slice(&self.weight_scale.to_le_bytes());
+        let ids = self
+            .ranks
+            .iter()
+            .chain(&self.first_edge_ids_fwd)
+            .chain(&self.first_edge_ids_bwd);
+        for &id in ids {
+            write_usize(buf, id);
+        }
+        let edges = self
+            .edges_fwd
+            .iter()
+            .chain(&self.edges_bwd)
+            .chain(&self.replaced_edges);
+        for edge in edges {
+            write_usize(buf, edge.base_node.0);
+            write_usize(buf, edge.adj_node.0);
+            write_usize(buf, edge.weight.0);
+            buf.extend_from_slice(&edge.length.to_le_bytes());
+            write_usize(buf, edge.replaced_in_edge);
+            write_usize(buf, edge.replaced_out_edge);
+            buf.extend_from_slice(&edge.data.to_le_bytes());
+            write_usize(buf, edge.base_edge_count);
+        }
+        for &(lat, lon) in &self.coordinates {
+            buf.extend_from_slice(&lat.to_le_bytes());
+            buf.extend_from_slice(&lon.to_le_bytes());
+        }
+    }
+
+    /// Reads a graph written by `serialize_into_buf`. The size of the data is checked before
+    /// anything is read and the arrays are decoded directly into the vectors of the graph, so
+    /// apart from the resulting graph no memory is allocated. Returns an error if the data was
+    /// not written by `serialize_into_buf` (with the same `FORMAT_VERSION`), has the wrong size
+    /// or does not describe a valid graph (see `check_invariants`).
+    pub fn from_bytes(data: &[u8]) -> Result<FastGraph, String> {
+        if data.len() < FastGraph::HEADER_BYTES || data[0..4] != FastGraph::BYTES_MAGIC {
+            return Err(String::from("The data does not contain a FastGraph"));
+        }
+        let mut reader = ByteReader { data, pos: 4 };
+        let version = reader.read_u32();
+        if version != FastGraph::FORMAT_VERSION {
+            return Err(format!(
+                "The FastGraph was serialized with format version {}, but this version of \
+                 fast_paths can only read format version {}. Please prepare the graph again.",
+                version,
+                FastGraph::FORMAT_VERSION
+            ));
+        }
+        let num_nodes = reader.read_usize();
+        let num_out_edges = reader.read_usize();
+        let num_in_edges = reader.read_usize();
+        let num_replaced_edges = reader.read_usize();
+        let num_coordinates = reader.read_usize();
+        let weight_scale = reader.read_f64();
+        let expected_bytes = FastGraph::get_num_bytes(
+            num_nodes,
+            num_out_edges,
+            num_in_edges,
+            num_replaced_edges,
+            num_coordinates,
+        );
+        if expected_bytes != Some(data.len()) {
+            return Err(format!(
+                "The FastGraph with {} nodes, {} out-edges, {} in-edges, {} replaced edges and \
+                 {} coordinates should have {} bytes, but there are {}",
+                num_nodes,
+                num_out_edges,
+                num_in_edges,
+                num_replaced_edges,
+                num_coordinates,
+                expected_bytes.map_or(String::from("too many"), |b| format!("{}", b)),
+                data.len()
+            ));
+        }
+        let mut graph = FastGraph::new(0);
+        graph.num_nodes = num_nodes;
+        graph.weight_scale = weight_scale;
+        graph.ranks = (0..num_nodes).map(|_| reader.read_usize()).collect();
+        graph.first_edge_ids_fwd = (0..=num_nodes).map(|_| reader.read_usize()).collect();
+        graph.first_edge_ids_bwd = (0..=num_nodes).map(|_| reader.read_usize()).collect();
+        graph.edges_fwd = (0..num_out_edges).map(|_| reader.read_edge()).collect();
+        graph.edges_bwd = (0..num_in_edges).map(|_| reader.read_edge()).collect();
+        graph.replaced_edges = (0..num_replaced_edges)
+            .map(|_| reader.read_edge())
+            .collect();
+        graph.coordinates = (0..num_coordinates)
+            .map(|_| (reader.read_f64(), reader.read_f64()))
+            .collect();
+        graph.check_invariants()?;
+        Ok(graph)
+    }
+
+    /// Returns the number of bytes written by `serialize_into_buf` or `None` if it does not fit
+    /// into a usize
+    fn get_num_bytes(
+        num_nodes: usize,
+        num_out_edges: usize,
+        num_in_edges: usize,
+        num_replaced_edges: usize,
+        num_coordinates: usize,
+    ) -> Option<usize> {
+        let num_ids = num_nodes.checked_mul(3)?.checked_add(2)?;
+        let num_edges = num_out_edges
+            .checked_add(num_in_edges)?
+            .checked_add(num_replaced_edges)?;
+        FastGraph::HEADER_BYTES
+            .checked_add(num_ids.checked_mul(8)?)?
+            .checked_add(num_edges.checked_mul(FastGraph::EDGE_BYTES)?)?
+            .checked_add(num_coordinates.checked_mul(16)?)
+    }
+
+    /// Returns a graph that contains the original (non-shortcut) edges of this graph, e.g. to run
+    /// a plain `Dijkstra` search that ignores the hierarchy as a baseline for benchmarks or to
+    /// check the results of the queries when the input graph is not available anymore. This
+    /// includes the edges of the input graph that were replaced by a shortcut with a smaller
+    /// weight during the preparation (see `get_replaced_edges`), so the returned graph has the
+    /// same edges as the input graph and can also serve as ground truth for the queries with
+    /// additional constraints, like `PathCalculator::calc_path_max_hops`.
+    #[cfg(feature = "std")]
+    pub fn to_preparation_graph(&self) -> PreparationGraph {
+        PreparationGraph::from_fast_graph(self)
+    }
+
+    /// Returns a (frozen) input graph that contains the original (non-shortcut) edges of this
+    /// graph along with their lengths and data, e.g. to prepare the graph again using different
+    /// `Params` when only the `FastGraph` was stored. The edges that were replaced by shortcuts
+    /// during the preparation are restored as well (see `get_replaced_edges`), so the returned
+    /// graph has the same edges as the input graph. The weight scale is kept, but the weight
+    /// profiles of time-dependent edges are not part of the `FastGraph`, so they are lost.
+    #[cfg(feature = "std")]
+    pub fn to_input_graph(&self) -> InputGraph {
+        InputGraph::from_fast_graph(self)
+    }
+
+    /// Returns a copy of this graph where the id of every node is its rank, along with the
+    /// original id of every node of the new graph, i.e. node `i` of the new graph is node
+    /// `mapping[i]` of this graph. Since the queries mostly visit high ranked nodes, storing the
+    /// nodes in rank order usually improves the cache locality of the queries. The nodes passed to
+    /// and returned by queries on the new graph need to be translated using the mapping.
+    pub fn reorder_by_rank(&self) -> (FastGraph, Vec<NodeId>) {
+        let mapping = self.get_node_ordering();
+        let reorder_edge = |edge: &FastGraphEdge| FastGraphEdge {
+            base_node: NodeId(self.ranks[edge.base_node.0]),
+            adj_node: NodeId(self.ranks[edge.adj_node.0]),
+            ..edge.clone()
+        };
+        let mut graph = FastGraph::new(self.num_nodes);
+        graph.ranks = (0..self.num_nodes).collect();
+        graph.edges_fwd = self.edges_fwd.iter().map(reorder_edge).collect();
+        graph.first_edge_ids_fwd = self.first_edge_ids_fwd.clone();
+        graph.edges_bwd = self.edges_bwd.iter().map(reorder_edge).collect();
+        graph.first_edge_ids_bwd = self.first_edge_ids_bwd.clone();
+        if !self.coordinates.is_empty() {
+            graph.coordinates = mapping.iter().map(|&n| self.coordinates[n.0]).collect();
+        }
+        if !self.rank_explanations.is_empty() {
+            graph.rank_explanations = mapping
+                .iter()
+                .map(|&n| self.rank_explanations[n.0])
+                .collect();
+        }
+        graph.replaced_edges = self.replaced_edges.iter().map(reorder_edge).collect();
+        graph.weight_scale = self.weight_scale;
+        (graph, mapping)
+    }
+
+    /// Adds a new node with the given (from, to, weight) edges to the prepared graph and returns
+    /// its id, which is `get_num_nodes()` before the call. Every edge must connect the new node
+    /// with an existing node, and the weights are also used as lengths of the edges. The new node
+    /// gets the highest rank, i.e. it is put on top of the hierarchy as if it was contracted
+    /// last. To keep the queries correct, shortcuts between the new node and the nodes that can
+    /// reach it (or that can be reached from it) via lower ranked nodes are added, while the
+    /// existing edges and shortcuts remain untouched. New edges that are replaced by such a
+    /// shortcut are kept, see `get_replaced_edges`. This is much faster than preparing the
+    /// graph again, but the new node is unlikely to be an important node, so the resulting
+    /// hierarchy is not optimal and adding many nodes this way makes the queries slower.
+    /// Coordinates (see `set_coordinates`) are removed, because there are none for the new node,
+    /// so they need to be set again. Returns an error if one of the edges does not connect the
+    /// new node with an existing node or has an infinite weight, just like such edges are not
+    /// allowed for the `InputGraph`.
+    pub fn add_node_and_edges(
+        &mut self,
+        edges: &[(NodeId, NodeId, Weight)],
+    ) -> Result<NodeId, String> {
+        let new_node = NodeId(self.num_nodes);
+        // the best (shortcut) edge from each existing node to the new node and vice versa
+        let mut to_new_node: Vec<Option<FastGraphEdge>> = vec![None; self.num_nodes];
+        let mut from_new_node: Vec<Option<FastGraphEdge>> = vec![None; self.num_nodes];
+        for &(from, to, weight) in edges {
+            let (node, best_edges) = if to == new_node && from < new_node {
+                (from, &mut to_new_node)
+            } else if from == new_node && to < new_node {
+                (to, &mut from_new_node)
+            } else {
+                return Err(format!(
+                    "Every edge must connect the new node {} with an existing node, but there is \
+                     an edge from {} to {}",
+                    new_node, from, to
+                ));
+            };
+            if is_infinite(weight) {
+                return Err(format!(
+                    "The weight of the edge from {} to {} must be finite, but it is {}",
+                    from, to, weight
+                ));
+            }
+            FastGraph::keep_lighter_edge(
+                &mut best_edges[node.0],
+                FastGraphEdge::new(
+                    node,
+                    new_node,
+                    weight,
+                    weight.0 as Length,
+                    INVALID_EDGE,
+                    INVALID_EDGE,
+                ),
+            );
+        }
+
+        // we visit the nodes in the order they were contracted. contracting a node with an edge to
+        // (or from) the new node creates shortcuts from (or to) its higher ranked neighbors. these
+        // are stored at the neighbors, so they are all known before we visit the neighbors.
+        let mut edges_fwd = Vec::with_capacity(self.edges_fwd.len() + self.num_nodes);
+        let mut edges_bwd = Vec::with_capacity(self.edges_bwd.len() + self.num_nodes);
+        let mut new_fwd_ids = vec![INVALID_EDGE; self.edges_fwd.len()];
+        let mut new_bwd_ids = vec![INVALID_EDGE; self.edges_bwd.len()];
+        let mut first_edge_ids_fwd = vec![0; self.num_nodes + 2];
+        let mut first_edge_ids_bwd = vec![0; self.num_nodes + 2];
+        // the new original edges that are replaced by lighter shortcuts via lower ranked nodes
+        let mut replaced_edges = vec![];
+        for (rank, node) in self.get_node_ordering().into_iter().enumerate() {
+            // the edges replaced by the shortcuts of this node are stored at lower ranked nodes,
+            // so we already know their new ids
+            for edge_id in self.begin_out_edges(node)..self.end_out_edges(node) {
+                let edge = self.edges_fwd[edge_id].with_new_ids(&new_fwd_ids, &new_bwd_ids);
+                new_fwd_ids[edge_id] = edges_fwd.len();
+                edges_fwd.push(edge);
+            }
+            let mut out_edge_to_new_node = INVALID_EDGE;
+            if let Some(edge) = to_new_node[node.0].take() {
+                out_edge_to_new_node = edges_fwd.len();
+                edges_fwd.push(edge);
+            }
+            for edge_id in self.begin_in_edges(node)..self.end_in_edges(node) {
+                let edge = self.edges_bwd[edge_id].with_new_ids(&new_fwd_ids, &new_bwd_ids);
+                new_bwd_ids[edge_id] = edges_bwd.len();
+                edges_bwd.push(edge);
+            }
+            let mut in_edge_from_new_node = INVALID_EDGE;
+            if let Some(edge) = from_new_node[node.0].take() {
+                in_edge_from_new_node = edges_bwd.len();
+                // in-edges point from the adj_node to the base_node
+                edges_bwd.push(FastGraphEdge {
+                    base_node: node,
+                    adj_node: new_node,
+                    ..edge
+                });
+            }
+            first_edge_ids_fwd[rank + 1] = edges_fwd.len();
+            first_edge_ids_bwd[rank + 1] = edges_bwd.len();
+
+            if out_edge_to_new_node != INVALID_EDGE {
+                let out_edge = &edges_fwd[out_edge_to_new_node];
+                let begin = first_edge_ids_bwd[rank];
+                for (i, in_edge) in edges_bwd[begin..first_edge_ids_bwd[rank + 1]]
+                    .iter()
+                    .enumerate()
+                {
+                    let in_edge_id = begin + i;
+                    if in_edge.adj_node == new_node {
+                        continue;
+                    }
+                    let shortcut = FastGraph::create_shortcut(
+                        in_edge.adj_node,
+                        new_node,
+                        in_edge,
+                        out_edge,
+                        in_edge_id,
+                        out_edge_to_new_node,
+                    );
+                    if let Some(replaced) =
+                        FastGraph::keep_lighter_edge(&mut to_new_node[in_edge.adj_node.0], shortcut)
+                    {
+                        replaced_edges.push(replaced);
+                    }
+                }
+            }
+            if in_edge_from_new_node != INVALID_EDGE {
+                let in_edge = &edges_bwd[in_edge_from_new_node];
+                let begin = first_edge_ids_fwd[rank];
+                for (i, out_edge) in edges_fwd[begin..first_edge_ids_fwd[rank + 1]]
+                    .iter()
+                    .enumerate()
+                {
+                    let out_edge_id = begin + i;
+                    if out_edge.adj_node == new_node {
+                        continue;
+                    }
+                    let shortcut = FastGraph::create_shortcut(
+                        out_edge.adj_node,
+                        new_node,
+                        in_edge,
+                        out_edge,
+                        in_edge_from_new_node,
+                        out_edge_id,
+                    );
+                    let best_edge = &mut from_new_node[out_edge.adj_node.0];
+                    if let Some(replaced) = FastGraph::keep_lighter_edge(best_edge, shortcut) {
+                        // the replaced edge points from the new node to the adjacent node
+                        replaced_edges.push(FastGraphEdge {
+                            base_node: new_node,
+                            adj_node: replaced.base_node,
+                            ..replaced
+                        });
+                    }
+                }
+            }
+        }
+        // the new node is on top of the hierarchy, so it does not have any edges itself
+        first_edge_ids_fwd[self.num_nodes + 1] = edges_fwd.len();
+        first_edge_ids_bwd[self.num_nodes + 1] = edges_bwd.len();
+
+        self.edges_fwd = edges_fwd;
+        self.edges_bwd = edges_bwd;
+        self.first_edge_ids_fwd = first_edge_ids_fwd;
+        self.first_edge_ids_bwd = first_edge_ids_bwd;
+        self.replaced_edges.extend(replaced_edges);
+        self.ranks.push(new_node.0);
+        self.num_nodes += 1;
+        self.coordinates.clear();
+        if !self.rank_explanations.is_empty() {
+            self.rank_explanations.push(None);
+        }
+        #[cfg(feature = "std")]
+        {
+            self.spatial_index = OnceLock::new();
+            self.down_edges = OnceLock::new();
+        }
+        Ok(new_node)
+    }
+
+    /// Creates the shortcut that replaces the given in- and out-edge of a node. `base_node` is the
+    /// node the shortcut is stored at and `adj_node` is its other node.
+    fn create_shortcut(
+        base_node: NodeId,
+        adj_node: NodeId,
+        in_edge: &FastGraphEdge,
+        out_edge: &FastGraphEdge,
+        in_edge_id: EdgeId,
+        out_edge_id: EdgeId,
+    ) -> FastGraphEdge {
+        FastGraphEdge {
+            base_node,
+            adj_node,
+            weight: add_weights(in_edge.weight, out_edge.weight),
+            length: in_edge.length + out_edge.length,
+            replaced_in_edge: in_edge_id,
+            replaced_out_edge: out_edge_id,
+            data: INVALID_EDGE_DATA,
+            base_edge_count: in_edge.base_edge_count + out_edge.base_edge_count,
+        }
+    }
+
+    /// Replaces `best` with the given edge unless `best` is at least as light already. Returns
+    /// the replaced edge if it is an original edge that is replaced by a shortcut, because it
+    /// needs to be kept, see `get_replaced_edges`.
+    fn keep_lighter_edge(
+        best: &mut Option<FastGraphEdge>,
+        edge: FastGraphEdge,
+    ) -> Option<FastGraphEdge> {
+        if best.as_ref().is_none_or(|best| edge.weight < best.weight) {
+            let replaced = best.replace(edge)?;
+            if !replaced.is_shortcut() && best.as_ref().unwrap().is_shortcut() {
+                return Some(replaced);
+            }
+        }
+        None
+    }
+
     pub fn get_num_nodes(&self) -> usize {
         self.num_nodes
     }
@@ -65,23 +794,505 @@ impl FastGraph {
         self.edges_bwd.len()
     }
 
+    /// Returns the number of out-edges that are edges of the input graph (no shortcuts)
+    pub fn get_num_original_out_edges(&self) -> usize {
+        self.get_num_out_edges() - self.get_num_shortcut_out_edges()
+    }
+
+    pub fn get_num_shortcut_out_edges(&self) -> usize {
+        self.edges_fwd.iter().filter(|e| e.is_shortcut()).count()
+    }
+
+    /// Returns the number of in-edges that are edges of the input graph (no shortcuts)
+    pub fn get_num_original_in_edges(&self) -> usize {
+        self.get_num_in_edges() - self.get_num_shortcut_in_edges()
+    }
+
+    pub fn get_num_shortcut_in_edges(&self) -> usize {
+        self.edges_bwd.iter().filter(|e| e.is_shortcut()).count()
+    }
+
+    /// Returns the offsets of the out-edges in compressed sparse row (CSR) form: the out-edges of
+    /// the node with rank `r` (see `ranks`) are `edges_fwd[offsets[r]..offsets[r + 1]]`. Both are
+    /// slices of the arrays the graph is stored in, so no copy is made.
+    pub fn out_edge_offsets(&self) -> &[usize] {
+        &self.first_edge_ids_fwd
+    }
+
+    /// Like `out_edge_offsets`, but for the in-edges (`edges_bwd`)
+    pub fn in_edge_offsets(&self) -> &[usize] {
+        &self.first_edge_ids_bwd
+    }
+
+    /// Returns the number of original edges the given edge is unpacked into, 1 for original edges.
+    /// Out-edge `i` (`edges_fwd[i]`) has the index `i` and in-edge `i` (`edges_bwd[i]`) has the
+    /// index `get_num_out_edges() + i`, just like for `PathCalculator::get_edge_usage_counts`.
+    pub fn shortcut_base_edge_count(&self, edge_idx: usize) -> usize {
+        if edge_idx < self.edges_fwd.len() {
+            self.edges_fwd[edge_idx].base_edge_count
+        } else {
+            self.edges_bwd[edge_idx - self.edges_fwd.len()].base_edge_count
+        }
+    }
+
+    /// Sets the `base_edge_count` of all edges. The edges a shortcut replaces belong to a node
+    /// with a lower rank, so we can calculate the counts going up the hierarchy.
+    #[cfg(feature = "std")]
+    pub(crate) fn update_base_edge_counts(&mut self) {
+        for rank in 0..self.num_nodes {
+            for edge_id in self.first_edge_ids_fwd[rank]..self.first_edge_ids_fwd[rank + 1] {
+                let count = self.calc_base_edge_count(&self.edges_fwd[edge_id]);
+                self.edges_fwd[edge_id].base_edge_count = count;
+            }
+            for edge_id in self.first_edge_ids_bwd[rank]..self.first_edge_ids_bwd[rank + 1] {
+                let count = self.calc_base_edge_count(&self.edges_bwd[edge_id]);
+                self.edges_bwd[edge_id].base_edge_count = count;
+            }
+        }
+    }
+
+    #[cfg(feature = "std")]
+    fn calc_base_edge_count(&self, edge: &FastGraphEdge) -> usize {
+        if edge.is_shortcut() {
+            self.edges_bwd[edge.replaced_in_edge].base_edge_count
+                + self.edges_fwd[edge.replaced_out_edge].base_edge_count
+        } else {
+            1
+        }
+    }
+
+    /// Returns the ids of the in-edges (`edges_bwd`) that point from the given node to a node with
+    /// lower rank, i.e. the in-edges whose `adj_node` is the given node. Together with the
+    /// out-edges of a node these are all its outgoing edges in the hierarchy. The index used for
+    /// this is built on the first call.
+    #[cfg(feature = "std")]
+    pub(crate) fn get_down_edges(&self, node: NodeId) -> &[EdgeId] {
+        let down_edges = self.down_edges.get_or_init(|| DownEdges::new(self));
+        let rank = self.ranks[node.0];
+        &down_edges.edge_ids[down_edges.offsets[rank]..down_edges.offsets[rank + 1]]
+    }
+
     pub fn begin_in_edges(&self, node: NodeId) -> usize {
-        self.first_edge_ids_bwd[self.ranks[node]]
+        self.first_edge_ids_bwd[self.ranks[node.0]]
     }
 
     pub fn end_in_edges(&self, node: NodeId) -> usize {
-        self.first_edge_ids_bwd[self.ranks[node] + 1]
+        self.first_edge_ids_bwd[self.ranks[node.0] + 1]
     }
 
     pub fn begin_out_edges(&self, node: NodeId) -> usize {
-        self.first_edge_ids_fwd[self.ranks[node]]
+        self.first_edge_ids_fwd[self.ranks[node.0]]
     }
 
     pub fn end_out_edges(&self, node: NodeId) -> usize {
-        self.first_edge_ids_fwd[self.ranks[node] + 1]
+        self.first_edge_ids_fwd[self.ranks[node.0] + 1]
+    }
+
+    /// Returns the weight of the (original or shortcut) edge from `from` to `to` stored in this
+    /// graph, or the smallest weight if there are multiple such edges, or `None` if there is no
+    /// such edge. Edges towards higher ranked nodes are out-edges of `from` and edges towards
+    /// lower ranked nodes are in-edges of `to`, so both are scanned, which takes time linear in
+    /// the degree of the two nodes. Panics if one of the nodes does not exist.
+    pub fn get_edge_weight(&self, from: NodeId, to: NodeId) -> Option<Weight> {
+        let out_edges = &self.edges_fwd[self.begin_out_edges(from)..self.end_out_edges(from)];
+        let in_edges = &self.edges_bwd[self.begin_in_edges(to)..self.end_in_edges(to)];
+        out_edges
+            .iter()
+            .filter(|e| e.adj_node == to)
+            .chain(in_edges.iter().filter(|e| e.adj_node == from))
+            .map(|e| e.weight)
+            .min()
+    }
+
+    /// Checks that this graph is internally consistent and returns an error describing the first
+    /// violation otherwise. This is meant as a tool for development, e.g. when trying out new
+    /// preparation parameters or modifying a prepared graph. The following is checked:
+    /// * the ranks are a permutation of the node ids
+    /// * the first edge ids of each rank start at zero, are sorted and end at the number of edges
+    /// * every edge is stored at the node with the corresponding rank and its adjacent node exists
+    /// * the replaced edges of every shortcut exist, form a path from the source to the target of
+    ///   the shortcut via a common center node and their weights add up to the shortcut weight
+    /// * the number of base edges of every edge is consistent with the replaced edges
+    /// * there are either no coordinates or one for each node
+    /// * there are either no rank explanations or one for each node
+    /// * the replaced edges are original edges between existing nodes
+    pub fn check_invariants(&self) -> Result<(), String> {
+        if self.ranks.len() != self.num_nodes {
+            return Err(format!(
+                "There are {} ranks, but {} nodes",
+                self.ranks.len(),
+                self.num_nodes
+            ));
+        }
+        let mut nodes_by_rank = vec![INVALID_NODE; self.num_nodes];
+        for (node, &rank) in self.ranks.iter().enumerate() {
+            if rank >= self.num_nodes {
+                return Err(format!("Node {} has the invalid rank {}", node, rank));
+            }
+            if nodes_by_rank[rank] != INVALID_NODE {
+                return Err(format!(
+                    "Nodes {} and {} both have rank {}",
+                    nodes_by_rank[rank], node, rank
+                ));
+            }
+            nodes_by_rank[rank] = NodeId(node);
+        }
+        for (first_edge_ids, edges, name) in [
+            (&self.first_edge_ids_fwd, &self.edges_fwd, "out"),
+            (&self.first_edge_ids_bwd, &self.edges_bwd, "in"),
+        ] {
+            if first_edge_ids.len() != self.num_nodes + 1
+                || first_edge_ids[0] != 0
+                || first_edge_ids[self.num_nodes] != edges.len()
+                || first_edge_ids.windows(2).any(|w| w[0] > w[1])
+            {
+                return Err(format!(
+                    "The first {}-edge ids must be sorted and range from 0 to {}",
+                    name,
+                    edges.len()
+                ));
+            }
+            for (rank, &node) in nodes_by_rank.iter().enumerate() {
+                let first_edge_id = first_edge_ids[rank];
+                let rank_edges = &edges[first_edge_id..first_edge_ids[rank + 1]];
+                for (edge_id, edge) in (first_edge_id..).zip(rank_edges) {
+                    if edge.base_node != node || edge.adj_node.0 >= self.num_nodes {
+                        return Err(format!(
+                            "The {}-edge {} from {} to {} is stored at node {}",
+                            name, edge_id, edge.base_node, edge.adj_node, node
+                        ));
+                    }
+                }
+            }
+        }
+        for (edge_id, edge) in self.edges_fwd.iter().enumerate() {
+            self.check_edge(edge, edge.base_node, edge.adj_node)
+                .map_err(|e| format!("Invalid out-edge {}: {}", edge_id, e))?;
+        }
+        for (edge_id, edge) in self.edges_bwd.iter().enumerate() {
+            // in-edges point from the adj node to the base node
+            self.check_edge(edge, edge.adj_node, edge.base_node)
+                .map_err(|e| format!("Invalid in-edge {}: {}", edge_id, e))?;
+        }
+        if !self.coordinates.is_empty() && self.coordinates.len() != self.num_nodes {
+            return Err(format!(
+                "There are {} coordinates, but {} nodes",
+                self.coordinates.len(),
+                self.num_nodes
+            ));
+        }
+        for (i, edge) in self.replaced_edges.iter().enumerate() {
+            if edge.replaced_in_edge != INVALID_EDGE
+                || edge.replaced_out_edge != INVALID_EDGE
+                || edge.base_edge_count != 1
+            {
+                return Err(format!("The replaced edge {} is not an original edge", i));
+            }
+            if edge.base_node.0 >= self.num_nodes || edge.adj_node.0 >= self.num_nodes {
+                return Err(format!(
+                    "The replaced edge {} from {} to {} has an invalid node",
+                    i, edge.base_node, edge.adj_node
+                ));
+            }
+        }
+        if !self.rank_explanations.is_empty() && self.rank_explanations.len() != self.num_nodes {
+            return Err(format!(
+                "There are {} rank explanations, but {} nodes",
+                self.rank_explanations.len(),
+                self.num_nodes
+            ));
+        }
+        Ok(())
+    }
+
+    /// Checks the replaced edges and the number of base edges of the given edge from `source` to
+    /// `target`, see `check_invariants`
+    fn check_edge(
+        &self,
+        edge: &FastGraphEdge,
+        source: NodeId,
+        target: NodeId,
+    ) -> Result<(), String> {
+        match (edge.replaced_in_edge, edge.replaced_out_edge) {
+            (INVALID_EDGE, INVALID_EDGE) => {
+                if edge.base_edge_count != 1 {
+                    return Err(format!(
+                        "it is an original edge, but has {} base edges",
+                        edge.base_edge_count
+                    ));
+                }
+                Ok(())
+            }
+            (INVALID_EDGE, _) | (_, INVALID_EDGE) => {
+                Err(String::from("only one of its replaced edges is set"))
+            }
+            (in_edge_id, out_edge_id) => {
+                let (Some(in_edge), Some(out_edge)) = (
+                    self.edges_bwd.get(in_edge_id),
+                    self.edges_fwd.get(out_edge_id),
+                ) else {
+                    return Err(String::from("its replaced edges do not exist"));
+                };
+                if in_edge.adj_node != source
+                    || out_edge.adj_node != target
+                    || in_edge.base_node != out_edge.base_node
+                {
+                    return Err(format!(
+                        "its replaced edges {} -> {} and {} -> {} do not form a path from {} to {}",
+                        in_edge.adj_node,
+                        in_edge.base_node,
+                        out_edge.base_node,
+                        out_edge.adj_node,
+                        source,
+                        target
+                    ));
+                }
+                if add_weights(in_edge.weight, out_edge.weight) != edge.weight {
+                    return Err(format!(
+                        "its weight is {}, but the weights of its replaced edges add up to {}",
+                        edge.weight,
+                        add_weights(in_edge.weight, out_edge.weight)
+                    ));
+                }
+                if in_edge.base_edge_count + out_edge.base_edge_count != edge.base_edge_count {
+                    return Err(format!(
+                        "it has {} base edges, but its replaced edges have {}",
+                        edge.base_edge_count,
+                        in_edge.base_edge_count + out_edge.base_edge_count
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    /// Compares this graph to another one that was prepared for the same input graph, e.g. using
+    /// different `Params`, and reports the edges that are contained in only one of the graphs and
+    /// the nodes whose rank differs. Edges are considered equal if they have the same direction,
+    /// adjacent nodes and weight and are either both shortcuts or both original edges.
+    pub fn diff(&self, other: &FastGraph) -> FastGraphDiff {
+        assert_eq!(
+            self.num_nodes, other.num_nodes,
+            "graphs must have the same number of nodes"
+        );
+        let mut edges = self.diff_edges();
+        let mut other_edges = other.diff_edges();
+        edges.sort();
+        other_edges.sort();
+        let mut diff = FastGraphDiff {
+            edges_only_in_self: vec![],
+            edges_only_in_other: vec![],
+            rank_changes: vec![],
+        };
+        let mut i = 0;
+        let mut j = 0;
+        while i < edges.len() || j < other_edges.len() {
+            if j == other_edges.len() || (i < edges.len() && edges[i] < other_edges[j]) {
+                diff.edges_only_in_self.push(edges[i].clone());
+                i += 1;
+            } else if i == edges.len() || other_edges[j] < edges[i] {
+                diff.edges_only_in_other.push(other_edges[j].clone());
+                j += 1;
+            } else {
+                i += 1;
+                j += 1;
+            }
+        }
+        for node in (0..self.num_nodes).map(NodeId) {
+            if self.ranks[node.0] != other.ranks[node.0] {
+                diff.rank_changes
+                    .push((node, self.ranks[node.0], other.ranks[node.0]));
+            }
+        }
+        diff
+    }
+
+    /// Returns all edges in their original direction, in-edges point from adj_node to base_node
+    fn diff_edges(&self) -> Vec<FastGraphDiffEdge> {
+        let fwd = self.edges_fwd.iter().map(|e| FastGraphDiffEdge {
+            from: e.base_node,
+            to: e.adj_node,
+            weight: e.weight,
+            shortcut: e.is_shortcut(),
+        });
+        let bwd = self.edges_bwd.iter().map(|e| FastGraphDiffEdge {
+            from: e.adj_node,
+            to: e.base_node,
+            weight: e.weight,
+            shortcut: e.is_shortcut(),
+        });
+        fwd.chain(bwd).collect()
+    }
+}
+
+/// The in-edges of a `FastGraph` grouped by the rank of their adjacent node, see
+/// `FastGraph::get_down_edges`
+#[cfg(feature = "std")]
+#[derive(Debug, Clone)]
+struct DownEdges {
+    offsets: Vec<usize>,
+    edge_ids: Vec<EdgeId>,
+}
+
+#[cfg(feature = "std")]
+impl DownEdges {
+    fn new(graph: &FastGraph) -> Self {
+        let mut offsets = vec![0; graph.num_nodes + 1];
+        for edge in &graph.edges_bwd {
+            offsets[graph.ranks[edge.adj_node.0] + 1] += 1;
+        }
+        for rank in 0..graph.num_nodes {
+            offsets[rank + 1] += offsets[rank];
+        }
+        let mut next = offsets.clone();
+        let mut edge_ids = vec![0; graph.edges_bwd.len()];
+        for (edge_id, edge) in graph.edges_bwd.iter().enumerate() {
+            let rank = graph.ranks[edge.adj_node.0];
+            edge_ids[next[rank]] = edge_id;
+            next[rank] += 1;
+        }
+        DownEdges { offsets, edge_ids }
+    }
+}
+
+/// Stands for `FastGraph::FORMAT_VERSION` in the serialized form of a `FastGraph` and fails
+/// deserialization if the serialized version does not match
+#[derive(Debug, Clone, Copy)]
+struct FormatVersion;
+
+impl Serialize for FormatVersion {
+    fn serialize<S: Serializer>(&self, s: S) -> Result<S::Ok, S::Error> {
+        s.serialize_u32(FastGraph::FORMAT_VERSION)
+    }
+}
+
+impl<'de> Deserialize<'de> for FormatVersion {
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        let version = u32::deserialize(d)?;
+        if version != FastGraph::FORMAT_VERSION {
+            return Err(D::Error::custom(format!(
+                "The FastGraph was serialized with format version {}, but this version of \
+                 fast_paths can only read format version {}. Please prepare the graph again.",
+                version,
+                FastGraph::FORMAT_VERSION
+            )));
+        }
+        Ok(FormatVersion)
+    }
+}
+
+/// Writes the given value as little-endian u64, where `usize::MAX` (used for `INVALID_EDGE` etc.)
+/// is written as `u64::MAX` on all platforms
+fn write_usize(buf: &mut Vec<u8>, value: usize) {
+    let value = if value == usize::MAX {
+        u64::MAX
+    } else {
+        value as u64
+    };
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// Reads the little-endian numbers written by `FastGraph::serialize_into_buf`. The size of the
+/// data must be checked before, so reading past its end panics.
+struct ByteReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl ByteReader<'_> {
+    fn read_u32(&mut self) -> u32 {
+        let mut buf = [0; 4];
+        buf.copy_from_slice(&self.data[self.pos..self.pos + 4]);
+        self.pos += 4;
+        u32::from_le_bytes(buf)
+    }
+
+    fn read_u64(&mut self) -> u64 {
+        let mut buf = [0; 8];
+        buf.copy_from_slice(&self.data[self.pos..self.pos + 8]);
+        self.pos += 8;
+        u64::from_le_bytes(buf)
+    }
+
+    fn read_usize(&mut self) -> usize {
+        let value = self.read_u64();
+        // see `write_usize`
+        if value == u64::MAX {
+            usize::MAX
+        } else {
+            value as usize
+        }
+    }
+
+    fn read_f64(&mut self) -> f64 {
+        f64::from_bits(self.read_u64())
+    }
+
+    fn read_edge(&mut self) -> FastGraphEdge {
+        FastGraphEdge {
+            base_node: NodeId(self.read_usize()),
+            adj_node: NodeId(self.read_usize()),
+            weight: Weight(self.read_usize()),
+            length: self.read_f64(),
+            replaced_in_edge: self.read_usize(),
+            replaced_out_edge: self.read_usize(),
+            data: self.read_u64(),
+            base_edge_count: self.read_usize(),
+        }
     }
 }
 
+/// The values the contraction priority of a node was calculated from, see
+/// `FastGraph::explain_rank`. Nodes with smaller priorities are contracted first and thus get
+/// lower ranks. The relevance is `1000 * (d * level + (q * num_shortcuts + 1) / (num_edges + 1))`
+/// where `d` and `q` are `Params::hierarchy_depth_factor` and `Params::edge_quotient_factor`, so
+/// nodes whose contraction would add many shortcuts compared to their number of edges (the edge
+/// quotient, our variant of the edge difference) and nodes on top of many contracted nodes end up
+/// higher in the hierarchy.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct RankExplanation {
+    /// the number of shortcuts the contraction of the node would have added
+    pub num_shortcuts: usize,
+    /// the number of in- and out-edges (including shortcuts) of the node
+    pub num_edges: usize,
+    /// the level of the node in the hierarchy, which is one more than the largest level of its
+    /// contracted neighbors, or zero if none of its neighbors was contracted before it
+    pub level: usize,
+    /// the relevance calculated from the values above
+    pub relevance: f32,
+    /// the priority added to the relevance for `OrderStrategy::SampledQueryCost` and
+    /// `Params::spatial_grid_size`, zero otherwise
+    pub bias: usize,
+    /// the priority of the node when it was contracted, i.e. the relevance (rounded down) plus
+    /// the bias
+    pub priority: usize,
+}
+
+/// The differences between two `FastGraph`s, see `FastGraph::diff`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FastGraphDiff {
+    pub edges_only_in_self: Vec<FastGraphDiffEdge>,
+    pub edges_only_in_other: Vec<FastGraphDiffEdge>,
+    /// the nodes whose rank differs along with their rank in self and in other
+    pub rank_changes: Vec<(NodeId, usize, usize)>,
+}
+
+impl FastGraphDiff {
+    pub fn is_empty(&self) -> bool {
+        self.edges_only_in_self.is_empty()
+            && self.edges_only_in_other.is_empty()
+            && self.rank_changes.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct FastGraphDiffEdge {
+    pub from: NodeId,
+    pub to: NodeId,
+    pub weight: Weight,
+    pub shortcut: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct FastGraphEdge {
     // todo: the base_node is 'redundant' for the routing query so to say, but makes the implementation easier for now
@@ -94,6 +1305,11 @@ pub struct FastGraphEdge {
     pub length: Length,
     pub replaced_in_edge: EdgeId,
     pub replaced_out_edge: EdgeId,
+    /// the user data of the original edge, `INVALID_EDGE_DATA` for shortcuts
+    pub data: EdgeData,
+    /// the number of original edges this edge is unpacked into, i.e. 1 for original edges. This
+    /// is set by `FastGraphBuilder` and is 1 for edges created using `new` or `new_with_data`.
+    pub base_edge_count: usize,
 }
 
 impl FastGraphEdge {
@@ -104,6 +1320,26 @@ impl FastGraphEdge {
         length: Length,
         replaced_edge1: EdgeId,
         replaced_edge2: EdgeId,
+    ) -> Self {
+        FastGraphEdge::new_with_data(
+            base_node,
+            adj_node,
+            weight,
+            length,
+            replaced_edge1,
+            replaced_edge2,
+            INVALID_EDGE_DATA,
+        )
+    }
+
+    pub fn new_with_data(
+        base_node: NodeId,
+        adj_node: NodeId,
+        weight: Weight,
+        length: Length,
+        replaced_edge1: EdgeId,
+        replaced_edge2: EdgeId,
+        data: EdgeData,
     ) -> Self {
         FastGraphEdge {
             base_node,
@@ -112,9 +1348,22 @@ impl FastGraphEdge {
             length,
             replaced_in_edge: replaced_edge1,
             replaced_out_edge: replaced_edge2,
+            data,
+            base_edge_count: 1,
         }
     }
 
+    /// Returns a copy of this edge where the ids of the replaced edges are translated using the
+    /// given mappings from old to new out-edge and in-edge ids
+    fn with_new_ids(&self, new_fwd_ids: &[EdgeId], new_bwd_ids: &[EdgeId]) -> Self {
+        let mut edge = self.clone();
+        if edge.is_shortcut() {
+            edge.replaced_in_edge = new_bwd_ids[edge.replaced_in_edge];
+            edge.replaced_out_edge = new_fwd_ids[edge.replaced_out_edge];
+        }
+        edge
+    }
+
     pub fn is_shortcut(&self) -> bool {
         assert!(
             (self.replaced_in_edge == INVALID_EDGE && self.replaced_out_edge == INVALID_EDGE)
@@ -124,3 +1373,793 @@ impl FastGraphEdge {
         self.replaced_in_edge != INVALID_EDGE
     }
 }
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::constants::WEIGHT_MAX;
+    use crate::input_graph::InputGraph;
+    use crate::path_calculator::PathCalculator;
+    use crate::{calc_path, prepare_with_order, prepare_with_params, Params};
+
+    use super::*;
+
+    #[test]
+    fn diff() {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        g.freeze();
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        assert!(fast_graph.diff(&fast_graph.clone()).is_empty());
+
+        let other = prepare_with_order(&g, &[NodeId(0), NodeId(1), NodeId(2)]).unwrap();
+        let diff = fast_graph.diff(&other);
+        // contracting node 1 first yields the shortcut 0 -> 2
+        assert_eq!(
+            vec![FastGraphDiffEdge {
+                from: NodeId(0),
+                to: NodeId(2),
+                weight: Weight(7),
+                shortcut: true,
+            }],
+            diff.edges_only_in_self
+        );
+        assert!(diff.edges_only_in_other.is_empty());
+        assert_eq!(
+            vec![(NodeId(0), 1, 0), (NodeId(1), 0, 1)],
+            diff.rank_changes
+        );
+
+        let reverse_diff = other.diff(&fast_graph);
+        assert_eq!(diff.edges_only_in_self, reverse_diff.edges_only_in_other);
+        assert!(reverse_diff.edges_only_in_self.is_empty());
+    }
+
+    #[test]
+    fn num_original_and_shortcut_edges() {
+        // 0 -> 1 -> 2 -> 3
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.freeze();
+        // contracting node 1 and 2 yields the shortcuts 0->2 (stored as in-edge of node 2) and 0->3
+        // (stored as out-edge of node 0)
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(1), NodeId(2), NodeId(0), NodeId(3)]).unwrap();
+        assert_eq!(1, fast_graph.get_num_shortcut_out_edges());
+        assert_eq!(1, fast_graph.get_num_shortcut_in_edges());
+        assert_eq!(
+            fast_graph.get_num_out_edges(),
+            fast_graph.get_num_original_out_edges() + fast_graph.get_num_shortcut_out_edges()
+        );
+        assert_eq!(
+            fast_graph.get_num_in_edges(),
+            fast_graph.get_num_original_in_edges() + fast_graph.get_num_shortcut_in_edges()
+        );
+        assert_eq!(
+            3,
+            fast_graph.get_num_original_out_edges() + fast_graph.get_num_original_in_edges()
+        );
+    }
+
+    #[test]
+    fn nearest_node() {
+        let mut fast_graph = FastGraph::new(3);
+        assert_eq!(None, fast_graph.nearest_node(52.0, 13.0));
+        assert!(fast_graph.set_coordinates(vec![(52.0, 13.0)]).is_err());
+        fast_graph
+            .set_coordinates(vec![(52.0, 13.0), (52.1, 13.1), (52.2, 13.2)])
+            .unwrap();
+        assert_eq!(Some(NodeId(1)), fast_graph.nearest_node(52.09, 13.12));
+        // the index is rebuilt when the coordinates change
+        fast_graph
+            .set_coordinates(vec![(52.0, 13.0), (53.1, 13.1), (52.2, 13.2)])
+            .unwrap();
+        assert_eq!(Some(NodeId(0)), fast_graph.nearest_node(52.09, 13.12));
+    }
+
+    #[test]
+    #[should_panic]
+    fn diff_with_different_node_count() {
+        FastGraph::new(3).diff(&FastGraph::new(4));
+    }
+
+    #[test]
+    fn csr_arrays() {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        g.freeze();
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        // node 1 has rank 0, so its edges come first
+        assert_eq!(&[0, 1, 2, 2], fast_graph.out_edge_offsets());
+        let targets: Vec<NodeId> = fast_graph.edges_fwd.iter().map(|e| e.adj_node).collect();
+        assert_eq!(&[NodeId(2), NodeId(2)], targets.as_slice());
+        assert_eq!(&[0, 1, 1, 1], fast_graph.in_edge_offsets());
+        assert_eq!(NodeId(0), fast_graph.edges_bwd[0].adj_node);
+        for node in 0..fast_graph.get_num_nodes() {
+            let rank = fast_graph.ranks[node];
+            assert_eq!(
+                fast_graph.begin_out_edges(NodeId(node)),
+                fast_graph.out_edge_offsets()[rank]
+            );
+            assert_eq!(
+                fast_graph.end_out_edges(NodeId(node)),
+                fast_graph.out_edge_offsets()[rank + 1]
+            );
+        }
+    }
+
+    #[test]
+    fn base_edge_count() {
+        // 0 -> 1 -> 2 -> 3
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.freeze();
+        // contracting node 1 and 2 yields the shortcuts 0->2 (in-edge of node 2) and 0->3
+        // (out-edge of node 0)
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(1), NodeId(2), NodeId(0), NodeId(3)]).unwrap();
+        let num_out = fast_graph.get_num_out_edges();
+        for (i, edge) in fast_graph.edges_fwd.iter().enumerate() {
+            let mut nodes = vec![];
+            PathCalculator::unpack_fwd(&fast_graph, &mut nodes, i, false);
+            assert_eq!(nodes.len(), edge.base_edge_count);
+            assert_eq!(nodes.len(), fast_graph.shortcut_base_edge_count(i));
+        }
+        for (i, edge) in fast_graph.edges_bwd.iter().enumerate() {
+            let mut nodes = vec![];
+            PathCalculator::unpack_bwd(&fast_graph, &mut nodes, i, false);
+            assert_eq!(nodes.len(), edge.base_edge_count);
+            assert_eq!(
+                nodes.len(),
+                fast_graph.shortcut_base_edge_count(num_out + i)
+            );
+        }
+        let counts = |edges: &[FastGraphEdge]| -> Vec<(NodeId, NodeId, usize)> {
+            edges
+                .iter()
+                .filter(|e| e.is_shortcut())
+                .map(|e| (e.base_node, e.adj_node, e.base_edge_count))
+                .collect()
+        };
+        assert_eq!(
+            vec![(NodeId(0), NodeId(3), 3)],
+            counts(&fast_graph.edges_fwd)
+        );
+        assert_eq!(
+            vec![(NodeId(2), NodeId(0), 2)],
+            counts(&fast_graph.edges_bwd)
+        );
+        // the counts survive the conversion to the 32bit representation
+        let converted = crate::fast_graph32::FastGraph32::new(&fast_graph).convert_to_usize();
+        assert_eq!(
+            vec![(NodeId(0), NodeId(3), 3)],
+            counts(&converted.edges_fwd)
+        );
+    }
+
+    #[test]
+    fn get_edge_weight() {
+        // 0 -> 1 -> 2 -> 3
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(4), 1.0);
+        g.freeze();
+        // contracting node 1 and 2 yields the shortcuts 0->2 (in-edge of node 2) and 0->3
+        // (out-edge of node 0)
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(1), NodeId(2), NodeId(0), NodeId(3)]).unwrap();
+        let weight = |from, to| fast_graph.get_edge_weight(NodeId(from), NodeId(to));
+        // original edges, stored as in-edges of the target
+        assert_eq!(Some(Weight(1)), weight(0, 1));
+        assert_eq!(Some(Weight(2)), weight(1, 2));
+        // original edge, stored as out-edge of the source
+        assert_eq!(Some(Weight(4)), weight(2, 3));
+        // shortcuts
+        assert_eq!(Some(Weight(3)), weight(0, 2));
+        assert_eq!(Some(Weight(7)), weight(0, 3));
+        // there are no edges in the opposite directions or between other nodes
+        assert_eq!(None, weight(1, 0));
+        assert_eq!(None, weight(3, 0));
+        assert_eq!(None, weight(1, 3));
+        assert_eq!(None, weight(0, 0));
+    }
+
+    #[test]
+    fn get_edge_weight_between_core_nodes() {
+        // 0 -> 1 -> 2, nodes 1 and 2 are not contracted, so the edge between them is stored both
+        // as out-edge of node 1 and as in-edge of node 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(5), 1.0);
+        g.freeze();
+        let fast_graph =
+            prepare_with_params(&g, &Params::with_core_nodes(vec![NodeId(1), NodeId(2)]));
+        let weight = |from, to| fast_graph.get_edge_weight(NodeId(from), NodeId(to));
+        assert_eq!(Some(Weight(3)), weight(0, 1));
+        assert_eq!(Some(Weight(5)), weight(1, 2));
+        assert_eq!(None, weight(2, 1));
+        assert_eq!(None, weight(0, 2));
+    }
+
+    #[test]
+    fn add_node_and_edges() {
+        // 0 -> 1 -> 2, the new node 3 is connected via 0 -> 3 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.freeze();
+        // node 0 has the highest rank, so reaching node 3 from node 1 requires the shortcut 1->3
+        let mut fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(2), NodeId(0)]).unwrap();
+        fast_graph.set_coordinates(vec![(0.0, 0.0); 3]).unwrap();
+        assert_eq!(
+            Err(String::from(
+                "Every edge must connect the new node 3 with an existing node, but there is an \
+                 edge from 0 to 1"
+            )),
+            fast_graph.add_node_and_edges(&[(NodeId(0), NodeId(1), Weight(1))])
+        );
+        assert!(fast_graph
+            .add_node_and_edges(&[(NodeId(3), NodeId(3), Weight(1))])
+            .is_err());
+        assert!(fast_graph
+            .add_node_and_edges(&[(NodeId(0), NodeId(4), Weight(1))])
+            .is_err());
+        assert!(fast_graph
+            .add_node_and_edges(&[(NodeId(0), NodeId(3), WEIGHT_MAX)])
+            .is_err());
+        assert_eq!(3, fast_graph.get_num_nodes());
+
+        assert_eq!(
+            Ok(NodeId(3)),
+            fast_graph.add_node_and_edges(&[
+                (NodeId(0), NodeId(3), Weight(5)),
+                (NodeId(3), NodeId(2), Weight(5)),
+                (NodeId(2), NodeId(3), Weight(4))
+            ])
+        );
+        assert_eq!(4, fast_graph.get_num_nodes());
+        assert_eq!(3, fast_graph.ranks[3]);
+        assert!(fast_graph.get_coordinates().is_empty());
+        let mut calc = PathCalculator::new(4);
+        let path = calc.calc_path(&fast_graph, NodeId(1), NodeId(3)).unwrap();
+        assert_eq!(&vec![NodeId(1), NodeId(2), NodeId(3)], path.get_nodes());
+        assert_eq!(Weight(5), path.get_weight());
+        let path = calc.calc_path(&fast_graph, NodeId(3), NodeId(1));
+        assert!(path.is_none());
+        let path = calc.calc_path(&fast_graph, NodeId(0), NodeId(2)).unwrap();
+        assert_eq!(&vec![NodeId(0), NodeId(1), NodeId(2)], path.get_nodes());
+        for (i, edge) in fast_graph.edges_fwd.iter().enumerate() {
+            let mut nodes = vec![];
+            PathCalculator::unpack_fwd(&fast_graph, &mut nodes, i, false);
+            assert_eq!(nodes.len(), edge.base_edge_count);
+        }
+    }
+
+    #[test]
+    fn add_node_and_edges_with_replaced_edges() {
+        // 0 -> 1 -> 2, the new node 3 is connected to node 1 via cheap edges, so the shortcuts
+        // 0->1->3 and 3->1->2 replace the (more expensive) direct edges 0->3 and 3->2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.freeze();
+        let mut fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(2), NodeId(0)]).unwrap();
+        let new_edges = [
+            (NodeId(0), NodeId(3), Weight(5)),
+            (NodeId(1), NodeId(3), Weight(1)),
+            (NodeId(3), NodeId(1), Weight(1)),
+            (NodeId(3), NodeId(2), Weight(5)),
+        ];
+        assert_eq!(Ok(NodeId(3)), fast_graph.add_node_and_edges(&new_edges));
+        fast_graph.check_invariants().unwrap();
+        let replaced: Vec<(NodeId, NodeId, Weight)> = fast_graph
+            .get_replaced_edges()
+            .iter()
+            .map(|e| (e.base_node, e.adj_node, e.weight))
+            .collect();
+        assert_eq!(
+            vec![
+                (NodeId(0), NodeId(3), Weight(5)),
+                (NodeId(3), NodeId(2), Weight(5))
+            ],
+            replaced
+        );
+
+        let mut extended_graph = InputGraph::new();
+        extended_graph.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        extended_graph.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        for &(from, to, weight) in &new_edges {
+            extended_graph.add_edge(from, to, weight, weight.0 as Length);
+        }
+        extended_graph.freeze();
+        assert_eq!(
+            extended_graph.unit_test_output_string(),
+            fast_graph.to_input_graph().unit_test_output_string()
+        );
+        let original_graph = PreparationGraph::from_input_graph(&extended_graph);
+        let mut dijkstra = crate::dijkstra::Dijkstra::new(4);
+        let mut calc = PathCalculator::new(4);
+        for &(source, target, weight) in &new_edges {
+            let path = calc.calc_path_max_hops(&fast_graph, source, target, 1);
+            assert_eq!(Some(weight), path.map(|p| p.get_weight()));
+            // the direct edges are not needed without the hop limit
+            let expected = dijkstra.calc_path(&original_graph, source, target);
+            let path = calc.calc_path_max_hops(&fast_graph, source, target, 3);
+            assert_eq!(
+                expected.map(|p| p.get_weight()),
+                path.map(|p| p.get_weight())
+            );
+        }
+    }
+
+    #[test]
+    fn highest_ranked_on_path() {
+        // 0 -> 1 -> 2 -> 3 -> 4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(0), NodeId(4), NodeId(1), NodeId(3), NodeId(2)])
+                .unwrap();
+        assert_eq!(
+            Some((NodeId(2), 4)),
+            fast_graph.highest_ranked_on_path(NodeId(0), NodeId(4))
+        );
+        assert_eq!(
+            Some((NodeId(3), 3)),
+            fast_graph.highest_ranked_on_path(NodeId(3), NodeId(4))
+        );
+        assert_eq!(
+            Some((NodeId(1), 2)),
+            fast_graph.highest_ranked_on_path(NodeId(0), NodeId(1))
+        );
+        assert_eq!(
+            Some((NodeId(0), 0)),
+            fast_graph.highest_ranked_on_path(NodeId(0), NodeId(0))
+        );
+        assert_eq!(
+            None,
+            fast_graph.highest_ranked_on_path(NodeId(4), NodeId(0))
+        );
+    }
+
+    #[test]
+    fn save_and_load_ordering() {
+        // 0 -> 1 -> 2 -> 3
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 2.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 3.0);
+        g.freeze();
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(2), NodeId(0), NodeId(3), NodeId(1)]).unwrap();
+        let file_name = "save_and_load_ordering.fpno";
+        fast_graph.save_ordering(file_name).unwrap();
+        let ordering = FastGraph::load_ordering(file_name);
+        let bytes = std::fs::read(file_name).unwrap();
+        std::fs::remove_file(file_name).unwrap();
+        assert_eq!(
+            vec![NodeId(2), NodeId(0), NodeId(3), NodeId(1)],
+            ordering.unwrap()
+        );
+        assert_eq!(4 + 8 + 4 * 8, bytes.len());
+
+        let load = |bytes: &[u8]| {
+            let file_name = "save_and_load_ordering_invalid.fpno";
+            std::fs::write(file_name, bytes).unwrap();
+            let result = FastGraph::load_ordering(file_name);
+            std::fs::remove_file(file_name).unwrap();
+            result.map_err(|e| e.kind())
+        };
+        assert_eq!(
+            Ok(vec![NodeId(2), NodeId(0), NodeId(3), NodeId(1)]),
+            load(&bytes)
+        );
+        // a missing node id
+        assert_eq!(
+            Err(io::ErrorKind::InvalidData),
+            load(&bytes[..bytes.len() - 8])
+        );
+        // an invalid node id
+        let mut invalid = bytes.clone();
+        invalid[12] = 4;
+        assert_eq!(Err(io::ErrorKind::InvalidData), load(&invalid));
+        // not an ordering file at all
+        let mut invalid = bytes.clone();
+        invalid[0] = b'X';
+        assert_eq!(Err(io::ErrorKind::InvalidData), load(&invalid));
+        assert_eq!(Err(io::ErrorKind::UnexpectedEof), load(&bytes[..6]));
+    }
+
+    #[test]
+    fn load_streaming() {
+        // 0 -> 1 -> 2, 0 -> 2 is replaced by a shortcut
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(1), 1.5, 3);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(5), 5.0);
+        g.freeze();
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let bytes = bincode::serialize(&crate::FastGraph32::new(&fast_graph)).unwrap();
+        let loaded = FastGraph::load_streaming(io::Cursor::new(&bytes)).unwrap();
+        assert!(fast_graph.diff(&loaded).is_empty());
+        assert_eq!(fast_graph.ranks, loaded.ranks);
+        assert_eq!(1, loaded.get_replaced_edges().len());
+        assert_eq!(Weight(5), loaded.get_replaced_edges()[0].weight);
+        let path = calc_path(&loaded, NodeId(0), NodeId(2)).unwrap();
+        assert_eq!(&vec![NodeId(0), NodeId(1), NodeId(2)], path.get_nodes());
+
+        // reading from a file
+        let file_name = "load_streaming.fp32";
+        let file = BufWriter::new(File::create(file_name).unwrap());
+        bincode::serialize_into(file, &crate::FastGraph32::new(&fast_graph)).unwrap();
+        let loaded = FastGraph::load_streaming(BufReader::new(File::open(file_name).unwrap()));
+        std::fs::remove_file(file_name).unwrap();
+        assert!(fast_graph.diff(&loaded.unwrap()).is_empty());
+
+        // truncated data
+        let result = FastGraph::load_streaming(&bytes[..bytes.len() / 2]);
+        assert_eq!(io::ErrorKind::UnexpectedEof, result.unwrap_err().kind());
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn save_and_load_compressed() {
+        // 0 -> 1 -> 2, 0 -> 2 is replaced by a shortcut
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(1), 1.5, 3);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(5), 5.0);
+        g.freeze();
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let file_name = "save_and_load_compressed.fp.zst";
+        fast_graph.save_compressed(file_name, 19).unwrap();
+        let loaded = FastGraph::load_compressed(file_name);
+        let bytes = std::fs::read(file_name).unwrap();
+        std::fs::remove_file(file_name).unwrap();
+        let loaded = loaded.unwrap();
+        assert!(fast_graph.diff(&loaded).is_empty());
+        assert_eq!(fast_graph.ranks, loaded.ranks);
+        assert_eq!(1, loaded.get_replaced_edges().len());
+        assert!(bytes.len() < bincode::serialize(&fast_graph).unwrap().len());
+
+        let load = |bytes: &[u8]| {
+            let file_name = "save_and_load_compressed_invalid.fp.zst";
+            std::fs::write(file_name, bytes).unwrap();
+            let result = FastGraph::load_compressed(file_name);
+            std::fs::remove_file(file_name).unwrap();
+            result.map(|_| ()).map_err(|e| e.kind())
+        };
+        assert_eq!(Ok(()), load(&bytes));
+        // an uncompressed graph
+        let uncompressed = bincode::serialize(&fast_graph).unwrap();
+        assert!(load(&uncompressed).is_err());
+        // a compressed graph with another format version
+        let mut other_version = uncompressed;
+        other_version[0..4].copy_from_slice(&(FastGraph::FORMAT_VERSION + 1).to_le_bytes());
+        let compressed = zstd::encode_all(other_version.as_slice(), 3).unwrap();
+        assert_eq!(Err(io::ErrorKind::InvalidData), load(&compressed));
+    }
+
+    #[test]
+    fn check_invariants() {
+        // 0 -> 1 -> 2 -> 3, nodes 1 and 2 are contracted first, so there are shortcuts
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 2.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 3.0);
+        g.freeze();
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(1), NodeId(2), NodeId(0), NodeId(3)]).unwrap();
+        assert_eq!(Ok(()), fast_graph.check_invariants());
+        assert_eq!(Ok(()), fast_graph.reorder_by_rank().0.check_invariants());
+        assert_eq!(Ok(()), FastGraph::new(0).check_invariants());
+        let shortcut = (0..fast_graph.get_num_out_edges())
+            .find(|&i| fast_graph.edges_fwd[i].is_shortcut())
+            .unwrap();
+
+        let mut graph = fast_graph.clone();
+        graph.ranks[0] = 1;
+        assert_eq!(
+            Err(String::from("Nodes 0 and 2 both have rank 1")),
+            graph.check_invariants()
+        );
+        let mut graph = fast_graph.clone();
+        graph.first_edge_ids_fwd[1] = 100;
+        assert!(graph.check_invariants().is_err());
+        let mut graph = fast_graph.clone();
+        graph.edges_fwd[0].adj_node = NodeId(4);
+        assert!(graph.check_invariants().is_err());
+        let mut graph = fast_graph.clone();
+        graph.edges_fwd[shortcut].weight += Weight(1);
+        assert!(graph
+            .check_invariants()
+            .unwrap_err()
+            .contains("weights of its replaced edges add up to"));
+        let mut graph = fast_graph.clone();
+        graph.edges_fwd[shortcut].replaced_out_edge = 100;
+        assert!(graph.check_invariants().is_err());
+        let mut graph = fast_graph.clone();
+        graph.edges_fwd[shortcut].replaced_in_edge = INVALID_EDGE;
+        assert!(graph.check_invariants().is_err());
+        let mut graph = fast_graph.clone();
+        graph.edges_fwd[shortcut].base_edge_count = 1;
+        assert!(graph.check_invariants().is_err());
+        let mut graph = fast_graph;
+        graph.coordinates = vec![(0.0, 0.0)];
+        assert!(graph.check_invariants().is_err());
+    }
+
+    #[test]
+    fn to_preparation_graph() {
+        // 0 -> 1 -> 2
+        //  \-------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(5), 5.0);
+        g.freeze();
+        // the edge 0->2 is replaced by a shortcut, but it is restored nonetheless
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let graph = fast_graph.to_preparation_graph();
+        let out_edges = |node| -> Vec<(NodeId, Weight)> {
+            let mut edges: Vec<(NodeId, Weight)> = graph
+                .get_out_edges(NodeId(node))
+                .iter()
+                .map(|a| (a.adj_node, a.weight))
+                .collect();
+            edges.sort();
+            edges
+        };
+        assert_eq!(
+            vec![(NodeId(1), Weight(1)), (NodeId(2), Weight(5))],
+            out_edges(0)
+        );
+        assert_eq!(vec![(NodeId(2), Weight(1))], out_edges(1));
+        assert!(out_edges(2).is_empty());
+        assert_eq!(2, graph.get_in_edges(NodeId(2)).len());
+    }
+
+    #[test]
+    fn to_input_graph() {
+        // 0 -> 1 -> 2 -> 3 <-> 4, nodes 3 and 4 are core nodes
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(1), 1.5, 10);
+        g.add_edge_with_data(NodeId(1), NodeId(2), Weight(2), 2.5, 11);
+        g.add_edge_with_data(NodeId(2), NodeId(3), Weight(3), 3.5, 12);
+        g.add_edge_with_data(NodeId(3), NodeId(4), Weight(4), 4.5, 13);
+        g.add_edge_with_data(NodeId(4), NodeId(3), Weight(5), 5.5, 14);
+        g.set_weight_scale(10.0);
+        g.freeze();
+        let fast_graph = crate::prepare_with_params(
+            &g,
+            &crate::Params::with_core_nodes(vec![NodeId(3), NodeId(4)]),
+        );
+        let input_graph = fast_graph.to_input_graph();
+        assert_eq!(5, input_graph.get_num_nodes());
+        assert_eq!(10.0, input_graph.get_weight_scale());
+        let to_tuples = |g: &InputGraph| -> Vec<(NodeId, NodeId, Weight, Length, EdgeData)> {
+            g.get_edges()
+                .iter()
+                .map(|e| (e.from, e.to, e.weight, e.length, e.data))
+                .collect()
+        };
+        assert_eq!(to_tuples(&g), to_tuples(&input_graph));
+        // nodes without edges are kept as well
+        let g = InputGraph::from_adjacency_list(&[vec![(NodeId(1), Weight(1))], vec![], vec![]]);
+        let input_graph = crate::prepare(&g).to_input_graph();
+        assert_eq!(3, input_graph.get_num_nodes());
+        assert_eq!(1, input_graph.get_num_edges());
+        // edges that were replaced by a shortcut are restored, so preparing the graph again gives
+        // the same results
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(1), 1.5, 10);
+        g.add_edge_with_data(NodeId(1), NodeId(2), Weight(1), 2.5, 11);
+        g.add_edge_with_data(NodeId(0), NodeId(2), Weight(5), 5.5, 12);
+        g.freeze();
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        assert_eq!(1, fast_graph.get_replaced_edges().len());
+        let input_graph = fast_graph.to_input_graph();
+        assert_eq!(to_tuples(&g), to_tuples(&input_graph));
+        let fast_graph =
+            prepare_with_order(&input_graph, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let mut calc = crate::PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc.calc_path_max_hops(&fast_graph, NodeId(0), NodeId(2), 1);
+        assert_eq!(Weight(5), path.unwrap().get_weight());
+    }
+
+    #[test]
+    fn reorder_by_rank() {
+        // 0 -> 1 -> 2 -> 3
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 2.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 3.0);
+        g.freeze();
+        let mut fast_graph =
+            prepare_with_order(&g, &[NodeId(2), NodeId(0), NodeId(3), NodeId(1)]).unwrap();
+        fast_graph
+            .set_coordinates(vec![(0.0, 0.0), (1.0, 1.0), (2.0, 2.0), (3.0, 3.0)])
+            .unwrap();
+        let (reordered, mapping) = fast_graph.reorder_by_rank();
+        assert_eq!(vec![NodeId(2), NodeId(0), NodeId(3), NodeId(1)], mapping);
+        assert_eq!(vec![0, 1, 2, 3], reordered.ranks);
+        assert_eq!(
+            &vec![(2.0, 2.0), (0.0, 0.0), (3.0, 3.0), (1.0, 1.0)],
+            reordered.get_coordinates()
+        );
+        assert!(reordered.get_num_shortcut_out_edges() + reordered.get_num_shortcut_in_edges() > 0);
+        let mut calc = PathCalculator::new(reordered.get_num_nodes());
+        for source in 0..4 {
+            for target in 0..4 {
+                let expected = calc.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                let path = calc
+                    .calc_path(
+                        &reordered,
+                        NodeId(fast_graph.ranks[source]),
+                        NodeId(fast_graph.ranks[target]),
+                    )
+                    .map(|p| {
+                        let nodes: Vec<NodeId> =
+                            p.get_nodes().iter().map(|&n| mapping[n.0]).collect();
+                        (p.get_weight(), nodes)
+                    });
+                assert_eq!(
+                    expected.map(|p| (p.get_weight(), p.get_nodes().clone())),
+                    path
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn replaced_edges() {
+        // 0 -> 1 -> 2
+        //  \-------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge_with_data(NodeId(0), NodeId(2), Weight(5), 5.5, 7);
+        g.freeze();
+        // contracting node 1 first replaces the edge 0->2 with the shortcut 0->1->2
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let replaced = |fast_graph: &FastGraph| -> Vec<(NodeId, NodeId, Weight, f64, u64)> {
+            fast_graph
+                .get_replaced_edges()
+                .iter()
+                .map(|e| (e.base_node, e.adj_node, e.weight, e.length, e.data))
+                .collect()
+        };
+        let expected = vec![(NodeId(0), NodeId(2), Weight(5), 5.5, 7)];
+        assert_eq!(expected, replaced(&fast_graph));
+        assert!(!fast_graph.get_replaced_edges()[0].is_shortcut());
+        fast_graph.check_invariants().unwrap();
+        // the edges are kept when the graph is serialized, converted or reordered
+        let deserialized: FastGraph =
+            bincode::deserialize(&bincode::serialize(&fast_graph).unwrap()).unwrap();
+        assert_eq!(expected, replaced(&deserialized));
+        let mut buf = vec![];
+        fast_graph.serialize_into_buf(&mut buf);
+        assert_eq!(expected, replaced(&FastGraph::from_bytes(&buf).unwrap()));
+        let converted = crate::fast_graph32::FastGraph32::new(&fast_graph).convert_to_usize();
+        assert_eq!(expected, replaced(&converted));
+        let (reordered, mapping) = fast_graph.reorder_by_rank();
+        assert_eq!(vec![NodeId(1), NodeId(0), NodeId(2)], mapping);
+        assert_eq!(
+            vec![(NodeId(1), NodeId(2), Weight(5), 5.5, 7)],
+            replaced(&reordered)
+        );
+        // no edges are replaced if the edge 0->2 is kept
+        let fast_graph = prepare_with_order(&g, &[NodeId(0), NodeId(1), NodeId(2)]).unwrap();
+        assert!(fast_graph.get_replaced_edges().is_empty());
+    }
+
+    #[test]
+    fn serialize_into_buf_and_from_bytes() {
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(3), 3.0, 7);
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.5);
+        g.add_edge(NodeId(2), NodeId(0), Weight(1), 1.0);
+        g.set_weight_scale(2.0);
+        g.freeze();
+        let mut fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        fast_graph
+            .set_coordinates(vec![(52.0, 13.0), (52.1, 13.1), (52.2, 13.2)])
+            .unwrap();
+        // the graph is appended to the existing content of the buffer
+        let mut buf = vec![1, 2, 3];
+        fast_graph.serialize_into_buf(&mut buf);
+        assert_eq!(&FastGraph::BYTES_MAGIC, &buf[3..7]);
+        let deserialized = FastGraph::from_bytes(&buf[3..]).unwrap();
+        assert!(fast_graph.diff(&deserialized).is_empty());
+        assert_eq!(fast_graph.get_coordinates(), deserialized.get_coordinates());
+        assert_eq!(2.0, deserialized.get_weight_scale());
+        assert_eq!(fast_graph.edges_fwd.len(), deserialized.edges_fwd.len());
+        for (e1, e2) in fast_graph.edges_fwd.iter().zip(&deserialized.edges_fwd) {
+            assert_eq!(e1.length, e2.length);
+            assert_eq!(e1.data, e2.data);
+            assert_eq!(e1.base_edge_count, e2.base_edge_count);
+        }
+        assert_eq!(
+            calc_path(&fast_graph, NodeId(0), NodeId(2)),
+            calc_path(&deserialized, NodeId(0), NodeId(2))
+        );
+
+        let mut empty_buf = vec![];
+        FastGraph::new(0).serialize_into_buf(&mut empty_buf);
+        assert_eq!(
+            0,
+            FastGraph::from_bytes(&empty_buf).unwrap().get_num_nodes()
+        );
+    }
+
+    #[test]
+    fn from_bytes_with_invalid_data() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        g.freeze();
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let mut buf = vec![];
+        fast_graph.serialize_into_buf(&mut buf);
+
+        assert_eq!(
+            Err(String::from("The data does not contain a FastGraph")),
+            FastGraph::from_bytes(&buf[1..]).map(|_| ())
+        );
+        let error = FastGraph::from_bytes(&buf[..buf.len() - 1]).unwrap_err();
+        assert!(error.contains("should have"), "{}", error);
+
+        let mut wrong_version = buf.clone();
+        wrong_version[4..8].copy_from_slice(&(FastGraph::FORMAT_VERSION + 1).to_le_bytes());
+        let error = FastGraph::from_bytes(&wrong_version).unwrap_err();
+        assert!(error.contains("format version"), "{}", error);
+
+        // the adjacent node of the first out-edge, which comes after the header and the ids
+        let mut invalid_node = buf.clone();
+        let offset = 48 + 8 * (3 * 3 + 2) + 8;
+        invalid_node[offset..offset + 8].copy_from_slice(&5u64.to_le_bytes());
+        assert!(FastGraph::from_bytes(&invalid_node).is_err());
+
+        // a huge number of nodes must not lead to an overflow
+        let mut huge = buf.clone();
+        huge[8..16].copy_from_slice(&u64::MAX.to_le_bytes());
+        assert!(FastGraph::from_bytes(&huge).is_err());
+    }
+
+    #[test]
+    fn format_version() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        g.freeze();
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let mut serialized = bincode::serialize(&fast_graph).unwrap();
+        assert_eq!(&FastGraph::FORMAT_VERSION.to_le_bytes(), &serialized[0..4]);
+        let deserialized: FastGraph = bincode::deserialize(&serialized).unwrap();
+        assert!(fast_graph.diff(&deserialized).is_empty());
+
+        serialized[0..4].copy_from_slice(&(FastGraph::FORMAT_VERSION + 1).to_le_bytes());
+        let error = bincode::deserialize::<FastGraph>(&serialized).unwrap_err();
+        assert_eq!(
+            format!(
+                "The FastGraph was serialized with format version {}, but this version of \
+                 fast_paths can only read format version {}. Please prepare the graph again.",
+                FastGraph::FORMAT_VERSION + 1,
+                FastGraph::FORMAT_VERSION
+            ),
+            error.to_string()
+        );
+    }
+}
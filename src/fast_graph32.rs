@@ -17,12 +17,19 @@
  * under the License.
  */
 
-use std::convert::TryFrom;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::convert::TryFrom;
+use core::fmt;
+use core::marker::PhantomData;
 
+use serde::de::{SeqAccess, Visitor};
 use serde::Deserialize;
+use serde::Deserializer;
 use serde::Serialize;
 
-use crate::constants::Length;
+use crate::constants::{EdgeData, Length, NodeId, Weight};
 use crate::fast_graph::FastGraphEdge;
 use crate::FastGraph;
 
@@ -38,21 +45,37 @@ pub struct FastGraph32 {
 
     pub edges_bwd: Vec<FastGraphEdge32>,
     pub first_edge_ids_bwd: Vec<u32>,
+
+    pub replaced_edges: Vec<FastGraphEdge32>,
 }
 
 impl FastGraph32 {
     /// Creates a 32bit Graph from a given `FastGraph`. All (potentially 64bit) `usize` integers are
     /// simply converted to u32 and if a value exceeds the 32bit limit an error is thrown. The only
-    /// exception is `std::u32::MAX`, which is converted to `std::usize::MAX`.
+    /// exception is `std::usize::MAX`, which is converted to `std::u32::MAX`.
+    /// Panics if any value exceeds the 32bit limit, use `try_new` to handle this case.
     pub fn new(fast_graph: &FastGraph) -> Self {
-        FastGraph32 {
-            num_nodes: usize_to_u32(fast_graph.get_num_nodes()),
-            ranks: usize_to_u32_vec(&fast_graph.ranks),
-            edges_fwd: usize_to_u32_edges(&fast_graph.edges_fwd),
-            first_edge_ids_fwd: usize_to_u32_vec(&fast_graph.first_edge_ids_fwd),
-            edges_bwd: usize_to_u32_edges(&fast_graph.edges_bwd),
-            first_edge_ids_bwd: usize_to_u32_vec(&fast_graph.first_edge_ids_bwd),
-        }
+        FastGraph32::try_new(fast_graph).unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// Like `new`, but returns an error naming the field and the value that exceeded the 32bit
+    /// limit instead of panicking.
+    pub fn try_new(fast_graph: &FastGraph) -> Result<Self, String> {
+        Ok(FastGraph32 {
+            num_nodes: usize_to_u32(fast_graph.get_num_nodes(), "num_nodes")?,
+            ranks: usize_to_u32_vec(&fast_graph.ranks, "ranks")?,
+            edges_fwd: usize_to_u32_edges(&fast_graph.edges_fwd, "edges_fwd")?,
+            first_edge_ids_fwd: usize_to_u32_vec(
+                &fast_graph.first_edge_ids_fwd,
+                "first_edge_ids_fwd",
+            )?,
+            edges_bwd: usize_to_u32_edges(&fast_graph.edges_bwd, "edges_bwd")?,
+            first_edge_ids_bwd: usize_to_u32_vec(
+                &fast_graph.first_edge_ids_bwd,
+                "first_edge_ids_bwd",
+            )?,
+            replaced_edges: usize_to_u32_edges(fast_graph.get_replaced_edges(), "replaced_edges")?,
+        })
     }
 
     /// Converts a 32bit Graph to an actual `FastGraph` using `usize` such that it can be used with
@@ -64,10 +87,89 @@ impl FastGraph32 {
         g.first_edge_ids_fwd = u32_to_usize_vec(&self.first_edge_ids_fwd);
         g.edges_bwd = u32_to_usize_edges(&self.edges_bwd);
         g.first_edge_ids_bwd = u32_to_usize_vec(&self.first_edge_ids_bwd);
+        g.set_replaced_edges(u32_to_usize_edges(&self.replaced_edges));
         g
     }
 }
 
+/// Deserializes a `FastGraph` that was serialized in its 32bit representation, i.e. as
+/// `FastGraph32`. Unlike deserializing a `FastGraph32` and calling `convert_to_usize` this
+/// converts the values while they are read, so we never keep both representations in memory.
+pub fn deserialize_to_usize<'de, D: Deserializer<'de>>(d: D) -> Result<FastGraph, D::Error> {
+    let fg32 = FastGraph32AsUsize::deserialize(d)?;
+    let mut g = FastGraph::new(fg32.num_nodes as usize);
+    g.ranks = fg32.ranks.0;
+    g.edges_fwd = fg32.edges_fwd.0;
+    g.first_edge_ids_fwd = fg32.first_edge_ids_fwd.0;
+    g.edges_bwd = fg32.edges_bwd.0;
+    g.first_edge_ids_bwd = fg32.first_edge_ids_bwd.0;
+    g.set_replaced_edges(fg32.replaced_edges.0);
+    Ok(g)
+}
+
+/// Has the same layout as `FastGraph32`, but its vectors are converted to `usize` during
+/// deserialization
+#[derive(Deserialize)]
+#[serde(rename = "FastGraph32")]
+struct FastGraph32AsUsize {
+    num_nodes: u32,
+    ranks: ConvertedVec<u32, usize>,
+    edges_fwd: ConvertedVec<FastGraphEdge32, FastGraphEdge>,
+    first_edge_ids_fwd: ConvertedVec<u32, usize>,
+    edges_bwd: ConvertedVec<FastGraphEdge32, FastGraphEdge>,
+    first_edge_ids_bwd: ConvertedVec<u32, usize>,
+    replaced_edges: ConvertedVec<FastGraphEdge32, FastGraphEdge>,
+}
+
+/// A vector of `T` that is deserialized from a sequence of `S`, one element at a time
+struct ConvertedVec<S, T>(Vec<T>, PhantomData<S>);
+
+trait ConvertToUsize<T> {
+    fn convert_to_usize(&self) -> T;
+}
+
+impl ConvertToUsize<usize> for u32 {
+    fn convert_to_usize(&self) -> usize {
+        u32_to_usize(*self)
+    }
+}
+
+impl ConvertToUsize<FastGraphEdge> for FastGraphEdge32 {
+    fn convert_to_usize(&self) -> FastGraphEdge {
+        u32_to_usize_edge(self)
+    }
+}
+
+impl<'de, S, T> Deserialize<'de> for ConvertedVec<S, T>
+where
+    S: Deserialize<'de> + ConvertToUsize<T>,
+{
+    fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+        struct ConvertedVecVisitor<S, T>(PhantomData<(S, T)>);
+
+        impl<'de, S, T> Visitor<'de> for ConvertedVecVisitor<S, T>
+        where
+            S: Deserialize<'de> + ConvertToUsize<T>,
+        {
+            type Value = ConvertedVec<S, T>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "a sequence")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut vec = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+                while let Some(elem) = seq.next_element::<S>()? {
+                    vec.push(elem.convert_to_usize());
+                }
+                Ok(ConvertedVec(vec, PhantomData))
+            }
+        }
+
+        d.deserialize_seq(ConvertedVecVisitor(PhantomData))
+    }
+}
+
 /// 32bit equivalent to `FastGraphEdge`, see `FastGraph32` docs.
 #[derive(Serialize, Deserialize, Debug, Clone, Copy)]
 pub struct FastGraphEdge32 {
@@ -77,40 +179,59 @@ pub struct FastGraphEdge32 {
     pub length: Length,
     pub replaced_in_edge: u32,
     pub replaced_out_edge: u32,
+    pub data: EdgeData,
+    pub base_edge_count: u32,
 }
 
-fn usize_to_u32(int: usize) -> u32 {
-    if int == std::usize::MAX {
-        usize_to_u32(std::u32::MAX as usize)
+fn usize_to_u32(int: usize, field: &str) -> Result<u32, String> {
+    if int == usize::MAX {
+        Ok(u32::MAX)
     } else if let Ok(x) = u32::try_from(int) {
-        x
+        Ok(x)
     } else {
-        panic!("Could not convert {} to a 32-bit integer", int);
+        Err(format!(
+            "Could not convert {} to a 32-bit integer, field: {}",
+            int, field
+        ))
     }
 }
 
-fn usize_to_u32_vec(vec: &[usize]) -> Vec<u32> {
-    vec.iter().map(|i| usize_to_u32(*i)).collect()
+fn usize_to_u32_vec(vec: &[usize], field: &str) -> Result<Vec<u32>, String> {
+    vec.iter()
+        .enumerate()
+        .map(|(i, int)| usize_to_u32(*int, &format!("{}[{}]", field, i)))
+        .collect()
 }
 
-fn usize_to_u32_edges(vec: &[FastGraphEdge]) -> Vec<FastGraphEdge32> {
-    vec.iter().map(|e| usize_to_u32_edge(e)).collect()
+fn usize_to_u32_edges(vec: &[FastGraphEdge], field: &str) -> Result<Vec<FastGraphEdge32>, String> {
+    vec.iter()
+        .enumerate()
+        .map(|(i, e)| usize_to_u32_edge(e, &format!("{}[{}]", field, i)))
+        .collect()
 }
 
-fn usize_to_u32_edge(edge: &FastGraphEdge) -> FastGraphEdge32 {
-    FastGraphEdge32 {
-        base_node: usize_to_u32(edge.base_node),
-        adj_node: usize_to_u32(edge.adj_node),
-        weight: usize_to_u32(edge.weight),
+fn usize_to_u32_edge(edge: &FastGraphEdge, field: &str) -> Result<FastGraphEdge32, String> {
+    Ok(FastGraphEdge32 {
+        base_node: usize_to_u32(edge.base_node.0, &format!("{}.base_node", field))?,
+        adj_node: usize_to_u32(edge.adj_node.0, &format!("{}.adj_node", field))?,
+        weight: usize_to_u32(edge.weight.0, &format!("{}.weight", field))?,
         length: edge.length,
-        replaced_in_edge: usize_to_u32(edge.replaced_in_edge),
-        replaced_out_edge: usize_to_u32(edge.replaced_out_edge),
-    }
+        replaced_in_edge: usize_to_u32(
+            edge.replaced_in_edge,
+            &format!("{}.replaced_in_edge", field),
+        )?,
+        replaced_out_edge: usize_to_u32(
+            edge.replaced_out_edge,
+            &format!("{}.replaced_out_edge", field),
+        )?,
+        data: edge.data,
+        base_edge_count: usize_to_u32(edge.base_edge_count, &format!("{}.base_edge_count", field))?,
+    })
 }
 
 fn u32_to_usize(int: u32) -> usize {
-    if int == std::u32::MAX {
-        std::usize::MAX
+    if int == core::u32::MAX {
+        core::usize::MAX
     } else {
         int as usize
     }
@@ -126,16 +247,18 @@ fn u32_to_usize_edges(vec: &[FastGraphEdge32]) -> Vec<FastGraphEdge> {
 
 fn u32_to_usize_edge(edge: &FastGraphEdge32) -> FastGraphEdge {
     FastGraphEdge {
-        base_node: u32_to_usize(edge.base_node),
-        adj_node: u32_to_usize(edge.adj_node),
-        weight: u32_to_usize(edge.weight),
+        base_node: NodeId(u32_to_usize(edge.base_node)),
+        adj_node: NodeId(u32_to_usize(edge.adj_node)),
+        weight: Weight(u32_to_usize(edge.weight)),
         length: edge.length,
         replaced_in_edge: u32_to_usize(edge.replaced_in_edge),
         replaced_out_edge: u32_to_usize(edge.replaced_out_edge),
+        data: edge.data,
+        base_edge_count: u32_to_usize(edge.base_edge_count),
     }
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
     use crate::fast_graph::FastGraph;
     use crate::fast_graph::FastGraphEdge;
@@ -148,23 +271,30 @@ mod tests {
         let ranks = vec![286, 45, 480_001, std::usize::MAX, 4468];
         let edges_fwd = vec![
             FastGraphEdge::new(
-                std::usize::MAX,
-                598,
-                48,
+                NodeId(std::usize::MAX),
+                NodeId(598),
+                Weight(48),
                 48.0,
                 std::usize::MAX,
                 std::usize::MAX,
             ),
             FastGraphEdge::new(
-                std::usize::MAX,
-                std::usize::MAX,
-                std::usize::MAX,
+                NodeId(std::usize::MAX),
+                NodeId(std::usize::MAX),
+                Weight(std::usize::MAX),
                 std::f64::MAX,
                 4,
                 std::usize::MAX,
             ),
         ];
-        let edges_bwd = vec![FastGraphEdge::new(0, 1, 3, 3.0, 4, std::usize::MAX)];
+        let edges_bwd = vec![FastGraphEdge::new(
+            NodeId(0),
+            NodeId(1),
+            Weight(3),
+            3.0,
+            4,
+            std::usize::MAX,
+        )];
         let first_edge_ids_fwd = vec![1, std::usize::MAX, std::usize::MAX];
         let first_edge_ids_bwd = vec![1, std::usize::MAX, 5, std::usize::MAX, 9, 10];
 
@@ -216,9 +346,9 @@ mod tests {
         assert_eq!(g_from32.first_edge_ids_fwd[2], std::usize::MAX);
         assert_eq!(g_from32.first_edge_ids_bwd[0], 1);
         assert_eq!(g_from32.first_edge_ids_bwd[1], std::usize::MAX);
-        assert_eq!(g_from32.edges_fwd[0].base_node, std::usize::MAX);
-        assert_eq!(g_from32.edges_fwd[0].adj_node, 598);
-        assert_eq!(g_from32.edges_fwd[0].weight, 48);
+        assert_eq!(g_from32.edges_fwd[0].base_node, NodeId(std::usize::MAX));
+        assert_eq!(g_from32.edges_fwd[0].adj_node, NodeId(598));
+        assert_eq!(g_from32.edges_fwd[0].weight, Weight(48));
         assert_eq!(g_from32.edges_bwd[0].replaced_in_edge, 4);
     }
 
@@ -230,4 +360,71 @@ mod tests {
         g.ranks = vec![5_000_000_000];
         FastGraph32::new(&g);
     }
+
+    #[test]
+    fn try_create_with_too_large_numbers() {
+        let mut g = FastGraph::new(5);
+        g.ranks = vec![0, 1, 2, 3, 4];
+        g.edges_fwd = vec![
+            FastGraphEdge::new(NodeId(0), NodeId(1), Weight(3), 3.0, usize::MAX, usize::MAX),
+            FastGraphEdge::new(
+                NodeId(1),
+                NodeId(2),
+                Weight(5_000_000_000),
+                3.0,
+                usize::MAX,
+                usize::MAX,
+            ),
+        ];
+        assert_eq!(
+            "Could not convert 5000000000 to a 32-bit integer, field: edges_fwd[1].weight",
+            FastGraph32::try_new(&g).unwrap_err()
+        );
+        g.edges_fwd.pop();
+        g.ranks[3] = 5_000_000_001;
+        assert_eq!(
+            "Could not convert 5000000001 to a 32-bit integer, field: ranks[3]",
+            FastGraph32::try_new(&g).unwrap_err()
+        );
+        g.ranks[3] = 3;
+        assert!(FastGraph32::try_new(&g).is_ok());
+    }
+
+    #[test]
+    fn deserialize_to_usize_while_reading() {
+        let mut g = FastGraph::new(3);
+        g.ranks = vec![2, 0, 1];
+        g.edges_fwd = vec![
+            FastGraphEdge::new(NodeId(1), NodeId(2), Weight(3), 3.0, usize::MAX, usize::MAX),
+            FastGraphEdge::new(NodeId(1), NodeId(0), Weight(7), 7.0, 5, 8),
+        ];
+        g.first_edge_ids_fwd = vec![0, 2, 2, 2];
+        g.edges_bwd = vec![FastGraphEdge::new(
+            NodeId(2),
+            NodeId(0),
+            Weight(4),
+            4.0,
+            usize::MAX,
+            usize::MAX,
+        )];
+        g.first_edge_ids_bwd = vec![0, 0, 1, 1];
+        let bytes = bincode::serialize(&FastGraph32::new(&g)).unwrap();
+        let FastGraphAsUsize(loaded) = bincode::deserialize_from(bytes.as_slice()).unwrap();
+        assert_eq!(3, loaded.get_num_nodes());
+        assert_eq!(g.ranks, loaded.ranks);
+        assert_eq!(g.first_edge_ids_fwd, loaded.first_edge_ids_fwd);
+        assert_eq!(g.first_edge_ids_bwd, loaded.first_edge_ids_bwd);
+        assert_eq!(usize::MAX, loaded.edges_fwd[0].replaced_in_edge);
+        assert_eq!(8, loaded.edges_fwd[1].replaced_out_edge);
+        assert_eq!(7.0, loaded.edges_fwd[1].length);
+        assert_eq!(Weight(4), loaded.edges_bwd[0].weight);
+    }
+
+    struct FastGraphAsUsize(FastGraph);
+
+    impl<'de> Deserialize<'de> for FastGraphAsUsize {
+        fn deserialize<D: Deserializer<'de>>(d: D) -> Result<Self, D::Error> {
+            deserialize_to_usize(d).map(FastGraphAsUsize)
+        }
+    }
 }
@@ -19,12 +19,19 @@
 
 use std::cmp::{max, Reverse};
 use std::collections::BTreeSet;
+use std::error::Error;
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
 
 use priority_queue::PriorityQueue;
+use serde::{Deserialize, Serialize};
 
+use crate::constants::CellId;
 use crate::constants::Weight;
 use crate::constants::{EdgeId, NodeId, INVALID_EDGE, INVALID_NODE};
 use crate::fast_graph::FastGraphEdge;
+use crate::fast_graph::RankExplanation;
 
 use super::fast_graph::FastGraph;
 use super::input_graph::InputGraph;
@@ -35,13 +42,25 @@ use crate::witness_search::WitnessSearch;
 pub struct FastGraphBuilder {
     fast_graph: FastGraph,
     num_nodes: usize,
+    /// the number of nodes contracted by `contract_nodes`, see `PreparationReport::rounds`
+    rounds: usize,
+    /// the largest priority of a contracted node, see `PreparationReport::peak_priority`
+    peak_priority: usize,
+    /// the number of shortcuts per contracted node if `Params::shortcut_accounting` is enabled,
+    /// see `PreparationReport::shortcuts_per_node`
+    shortcuts_per_node: Vec<usize>,
 }
 
 impl FastGraphBuilder {
     fn new(input_graph: &InputGraph) -> Self {
+        let mut fast_graph = FastGraph::new(input_graph.get_num_nodes());
+        fast_graph.set_weight_scale(input_graph.get_weight_scale());
         FastGraphBuilder {
-            fast_graph: FastGraph::new(input_graph.get_num_nodes()),
+            fast_graph,
             num_nodes: input_graph.get_num_nodes(),
+            rounds: 0,
+            peak_priority: 0,
+            shortcuts_per_node: vec![],
         }
     }
 
@@ -50,9 +69,47 @@ impl FastGraphBuilder {
     }
 
     pub fn build_with_params(input_graph: &InputGraph, params: &Params) -> FastGraph {
+        FastGraphBuilder::build_with_params_cancellable(
+            input_graph,
+            params,
+            &AtomicBool::new(false),
+        )
+        .expect("the preparation cannot be cancelled")
+    }
+
+    /// Like `build_with_params`, but also returns a `PreparationReport` that summarizes what the
+    /// preparation did, e.g. to emit metrics.
+    pub fn build_with_params_reported(
+        input_graph: &InputGraph,
+        params: &Params,
+    ) -> (FastGraph, PreparationReport) {
+        let start = Instant::now();
+        let mut builder = FastGraphBuilder::new(input_graph);
+        builder
+            .run_contraction(input_graph, params, &AtomicBool::new(false))
+            .expect("the preparation cannot be cancelled");
+        let report = PreparationReport {
+            duration: start.elapsed(),
+            shortcuts_added: builder.fast_graph.get_num_shortcut_out_edges()
+                + builder.fast_graph.get_num_shortcut_in_edges(),
+            rounds: builder.rounds,
+            peak_priority: builder.peak_priority,
+            shortcuts_per_node: builder.shortcuts_per_node,
+        };
+        (builder.fast_graph, report)
+    }
+
+    /// Like `build_with_params`, but the preparation is aborted once `cancel` is set to true, e.g.
+    /// from another thread (use an `Arc<AtomicBool>` to share the flag). The flag is checked
+    /// after each contracted node, so the preparation stops shortly after it was set.
+    pub fn build_with_params_cancellable(
+        input_graph: &InputGraph,
+        params: &Params,
+        cancel: &AtomicBool,
+    ) -> Result<FastGraph, PreparationError> {
         let mut builder = FastGraphBuilder::new(input_graph);
-        builder.run_contraction(input_graph, params);
-        builder.fast_graph
+        builder.run_contraction(input_graph, params, cancel)?;
+        Ok(builder.fast_graph)
     }
 
     pub fn build_with_order(
@@ -71,93 +128,371 @@ impl FastGraphBuilder {
         order: &[NodeId],
         params: &ParamsWithOrder,
     ) -> Result<FastGraph, String> {
-        if input_graph.get_num_nodes() != order.len() {
-            return Err(String::from(
-                "The given order must have as many nodes as the input graph",
+        FastGraphBuilder::check_order(input_graph.get_num_nodes(), order)?;
+        let mut builder = FastGraphBuilder::new(input_graph);
+        builder.run_contraction_with_order(input_graph, order, params);
+        Ok(builder.fast_graph)
+    }
+
+    /// Checks that the given order is a permutation of the node ids [0, num_nodes)
+    fn check_order(num_nodes: usize, order: &[NodeId]) -> Result<(), String> {
+        if order.len() != num_nodes {
+            return Err(format!(
+                "The given order must have as many nodes as the input graph, but it has {} nodes \
+                 and the input graph has {} nodes",
+                order.len(),
+                num_nodes
+            ));
+        }
+        let mut first_index = vec![None; num_nodes];
+        for (index, &node) in order.iter().enumerate() {
+            if node.0 >= num_nodes {
+                return Err(format!(
+                    "The given order contains the invalid node id {} at index {}, node ids must \
+                     be in [0, {})",
+                    node, index, num_nodes
+                ));
+            }
+            if let Some(first) = first_index[node.0] {
+                return Err(format!(
+                    "The given order contains node {} twice, at index {} and at index {}",
+                    node, first, index
+                ));
+            }
+            first_index[node.0] = Some(index);
+        }
+        Ok(())
+    }
+
+    /// Prepares the graph based on the given partition of the nodes into cells, i.e.
+    /// `partition[node]` is the cell of each node. First only the interior nodes of the cells
+    /// (the nodes whose edges all lead to nodes of the same cell) are contracted, so the
+    /// shortcuts created in this phase never cross cell boundaries. Afterwards the boundary nodes
+    /// are contracted on top of them, which yields a hierarchy with an overlay of boundary nodes
+    /// at the top. Both phases use the usual node priorities (see `Params`). Returns an error if
+    /// the partition does not have an entry for every node.
+    pub fn build_partitioned(
+        input_graph: &InputGraph,
+        partition: &[CellId],
+        params: &Params,
+    ) -> Result<FastGraph, String> {
+        let num_nodes = input_graph.get_num_nodes();
+        if partition.len() != num_nodes {
+            return Err(format!(
+                "The given partition must have as many entries as the input graph has nodes, but \
+                 it has {} entries and the input graph has {} nodes",
+                partition.len(),
+                num_nodes
             ));
         }
+        let mut is_boundary = vec![false; num_nodes];
+        for edge in input_graph.get_edges() {
+            if partition[edge.from.0] != partition[edge.to.0] {
+                is_boundary[edge.from.0] = true;
+                is_boundary[edge.to.0] = true;
+            }
+        }
+        let boundary_nodes: Vec<NodeId> = (0..num_nodes)
+            .map(NodeId)
+            .filter(|&n| is_boundary[n.0])
+            .collect();
         let mut builder = FastGraphBuilder::new(input_graph);
-        builder.run_contraction_with_order(input_graph, order, params);
+        let mut state = ContractionState::new_excluding(input_graph, params, &boundary_nodes);
+        let never_cancel = AtomicBool::new(false);
+        builder
+            .contract_nodes(&mut state, params, usize::MAX, &mut |_| {}, &never_cancel)
+            .expect("the preparation cannot be cancelled");
+        state.enqueue(params, &boundary_nodes);
+        builder
+            .contract_nodes(&mut state, params, usize::MAX, &mut |_| {}, &never_cancel)
+            .expect("the preparation cannot be cancelled");
+        builder.add_core_nodes(&mut state, &params.core_nodes);
+        builder.fast_graph = state.fast_graph;
+        builder.finish_contraction(&mut state.preparation_graph);
         Ok(builder.fast_graph)
     }
 
-    fn run_contraction(&mut self, input_graph: &InputGraph, params: &Params) {
-        let mut preparation_graph = PreparationGraph::from_input_graph(input_graph);
-        let mut witness_search = WitnessSearch::new(self.num_nodes);
-        let mut levels = vec![0; self.num_nodes];
-        let mut queue = PriorityQueue::new();
-        for node in 0..self.num_nodes {
-            let priority = node_contractor::calc_relevance(
-                &mut preparation_graph,
-                params,
-                &mut witness_search,
-                node,
-                0,
-                params.max_settled_nodes_initial_relevance,
-            ) as Weight;
-            queue.push(node, Reverse(priority));
+    /// Like `build_with_params`, but the initial priorities of the nodes are taken from
+    /// `priorities` instead of calculating them, e.g. to reuse good estimates from a previous
+    /// preparation of a similar graph. The priorities are only hints: nodes with lower priorities
+    /// are contracted first, but the priorities of the neighbors of every contracted node are
+    /// recalculated as usual, so unlike `build_with_order` the contraction still adapts to the
+    /// shortcuts it creates. The priorities should be in the same range as the calculated ones
+    /// (see `PreparationReport::peak_priority`) and negative priorities are treated as zero.
+    /// Returns an error if there is not exactly one priority per node.
+    pub fn build_with_initial_priorities(
+        input_graph: &InputGraph,
+        priorities: &[i64],
+        params: &Params,
+    ) -> Result<FastGraph, String> {
+        if priorities.len() != input_graph.get_num_nodes() {
+            return Err(format!(
+                "The given priorities must have as many entries as the input graph has nodes, but \
+                 there are {} priorities and the input graph has {} nodes",
+                priorities.len(),
+                input_graph.get_num_nodes()
+            ));
         }
-        let mut rank = 0;
-        while !queue.is_empty() {
+        let mut builder = FastGraphBuilder::new(input_graph);
+        let mut state = ContractionState::new_with_priorities(input_graph, params, priorities);
+        builder
+            .contract_nodes(
+                &mut state,
+                params,
+                usize::MAX,
+                &mut |_| {},
+                &AtomicBool::new(false),
+            )
+            .expect("the preparation cannot be cancelled");
+        builder.add_core_nodes(&mut state, &params.core_nodes);
+        builder.fast_graph = state.fast_graph;
+        builder.finish_contraction(&mut state.preparation_graph);
+        Ok(builder.fast_graph)
+    }
+
+    /// Like `build_with_params`, but allows to interrupt the preparation and resume it later.
+    /// Every `checkpoint_interval` contracted nodes `checkpoint` is called with the current
+    /// `ContractionState`, which can be serialized (e.g. to disk using bincode). Pass a
+    /// deserialized state as `resume_from` to continue the preparation where the state was
+    /// captured. Note that the same input graph and parameters must be used when resuming the
+    /// preparation. The resulting graph is a valid hierarchy, but it is not guaranteed to be
+    /// identical to the one obtained without interruption, because nodes with equal priority
+    /// might be contracted in a different order.
+    pub fn build_resumable<F>(
+        input_graph: &InputGraph,
+        params: &Params,
+        resume_from: Option<ContractionState>,
+        checkpoint_interval: usize,
+        mut checkpoint: F,
+    ) -> Result<FastGraph, String>
+    where
+        F: FnMut(&ContractionState),
+    {
+        let mut builder = FastGraphBuilder::new(input_graph);
+        let mut state = match resume_from {
+            Some(state) => {
+                if state.fast_graph.get_num_nodes() != builder.num_nodes {
+                    return Err(String::from(
+                        "The given contraction state must have as many nodes as the input graph",
+                    ));
+                }
+                state
+            }
+            None => ContractionState::new(input_graph, params),
+        };
+        builder
+            .contract_nodes(
+                &mut state,
+                params,
+                checkpoint_interval,
+                &mut checkpoint,
+                &AtomicBool::new(false),
+            )
+            .map_err(|e| e.to_string())?;
+        builder.add_core_nodes(&mut state, &params.core_nodes);
+        builder.fast_graph = state.fast_graph;
+        builder.finish_contraction(&mut state.preparation_graph);
+        Ok(builder.fast_graph)
+    }
+
+    fn run_contraction(
+        &mut self,
+        input_graph: &InputGraph,
+        params: &Params,
+        cancel: &AtomicBool,
+    ) -> Result<(), PreparationError> {
+        let mut state = ContractionState::new(input_graph, params);
+        self.contract_nodes(&mut state, params, usize::MAX, &mut |_| {}, cancel)?;
+        self.add_core_nodes(&mut state, &params.core_nodes);
+        self.fast_graph = state.fast_graph;
+        self.finish_contraction(&mut state.preparation_graph);
+        Ok(())
+    }
+
+    fn contract_nodes<F>(
+        &mut self,
+        state: &mut ContractionState,
+        params: &Params,
+        checkpoint_interval: usize,
+        checkpoint: &mut F,
+        cancel: &AtomicBool,
+    ) -> Result<(), PreparationError>
+    where
+        F: FnMut(&ContractionState),
+    {
+        let mut witness_search = WitnessSearch::new(self.num_nodes);
+        let max_contracted_nodes = params.get_max_contracted_nodes(self.num_nodes);
+        let mut pruning_search = if params.shortcut_pruning {
+            Some(WitnessSearch::new(self.num_nodes))
+        } else {
+            None
+        };
+        while !state.queue.is_empty() {
+            witness_search.set_hop_limit(params.get_witness_hop_limit(state.rank, self.num_nodes));
+            if cancel.load(Ordering::Relaxed) {
+                return Err(PreparationError::Cancelled);
+            }
+            let (&next_node, _) = state.queue.peek().unwrap();
+            if state.levels[next_node.0] >= params.max_hierarchy_levels {
+                // the remaining nodes are added to the core, see `Params::max_hierarchy_levels`
+                break;
+            }
+            if state.rank >= max_contracted_nodes {
+                // ... same here, see `Params::contraction_budget`
+                break;
+            }
+            if let OrderStrategy::SampledQueryCost { samples } = params.order_strategy {
+                if state.queue.len() <= state.next_sampling {
+                    state.update_query_costs(samples);
+                    state.next_sampling = state.queue.len() / 2;
+                }
+            }
             // This normally yields the greatest priority, but since we use Reverse, it's the
             // least.
-            let node = queue.pop().unwrap().0;
+            let (node, Reverse(priority)) = state.queue.pop().unwrap();
+            self.rounds += 1;
+            self.peak_priority = max(self.peak_priority, priority);
+            if params.rank_explanations {
+                state.record_rank_explanation(node, priority);
+            }
+            let rank = state.rank;
             let mut neighbors = BTreeSet::new();
-            for out_edge in &preparation_graph.out_edges[node] {
+            for out_edge in &state.preparation_graph.out_edges[node.0] {
                 neighbors.insert(out_edge.adj_node);
-                self.fast_graph.edges_fwd.push(FastGraphEdge::new(
-                    node,
-                    out_edge.adj_node,
-                    out_edge.weight,
-                    out_edge.length,
-                    out_edge.center_node,
-                    INVALID_EDGE,
-                ));
+                state
+                    .fast_graph
+                    .edges_fwd
+                    .push(FastGraphEdge::new_with_data(
+                        node,
+                        out_edge.adj_node,
+                        out_edge.weight,
+                        out_edge.length,
+                        out_edge.center_node.0,
+                        INVALID_EDGE,
+                        out_edge.data,
+                    ));
             }
-            self.fast_graph.first_edge_ids_fwd[rank + 1] = self.fast_graph.get_num_out_edges();
+            state.fast_graph.first_edge_ids_fwd[rank + 1] = state.fast_graph.get_num_out_edges();
 
-            for in_edge in &preparation_graph.in_edges[node] {
+            for in_edge in &state.preparation_graph.in_edges[node.0] {
                 neighbors.insert(in_edge.adj_node);
-                self.fast_graph.edges_bwd.push(FastGraphEdge::new(
-                    node,
-                    in_edge.adj_node,
-                    in_edge.weight,
-                    in_edge.length,
-                    in_edge.center_node,
-                    INVALID_EDGE,
-                ));
+                state
+                    .fast_graph
+                    .edges_bwd
+                    .push(FastGraphEdge::new_with_data(
+                        node,
+                        in_edge.adj_node,
+                        in_edge.weight,
+                        in_edge.length,
+                        in_edge.center_node.0,
+                        INVALID_EDGE,
+                        in_edge.data,
+                    ));
             }
-            self.fast_graph.first_edge_ids_bwd[rank + 1] = self.fast_graph.get_num_in_edges();
+            state.fast_graph.first_edge_ids_bwd[rank + 1] = state.fast_graph.get_num_in_edges();
 
-            self.fast_graph.ranks[node] = rank;
-            node_contractor::contract_node(
-                &mut preparation_graph,
-                &mut witness_search,
-                node,
-                params.max_settled_nodes_contraction,
-            );
+            state.fast_graph.ranks[node.0] = rank;
+            let num_shortcuts = if let Some(pruning_search) = pruning_search.as_mut() {
+                node_contractor::contract_node_with_pruning(
+                    &mut state.preparation_graph,
+                    &mut witness_search,
+                    pruning_search,
+                    node,
+                    params.max_settled_nodes_contraction,
+                )
+            } else {
+                node_contractor::contract_node(
+                    &mut state.preparation_graph,
+                    &mut witness_search,
+                    node,
+                    params.max_settled_nodes_contraction,
+                )
+            };
+            if params.shortcut_accounting {
+                if self.shortcuts_per_node.is_empty() {
+                    self.shortcuts_per_node = vec![0; self.num_nodes];
+                }
+                self.shortcuts_per_node[node.0] = num_shortcuts;
+            }
             for neighbor in neighbors {
-                levels[neighbor] = max(levels[neighbor], levels[node] + 1);
-                let priority = node_contractor::calc_relevance(
-                    &mut preparation_graph,
+                state.levels[neighbor.0] = max(state.levels[neighbor.0], state.levels[node.0] + 1);
+                if state.queue.get(&neighbor).is_none() {
+                    // core nodes are not contracted, so there is no need to update their priority
+                    continue;
+                }
+                let priority = state.calc_priority(
                     params,
                     &mut witness_search,
                     neighbor,
-                    levels[neighbor],
                     params.max_settled_nodes_neighbor_relevance,
-                ) as Weight;
-                queue.change_priority(&neighbor, Reverse(priority));
+                );
+                state.queue.change_priority(&neighbor, Reverse(priority));
             }
             debug!(
                 "contracted node {} / {}, num edges fwd: {}, num edges bwd: {}",
                 rank + 1,
                 self.num_nodes,
-                self.fast_graph.get_num_out_edges(),
-                self.fast_graph.get_num_in_edges()
+                state.fast_graph.get_num_out_edges(),
+                state.fast_graph.get_num_in_edges()
             );
-            rank += 1;
+            state.rank += 1;
+            if state.rank.is_multiple_of(checkpoint_interval) && !state.queue.is_empty() {
+                checkpoint(state);
+            }
+        }
+        Ok(())
+    }
+
+    /// Adds the core nodes on top of the hierarchy without contracting them. Core nodes keep all
+    /// their edges, so every edge between two core nodes is stored as out-edge of its source and
+    /// as in-edge of its target and the query search can move freely between the core nodes.
+    /// Nodes that are still in the queue, because the contraction stopped at
+    /// `Params::max_hierarchy_levels` or `Params::contraction_budget`, are added to the core below
+    /// the given core nodes.
+    fn add_core_nodes(&mut self, state: &mut ContractionState, core_nodes: &[NodeId]) {
+        let mut remaining_nodes: Vec<NodeId> = state.queue.iter().map(|(node, _)| *node).collect();
+        remaining_nodes.sort_unstable();
+        state.queue.clear();
+        let mut added = vec![false; self.num_nodes];
+        for &node in remaining_nodes.iter().chain(core_nodes) {
+            if added[node.0] {
+                continue;
+            }
+            added[node.0] = true;
+            let rank = state.rank;
+            for out_edge in &state.preparation_graph.out_edges[node.0] {
+                state
+                    .fast_graph
+                    .edges_fwd
+                    .push(FastGraphEdge::new_with_data(
+                        node,
+                        out_edge.adj_node,
+                        out_edge.weight,
+                        out_edge.length,
+                        out_edge.center_node.0,
+                        INVALID_EDGE,
+                        out_edge.data,
+                    ));
+            }
+            state.fast_graph.first_edge_ids_fwd[rank + 1] = state.fast_graph.get_num_out_edges();
+            for in_edge in &state.preparation_graph.in_edges[node.0] {
+                state
+                    .fast_graph
+                    .edges_bwd
+                    .push(FastGraphEdge::new_with_data(
+                        node,
+                        in_edge.adj_node,
+                        in_edge.weight,
+                        in_edge.length,
+                        in_edge.center_node.0,
+                        INVALID_EDGE,
+                        in_edge.data,
+                    ));
+            }
+            state.fast_graph.first_edge_ids_bwd[rank + 1] = state.fast_graph.get_num_in_edges();
+            state.fast_graph.ranks[node.0] = rank;
+            state.rank += 1;
         }
-        self.finish_contraction();
     }
 
     fn run_contraction_with_order(
@@ -169,34 +504,33 @@ impl FastGraphBuilder {
         let mut preparation_graph = PreparationGraph::from_input_graph(input_graph);
         let mut witness_search = WitnessSearch::new(self.num_nodes);
         for (rank, node) in order.iter().cloned().enumerate() {
-            if node >= self.num_nodes {
-                panic!("Order contains invalid node id: {}", node);
-            }
-            for out_edge in &preparation_graph.out_edges[node] {
-                self.fast_graph.edges_fwd.push(FastGraphEdge::new(
+            for out_edge in &preparation_graph.out_edges[node.0] {
+                self.fast_graph.edges_fwd.push(FastGraphEdge::new_with_data(
                     node,
                     out_edge.adj_node,
                     out_edge.weight,
                     out_edge.length,
-                    out_edge.center_node,
+                    out_edge.center_node.0,
                     INVALID_EDGE,
+                    out_edge.data,
                 ));
             }
             self.fast_graph.first_edge_ids_fwd[rank + 1] = self.fast_graph.get_num_out_edges();
 
-            for in_edge in &preparation_graph.in_edges[node] {
-                self.fast_graph.edges_bwd.push(FastGraphEdge::new(
+            for in_edge in &preparation_graph.in_edges[node.0] {
+                self.fast_graph.edges_bwd.push(FastGraphEdge::new_with_data(
                     node,
                     in_edge.adj_node,
                     in_edge.weight,
                     in_edge.length,
-                    in_edge.center_node,
+                    in_edge.center_node.0,
                     INVALID_EDGE,
+                    in_edge.data,
                 ));
             }
             self.fast_graph.first_edge_ids_bwd[rank + 1] = self.fast_graph.get_num_in_edges();
 
-            self.fast_graph.ranks[node] = rank;
+            self.fast_graph.ranks[node.0] = rank;
             node_contractor::contract_node(
                 &mut preparation_graph,
                 &mut witness_search,
@@ -211,33 +545,38 @@ impl FastGraphBuilder {
                 self.fast_graph.get_num_in_edges()
             );
         }
-        self.finish_contraction();
+        self.finish_contraction(&mut preparation_graph);
     }
 
-    fn finish_contraction(&mut self) {
-        for i in 0..self.num_nodes {
+    /// Sets the ids of the edges replaced by the shortcuts and keeps the original edges that were
+    /// replaced by shortcuts, see `FastGraph::get_replaced_edges`
+    fn finish_contraction(&mut self, preparation_graph: &mut PreparationGraph) {
+        self.fast_graph
+            .set_replaced_edges(preparation_graph.take_replaced_edges());
+        for i in (0..self.num_nodes).map(NodeId) {
             for edge_id in self.fast_graph.begin_out_edges(i)..self.fast_graph.end_out_edges(i) {
                 // we temporarily stored the center node in the replaced_in_edge field. now we
                 // set the actual replaced edges
                 let c = self.fast_graph.edges_fwd[edge_id].replaced_in_edge;
-                if c == INVALID_NODE {
+                if c == INVALID_NODE.0 {
                     self.fast_graph.edges_fwd[edge_id].replaced_in_edge = INVALID_EDGE;
                     debug_assert_eq!(
                         INVALID_EDGE,
                         self.fast_graph.edges_fwd[edge_id].replaced_out_edge
                     );
                 } else {
-                    self.fast_graph.edges_fwd[edge_id].replaced_in_edge = self.get_in_edge_id(c, i);
-                    self.fast_graph.edges_fwd[edge_id].replaced_out_edge =
-                        self.get_out_edge_id(c, self.fast_graph.edges_fwd[edge_id].adj_node);
+                    self.fast_graph.edges_fwd[edge_id].replaced_in_edge =
+                        self.get_in_edge_id(NodeId(c), i);
+                    self.fast_graph.edges_fwd[edge_id].replaced_out_edge = self
+                        .get_out_edge_id(NodeId(c), self.fast_graph.edges_fwd[edge_id].adj_node);
                 }
             }
         }
 
-        for i in 0..self.num_nodes {
+        for i in (0..self.num_nodes).map(NodeId) {
             for edge_id in self.fast_graph.begin_in_edges(i)..self.fast_graph.end_in_edges(i) {
                 let c = self.fast_graph.edges_bwd[edge_id].replaced_in_edge;
-                if c == INVALID_NODE {
+                if c == INVALID_NODE.0 {
                     self.fast_graph.edges_bwd[edge_id].replaced_in_edge = INVALID_EDGE;
                     debug_assert_eq!(
                         INVALID_EDGE,
@@ -245,12 +584,13 @@ impl FastGraphBuilder {
                     );
                 } else {
                     self.fast_graph.edges_bwd[edge_id].replaced_in_edge =
-                        self.get_in_edge_id(c, self.fast_graph.edges_bwd[edge_id].adj_node);
+                        self.get_in_edge_id(NodeId(c), self.fast_graph.edges_bwd[edge_id].adj_node);
                     self.fast_graph.edges_bwd[edge_id].replaced_out_edge =
-                        self.get_out_edge_id(c, i);
+                        self.get_out_edge_id(NodeId(c), i);
                 }
             }
         }
+        self.fast_graph.update_base_edge_counts();
     }
 
     fn get_out_edge_id(&self, node: NodeId, adj_node: NodeId) -> EdgeId {
@@ -272,6 +612,201 @@ impl FastGraphBuilder {
     }
 }
 
+/// The intermediate state of the graph preparation, see `FastGraphBuilder::build_resumable`.
+#[derive(Serialize, Deserialize)]
+pub struct ContractionState {
+    fast_graph: FastGraph,
+    preparation_graph: PreparationGraph,
+    levels: Vec<usize>,
+    queue: PriorityQueue<NodeId, Reverse<usize>>,
+    rank: usize,
+    /// the priority added to each node for `OrderStrategy::SampledQueryCost`
+    query_costs: Vec<usize>,
+    /// the priority added to each node for `Params::spatial_grid_size`
+    spatial_costs: Vec<usize>,
+    /// the query costs are updated once the number of remaining nodes drops to this value
+    next_sampling: usize,
+    random_state: u64,
+    /// the values the current priority of each node was calculated from, empty unless
+    /// `Params::rank_explanations` is enabled
+    explanations: Vec<Option<RankExplanation>>,
+}
+
+impl ContractionState {
+    fn new(input_graph: &InputGraph, params: &Params) -> Self {
+        ContractionState::new_excluding(input_graph, params, &[])
+    }
+
+    /// Like `new`, but the `excluded` nodes are not put into the queue, so they are treated like
+    /// core nodes until they are added using `enqueue`
+    fn new_excluding(input_graph: &InputGraph, params: &Params, excluded: &[NodeId]) -> Self {
+        let num_nodes = input_graph.get_num_nodes();
+        let preparation_graph = PreparationGraph::from_input_graph(input_graph);
+        let mut witness_search = WitnessSearch::new(num_nodes);
+        witness_search.set_hop_limit(params.get_witness_hop_limit(0, num_nodes));
+        let mut is_core = vec![false; num_nodes];
+        for &node in &params.core_nodes {
+            if node.0 >= num_nodes {
+                panic!("Core nodes contain invalid node id: {}", node);
+            }
+            is_core[node.0] = true;
+        }
+        for &node in excluded {
+            is_core[node.0] = true;
+        }
+        let spatial_costs = match params.spatial_grid_size {
+            Some(grid_size) => {
+                if params.node_coordinates.len() != num_nodes {
+                    panic!(
+                        "The number of node coordinates ({}) must be equal to the number of \
+                         nodes ({})",
+                        params.node_coordinates.len(),
+                        num_nodes
+                    );
+                }
+                node_contractor::calc_spatial_costs(&params.node_coordinates, grid_size)
+            }
+            None => vec![0; num_nodes],
+        };
+        let mut fast_graph = FastGraph::new(num_nodes);
+        fast_graph.set_weight_scale(input_graph.get_weight_scale());
+        let mut explanations = vec![];
+        if params.rank_explanations {
+            fast_graph.init_rank_explanations();
+            explanations = vec![None; num_nodes];
+        }
+        let mut state = ContractionState {
+            fast_graph,
+            preparation_graph,
+            levels: vec![0; num_nodes],
+            queue: PriorityQueue::new(),
+            rank: 0,
+            query_costs: vec![0; num_nodes],
+            spatial_costs,
+            next_sampling: usize::MAX,
+            random_state: 0x2545_f491_4f6c_dd1d,
+            explanations,
+        };
+        for node in (0..num_nodes).map(NodeId).filter(|&node| !is_core[node.0]) {
+            let priority = state.calc_priority(
+                params,
+                &mut witness_search,
+                node,
+                params.max_settled_nodes_initial_relevance,
+            );
+            state.queue.push(node, Reverse(priority));
+        }
+        state
+    }
+
+    /// Like `new`, but the nodes are put into the queue using the given priorities instead of
+    /// calculating them, negative priorities are treated as zero
+    fn new_with_priorities(input_graph: &InputGraph, params: &Params, priorities: &[i64]) -> Self {
+        let num_nodes = input_graph.get_num_nodes();
+        let all_nodes: Vec<NodeId> = (0..num_nodes).map(NodeId).collect();
+        let mut state = ContractionState::new_excluding(input_graph, params, &all_nodes);
+        let mut is_core = vec![false; num_nodes];
+        for &node in &params.core_nodes {
+            is_core[node.0] = true;
+        }
+        for node in (0..num_nodes).map(NodeId).filter(|&node| !is_core[node.0]) {
+            let priority = max(priorities[node.0], 0) as usize;
+            state.queue.push(node, Reverse(priority));
+        }
+        state
+    }
+
+    /// Adds the given (not yet contracted) nodes to the queue, except for core nodes
+    fn enqueue(&mut self, params: &Params, nodes: &[NodeId]) {
+        let num_nodes = self.fast_graph.get_num_nodes();
+        let mut witness_search = WitnessSearch::new(num_nodes);
+        witness_search.set_hop_limit(params.get_witness_hop_limit(self.rank, num_nodes));
+        let mut is_core = vec![false; num_nodes];
+        for &node in &params.core_nodes {
+            is_core[node.0] = true;
+        }
+        for &node in nodes.iter().filter(|node| !is_core[node.0]) {
+            let priority = self.calc_priority(
+                params,
+                &mut witness_search,
+                node,
+                params.max_settled_nodes_neighbor_relevance,
+            );
+            self.queue.push(node, Reverse(priority));
+        }
+    }
+
+    /// Calculates the priority of the given node, i.e. its relevance plus the priority offset, and
+    /// keeps the values it was calculated from if `Params::rank_explanations` is enabled
+    fn calc_priority(
+        &mut self,
+        params: &Params,
+        witness_search: &mut WitnessSearch,
+        node: NodeId,
+        max_settled_nodes: usize,
+    ) -> usize {
+        let explanation = node_contractor::calc_relevance(
+            &mut self.preparation_graph,
+            params,
+            witness_search,
+            node,
+            self.levels[node.0],
+            max_settled_nodes,
+        );
+        if params.rank_explanations {
+            self.explanations[node.0] = Some(explanation);
+        }
+        explanation.relevance as usize + self.get_priority_offset(node)
+    }
+
+    /// Stores why the given node, which is contracted with the given priority, got its rank, see
+    /// `FastGraph::explain_rank`
+    fn record_rank_explanation(&mut self, node: NodeId, priority: usize) {
+        if let Some(explanation) = self.explanations[node.0] {
+            let explanation = RankExplanation {
+                bias: self.get_priority_offset(node),
+                priority,
+                ..explanation
+            };
+            self.fast_graph.set_rank_explanation(node, explanation);
+        }
+    }
+
+    /// Returns the priority that is added to the relevance of the given node, see
+    /// `OrderStrategy::SampledQueryCost` and `Params::spatial_grid_size`
+    fn get_priority_offset(&self, node: NodeId) -> usize {
+        self.query_costs[node.0] + self.spatial_costs[node.0]
+    }
+
+    /// Samples shortest path queries between the remaining nodes and adds the resulting query
+    /// costs to their priorities, see `OrderStrategy::SampledQueryCost`
+    fn update_query_costs(&mut self, samples: usize) {
+        let mut nodes: Vec<NodeId> = self.queue.iter().map(|(node, _)| *node).collect();
+        nodes.sort_unstable();
+        let query_costs = node_contractor::calc_query_costs(
+            &self.preparation_graph,
+            &nodes,
+            samples,
+            &mut self.random_state,
+        );
+        for node in nodes {
+            let old_cost = self.query_costs[node.0];
+            let new_cost = query_costs[node.0];
+            if old_cost != new_cost {
+                let priority = self.queue.get_priority(&node).unwrap().0;
+                self.queue
+                    .change_priority(&node, Reverse(priority - old_cost + new_cost));
+                self.query_costs[node.0] = new_cost;
+            }
+        }
+    }
+
+    /// Returns the number of nodes that have been contracted so far
+    pub fn get_num_contracted_nodes(&self) -> usize {
+        self.rank
+    }
+}
+
 pub struct Params {
     /// Smaller values typically yield less shortcuts and a faster preparation time. The relation to
     /// query speeds is less clear. For large values that yield a much higher number of shortcuts
@@ -299,6 +834,66 @@ pub struct Params {
     /// like ~500+ mean less shortcuts (fast graph edges), slower preparation and faster queries.
     /// Lower values mean more shortcuts, slower queries and faster preparation.
     pub max_settled_nodes_contraction: usize,
+    /// Nodes that will not be contracted. They are put on top of the hierarchy in the given order
+    /// and keep all their edges, so the resulting graph is only a partial hierarchy (a 'core CH').
+    /// Queries are still exact, but within the core the query search does not benefit from the
+    /// hierarchy, so a large core makes queries slower.
+    pub core_nodes: Vec<NodeId>,
+    /// The contraction stops once the next node to be contracted has this level in the
+    /// hierarchy, i.e. it lies on top of this many contracted nodes. All remaining nodes are
+    /// added to the core (see `core_nodes`) and keep their edges. Limiting the height of the
+    /// hierarchy reduces the preparation time and the number of shortcuts (and thus the memory
+    /// usage), but the query search has to explore the core without the help of the hierarchy,
+    /// so queries become slower the more nodes are left in the core. Queries are still exact.
+    /// The default is `usize::MAX`, i.e. all nodes are contracted.
+    pub max_hierarchy_levels: usize,
+    /// The fraction (between 0 and 1) of the nodes that are contracted before the contraction
+    /// stops. Like for `max_hierarchy_levels` all remaining nodes are added to the core and keep
+    /// their edges. The last nodes of the contraction usually need the most time and add the
+    /// most shortcuts, so a budget slightly below 1 (like 0.95-0.99) can save a lot of
+    /// preparation time and memory for huge graphs, while the queries complete their search on
+    /// the (uncontracted) core, which makes them slower. Queries are still exact. The default is
+    /// 1, i.e. all nodes are contracted.
+    pub contraction_budget: f64,
+    /// Limits the number of edges of the witness paths depending on the progress of the
+    /// contraction, i.e. the fraction of the nodes that have been contracted so far (between 0
+    /// and 1). Each (fraction, hops) entry means that the witness searches use a limit of `hops`
+    /// edges once at least this fraction of the nodes has been contracted, and the last such
+    /// entry applies. There is no limit before the first entry and if the schedule is empty,
+    /// which is the default. The entries must be sorted by fraction. Early in the contraction
+    /// the nodes have few and short edges, so a tight limit (like 1-3) saves time without
+    /// adding many shortcuts, while later a looser limit (or `usize::MAX`) keeps the number of
+    /// shortcuts among the remaining important nodes small. The limit comes on top of the
+    /// settled nodes limits above.
+    pub witness_hop_schedule: Vec<(f32, usize)>,
+    /// The strategy used to determine the order in which the nodes are contracted
+    pub order_strategy: OrderStrategy,
+    /// If enabled, every shortcut for which the witness search did not find a witness (within
+    /// the limits above) is checked once more using a witness search without any limits before
+    /// it is added, so no unnecessary shortcuts are added when a node is contracted. This makes
+    /// the preparation slower, but yields fewer shortcuts and thus a smaller graph and (usually)
+    /// faster queries. Disabled by default.
+    pub shortcut_pruning: bool,
+    /// If set, the nodes are put into the cells of a grid with the given cell size (in the units
+    /// of `node_coordinates`, e.g. degrees) and a priority between 0 and 1000 is added to each
+    /// node depending on the position of its cell along a space-filling (Z-order) curve. This
+    /// biases the contraction such that nodes within the same cell are contracted at similar
+    /// times, which can improve the memory locality of the queries. The added priority is in the
+    /// same range as the edge quotient term, so it only changes the order of nodes with similar
+    /// priorities. Requires `node_coordinates`. Disabled by default.
+    pub spatial_grid_size: Option<f64>,
+    /// The (lat, lon) coordinates of the nodes used for `spatial_grid_size`, the coordinates of
+    /// node i must be given at index i
+    pub node_coordinates: Vec<(f64, f64)>,
+    /// If enabled, the number of shortcuts each node introduced when it was contracted is
+    /// recorded and returned by `FastGraphBuilder::build_with_params_reported`, see
+    /// `PreparationReport::top_shortcut_nodes`. This points at dense hub nodes that dominate the
+    /// preparation time and memory usage. Disabled by default.
+    pub shortcut_accounting: bool,
+    /// If enabled, the values that determined the priority of each node when it was contracted
+    /// are stored in the prepared graph, see `FastGraph::explain_rank`. This is meant for
+    /// debugging and teaching and needs some extra memory per node. Disabled by default.
+    pub rank_explanations: bool,
 }
 
 impl Params {
@@ -314,12 +909,141 @@ impl Params {
             max_settled_nodes_initial_relevance,
             max_settled_nodes_neighbor_relevance,
             max_settled_nodes_contraction,
+            core_nodes: vec![],
+            max_hierarchy_levels: usize::MAX,
+            contraction_budget: 1.0,
+            witness_hop_schedule: vec![],
+            order_strategy: OrderStrategy::Priority,
+            shortcut_pruning: false,
+            spatial_grid_size: None,
+            node_coordinates: vec![],
+            shortcut_accounting: false,
+            rank_explanations: false,
         }
     }
 
     pub fn default() -> Self {
         Params::new(0.1, 500, 100, 500)
     }
+
+    /// Creates the default parameters, but the given nodes will not be contracted, see
+    /// `core_nodes`.
+    pub fn with_core_nodes(core_nodes: Vec<NodeId>) -> Self {
+        Params {
+            core_nodes,
+            ..Params::default()
+        }
+    }
+
+    /// Creates the default parameters, but the contraction stops after the given number of
+    /// hierarchy levels, see `max_hierarchy_levels`.
+    pub fn with_max_hierarchy_levels(max_hierarchy_levels: usize) -> Self {
+        Params {
+            max_hierarchy_levels,
+            ..Params::default()
+        }
+    }
+
+    /// Creates the default parameters, but the contraction stops after the given fraction of the
+    /// nodes has been contracted, see `contraction_budget`
+    pub fn with_contraction_budget(contraction_budget: f64) -> Self {
+        Params {
+            contraction_budget,
+            ..Params::default()
+        }
+    }
+
+    /// Returns the number of nodes that are contracted before the contraction stops, see
+    /// `contraction_budget`
+    fn get_max_contracted_nodes(&self, num_nodes: usize) -> usize {
+        if !(0.0..=1.0).contains(&self.contraction_budget) {
+            panic!(
+                "The contraction budget must be between 0 and 1, but was {}",
+                self.contraction_budget
+            );
+        }
+        (self.contraction_budget * num_nodes as f64) as usize
+    }
+
+    /// Creates the default parameters, but with the given schedule for the number of edges of the
+    /// witness paths, see `witness_hop_schedule`
+    pub fn with_adaptive_witness_hops(witness_hop_schedule: Vec<(f32, usize)>) -> Self {
+        Params {
+            witness_hop_schedule,
+            ..Params::default()
+        }
+    }
+
+    /// Returns the hop limit of the witness searches after `num_contracted` of the `num_nodes`
+    /// nodes have been contracted, see `witness_hop_schedule`
+    fn get_witness_hop_limit(&self, num_contracted: usize, num_nodes: usize) -> usize {
+        let progress = num_contracted as f32 / num_nodes.max(1) as f32;
+        self.witness_hop_schedule
+            .iter()
+            .take_while(|(fraction, _)| *fraction <= progress)
+            .last()
+            .map_or(usize::MAX, |&(_, hops)| hops)
+    }
+
+    /// Creates the default parameters, but enables or disables the additional witness check for
+    /// every shortcut, see `shortcut_pruning`
+    pub fn with_shortcut_pruning(shortcut_pruning: bool) -> Self {
+        Params {
+            shortcut_pruning,
+            ..Params::default()
+        }
+    }
+
+    /// Creates the default parameters, but biases the contraction order such that nodes within
+    /// the same cell of a grid with the given cell size are contracted at similar times, see
+    /// `spatial_grid_size`
+    pub fn with_spatial_priority(grid_size: f64, node_coordinates: Vec<(f64, f64)>) -> Self {
+        Params {
+            spatial_grid_size: Some(grid_size),
+            node_coordinates,
+            ..Params::default()
+        }
+    }
+
+    /// Creates the default parameters, but enables or disables recording the number of shortcuts
+    /// per contracted node, see `shortcut_accounting`
+    pub fn with_shortcut_accounting(shortcut_accounting: bool) -> Self {
+        Params {
+            shortcut_accounting,
+            ..Params::default()
+        }
+    }
+
+    /// Creates the default parameters, but enables or disables recording why each node got its
+    /// rank, see `rank_explanations`
+    pub fn with_rank_explanations(rank_explanations: bool) -> Self {
+        Params {
+            rank_explanations,
+            ..Params::default()
+        }
+    }
+
+    /// Creates the default parameters, but uses the given strategy to determine the node order
+    pub fn with_order_strategy(order_strategy: OrderStrategy) -> Self {
+        Params {
+            order_strategy,
+            ..Params::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OrderStrategy {
+    /// Nodes are contracted in the order of a priority that depends on the number of shortcuts
+    /// their contraction requires and their level in the hierarchy, see `Params`.
+    Priority,
+    /// Like `Priority`, but additionally runs `samples` random shortest path queries on the
+    /// remaining (not yet contracted) graph whenever the number of remaining nodes has halved.
+    /// Nodes that occur on many of the sampled shortest paths get a higher priority, so they end
+    /// up higher in the hierarchy, which tends to keep the query search spaces small. The
+    /// sampling is expensive for the first rounds and increases the preparation time
+    /// considerably.
+    SampledQueryCost { samples: usize },
 }
 
 pub struct ParamsWithOrder {
@@ -343,8 +1067,79 @@ impl ParamsWithOrder {
     }
 }
 
+/// Summarizes a graph preparation, see `FastGraphBuilder::build_with_params_reported`
+#[derive(Debug, Clone, PartialEq)]
+pub struct PreparationReport {
+    /// the time the preparation took
+    pub duration: Duration,
+    /// the number of shortcuts in the prepared graph, counting both out- and in-edges
+    pub shortcuts_added: usize,
+    /// the number of nodes that were contracted, i.e. all nodes except the core nodes
+    pub rounds: usize,
+    /// the largest priority a node had when it was contracted
+    pub peak_priority: usize,
+    /// the number of shortcuts that were added (or that reduced the weight of an existing edge)
+    /// when each node was contracted, zero for core nodes. Only recorded if
+    /// `Params::shortcut_accounting` is enabled, empty otherwise.
+    pub shortcuts_per_node: Vec<usize>,
+}
+
+impl PreparationReport {
+    /// Returns (up to) `n` nodes that introduced the most shortcuts along with their number of
+    /// shortcuts, sorted by the number of shortcuts (descending) and node id. Nodes without
+    /// shortcuts are not included. Requires `Params::shortcut_accounting`, otherwise the result
+    /// is empty.
+    pub fn top_shortcut_nodes(&self, n: usize) -> Vec<(NodeId, usize)> {
+        let mut nodes: Vec<(NodeId, usize)> = self
+            .shortcuts_per_node
+            .iter()
+            .copied()
+            .enumerate()
+            .map(|(node, num_shortcuts)| (NodeId(node), num_shortcuts))
+            .filter(|&(_, num_shortcuts)| num_shortcuts > 0)
+            .collect();
+        nodes.sort_by_key(|&(node, num_shortcuts)| (Reverse(num_shortcuts), node));
+        nodes.truncate(n);
+        nodes
+    }
+}
+
+/// The result of one of the `Params` configurations passed to `fast_paths::compare_params`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParamsResult {
+    /// the time the preparation took
+    pub preparation_time: Duration,
+    /// the number of shortcuts in the prepared graph, see `PreparationReport::shortcuts_added`
+    pub shortcuts_added: usize,
+    /// the average time of the queries, zero if there were no queries
+    pub mean_query_time: Duration,
+    /// the sum of the weights of all shortest paths that were found, which must be the same for
+    /// all configurations
+    pub total_weight: Weight,
+    /// the number of queries for which no path was found
+    pub num_not_found: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PreparationError {
+    /// The preparation was cancelled using the cancel flag
+    Cancelled,
+}
+
+impl fmt::Display for PreparationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PreparationError::Cancelled => write!(f, "The preparation was cancelled"),
+        }
+    }
+}
+
+impl Error for PreparationError {}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use crate::shortest_path::ShortestPath;
 
     use super::*;
@@ -354,26 +1149,105 @@ mod tests {
         calc_path, create_calculator, prepare, prepare_with_order, PathCalculator, WEIGHT_MAX,
     };
 
+    #[test]
+    fn cancel_preparation() {
+        let g = create_order_test_graph();
+        let cancel = Arc::new(AtomicBool::new(true));
+        assert_eq!(
+            PreparationError::Cancelled,
+            FastGraphBuilder::build_with_params_cancellable(&g, &Params::default(), &cancel)
+                .unwrap_err()
+        );
+        cancel.store(false, Ordering::Relaxed);
+        let fast_graph =
+            FastGraphBuilder::build_with_params_cancellable(&g, &Params::default(), &cancel)
+                .unwrap();
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(2),
+            Weight(2),
+            vec![NodeId(0), NodeId(1), NodeId(2)],
+        );
+    }
+
+    #[test]
+    fn order_with_wrong_length() {
+        let g = create_order_test_graph();
+        assert_eq!(
+            Err(String::from(
+                "The given order must have as many nodes as the input graph, but it has 2 nodes \
+                 and the input graph has 3 nodes"
+            )),
+            prepare_with_order(&g, &[NodeId(0), NodeId(1)]).map(|_| ())
+        );
+        assert!(prepare_with_order(&g, &[NodeId(0), NodeId(1), NodeId(2), NodeId(0)]).is_err());
+    }
+
+    #[test]
+    fn order_with_invalid_node_id() {
+        let g = create_order_test_graph();
+        assert_eq!(
+            Err(String::from(
+                "The given order contains the invalid node id 3 at index 1, node ids must be in \
+                 [0, 3)"
+            )),
+            prepare_with_order(&g, &[NodeId(0), NodeId(3), NodeId(1)]).map(|_| ())
+        );
+    }
+
+    #[test]
+    fn order_with_duplicate_node_id() {
+        let g = create_order_test_graph();
+        assert_eq!(
+            Err(String::from(
+                "The given order contains node 2 twice, at index 0 and at index 2"
+            )),
+            prepare_with_order(&g, &[NodeId(2), NodeId(1), NodeId(2)]).map(|_| ())
+        );
+    }
+
+    fn create_order_test_graph() -> InputGraph {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.freeze();
+        g
+    }
+
     #[test]
     fn calc_path_linear_bwd_only() {
         // 2->0->1
         let mut g = InputGraph::new();
-        g.add_edge(2, 0, 9, 9.0);
-        g.add_edge(0, 1, 49, 49.0);
+        g.add_edge(NodeId(2), NodeId(0), Weight(9), 9.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(49), 49.0);
         g.freeze();
-        let fast_graph = prepare_with_order(&g, &vec![0, 1, 2]).unwrap();
-        assert_path(&fast_graph, 2, 1, 58, vec![2, 0, 1]);
+        let fast_graph = prepare_with_order(&g, &[NodeId(0), NodeId(1), NodeId(2)]).unwrap();
+        assert_path(
+            &fast_graph,
+            NodeId(2),
+            NodeId(1),
+            Weight(58),
+            vec![NodeId(2), NodeId(0), NodeId(1)],
+        );
     }
 
     #[test]
     fn calc_path_linear_fwd_only() {
         // 1->0->2
         let mut g = InputGraph::new();
-        g.add_edge(1, 0, 9, 9.0);
-        g.add_edge(0, 2, 49, 49.0);
+        g.add_edge(NodeId(1), NodeId(0), Weight(9), 9.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(49), 49.0);
         g.freeze();
-        let fast_graph = prepare_with_order(&g, &vec![0, 1, 2]).unwrap();
-        assert_path(&fast_graph, 1, 2, 58, vec![1, 0, 2]);
+        let fast_graph = prepare_with_order(&g, &[NodeId(0), NodeId(1), NodeId(2)]).unwrap();
+        assert_path(
+            &fast_graph,
+            NodeId(1),
+            NodeId(2),
+            Weight(58),
+            vec![NodeId(1), NodeId(0), NodeId(2)],
+        );
     }
 
     #[test]
@@ -382,18 +1256,44 @@ mod tests {
         //  /          |
         // 0 - 1 - 2 - 3
         let mut g = InputGraph::new();
-        g.add_edge_bidir(0, 1, 5, 5.0);
-        g.add_edge_bidir(1, 2, 3, 3.0);
-        g.add_edge_bidir(2, 3, 2, 2.0);
-        g.add_edge_bidir(3, 4, 6, 6.0);
-        g.add_edge(0, 4, 2, 2.0);
+        g.add_edge_bidir(NodeId(0), NodeId(1), Weight(5), 5.0);
+        g.add_edge_bidir(NodeId(1), NodeId(2), Weight(3), 3.0);
+        g.add_edge_bidir(NodeId(2), NodeId(3), Weight(2), 2.0);
+        g.add_edge_bidir(NodeId(3), NodeId(4), Weight(6), 6.0);
+        g.add_edge(NodeId(0), NodeId(4), Weight(2), 2.0);
         g.freeze();
 
-        let fast_graph = prepare_with_order(&g, &vec![0, 1, 2, 3, 4]).unwrap();
-        assert_path(&fast_graph, 0, 4, 2, vec![0, 4]);
-        assert_path(&fast_graph, 4, 0, 16, vec![4, 3, 2, 1, 0]);
-        assert_path(&fast_graph, 1, 4, 7, vec![1, 0, 4]);
-        assert_path(&fast_graph, 2, 4, 8, vec![2, 3, 4]);
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)])
+                .unwrap();
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(4),
+            Weight(2),
+            vec![NodeId(0), NodeId(4)],
+        );
+        assert_path(
+            &fast_graph,
+            NodeId(4),
+            NodeId(0),
+            Weight(16),
+            vec![NodeId(4), NodeId(3), NodeId(2), NodeId(1), NodeId(0)],
+        );
+        assert_path(
+            &fast_graph,
+            NodeId(1),
+            NodeId(4),
+            Weight(7),
+            vec![NodeId(1), NodeId(0), NodeId(4)],
+        );
+        assert_path(
+            &fast_graph,
+            NodeId(2),
+            NodeId(4),
+            Weight(8),
+            vec![NodeId(2), NodeId(3), NodeId(4)],
+        );
     }
 
     #[test]
@@ -404,17 +1304,25 @@ mod tests {
         // |    |
         // 3  - 1
         let mut g = InputGraph::new();
-        g.add_edge(0, 2, 1, 1.0);
-        g.add_edge(0, 4, 9, 9.0);
-        g.add_edge(1, 3, 3, 3.0);
-        g.add_edge(2, 1, 8, 8.0);
-        g.add_edge(3, 0, 4, 4.0);
-        g.add_edge(3, 1, 8, 8.0);
-        g.add_edge(4, 2, 4, 4.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(4), Weight(9), 9.0);
+        g.add_edge(NodeId(1), NodeId(3), Weight(3), 3.0);
+        g.add_edge(NodeId(2), NodeId(1), Weight(8), 8.0);
+        g.add_edge(NodeId(3), NodeId(0), Weight(4), 4.0);
+        g.add_edge(NodeId(3), NodeId(1), Weight(8), 8.0);
+        g.add_edge(NodeId(4), NodeId(2), Weight(4), 4.0);
         g.freeze();
 
-        let fast_graph = prepare_with_order(&g, &vec![0, 1, 2, 3, 4]).unwrap();
-        assert_path(&fast_graph, 4, 3, 15, vec![4, 2, 1, 3]);
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)])
+                .unwrap();
+        assert_path(
+            &fast_graph,
+            NodeId(4),
+            NodeId(3),
+            Weight(15),
+            vec![NodeId(4), NodeId(2), NodeId(1), NodeId(3)],
+        );
     }
 
     fn assert_path(
@@ -438,11 +1346,11 @@ mod tests {
         // 0 -> 1 -> 2 <- 5
         // 3 -> 4 ->/
         let mut input_graph = InputGraph::new();
-        input_graph.add_edge(0, 1, 3, 3.0);
-        input_graph.add_edge(1, 2, 4, 4.0);
-        input_graph.add_edge(3, 4, 2, 2.0);
-        input_graph.add_edge(4, 2, 3, 3.0);
-        input_graph.add_edge(5, 2, 2, 2.0);
+        input_graph.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        input_graph.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        input_graph.add_edge(NodeId(3), NodeId(4), Weight(2), 2.0);
+        input_graph.add_edge(NodeId(4), NodeId(2), Weight(3), 3.0);
+        input_graph.add_edge(NodeId(5), NodeId(2), Weight(2), 2.0);
         input_graph.freeze();
         let fast_graph = prepare(&input_graph);
         let mut path_calculator = create_calculator(&fast_graph);
@@ -450,80 +1358,80 @@ mod tests {
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(0, 0), (3, 0)],
-            vec![(2, 0)],
-            vec![3, 4, 2],
-            5,
+            vec![(NodeId(0), Weight(0)), (NodeId(3), Weight(0))],
+            vec![(NodeId(2), Weight(0))],
+            vec![NodeId(3), NodeId(4), NodeId(2)],
+            Weight(5),
         );
         // two different options for source, with initial weights, 0->1->2's weight is higher,
         // but since the initial weight is smaller it is the shortest path
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(0, 1), (3, 4)],
-            vec![(2, 0)],
-            vec![0, 1, 2],
-            8,
+            vec![(NodeId(0), Weight(1)), (NodeId(3), Weight(4))],
+            vec![(NodeId(2), Weight(0))],
+            vec![NodeId(0), NodeId(1), NodeId(2)],
+            Weight(8),
         );
         // one option appearing twice with different initial weights, the smaller one should be taken
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(0, 5), (0, 3)],
-            vec![(2, 0)],
-            vec![0, 1, 2],
-            10,
+            vec![(NodeId(0), Weight(5)), (NodeId(0), Weight(3))],
+            vec![(NodeId(2), Weight(0))],
+            vec![NodeId(0), NodeId(1), NodeId(2)],
+            Weight(10),
         );
         // ... now put the smaller weight first
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(5, 10), (5, 20)],
-            vec![(2, 0)],
-            vec![5, 2],
-            12,
+            vec![(NodeId(5), Weight(10)), (NodeId(5), Weight(20))],
+            vec![(NodeId(2), Weight(0))],
+            vec![NodeId(5), NodeId(2)],
+            Weight(12),
         );
         // start options equal the target
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(1, 10), (1, 1)],
-            vec![(1, 0)],
-            vec![1],
-            1,
+            vec![(NodeId(1), Weight(10)), (NodeId(1), Weight(1))],
+            vec![(NodeId(1), Weight(0))],
+            vec![NodeId(1)],
+            Weight(1),
         );
         // one of the start options equals the target, but still the shortest path is another one
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(2, 10), (0, 0)],
-            vec![(2, 0)],
-            vec![0, 1, 2],
-            7,
+            vec![(NodeId(2), Weight(10)), (NodeId(0), Weight(0))],
+            vec![(NodeId(2), Weight(0))],
+            vec![NodeId(0), NodeId(1), NodeId(2)],
+            Weight(7),
         );
         // start options with max weight cannot yield a shortest path
         assert_path_multiple_sources_and_targets_not_found(
             &mut path_calculator,
             &fast_graph,
-            vec![(1, WEIGHT_MAX)],
-            vec![(1, 0)],
+            vec![(NodeId(1), WEIGHT_MAX)],
+            vec![(NodeId(1), Weight(0))],
         );
         // .. or at least they are ignored in case there are other ones
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(1, WEIGHT_MAX), (0, 3)],
-            vec![(1, 0)],
-            vec![0, 1],
-            6,
+            vec![(NodeId(1), WEIGHT_MAX), (NodeId(0), Weight(3))],
+            vec![(NodeId(1), Weight(0))],
+            vec![NodeId(0), NodeId(1)],
+            Weight(6),
         );
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(1, WEIGHT_MAX), (3, 3)],
-            vec![(2, 0)],
-            vec![3, 4, 2],
-            8,
+            vec![(NodeId(1), WEIGHT_MAX), (NodeId(3), Weight(3))],
+            vec![(NodeId(2), Weight(0))],
+            vec![NodeId(3), NodeId(4), NodeId(2)],
+            Weight(8),
         );
     }
 
@@ -532,10 +1440,10 @@ mod tests {
         // 0 <- 1 <- 2
         // 3 <- 4 <-/
         let mut input_graph = InputGraph::new();
-        input_graph.add_edge(1, 0, 3, 3.0);
-        input_graph.add_edge(2, 1, 4, 4.0);
-        input_graph.add_edge(4, 3, 2, 2.0);
-        input_graph.add_edge(2, 4, 3, 3.0);
+        input_graph.add_edge(NodeId(1), NodeId(0), Weight(3), 3.0);
+        input_graph.add_edge(NodeId(2), NodeId(1), Weight(4), 4.0);
+        input_graph.add_edge(NodeId(4), NodeId(3), Weight(2), 2.0);
+        input_graph.add_edge(NodeId(2), NodeId(4), Weight(3), 3.0);
         input_graph.freeze();
         let fast_graph = prepare(&input_graph);
         let mut path_calculator = create_calculator(&fast_graph);
@@ -543,36 +1451,36 @@ mod tests {
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(2, 0)],
-            vec![(0, 0), (3, 0)],
-            vec![2, 4, 3],
-            5,
+            vec![(NodeId(2), Weight(0))],
+            vec![(NodeId(0), Weight(0)), (NodeId(3), Weight(0))],
+            vec![NodeId(2), NodeId(4), NodeId(3)],
+            Weight(5),
         );
         // two different options for target, with initial weight
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(2, 0)],
-            vec![(0, 0), (3, 1)],
-            vec![2, 4, 3],
-            6,
+            vec![(NodeId(2), Weight(0))],
+            vec![(NodeId(0), Weight(0)), (NodeId(3), Weight(1))],
+            vec![NodeId(2), NodeId(4), NodeId(3)],
+            Weight(6),
         );
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(2, 0)],
-            vec![(0, 0), (3, 3)],
-            vec![2, 1, 0],
-            7,
+            vec![(NodeId(2), Weight(0))],
+            vec![(NodeId(0), Weight(0)), (NodeId(3), Weight(3))],
+            vec![NodeId(2), NodeId(1), NodeId(0)],
+            Weight(7),
         );
         // start==end
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(4, 0)],
-            vec![(4, 3), (4, 1)],
-            vec![4],
-            1,
+            vec![(NodeId(4), Weight(0))],
+            vec![(NodeId(4), Weight(3)), (NodeId(4), Weight(1))],
+            vec![NodeId(4)],
+            Weight(1),
         )
     }
 
@@ -581,33 +1489,451 @@ mod tests {
         // 0 -- 1 -- 2 -- 3 -- 4
         // 5 -- 6 --/ \-- 7 -- 8
         let mut input_graph = InputGraph::new();
-        input_graph.add_edge_bidir(0, 1, 1, 1.0);
-        input_graph.add_edge_bidir(1, 2, 2, 2.0);
-        input_graph.add_edge_bidir(2, 3, 3, 3.0);
-        input_graph.add_edge_bidir(3, 4, 4, 4.0);
-        input_graph.add_edge_bidir(5, 6, 5, 5.0);
-        input_graph.add_edge_bidir(6, 2, 6, 6.0);
-        input_graph.add_edge_bidir(2, 7, 7, 7.0);
-        input_graph.add_edge_bidir(7, 8, 8, 8.0);
+        input_graph.add_edge_bidir(NodeId(0), NodeId(1), Weight(1), 1.0);
+        input_graph.add_edge_bidir(NodeId(1), NodeId(2), Weight(2), 2.0);
+        input_graph.add_edge_bidir(NodeId(2), NodeId(3), Weight(3), 3.0);
+        input_graph.add_edge_bidir(NodeId(3), NodeId(4), Weight(4), 4.0);
+        input_graph.add_edge_bidir(NodeId(5), NodeId(6), Weight(5), 5.0);
+        input_graph.add_edge_bidir(NodeId(6), NodeId(2), Weight(6), 6.0);
+        input_graph.add_edge_bidir(NodeId(2), NodeId(7), Weight(7), 7.0);
+        input_graph.add_edge_bidir(NodeId(7), NodeId(8), Weight(8), 8.0);
         input_graph.freeze();
         let fast_graph = prepare(&input_graph);
         let mut path_calculator = create_calculator(&fast_graph);
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(1, 7), (6, 2), (5, 6)],
-            vec![(3, 1), (4, 9), (5, 7)],
-            vec![6, 2, 3],
-            12,
+            vec![
+                (NodeId(1), Weight(7)),
+                (NodeId(6), Weight(2)),
+                (NodeId(5), Weight(6)),
+            ],
+            vec![
+                (NodeId(3), Weight(1)),
+                (NodeId(4), Weight(9)),
+                (NodeId(5), Weight(7)),
+            ],
+            vec![NodeId(6), NodeId(2), NodeId(3)],
+            Weight(12),
         );
         assert_path_multiple_sources_and_targets(
             &mut path_calculator,
             &fast_graph,
-            vec![(1, 7), (6, 2)],
-            vec![(1, 9), (6, 3)],
-            vec![6],
-            5,
+            vec![(NodeId(1), Weight(7)), (NodeId(6), Weight(2))],
+            vec![(NodeId(1), Weight(9)), (NodeId(6), Weight(3))],
+            vec![NodeId(6)],
+            Weight(5),
+        );
+    }
+
+    #[test]
+    fn preparation_report() {
+        // 0 -> 1 -> 2 -> 3 -> 4
+        //  \-------<------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(0), Weight(5), 5.0);
+        g.freeze();
+        let (fast_graph, report) =
+            FastGraphBuilder::build_with_params_reported(&g, &Params::default());
+        assert_eq!(
+            fast_graph.get_num_shortcut_out_edges() + fast_graph.get_num_shortcut_in_edges(),
+            report.shortcuts_added
+        );
+        assert!(report.shortcuts_added > 0);
+        assert_eq!(5, report.rounds);
+        assert!(report.peak_priority > 0);
+        assert_eq!(
+            FastGraphBuilder::build(&g).get_node_ordering(),
+            fast_graph.get_node_ordering()
+        );
+
+        // the core nodes are not contracted
+        let (_, report) = FastGraphBuilder::build_with_params_reported(
+            &g,
+            &Params::with_core_nodes(vec![NodeId(3), NodeId(0)]),
         );
+        assert_eq!(3, report.rounds);
+    }
+
+    #[test]
+    fn shortcut_accounting() {
+        // 0 -> 1 -> 2 -> 3 -> 4
+        let mut g = InputGraph::new();
+        for i in 0..4 {
+            g.add_edge(NodeId(i), NodeId(i + 1), Weight(1), 1.0);
+        }
+        g.freeze();
+        let (_, report) = FastGraphBuilder::build_with_params_reported(&g, &Params::default());
+        assert!(report.shortcuts_per_node.is_empty());
+        assert!(report.top_shortcut_nodes(3).is_empty());
+
+        let params = Params {
+            core_nodes: vec![NodeId(1), NodeId(2), NodeId(3)],
+            shortcut_accounting: true,
+            ..Params::default()
+        };
+        // no shortcuts are needed when contracting the end nodes
+        let (_, report) = FastGraphBuilder::build_with_params_reported(&g, &params);
+        assert_eq!(vec![0; 5], report.shortcuts_per_node);
+        assert!(report.top_shortcut_nodes(3).is_empty());
+
+        let params = Params {
+            core_nodes: vec![NodeId(0), NodeId(4)],
+            shortcut_accounting: true,
+            ..Params::default()
+        };
+        // each inner node has one in- and one out-neighbor when it is contracted
+        let (_, report) = FastGraphBuilder::build_with_params_reported(&g, &params);
+        assert_eq!(vec![0, 1, 1, 1, 0], report.shortcuts_per_node);
+        assert_eq!(
+            vec![(NodeId(1), 1), (NodeId(2), 1)],
+            report.top_shortcut_nodes(2)
+        );
+        assert_eq!(3, report.top_shortcut_nodes(10).len());
+    }
+
+    #[test]
+    fn rank_explanations() {
+        //   1   2
+        //    \ /
+        // 3 - 0 - 4
+        let mut g = InputGraph::new();
+        for i in 1..5 {
+            g.add_edge_bidir(NodeId(0), NodeId(i), Weight(1), 1.0);
+        }
+        g.freeze();
+        let fast_graph = FastGraphBuilder::build(&g);
+        assert_eq!(None, fast_graph.explain_rank(NodeId(0)));
+
+        let fast_graph =
+            FastGraphBuilder::build_with_params(&g, &Params::with_rank_explanations(true));
+        fast_graph.check_invariants().unwrap();
+        // the leaves do not require any shortcuts, so they are contracted first
+        for i in 1..5 {
+            let explanation = fast_graph.explain_rank(NodeId(i)).unwrap();
+            assert!(fast_graph.ranks[i] < fast_graph.ranks[0]);
+            assert_eq!(0, explanation.num_shortcuts);
+            assert_eq!(2, explanation.num_edges);
+            assert_eq!(0, explanation.level);
+        }
+        // the center lies on top of its contracted neighbors and lost its edges to them
+        let explanation = fast_graph.explain_rank(NodeId(0)).unwrap();
+        assert_eq!(1, explanation.level);
+        assert_eq!(0, explanation.num_edges);
+        assert_eq!(0, explanation.bias);
+        assert_eq!(explanation.relevance as usize, explanation.priority);
+        for i in 0..5 {
+            let explanation = fast_graph.explain_rank(NodeId(i)).unwrap();
+            assert!(explanation.priority <= fast_graph.explain_rank(NodeId(0)).unwrap().priority);
+        }
+        // the explanations are reordered along with the nodes
+        let (reordered, mapping) = fast_graph.reorder_by_rank();
+        for (i, &node) in mapping.iter().enumerate() {
+            assert_eq!(
+                fast_graph.explain_rank(node),
+                reordered.explain_rank(NodeId(i))
+            );
+        }
+
+        let params = Params {
+            core_nodes: vec![NodeId(0)],
+            rank_explanations: true,
+            ..Params::default()
+        };
+        let fast_graph = FastGraphBuilder::build_with_params(&g, &params);
+        assert_eq!(None, fast_graph.explain_rank(NodeId(0)));
+        assert!(fast_graph.explain_rank(NodeId(1)).is_some());
+        assert_eq!(None, fast_graph.explain_rank(NodeId(5)));
+    }
+
+    #[test]
+    fn core_nodes_are_not_contracted() {
+        // 0 -> 1 -> 2 -> 3 -> 4
+        //  \-------<------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(0), Weight(5), 5.0);
+        g.freeze();
+        let fast_graph = FastGraphBuilder::build_with_params(
+            &g,
+            &Params::with_core_nodes(vec![NodeId(3), NodeId(0), NodeId(3)]),
+        );
+        assert_eq!(3, fast_graph.ranks[3]);
+        assert_eq!(4, fast_graph.ranks[0]);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(4),
+            Weight(4),
+            vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+        );
+        assert_path(
+            &fast_graph,
+            NodeId(3),
+            NodeId(2),
+            Weight(7),
+            vec![NodeId(3), NodeId(0), NodeId(1), NodeId(2)],
+        );
+        assert_path_multiple_sources_and_targets(
+            &mut calc,
+            &fast_graph,
+            vec![(NodeId(2), Weight(0))],
+            vec![(NodeId(1), Weight(0))],
+            vec![NodeId(2), NodeId(3), NodeId(0), NodeId(1)],
+            Weight(7),
+        );
+    }
+
+    #[test]
+    fn build_partitioned() {
+        // cell 0: 0 - 1 - 2, cell 1: 3 - 4 - 5, connected via 2 - 3
+        let mut g = InputGraph::new();
+        g.add_edge_bidir(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge_bidir(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge_bidir(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge_bidir(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.add_edge_bidir(NodeId(4), NodeId(5), Weight(1), 1.0);
+        g.freeze();
+        let partition = vec![0, 0, 0, 1, 1, 1];
+        let fast_graph =
+            FastGraphBuilder::build_partitioned(&g, &partition, &Params::default()).unwrap();
+        // the boundary nodes are on top of the hierarchy
+        assert!(fast_graph.ranks[2] >= 4);
+        assert!(fast_graph.ranks[3] >= 4);
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(5),
+            Weight(5),
+            vec![
+                NodeId(0),
+                NodeId(1),
+                NodeId(2),
+                NodeId(3),
+                NodeId(4),
+                NodeId(5),
+            ],
+        );
+        assert_path(
+            &fast_graph,
+            NodeId(4),
+            NodeId(1),
+            Weight(3),
+            vec![NodeId(4), NodeId(3), NodeId(2), NodeId(1)],
+        );
+
+        // core nodes are still not contracted
+        let params = Params::with_core_nodes(vec![NodeId(1)]);
+        let fast_graph = FastGraphBuilder::build_partitioned(&g, &partition, &params).unwrap();
+        assert_eq!(5, fast_graph.ranks[1]);
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(5),
+            Weight(5),
+            vec![
+                NodeId(0),
+                NodeId(1),
+                NodeId(2),
+                NodeId(3),
+                NodeId(4),
+                NodeId(5),
+            ],
+        );
+    }
+
+    #[test]
+    fn build_partitioned_with_wrong_partition_length() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.freeze();
+        let result = FastGraphBuilder::build_partitioned(&g, &[0], &Params::default());
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn build_with_initial_priorities() {
+        // 0 -> 1 -> 2 -> 3 -> 4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        assert!(
+            FastGraphBuilder::build_with_initial_priorities(&g, &[0; 4], &Params::default())
+                .is_err()
+        );
+        // the two end nodes are contracted first in any case, but the hints decide which one
+        let priorities = [-5, 9000, 9000, 9000, 3];
+        let fast_graph =
+            FastGraphBuilder::build_with_initial_priorities(&g, &priorities, &Params::default())
+                .unwrap();
+        assert_eq!(0, fast_graph.ranks[0]);
+        assert_eq!(1, fast_graph.ranks[4]);
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(4),
+            Weight(4),
+            vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+        );
+        let priorities = [3, 9000, 9000, 9000, 0];
+        let fast_graph =
+            FastGraphBuilder::build_with_initial_priorities(&g, &priorities, &Params::default())
+                .unwrap();
+        assert_eq!(0, fast_graph.ranks[4]);
+        assert_eq!(1, fast_graph.ranks[0]);
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(4),
+            Weight(4),
+            vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+        );
+    }
+
+    #[test]
+    fn witness_hop_limit() {
+        assert_eq!(usize::MAX, Params::default().get_witness_hop_limit(5, 10));
+        let params = Params::with_adaptive_witness_hops(vec![(0.2, 1), (0.5, 3), (0.9, 10)]);
+        assert_eq!(usize::MAX, params.get_witness_hop_limit(0, 10));
+        assert_eq!(1, params.get_witness_hop_limit(2, 10));
+        assert_eq!(1, params.get_witness_hop_limit(4, 10));
+        assert_eq!(3, params.get_witness_hop_limit(5, 10));
+        assert_eq!(10, params.get_witness_hop_limit(10, 10));
+    }
+
+    #[test]
+    fn max_hierarchy_levels() {
+        // 0 -> 1 -> 2 -> 3 -> 4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        // no level at all means nothing is contracted and all edges are kept
+        let fast_graph =
+            FastGraphBuilder::build_with_params(&g, &Params::with_max_hierarchy_levels(0));
+        assert_eq!(vec![0, 1, 2, 3, 4], fast_graph.ranks);
+        assert_eq!(4, fast_graph.get_num_out_edges());
+        assert_eq!(4, fast_graph.get_num_in_edges());
+        assert_eq!(0, fast_graph.get_num_shortcut_out_edges());
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(4),
+            Weight(4),
+            vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+        );
+
+        // with a single level the contraction stops once a node has a contracted neighbor, so
+        // only the nodes at both ends are contracted and 1, 2, 3 remain in the core
+        let fast_graph =
+            FastGraphBuilder::build_with_params(&g, &Params::with_max_hierarchy_levels(1));
+        assert_eq!(vec![0, 2, 3, 4, 1], fast_graph.ranks);
+        // the two edges between the core nodes are stored at both of their nodes
+        assert_eq!(3, fast_graph.get_num_out_edges());
+        assert_eq!(3, fast_graph.get_num_in_edges());
+        assert_eq!(0, fast_graph.get_num_shortcut_out_edges());
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(4),
+            Weight(4),
+            vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+        );
+        assert_path(
+            &fast_graph,
+            NodeId(1),
+            NodeId(3),
+            Weight(2),
+            vec![NodeId(1), NodeId(2), NodeId(3)],
+        );
+        assert!(calc_path(&fast_graph, NodeId(4), NodeId(0)).is_none());
+    }
+
+    #[test]
+    fn contraction_budget() {
+        // 0 -> 1 -> 2 -> 3 -> 4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        let (fast_graph, report) =
+            FastGraphBuilder::build_with_params_reported(&g, &Params::with_contraction_budget(0.0));
+        assert_eq!(0, report.rounds);
+        assert_eq!(vec![0, 1, 2, 3, 4], fast_graph.ranks);
+        assert_eq!(0, fast_graph.get_num_shortcut_out_edges());
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(4),
+            Weight(4),
+            vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+        );
+
+        // 2.5 nodes are rounded down to 2
+        let (fast_graph, report) =
+            FastGraphBuilder::build_with_params_reported(&g, &Params::with_contraction_budget(0.5));
+        assert_eq!(2, report.rounds);
+        fast_graph.check_invariants().unwrap();
+        assert_path(
+            &fast_graph,
+            NodeId(0),
+            NodeId(4),
+            Weight(4),
+            vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+        );
+        assert_path(
+            &fast_graph,
+            NodeId(1),
+            NodeId(3),
+            Weight(2),
+            vec![NodeId(1), NodeId(2), NodeId(3)],
+        );
+        assert!(calc_path(&fast_graph, NodeId(4), NodeId(0)).is_none());
+
+        let (_, report) =
+            FastGraphBuilder::build_with_params_reported(&g, &Params::with_contraction_budget(1.0));
+        assert_eq!(5, report.rounds);
+    }
+
+    #[test]
+    #[should_panic(expected = "The contraction budget must be between 0 and 1, but was 1.5")]
+    fn contraction_budget_too_large() {
+        let input_graph = create_order_test_graph();
+        FastGraphBuilder::build_with_params(&input_graph, &Params::with_contraction_budget(1.5));
+    }
+
+    #[test]
+    #[should_panic(expected = "The number of node coordinates (2) must be equal")]
+    fn spatial_priority_with_wrong_number_of_coordinates() {
+        let input_graph = create_order_test_graph();
+        FastGraphBuilder::build_with_params(
+            &input_graph,
+            &Params::with_spatial_priority(1.0, vec![(0.0, 0.0), (1.0, 1.0)]),
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Core nodes contain invalid node id: 5")]
+    fn core_nodes_with_invalid_node_id() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.freeze();
+        FastGraphBuilder::build_with_params(&g, &Params::with_core_nodes(vec![NodeId(5)]));
     }
 
     fn assert_path_multiple_sources_and_targets(
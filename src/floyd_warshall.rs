@@ -45,13 +45,13 @@ impl FloydWarshall {
         );
         let n = self.num_nodes;
         for e in input_graph.get_edges() {
-            self.matrix[e.from * n + e.to] = e.weight;
+            self.matrix[e.from.0 * n + e.to.0] = e.weight;
         }
         for k in 0..n {
             for i in 0..n {
                 for j in 0..n {
                     if i == j {
-                        self.matrix[i * n + j] = 0;
+                        self.matrix[i * n + j] = Weight(0);
                     }
                     let weight_ik = self.matrix[i * n + k];
                     let weight_kj = self.matrix[k * n + j];
@@ -66,7 +66,7 @@ impl FloydWarshall {
     }
 
     pub fn calc_weight(&self, source: NodeId, target: NodeId) -> Weight {
-        return self.matrix[source * self.num_nodes + target];
+        return self.matrix[source.0 * self.num_nodes + target.0];
     }
 }
 
@@ -82,24 +82,24 @@ mod tests {
         //      |    |
         //      7 -> 8
         let mut g = InputGraph::new();
-        g.add_edge(0, 1, 6, 6.0);
-        g.add_edge(0, 4, 1, 1.0);
-        g.add_edge(4, 5, 1, 1.0);
-        g.add_edge(5, 7, 1, 1.0);
-        g.add_edge(7, 8, 1, 1.0);
-        g.add_edge(8, 6, 1, 1.0);
-        g.add_edge(6, 3, 1, 1.0);
-        g.add_edge(3, 1, 1, 1.0);
-        g.add_edge(1, 3, 1, 1.0);
-        g.add_edge(5, 6, 4, 4.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(6), 6.0);
+        g.add_edge(NodeId(0), NodeId(4), Weight(1), 1.0);
+        g.add_edge(NodeId(4), NodeId(5), Weight(1), 1.0);
+        g.add_edge(NodeId(5), NodeId(7), Weight(1), 1.0);
+        g.add_edge(NodeId(7), NodeId(8), Weight(1), 1.0);
+        g.add_edge(NodeId(8), NodeId(6), Weight(1), 1.0);
+        g.add_edge(NodeId(6), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(5), NodeId(6), Weight(4), 4.0);
         g.freeze();
         let mut fw = FloydWarshall::new(g.get_num_nodes());
         fw.prepare(&g);
-        assert_eq!(fw.calc_weight(0, 3), 6);
-        assert_eq!(fw.calc_weight(5, 3), 4);
-        assert_eq!(fw.calc_weight(1, 1), 0);
-        assert_eq!(fw.calc_weight(5, 5), 0);
-        assert_eq!(fw.calc_weight(6, 5), WEIGHT_MAX);
-        assert_eq!(fw.calc_weight(8, 0), WEIGHT_MAX);
+        assert_eq!(fw.calc_weight(NodeId(0), NodeId(3)), Weight(6));
+        assert_eq!(fw.calc_weight(NodeId(5), NodeId(3)), Weight(4));
+        assert_eq!(fw.calc_weight(NodeId(1), NodeId(1)), Weight(0));
+        assert_eq!(fw.calc_weight(NodeId(5), NodeId(5)), Weight(0));
+        assert_eq!(fw.calc_weight(NodeId(6), NodeId(5)), WEIGHT_MAX);
+        assert_eq!(fw.calc_weight(NodeId(8), NodeId(0)), WEIGHT_MAX);
     }
 }
@@ -17,7 +17,7 @@
  * under the License.
  */
 
-use std::cmp::Ordering;
+use core::cmp::Ordering;
 
 use crate::constants::NodeId;
 use crate::constants::Weight;
@@ -40,14 +40,19 @@ impl PartialOrd for HeapItem {
     }
 }
 
+/// Items with smaller weight are greater, so a `BinaryHeap` yields them first. Items with equal
+/// weight are ordered by node id, so the order in which nodes are settled does not depend on the
+/// order in which they were pushed.
 impl Ord for HeapItem {
     fn cmp(&self, other: &HeapItem) -> Ordering {
-        self.weight.cmp(&other.weight).reverse()
+        (self.weight, self.node_id)
+            .cmp(&(other.weight, other.node_id))
+            .reverse()
     }
 }
 
 impl PartialEq for HeapItem {
     fn eq(&self, other: &HeapItem) -> bool {
-        self.weight == other.weight
+        self.weight == other.weight && self.node_id == other.node_id
     }
 }
@@ -0,0 +1,242 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use crate::heap_item::HeapItem;
+
+const ARITY: usize = 4;
+const NOT_IN_HEAP: usize = usize::MAX;
+
+/// A 4-ary min-heap of nodes that keeps track of the position of every node so the weight of a
+/// node that is already in the heap can be decreased instead of pushing another item for the
+/// same node. Nodes with equal weight are popped in order of their id, see `HeapItem`.
+pub struct IndexedHeap {
+    items: Vec<HeapItem>,
+    positions: Vec<usize>,
+}
+
+impl IndexedHeap {
+    #[cfg(any(test, feature = "std"))]
+    pub fn new(num_nodes: usize) -> Self {
+        IndexedHeap::with_capacity(num_nodes, 0)
+    }
+
+    /// Creates a heap that can hold `capacity` nodes before it needs to grow. The capacity is
+    /// limited to the number of nodes, because every node is contained at most once.
+    pub fn with_capacity(num_nodes: usize, capacity: usize) -> Self {
+        IndexedHeap {
+            items: Vec::with_capacity(capacity.min(num_nodes)),
+            positions: vec![NOT_IN_HEAP; num_nodes],
+        }
+    }
+
+    #[cfg(test)]
+    pub fn capacity(&self) -> usize {
+        self.items.capacity()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Removes all items. This takes time proportional to the number of items in the heap, not
+    /// the number of nodes.
+    pub fn clear(&mut self) {
+        for item in &self.items {
+            self.positions[item.node_id.0] = NOT_IN_HEAP;
+        }
+        self.items.clear();
+    }
+
+    /// Removes all items and writes to the memory of all positions, so it is resident afterwards
+    pub fn reset(&mut self) {
+        self.items.clear();
+        self.positions.fill(NOT_IN_HEAP);
+    }
+
+    #[cfg(any(test, feature = "std"))]
+    pub fn peek(&self) -> Option<&HeapItem> {
+        self.items.first()
+    }
+
+    /// Adds the given node to the heap, or updates its weight if the node is already contained
+    /// and the new weight is smaller. Larger weights are ignored for nodes in the heap.
+    pub fn push_or_decrease(&mut self, item: HeapItem) {
+        let pos = self.positions[item.node_id.0];
+        if pos == NOT_IN_HEAP {
+            self.items.push(item);
+            self.sift_up(self.items.len() - 1);
+        } else if item.weight < self.items[pos].weight {
+            self.items[pos].weight = item.weight;
+            self.sift_up(pos);
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<HeapItem> {
+        if self.items.is_empty() {
+            return None;
+        }
+        let result = self.items.swap_remove(0);
+        self.positions[result.node_id.0] = NOT_IN_HEAP;
+        if !self.items.is_empty() {
+            self.sift_down(0);
+        }
+        Some(result)
+    }
+
+    fn sift_up(&mut self, mut pos: usize) {
+        let item = self.items[pos];
+        while pos > 0 {
+            let parent = (pos - 1) / ARITY;
+            if item <= self.items[parent] {
+                break;
+            }
+            self.move_item(parent, pos);
+            pos = parent;
+        }
+        self.place_item(item, pos);
+    }
+
+    fn sift_down(&mut self, mut pos: usize) {
+        let item = self.items[pos];
+        let len = self.items.len();
+        loop {
+            let first_child = ARITY * pos + 1;
+            if first_child >= len {
+                break;
+            }
+            let last_child = (first_child + ARITY).min(len);
+            let mut min_child = first_child;
+            for child in first_child + 1..last_child {
+                if self.items[child] > self.items[min_child] {
+                    min_child = child;
+                }
+            }
+            if item >= self.items[min_child] {
+                break;
+            }
+            self.move_item(min_child, pos);
+            pos = min_child;
+        }
+        self.place_item(item, pos);
+    }
+
+    fn move_item(&mut self, from: usize, to: usize) {
+        self.items[to] = self.items[from];
+        self.positions[self.items[to].node_id.0] = to;
+    }
+
+    fn place_item(&mut self, item: HeapItem, pos: usize) {
+        self.items[pos] = item;
+        self.positions[item.node_id.0] = pos;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::Rng;
+
+    use super::*;
+    use crate::constants::{NodeId, Weight};
+
+    #[test]
+    fn with_capacity() {
+        assert_eq!(0, IndexedHeap::new(10).capacity());
+        assert!(IndexedHeap::with_capacity(10, 4).capacity() >= 4);
+        let mut heap = IndexedHeap::with_capacity(10, 100);
+        assert!(heap.capacity() >= 10 && heap.capacity() < 100);
+        for node in 0..10 {
+            heap.push_or_decrease(HeapItem::new(Weight(10 - node), NodeId(node)));
+        }
+        assert_eq!(pop(&mut heap), Some((Weight(1), NodeId(9))));
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let mut heap = IndexedHeap::new(10);
+        assert!(heap.is_empty());
+        assert_eq!(None, heap.pop());
+        heap.push_or_decrease(HeapItem::new(Weight(5), NodeId(3)));
+        heap.push_or_decrease(HeapItem::new(Weight(2), NodeId(7)));
+        heap.push_or_decrease(HeapItem::new(Weight(9), NodeId(1)));
+        assert_eq!(NodeId(7), heap.peek().unwrap().node_id);
+        assert_eq!(pop(&mut heap), Some((Weight(2), NodeId(7))));
+        assert_eq!(pop(&mut heap), Some((Weight(5), NodeId(3))));
+        assert_eq!(pop(&mut heap), Some((Weight(9), NodeId(1))));
+        assert_eq!(pop(&mut heap), None);
+    }
+
+    #[test]
+    fn decrease_key() {
+        let mut heap = IndexedHeap::new(10);
+        heap.push_or_decrease(HeapItem::new(Weight(5), NodeId(3)));
+        heap.push_or_decrease(HeapItem::new(Weight(4), NodeId(7)));
+        heap.push_or_decrease(HeapItem::new(Weight(3), NodeId(3)));
+        // larger weights are ignored
+        heap.push_or_decrease(HeapItem::new(Weight(8), NodeId(7)));
+        assert_eq!(pop(&mut heap), Some((Weight(3), NodeId(3))));
+        assert_eq!(pop(&mut heap), Some((Weight(4), NodeId(7))));
+        assert!(heap.is_empty());
+    }
+
+    #[test]
+    fn clear() {
+        let mut heap = IndexedHeap::new(10);
+        heap.push_or_decrease(HeapItem::new(Weight(5), NodeId(3)));
+        heap.push_or_decrease(HeapItem::new(Weight(4), NodeId(7)));
+        heap.clear();
+        assert!(heap.is_empty());
+        heap.push_or_decrease(HeapItem::new(Weight(6), NodeId(3)));
+        assert_eq!(pop(&mut heap), Some((Weight(6), NodeId(3))));
+        assert_eq!(pop(&mut heap), None);
+    }
+
+    #[test]
+    fn random_operations() {
+        const NUM_NODES: usize = 100;
+        let mut rng = rand::thread_rng();
+        for _i in 0..100 {
+            let mut heap = IndexedHeap::new(NUM_NODES);
+            let mut weights: Vec<Option<Weight>> = vec![None; NUM_NODES];
+            for _j in 0..500 {
+                if rng.gen_bool(0.3) {
+                    let expected = weights.iter().filter_map(|w| *w).min();
+                    let popped = heap.pop();
+                    assert_eq!(expected, popped.map(|item| item.weight));
+                    if let Some(item) = popped {
+                        assert_eq!(weights[item.node_id.0], Some(item.weight));
+                        weights[item.node_id.0] = None;
+                    }
+                } else {
+                    let node = rng.gen_range(0, NUM_NODES);
+                    let weight = rng.gen_range(0, 1000);
+                    heap.push_or_decrease(HeapItem::new(Weight(weight), NodeId(node)));
+                    weights[node] =
+                        Some(weights[node].map_or(Weight(weight), |w| w.min(Weight(weight))));
+                }
+            }
+        }
+    }
+
+    fn pop(heap: &mut IndexedHeap) -> Option<(Weight, NodeId)> {
+        heap.pop().map(|item| (item.weight, item.node_id))
+    }
+}
@@ -18,8 +18,10 @@
  */
 
 use std::cmp;
+use std::error::Error;
 use std::fmt;
 use std::fs::File;
+use std::io;
 use std::io::{BufRead, BufReader, BufWriter, Write};
 
 #[cfg(test)]
@@ -29,13 +31,59 @@ use rand::Rng;
 
 use serde::{Deserialize, Serialize};
 
-use crate::constants::{Length, NodeId, Weight};
+use crate::constants::{
+    is_infinite, EdgeData, Length, NodeId, TimeOfDay, Weight, INVALID_EDGE_DATA, WEIGHT_MAX,
+};
+use crate::fast_graph::FastGraph;
+use crate::time_dependent::WeightProfile;
 
 #[derive(Serialize, Deserialize, Clone)]
 pub struct InputGraph {
     edges: Vec<Edge>,
     num_nodes: usize,
     frozen: bool,
+    /// the weight profiles of time-dependent edges, sorted by (from, to) once frozen
+    #[serde(default)]
+    profiles: Vec<(NodeId, NodeId, WeightProfile)>,
+    /// the factor the weights passed to `add_edge_f64` are multiplied with
+    #[serde(default = "default_weight_scale")]
+    weight_scale: f64,
+    /// the way the (scaled) weights passed to `add_edge_f64` are rounded to integers
+    #[serde(default)]
+    rounding: RoundingMode,
+    /// the number of incoming edges of each node, computed by `freeze`, see `in_degree`
+    #[serde(skip)]
+    in_degrees: Vec<usize>,
+}
+
+fn default_weight_scale() -> f64 {
+    1.0
+}
+
+/// Determines how the floating point weights passed to `InputGraph::add_edge_f64` are converted
+/// to integer weights, see `InputGraph::set_rounding`
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoundingMode {
+    /// Rounds to the nearest integer (half-way cases away from zero). The rounding errors of the
+    /// individual edges tend to cancel out, so this yields the smallest error for the weight of
+    /// long paths.
+    #[default]
+    Nearest,
+    /// Rounds up, so the integer weights are never smaller than the actual weights and edges with
+    /// a small positive weight never get a zero weight
+    Ceil,
+    /// Rounds down, so the integer weights are never larger than the actual weights
+    Floor,
+}
+
+impl RoundingMode {
+    fn round(self, weight: f64) -> f64 {
+        match self {
+            RoundingMode::Nearest => weight.round(),
+            RoundingMode::Ceil => weight.ceil(),
+            RoundingMode::Floor => weight.floor(),
+        }
+    }
 }
 
 impl InputGraph {
@@ -44,24 +92,130 @@ impl InputGraph {
             edges: Vec::new(),
             num_nodes: 0,
             frozen: false,
+            profiles: Vec::new(),
+            weight_scale: default_weight_scale(),
+            rounding: RoundingMode::default(),
+            in_degrees: Vec::new(),
         }
     }
 
+    /// Like `new`, but reserves space for `num_edges` edges, which avoids reallocating the edges
+    /// while they are added, e.g. when importing a large graph whose size is known beforehand.
+    /// The graph has at least `num_nodes` nodes (more if an edge is added for a node with a larger
+    /// id), so unlike for `new` nodes without edges at the end are kept.
+    pub fn with_capacity(num_nodes: usize, num_edges: usize) -> Self {
+        let mut g = InputGraph::new();
+        g.num_nodes = num_nodes;
+        g.in_degrees.reserve(num_nodes);
+        g.reserve_edges(num_edges);
+        g
+    }
+
+    /// Reserves space for at least `additional` more edges. Note that bidirectional edges (see
+    /// `add_edge_bidir`) are stored as two edges.
+    pub fn reserve_edges(&mut self, additional: usize) {
+        self.edges.reserve(additional);
+    }
+
+    /// Builds a (frozen) input graph from the out-edges of each node: `adj[i]` contains the
+    /// (target, weight) pairs of the edges starting at node i, so the graph has `adj.len()` nodes.
+    /// The weights are also used as lengths of the edges. Panics if there is an edge to a node
+    /// that is not in [0, adj.len()).
+    pub fn from_adjacency_list(adj: &[Vec<(NodeId, Weight)>]) -> Self {
+        let mut g = InputGraph::new();
+        for (from, edges) in adj.iter().enumerate() {
+            for &(to, weight) in edges {
+                if to.0 >= adj.len() {
+                    panic!(
+                        "invalid target node id {} for an edge of node {}, must be in [0, {})",
+                        to,
+                        from,
+                        adj.len()
+                    );
+                }
+                g.add_edge(NodeId(from), to, weight, weight.0 as Length);
+            }
+        }
+        // nodes without any edges still count
+        g.num_nodes = adj.len();
+        g.freeze();
+        g
+    }
+
+    /// Builds a (frozen) input graph from the given `petgraph::Graph`. The node indices of a
+    /// `petgraph::Graph` are dense, so node `i` of the returned graph is the node with index `i`,
+    /// including the nodes without edges. The returned vector maps the node ids back to the
+    /// node indices. The weight of every edge is calculated by `weight_fn` and added using
+    /// `add_edge_f64`, so it is rounded to an integer and edges with a negative or NaN weight are
+    /// skipped. The weights are also used as lengths of the edges. Undirected edges are added in
+    /// both directions.
+    #[cfg(feature = "petgraph")]
+    pub fn from_petgraph<N, E, Ty, Ix>(
+        graph: &petgraph::Graph<N, E, Ty, Ix>,
+        weight_fn: impl Fn(&E) -> f64,
+    ) -> (Self, Vec<petgraph::graph::NodeIndex<Ix>>)
+    where
+        Ty: petgraph::EdgeType,
+        Ix: petgraph::graph::IndexType,
+    {
+        let mut g = InputGraph::new();
+        for edge in graph.raw_edges() {
+            let from = NodeId(edge.source().index());
+            let to = NodeId(edge.target().index());
+            let weight = weight_fn(&edge.weight);
+            g.add_edge_f64(from, to, weight, weight);
+            if !graph.is_directed() {
+                g.add_edge_f64(to, from, weight, weight);
+            }
+        }
+        // nodes without any edges still count
+        g.num_nodes = graph.node_count();
+        g.freeze();
+        (g, graph.node_indices().collect())
+    }
+
+    /// Builds a (frozen) input graph from the original (non-shortcut) edges of the given
+    /// `FastGraph` and the original edges that were replaced by shortcuts, including their lengths
+    /// and data, see `FastGraph::to_input_graph`
+    pub(crate) fn from_fast_graph(fast_graph: &FastGraph) -> Self {
+        let mut g = InputGraph::new();
+        let original_edges = fast_graph.edges_fwd.iter().filter(|e| !e.is_shortcut());
+        for e in original_edges.chain(fast_graph.get_replaced_edges()) {
+            g.add_edge_with_data(e.base_node, e.adj_node, e.weight, e.length, e.data);
+        }
+        // original edges stored as in-edges point from the adj_node to the base_node
+        for e in fast_graph.edges_bwd.iter().filter(|e| !e.is_shortcut()) {
+            g.add_edge_with_data(e.adj_node, e.base_node, e.weight, e.length, e.data);
+        }
+        // the edges between core nodes are stored both as out- and as in-edges
+        g.merge_parallel_edges();
+        g.num_nodes = fast_graph.get_num_nodes();
+        g.weight_scale = fast_graph.get_weight_scale();
+        g.freeze();
+        g
+    }
+
     /// Builds a random input graph, mostly used for testing purposes
     #[cfg(test)]
     pub fn random(rng: &mut StdRng, num_nodes: usize, mean_degree: f32) -> Self {
         InputGraph::build_random_graph(rng, num_nodes, mean_degree)
     }
 
-    /// Reads an input graph from a text file, using the following format:
-    /// a <from> <to> <weight>
-    /// where <from>,<to> and <weight> must be >= 0.
-    /// All other lines are ignored.
-    /// This function is compatible to DIMACS files, but consider using InputGraph::from_dimacs
-    /// for these instead.
+    /// Reads an input graph from a text file. The format is detected from the first line that is
+    /// neither empty nor a comment (starting with 'c'):
+    /// * 'p': the file is read as DIMACS file, see InputGraph::from_dimacs_file
+    /// * 'a': there is one line per (directed) edge:
+    ///   a <from> <to> <weight>
+    ///   where <from>,<to> and <weight> must be >= 0.
+    ///   All other lines are ignored.
+    /// * a number: there is one line per (directed) edge:
+    ///   <from> <to> <weight>
+    ///   where <from>,<to> and <weight> must be >= 0.
+    ///
+    /// Returns an error stating the line number if the file contains a malformed line.
     /// Mostly used for performance testing.
-    pub fn from_file(filename: &str) -> Self {
-        InputGraph::read_from_file(filename)
+    pub fn from_file(filename: &str) -> Result<Self, ParseError> {
+        InputGraph::read_from_file(filename, None)
     }
 
     /// Writes the input graph to a text file, using the following format:
@@ -89,9 +243,10 @@ impl InputGraph {
     ///   Note that here, in contrast to InputGraph::from_file, the node IDs are 1-based, not
     ///   0-based. They will be converted to 0-based IDs internally.
     ///
+    /// Returns an error stating the line number if the file contains a malformed line.
     /// Mostly used for performance testing.
-    pub fn from_dimacs_file(filename: &str) -> Self {
-        InputGraph::read_from_dimacs(filename)
+    pub fn from_dimacs_file(filename: &str) -> Result<Self, ParseError> {
+        InputGraph::read_from_file(filename, Some(FileFormat::Dimacs))
     }
 
     /// Writes the input graph to a text file, using the DIMACS format:
@@ -104,13 +259,168 @@ impl InputGraph {
         let mut f = BufWriter::new(File::create(filename)?);
         writeln!(f, "p sp {} {}", self.get_num_nodes(), self.get_num_edges())?;
         for edge in self.get_edges() {
-            writeln!(f, "a {} {} {}", edge.from + 1, edge.to + 1, edge.weight)?;
+            writeln!(f, "a {} {} {}", edge.from.0 + 1, edge.to.0 + 1, edge.weight)?;
         }
         Ok(())
     }
 
     pub fn add_edge(&mut self, from: NodeId, to: NodeId, weight: Weight, length: Length) -> usize {
-        self.do_add_edge(from, to, weight, length, false)
+        self.do_add_edge(from, to, weight, length, INVALID_EDGE_DATA, false)
+    }
+
+    /// Sets the factor that the weights passed to all following calls of `add_edge_f64` are
+    /// multiplied with before they are rounded to integers. Edges that were added before are not
+    /// changed. The factor is passed on to the prepared graph, so the weights of the calculated
+    /// shortest paths can be scaled back using `ShortestPath::get_weight_f64`. Panics if the
+    /// factor is not a positive finite number.
+    pub fn set_weight_scale(&mut self, factor: f64) {
+        if !(factor > 0.0 && factor.is_finite()) {
+            panic!(
+                "The weight scale must be positive and finite, but was {}",
+                factor
+            );
+        }
+        self.weight_scale = factor;
+    }
+
+    pub fn get_weight_scale(&self) -> f64 {
+        self.weight_scale
+    }
+
+    /// Multiplies the weights of all edges that were added so far with the given factor and
+    /// rounds them to integers (see `set_rounding`), e.g. to switch from meters to decimeters
+    /// for a better precision of the integer weights. Edges whose weight becomes infinite are
+    /// removed, just like they would be skipped by `add_edge`. The weight scale (see
+    /// `set_weight_scale`) is multiplied with the factor as well, so `ShortestPath::get_weight_f64`
+    /// still returns the weights in the original unit. The weight profiles of time-dependent
+    /// edges are not changed. Panics if the graph is frozen already or if the factor is not a
+    /// positive finite number.
+    pub fn scale_weights(&mut self, factor: f64) {
+        if self.frozen {
+            panic!("Graph is frozen already, for further changes first use thaw()");
+        }
+        if !(factor > 0.0 && factor.is_finite()) {
+            panic!(
+                "The scale factor must be positive and finite, but was {}",
+                factor
+            );
+        }
+        let rounding = self.rounding;
+        for edge in &mut self.edges {
+            let scaled_weight = rounding.round(edge.weight.0 as f64 * factor);
+            edge.weight = if scaled_weight >= WEIGHT_MAX.0 as f64 {
+                WEIGHT_MAX
+            } else {
+                Weight(scaled_weight as usize)
+            };
+        }
+        let len_before = self.edges.len();
+        self.edges.retain(|edge| !is_infinite(edge.weight));
+        let num_removed = len_before - self.edges.len();
+        if num_removed > 0 {
+            warn!(
+                "There were {} edges with infinite weight after scaling, they were removed",
+                num_removed
+            );
+        }
+        self.weight_scale *= factor;
+    }
+
+    /// Sets how the weights passed to all following calls of `add_edge_f64` are rounded to
+    /// integers after they were multiplied with the weight scale. The default is
+    /// `RoundingMode::Nearest`. Edges that were added before are not changed.
+    pub fn set_rounding(&mut self, rounding: RoundingMode) {
+        self.rounding = rounding;
+    }
+
+    pub fn get_rounding(&self) -> RoundingMode {
+        self.rounding
+    }
+
+    /// Adds an edge with a floating point weight, which is multiplied with the weight scale (see
+    /// `set_weight_scale`) and rounded to an integer (see `set_rounding`). Edges with a negative
+    /// or NaN weight are skipped, and just like for `add_edge` so are edges whose (scaled) weight
+    /// is infinite.
+    pub fn add_edge_f64(&mut self, from: NodeId, to: NodeId, weight: f64, length: Length) -> usize {
+        let scaled_weight = self.rounding.round(weight * self.weight_scale);
+        if scaled_weight.is_nan() || scaled_weight < 0.0 {
+            warn!(
+                "Negative or NaN weights are not allowed. Skipped edge! from: {}, to: {}, weight: \
+                 {}",
+                from, to, weight
+            );
+            return 0;
+        }
+        self.do_add_edge(
+            from,
+            to,
+            Weight(scaled_weight as usize),
+            length,
+            INVALID_EDGE_DATA,
+            false,
+        )
+    }
+
+    /// Adds an edge that carries some opaque user `data`, like an id referring to further
+    /// attributes of the edge. The data of the original edges of a shortest path can be obtained
+    /// using `ShortestPath::get_edge_data`. Note that if there are multiple edges between the same
+    /// two nodes only the one with the lowest weight is kept (along with its data).
+    pub fn add_edge_with_data(
+        &mut self,
+        from: NodeId,
+        to: NodeId,
+        weight: Weight,
+        length: Length,
+        data: EdgeData,
+    ) -> usize {
+        self.do_add_edge(from, to, weight, length, data, false)
+    }
+
+    /// Adds an edge whose weight depends on the time it is entered, see `WeightProfile` for the
+    /// interpolation of the given (time, weight) points and the requirements they must fulfill.
+    /// Invalid profiles are skipped just like invalid edges. The time-dependent weight is used by
+    /// `PathCalculator::calc_path_at`. For all other calculations (and the graph preparation)
+    /// the edge is treated like a regular edge with the minimum weight of the profile. If there
+    /// are multiple edges between the same two nodes and one of them has a profile, the profile
+    /// is used for time-dependent calculations.
+    pub fn add_edge_profile(
+        &mut self,
+        from: NodeId,
+        to: NodeId,
+        profile: Vec<(TimeOfDay, Weight)>,
+    ) -> usize {
+        let profile = match WeightProfile::new(profile) {
+            Ok(profile) => profile,
+            Err(message) => {
+                warn!(
+                    "Invalid weight profile. Skipped edge! from: {}, to: {}, reason: {}",
+                    from, to, message
+                );
+                return 0;
+            }
+        };
+        let weight = profile.get_min_weight();
+        let added = self.do_add_edge(
+            from,
+            to,
+            weight,
+            weight.0 as Length,
+            INVALID_EDGE_DATA,
+            false,
+        );
+        if added > 0 {
+            self.profiles.push((from, to, profile));
+        }
+        added
+    }
+
+    /// Returns the weight profile of the edge between the given nodes, if it has one
+    pub fn get_edge_profile(&self, from: NodeId, to: NodeId) -> Option<&WeightProfile> {
+        self.check_frozen();
+        self.profiles
+            .binary_search_by(|(f, t, _)| (*f, *t).cmp(&(from, to)))
+            .ok()
+            .map(|index| &self.profiles[index].2)
     }
 
     pub fn add_edge_bidir(
@@ -120,7 +430,7 @@ impl InputGraph {
         weight: Weight,
         length: Length,
     ) -> usize {
-        self.do_add_edge(from, to, weight, length, true)
+        self.do_add_edge(from, to, weight, length, INVALID_EDGE_DATA, true)
     }
 
     pub fn get_edges(&self) -> &Vec<Edge> {
@@ -128,6 +438,85 @@ impl InputGraph {
         &self.edges
     }
 
+    /// Returns the edges from `source` to `target` that were added so far. Unlike most other
+    /// methods this can also be used before the graph is frozen, in which case there can be
+    /// multiple such edges and it takes O(edges) time. Once the graph is frozen duplicate edges
+    /// are removed and the edges are found using binary search.
+    pub fn get_edges_between(&self, source: NodeId, target: NodeId) -> Vec<&Edge> {
+        if self.frozen {
+            let begin = self
+                .edges
+                .partition_point(|e| (e.from, e.to) < (source, target));
+            let end = self
+                .edges
+                .partition_point(|e| (e.from, e.to) <= (source, target));
+            self.edges[begin..end].iter().collect()
+        } else {
+            self.edges
+                .iter()
+                .filter(|e| e.from == source && e.to == target)
+                .collect()
+        }
+    }
+
+    /// Returns true if there is an edge from `source` to `target`, see `get_edges_between`
+    pub fn has_edge(&self, source: NodeId, target: NodeId) -> bool {
+        !self.get_edges_between(source, target).is_empty()
+    }
+
+    /// Returns the nodes without incoming edges in ascending order, e.g. to find one-way traps
+    /// that cannot be reached from anywhere else. Nodes without any edges are included as well.
+    /// Like `get_edges_between` this can also be used before the graph is frozen.
+    pub fn sources(&self) -> Vec<NodeId> {
+        let mut has_in_edge = vec![false; self.num_nodes];
+        for edge in &self.edges {
+            has_in_edge[edge.to.0] = true;
+        }
+        (0..self.num_nodes)
+            .map(NodeId)
+            .filter(|&n| !has_in_edge[n.0])
+            .collect()
+    }
+
+    /// Returns the nodes without outgoing edges (dead-ends) in ascending order, see `sources`
+    pub fn sinks(&self) -> Vec<NodeId> {
+        let mut has_out_edge = vec![false; self.num_nodes];
+        for edge in &self.edges {
+            has_out_edge[edge.from.0] = true;
+        }
+        (0..self.num_nodes)
+            .map(NodeId)
+            .filter(|&n| !has_out_edge[n.0])
+            .collect()
+    }
+
+    /// Returns the number of outgoing edges of the given node, zero for nodes without edges or
+    /// node ids that are not in the graph. Like `get_edges_between` this can also be used before
+    /// the graph is frozen, in which case there can be duplicate edges (which are counted) and it
+    /// takes O(edges) time. Once the graph is frozen the edges are found using binary search,
+    /// which takes O(log(edges)) time.
+    pub fn out_degree(&self, node: NodeId) -> usize {
+        if self.frozen {
+            let begin = self.edges.partition_point(|e| e.from < node);
+            let end = self.edges.partition_point(|e| e.from <= node);
+            end - begin
+        } else {
+            self.edges.iter().filter(|e| e.from == node).count()
+        }
+    }
+
+    /// Returns the number of incoming edges of the given node, see `out_degree`. Once the graph
+    /// is frozen this takes O(1) time, because the in-degrees are counted by `freeze`. They are
+    /// not serialized though, so for a deserialized graph this takes O(edges) time until it is
+    /// thawed and frozen again.
+    pub fn in_degree(&self, node: NodeId) -> usize {
+        if self.frozen && !self.in_degrees.is_empty() {
+            self.in_degrees.get(node.0).copied().unwrap_or(0)
+        } else {
+            self.edges.iter().filter(|e| e.to == node).count()
+        }
+    }
+
     pub fn get_num_nodes(&self) -> usize {
         self.check_frozen();
         self.num_nodes
@@ -143,11 +532,28 @@ impl InputGraph {
             panic!("Input graph is already frozen");
         }
         self.sort();
-        self.remove_duplicate_edges();
+        let num_removed = self.remove_duplicate_edges();
+        if num_removed > 0 {
+            warn!(
+                "There were {} duplicate edges, only the ones with lowest weight were kept",
+                num_removed
+            );
+        }
+        self.profiles.sort_by(|a, b| {
+            (a.0, a.1, a.2.get_min_weight()).cmp(&(b.0, b.1, b.2.get_min_weight()))
+        });
+        self.profiles.dedup_by(|a, b| a.0 == b.0 && a.1 == b.1);
+        // this reuses the memory reserved by `with_capacity`
+        self.in_degrees.clear();
+        self.in_degrees.resize(self.num_nodes, 0);
+        for edge in &self.edges {
+            self.in_degrees[edge.to.0] += 1;
+        }
         self.frozen = true;
     }
 
     pub fn thaw(&mut self) {
+        self.in_degrees.clear();
         self.frozen = false;
     }
 
@@ -160,16 +566,22 @@ impl InputGraph {
         });
     }
 
-    fn remove_duplicate_edges(&mut self) {
+    /// Removes all but the lowest weight edge between every pair of nodes and returns the number
+    /// of removed edges. This is also done by `freeze`, but doing it earlier can save memory when
+    /// the edges are added in several steps. Panics if the graph is frozen already.
+    pub fn merge_parallel_edges(&mut self) -> usize {
+        if self.frozen {
+            panic!("Graph is frozen already, for further changes first use thaw()");
+        }
+        self.sort();
+        self.remove_duplicate_edges()
+    }
+
+    fn remove_duplicate_edges(&mut self) -> usize {
         // we go through (already sorted!) list of edges and remove duplicates
         let len_before = self.edges.len();
         self.edges.dedup_by(|a, b| a.from == b.from && a.to == b.to);
-        if len_before != self.edges.len() {
-            warn!(
-                "There were {} duplicate edges, only the ones with lowest weight were kept",
-                len_before - self.edges.len()
-            );
-        }
+        len_before - self.edges.len()
     }
 
     pub fn unit_test_output_string(&self) -> String {
@@ -194,6 +606,7 @@ impl InputGraph {
         to: NodeId,
         weight: Weight,
         length: Length,
+        data: EdgeData,
         bidir: bool,
     ) -> usize {
         if self.frozen {
@@ -206,17 +619,19 @@ impl InputGraph {
             );
             return 0;
         }
-        if weight < 1 {
+        if is_infinite(weight) {
             warn!(
-                "Zero weight edges are not allowed. Skipped edge! from: {}, to: {}, weight: {}",
+                "Infinite weight edges are not allowed. Skipped edge! from: {}, to: {}, weight: {}",
                 from, to, weight
             );
             return 0;
         }
-        self.num_nodes = cmp::max(self.num_nodes, cmp::max(from, to) + 1);
-        self.edges.push(Edge::new(from, to, weight, length));
+        self.num_nodes = cmp::max(self.num_nodes, cmp::max(from, to).0 + 1);
+        self.edges
+            .push(Edge::new_with_data(from, to, weight, length, data));
         if bidir {
-            self.edges.push(Edge::new(to, from, weight, length));
+            self.edges
+                .push(Edge::new_with_data(to, from, weight, length, data));
         }
         if bidir {
             2
@@ -236,7 +651,8 @@ impl InputGraph {
             // limit max weight, but otherwise allow duplicates, loops etc. to make sure clean-up
             // inside InputGraph works correctly
             let weight = rng.gen_range(1, 100);
-            edge_count += result.add_edge(tail, head, weight, weight as f64);
+            edge_count +=
+                result.add_edge(NodeId(tail), NodeId(head), Weight(weight), weight as f64);
             if edge_count == num_edges {
                 break;
             }
@@ -245,104 +661,178 @@ impl InputGraph {
         result
     }
 
-    fn read_from_file(filename: &str) -> Self {
-        let file = File::open(filename).unwrap();
+    fn read_from_file(filename: &str, format: Option<FileFormat>) -> Result<Self, ParseError> {
+        let file = File::open(filename)?;
         let reader = BufReader::new(file);
         let mut g = InputGraph::new();
+        let mut format = format;
+        let mut dimacs_counts = None;
+        let mut curr_edges = 0;
         for (index, line) in reader.lines().enumerate() {
-            let s: String = line.unwrap();
-            if s.starts_with("a ") {
-                let (from, to, weight) = InputGraph::read_arc_line(index, &s);
-                g.add_edge(from, to, weight, weight as f64);
-            } else {
+            let s: String = line?;
+            let invalid_line = |message: &str| ParseError::InvalidLine {
+                line_number: index + 1,
+                line: s.clone(),
+                message: String::from(message),
+            };
+            if s.trim().is_empty() || s.starts_with('c') {
                 continue;
             }
+            let curr_format = match format {
+                Some(f) => f,
+                None => {
+                    let f = FileFormat::detect(&s)
+                        .ok_or_else(|| invalid_line("Could not detect the file format"))?;
+                    format = Some(f);
+                    f
+                }
+            };
+            match curr_format {
+                FileFormat::Arcs => {
+                    if let Some(arc) = s.strip_prefix("a ") {
+                        let (from, to, weight) = InputGraph::read_arc_line(arc)
+                            .ok_or_else(|| invalid_line("Invalid arc line"))?;
+                        g.add_edge(NodeId(from), NodeId(to), Weight(weight), weight as f64);
+                    }
+                }
+                FileFormat::Triples => {
+                    let (from, to, weight) = InputGraph::read_arc_line(&s).ok_or_else(|| {
+                        invalid_line("Expected three numbers: <from> <to> <weight>")
+                    })?;
+                    g.add_edge(NodeId(from), NodeId(to), Weight(weight), weight as f64);
+                }
+                FileFormat::Dimacs => {
+                    if let Some(problem) = s.strip_prefix("p sp ") {
+                        if dimacs_counts.is_some() {
+                            return Err(invalid_line("There should be only one problem line"));
+                        }
+                        let mut split = problem.split_whitespace();
+                        let nodes = split.next().and_then(|n| n.parse::<usize>().ok());
+                        let edges = split.next().and_then(|n| n.parse::<usize>().ok());
+                        match (nodes, edges, split.next()) {
+                            (Some(nodes), Some(edges), None) => {
+                                dimacs_counts = Some((nodes, edges))
+                            }
+                            _ => return Err(invalid_line("Invalid problem line")),
+                        }
+                    } else if let Some(arc) = s.strip_prefix("a ") {
+                        let (nodes, edges) = dimacs_counts.ok_or_else(|| {
+                            invalid_line("The problem line must be written before the arc lines")
+                        })?;
+                        let (from, to, weight) = InputGraph::read_arc_line(arc)
+                            .ok_or_else(|| invalid_line("Invalid arc line"))?;
+                        if from == 0 || to == 0 || from > nodes || to > nodes {
+                            return Err(invalid_line("Invalid nodes"));
+                        }
+                        if curr_edges == edges {
+                            return Err(invalid_line("Too many arc lines"));
+                        }
+                        // we convert 1-based node IDs from DIMACS to 0-based node IDs
+                        g.add_edge(
+                            NodeId(from - 1),
+                            NodeId(to - 1),
+                            Weight(weight),
+                            weight as f64,
+                        );
+                        curr_edges += 1;
+                    } else {
+                        return Err(invalid_line(
+                            "All non-empty lines must start with 'c', 'p' or 'a'",
+                        ));
+                    }
+                }
+            }
+        }
+        if let Some((_, edges)) = dimacs_counts {
+            if curr_edges != edges {
+                return Err(ParseError::MissingArcLines {
+                    found: curr_edges,
+                    expected: edges,
+                });
+            }
         }
         g.freeze();
-        g
+        Ok(g)
     }
 
-    fn read_from_dimacs(filename: &str) -> Self {
-        let file = File::open(filename).unwrap();
-        let reader = BufReader::new(file);
-        let mut g = InputGraph::new();
-        let mut nodes = 0;
-        let mut edges = 0;
-        let mut curr_edges = 0;
-        let mut found_problem_line = false;
-        for (index, line) in reader.lines().enumerate() {
-            let s: String = line.unwrap();
-            if s.is_empty() || s.starts_with("c") {
-                continue;
-            } else if s.starts_with("p sp ") {
-                if found_problem_line {
-                    panic!(
-                        "There should be only one problem line, but found: {} | {}",
-                        index + 1,
-                        s
-                    );
-                }
-                let mut split = s[5..].split_whitespace();
-                nodes = split.next().unwrap().parse::<usize>().unwrap();
-                edges = split.next().unwrap().parse::<usize>().unwrap();
-                assert!(split.next().is_none(), "Invalid problem line: {}", s);
-                found_problem_line = true;
-            } else if s.starts_with("a ") {
-                assert!(
-                    found_problem_line,
-                    "The problem line must be written before the arc lines"
-                );
-                let (from, to, weight) = InputGraph::read_arc_line(index, &s);
-                assert!(
-                    from <= nodes && to <= nodes,
-                    "Invalid nodes in line: {} | {}",
-                    index + 1,
-                    s
-                );
-                assert!(
-                    curr_edges < edges,
-                    "Too many arc lines: {}, expected: {}",
-                    curr_edges + 1,
-                    edges
-                );
+    /// Parses a line of the form <from> <to> <weight>
+    fn read_arc_line(line: &str) -> Option<(usize, usize, usize)> {
+        let mut split = line.split_whitespace();
+        let mut next_number = || split.next().and_then(|n| n.parse::<usize>().ok());
+        let from = next_number()?;
+        let to = next_number()?;
+        let weight = next_number()?;
+        if split.next().is_some() {
+            return None;
+        }
+        Some((from, to, weight))
+    }
+}
 
-                assert!(
-                    from > 0 && to > 0,
-                    "Invalid arc line: {} | {}",
-                    index + 1,
-                    s
-                );
-                // we convert 1-based node IDs from DIMACS to 0-based node IDs
-                g.add_edge(from - 1, to - 1, weight, weight as f64);
-                curr_edges += 1;
-            } else {
-                panic!(
-                    "Invalid line: {} {}\nAll non-empty lines must start with 'c', 'p' or 'a'",
-                    index, s
-                );
+#[derive(Copy, Clone)]
+enum FileFormat {
+    Arcs,
+    Dimacs,
+    Triples,
+}
+
+impl FileFormat {
+    fn detect(line: &str) -> Option<FileFormat> {
+        let first_token = line.split_whitespace().next()?;
+        if first_token == "p" {
+            Some(FileFormat::Dimacs)
+        } else if first_token == "a" {
+            Some(FileFormat::Arcs)
+        } else if first_token.parse::<usize>().is_ok() {
+            Some(FileFormat::Triples)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ParseError {
+    /// The file could not be read
+    Io(io::Error),
+    /// The file contains a malformed line, `line_number` starts at 1
+    InvalidLine {
+        line_number: usize,
+        line: String,
+        message: String,
+    },
+    /// A DIMACS file contains less arc lines than stated in its problem line
+    MissingArcLines { found: usize, expected: usize },
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Io(e) => write!(f, "Could not read file: {}", e),
+            ParseError::InvalidLine {
+                line_number,
+                line,
+                message,
+            } => write!(f, "{} in line: {} | {}", message, line_number, line),
+            ParseError::MissingArcLines { found, expected } => {
+                write!(f, "Not enough arc lines: {}, expected: {}", found, expected)
             }
         }
-        assert_eq!(
-            curr_edges, edges,
-            "Not enough arc lines: {}, expected: {}",
-            curr_edges, edges
-        );
-        g.freeze();
-        g
     }
+}
 
-    fn read_arc_line(index: usize, line: &String) -> (usize, usize, usize) {
-        let mut split = line[2..].split_whitespace();
-        let from = split.next().unwrap().parse::<usize>().unwrap();
-        let to = split.next().unwrap().parse::<usize>().unwrap();
-        let weight = split.next().unwrap().parse::<usize>().unwrap();
-        assert!(
-            split.next().is_none(),
-            "Invalid arc line: {} | {}",
-            index + 1,
-            line
-        );
-        (from, to, weight)
+impl Error for ParseError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            ParseError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ParseError {
+    fn from(e: io::Error) -> Self {
+        ParseError::Io(e)
     }
 }
 
@@ -364,15 +854,27 @@ pub struct Edge {
     pub to: NodeId,
     pub weight: Weight,
     pub length: Length,
+    pub data: EdgeData,
 }
 
 impl Edge {
     pub fn new(from: NodeId, to: NodeId, weight: Weight, length: Length) -> Edge {
+        Edge::new_with_data(from, to, weight, length, INVALID_EDGE_DATA)
+    }
+
+    pub fn new_with_data(
+        from: NodeId,
+        to: NodeId,
+        weight: Weight,
+        length: Length,
+        data: EdgeData,
+    ) -> Edge {
         Edge {
             from,
             to,
             weight,
             length,
+            data,
         }
     }
 
@@ -384,12 +886,312 @@ impl Edge {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::constants::WEIGHT_MAX;
+
+    #[test]
+    fn get_edges_between() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(5), 5.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(3), 3.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(4), 4.0);
+        g.add_edge_bidir(NodeId(2), NodeId(3), Weight(1), 1.0);
+        // before freezing all edges are kept
+        let weights: Vec<Weight> = g
+            .get_edges_between(NodeId(0), NodeId(1))
+            .iter()
+            .map(|e| e.weight)
+            .collect();
+        assert_eq!(vec![Weight(5), Weight(4)], weights);
+        assert!(g.has_edge(NodeId(3), NodeId(2)));
+        assert!(!g.has_edge(NodeId(1), NodeId(0)));
+        assert!(!g.has_edge(NodeId(7), NodeId(8)));
+        g.freeze();
+        let weights: Vec<Weight> = g
+            .get_edges_between(NodeId(0), NodeId(1))
+            .iter()
+            .map(|e| e.weight)
+            .collect();
+        assert_eq!(vec![Weight(4)], weights);
+        assert!(g.has_edge(NodeId(1), NodeId(2)));
+        assert!(g.has_edge(NodeId(2), NodeId(3)));
+        assert!(!g.has_edge(NodeId(2), NodeId(1)));
+        assert!(g.get_edges_between(NodeId(3), NodeId(3)).is_empty());
+    }
+
+    #[test]
+    fn degrees() {
+        // 0 -> 1 -> 2 and 0 -> 2, 3 <-> 1
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(3), 1.0);
+        g.add_edge_bidir(NodeId(3), NodeId(1), Weight(2), 1.0);
+        // duplicates are counted until the graph is frozen
+        g.add_edge(NodeId(0), NodeId(1), Weight(2), 1.0);
+        let degrees = |g: &InputGraph| -> Vec<(usize, usize)> {
+            (0..5)
+                .map(|n| (g.out_degree(NodeId(n)), g.in_degree(NodeId(n))))
+                .collect()
+        };
+        assert_eq!(vec![(3, 0), (2, 3), (0, 2), (1, 1), (0, 0)], degrees(&g));
+        g.freeze();
+        assert_eq!(vec![(2, 0), (2, 2), (0, 2), (1, 1), (0, 0)], degrees(&g));
+        g.thaw();
+        g.add_edge(NodeId(2), NodeId(0), Weight(1), 1.0);
+        assert_eq!(vec![(2, 1), (2, 2), (1, 2), (1, 1), (0, 0)], degrees(&g));
+        g.freeze();
+        assert_eq!(vec![(2, 1), (2, 2), (1, 2), (1, 1), (0, 0)], degrees(&g));
+        // the in-degrees are not serialized, but still available
+        let deserialized: InputGraph =
+            bincode::deserialize(&bincode::serialize(&g).unwrap()).unwrap();
+        assert_eq!(degrees(&g), degrees(&deserialized));
+    }
+
+    #[test]
+    fn add_edge_f64() {
+        let mut g = InputGraph::new();
+        assert_eq!(1.0, g.get_weight_scale());
+        g.add_edge_f64(NodeId(0), NodeId(1), 2.6, 2.6);
+        g.set_weight_scale(10.0);
+        g.add_edge_f64(NodeId(1), NodeId(2), 2.64, 2.64);
+        // zero after rounding
+        assert_eq!(1, g.add_edge_f64(NodeId(2), NodeId(3), 0.04, 0.04));
+        assert_eq!(0, g.add_edge_f64(NodeId(2), NodeId(3), -1.0, 1.0));
+        assert_eq!(0, g.add_edge_f64(NodeId(2), NodeId(3), f64::NAN, 1.0));
+        g.freeze();
+        assert_eq!(10.0, g.get_weight_scale());
+        assert_eq!(3, g.get_num_edges());
+        assert_eq!(Weight(3), g.get_edges()[0].weight);
+        assert_eq!(Weight(26), g.get_edges()[1].weight);
+        assert_eq!(Weight(0), g.get_edges()[2].weight);
+    }
+
+    #[test]
+    fn scale_weights() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(5), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(12), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.scale_weights(10.0);
+        g.set_rounding(RoundingMode::Floor);
+        g.scale_weights(0.05);
+        g.freeze();
+        assert_eq!(0.5, g.get_weight_scale());
+        // the weight of the last edge is rounded down to zero
+        assert_eq!(3, g.get_num_edges());
+        assert_eq!(Weight(2), g.get_edges()[0].weight);
+        assert_eq!(Weight(6), g.get_edges()[1].weight);
+        assert_eq!(Weight(0), g.get_edges()[2].weight);
+    }
+
+    #[test]
+    #[should_panic(expected = "Graph is frozen already")]
+    fn scale_weights_frozen() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(5), 1.0);
+        g.freeze();
+        g.scale_weights(10.0);
+    }
+
+    #[test]
+    fn rounding() {
+        // the exact weights are 1.000, 1.001, ..., 1.999, so their sum is 1499.5
+        let sum_of_weights = |rounding: RoundingMode| -> Weight {
+            let mut g = InputGraph::new();
+            g.set_rounding(rounding);
+            for i in 0..1000 {
+                g.add_edge_f64(NodeId(i), NodeId(i + 1), 1.0 + i as f64 / 1000.0, 1.0);
+            }
+            g.freeze();
+            g.get_edges().iter().map(|e| e.weight).sum()
+        };
+        assert_eq!(RoundingMode::Nearest, InputGraph::new().get_rounding());
+        let error = |rounding| (sum_of_weights(rounding).0 as f64 - 1499.5).abs();
+        assert_eq!(0.5, error(RoundingMode::Nearest));
+        assert_eq!(499.5, error(RoundingMode::Ceil));
+        assert_eq!(499.5, error(RoundingMode::Floor));
+
+        // small weights only stay positive when rounding up
+        let mut g = InputGraph::new();
+        g.add_edge_f64(NodeId(0), NodeId(1), 0.2, 1.0);
+        g.set_rounding(RoundingMode::Ceil);
+        g.add_edge_f64(NodeId(1), NodeId(2), 0.2, 1.0);
+        g.set_rounding(RoundingMode::Floor);
+        g.add_edge_f64(NodeId(2), NodeId(3), 1.9, 1.0);
+        g.freeze();
+        assert_eq!(Weight(0), g.get_edges()[0].weight);
+        assert_eq!(Weight(1), g.get_edges()[1].weight);
+        assert_eq!(Weight(1), g.get_edges()[2].weight);
+    }
+
+    #[test]
+    #[should_panic(expected = "The weight scale must be positive and finite, but was 0")]
+    fn set_weight_scale_zero() {
+        InputGraph::new().set_weight_scale(0.0);
+    }
+
+    #[test]
+    fn from_adjacency_list() {
+        let g = InputGraph::from_adjacency_list(&[
+            vec![(NodeId(1), Weight(3)), (NodeId(2), Weight(5))],
+            vec![(NodeId(2), Weight(1))],
+            vec![],
+            vec![],
+        ]);
+        assert_eq!(4, g.get_num_nodes());
+        assert_eq!(3, g.get_num_edges());
+        let edges: Vec<(NodeId, NodeId, Weight)> = g
+            .get_edges()
+            .iter()
+            .map(|e| (e.from, e.to, e.weight))
+            .collect();
+        assert_eq!(
+            vec![
+                (NodeId(0), NodeId(1), Weight(3)),
+                (NodeId(0), NodeId(2), Weight(5)),
+                (NodeId(1), NodeId(2), Weight(1))
+            ],
+            edges
+        );
+        assert_eq!(0, InputGraph::from_adjacency_list(&[]).get_num_nodes());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid target node id 2 for an edge of node 1, must be in [0, 2)")]
+    fn from_adjacency_list_invalid_target() {
+        InputGraph::from_adjacency_list(&[
+            vec![(NodeId(1), Weight(3))],
+            vec![(NodeId(2), Weight(1))],
+        ]);
+    }
+
+    #[test]
+    fn edge_profiles() {
+        let mut g = InputGraph::new();
+        assert_eq!(
+            1,
+            g.add_edge_profile(
+                NodeId(1),
+                NodeId(2),
+                vec![(Weight(0), Weight(5)), (Weight(10), Weight(3))]
+            )
+        );
+        // violates the FIFO property
+        assert_eq!(
+            0,
+            g.add_edge_profile(
+                NodeId(2),
+                NodeId(0),
+                vec![(Weight(0), Weight(20)), (Weight(5), Weight(10))]
+            )
+        );
+        g.add_edge(NodeId(0), NodeId(1), Weight(4), 4.0);
+        g.freeze();
+        assert_eq!(2, g.get_num_edges());
+        assert_eq!(Weight(3), g.get_edges()[1].weight);
+        assert_eq!(
+            Weight(4),
+            g.get_edge_profile(NodeId(1), NodeId(2))
+                .unwrap()
+                .weight_at(Weight(5))
+        );
+        assert!(g.get_edge_profile(NodeId(0), NodeId(1)).is_none());
+        assert!(g.get_edge_profile(NodeId(2), NodeId(0)).is_none());
+    }
+
+    #[test]
+    fn edge_data_of_duplicate_edges() {
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(5), 5.0, 3);
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(2), 2.0, 7);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 2.0);
+        g.freeze();
+        assert_eq!(2, g.get_num_edges());
+        assert_eq!(7, g.get_edges()[0].data);
+        assert_eq!(INVALID_EDGE_DATA, g.get_edges()[1].data);
+    }
+
+    #[test]
+    fn from_file_detects_format() {
+        let g = read_graph(
+            "from_file_arcs.gr",
+            "c comment\na 0 1 5\nx ignored\na 1 2 3\n",
+        )
+        .unwrap();
+        assert_eq!(3, g.get_num_nodes());
+        assert_eq!(2, g.get_num_edges());
+
+        let g = read_graph("from_file_triples.gr", "0 1 5\n\n1 2 3\n2 3 4\n").unwrap();
+        assert_eq!(4, g.get_num_nodes());
+        assert_eq!(3, g.get_num_edges());
+
+        // DIMACS node ids are 1-based
+        let g = read_graph(
+            "from_file_dimacs.gr",
+            "c comment\np sp 3 2\na 1 2 5\na 2 3 3\n",
+        )
+        .unwrap();
+        assert_eq!(3, g.get_num_nodes());
+        assert_eq!(NodeId(0), g.get_edges()[0].from);
+        assert_eq!(NodeId(2), g.get_edges()[1].to);
+    }
+
+    #[test]
+    fn from_file_reports_malformed_lines() {
+        assert_invalid_line("a 0 1 5\na 1 x 3\n", 2, "Invalid arc line");
+        assert_invalid_line("0 1 5\n1 2\n", 2, "Expected three numbers");
+        assert_invalid_line(
+            "c comment\nfoo bar\n",
+            2,
+            "Could not detect the file format",
+        );
+        assert_invalid_line(
+            "p sp 2 1\na 1 2 3\nc comment\na 2 1 3\n",
+            4,
+            "Too many arc lines",
+        );
+        assert_invalid_line("p sp 2 1\na 1 3 3\n", 2, "Invalid nodes");
+        assert_invalid_line("p sp 2 1\np sp 2 1\n", 2, "only one problem line");
+        match read_graph("from_file_missing_arcs.gr", "p sp 2 2\na 1 2 3\n") {
+            Err(ParseError::MissingArcLines { found, expected }) => {
+                assert_eq!(1, found);
+                assert_eq!(2, expected);
+            }
+            _ => panic!("expected missing arc lines error"),
+        }
+        assert!(matches!(
+            InputGraph::from_file("does_not_exist.gr"),
+            Err(ParseError::Io(_))
+        ));
+    }
+
+    fn assert_invalid_line(content: &str, expected_line_number: usize, expected_message: &str) {
+        match read_graph("from_file_invalid.gr", content) {
+            Err(ParseError::InvalidLine {
+                line_number,
+                message,
+                ..
+            }) => {
+                assert_eq!(expected_line_number, line_number);
+                assert!(message.contains(expected_message), "{}", message);
+            }
+            Err(e) => panic!("unexpected error: {}", e),
+            Ok(_) => panic!("expected an error"),
+        }
+    }
+
+    fn read_graph(filename: &str, content: &str) -> Result<InputGraph, ParseError> {
+        std::fs::write(filename, content).unwrap();
+        let result = InputGraph::from_file(filename);
+        std::fs::remove_file(filename).unwrap();
+        result
+    }
 
     #[test]
     #[should_panic]
     fn panic_if_not_frozen_get_edges() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 1, 3, 3.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
         g.get_edges();
     }
 
@@ -397,7 +1199,7 @@ mod tests {
     #[should_panic]
     fn panic_if_not_frozen_get_num_edges() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 1, 3, 3.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
         g.get_num_edges();
     }
 
@@ -405,7 +1207,7 @@ mod tests {
     #[should_panic]
     fn panic_if_not_frozen_get_num_nodes() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 1, 3, 3.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
         g.get_num_nodes();
     }
 
@@ -413,31 +1215,54 @@ mod tests {
     #[should_panic]
     fn panic_if_frozen_add_edge() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 1, 3, 3.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
         g.freeze();
-        g.add_edge(2, 5, 4, 4.0);
+        g.add_edge(NodeId(2), NodeId(5), Weight(4), 4.0);
     }
 
     #[test]
     fn freeze_and_thaw() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 5, 10, 10.0);
-        g.add_edge(0, 5, 5, 5.0);
+        g.add_edge(NodeId(0), NodeId(5), Weight(10), 10.0);
+        g.add_edge(NodeId(0), NodeId(5), Weight(5), 5.0);
         g.freeze();
         assert_eq!(1, g.get_num_edges());
         g.thaw();
-        g.add_edge(0, 5, 1, 1.0);
+        g.add_edge(NodeId(0), NodeId(5), Weight(1), 1.0);
         g.freeze();
         assert_eq!(1, g.get_num_edges());
-        assert_eq!(1, g.get_edges()[0].weight);
+        assert_eq!(Weight(1), g.get_edges()[0].weight);
+    }
+
+    #[test]
+    fn with_capacity() {
+        let mut g = InputGraph::with_capacity(3, 100);
+        assert!(g.edges.capacity() >= 100);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge_bidir(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.reserve_edges(1000);
+        assert!(g.edges.capacity() >= 1003);
+        g.freeze();
+        assert_eq!(3, g.get_num_nodes());
+        assert_eq!(3, g.get_num_edges());
+        // the number of nodes is a lower bound, so nodes without edges are kept
+        let mut g = InputGraph::with_capacity(5, 1);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.freeze();
+        assert_eq!(5, g.get_num_nodes());
+        assert_eq!(0, g.in_degree(NodeId(4)));
+        let mut g = InputGraph::with_capacity(2, 1);
+        g.add_edge(NodeId(0), NodeId(3), Weight(1), 1.0);
+        g.freeze();
+        assert_eq!(4, g.get_num_nodes());
     }
 
     #[test]
     fn num_nodes() {
         let mut g = InputGraph::new();
-        g.add_edge(7, 1, 2, 2.0);
-        g.add_edge(5, 6, 4, 4.0);
-        g.add_edge(11, 8, 3, 3.0);
+        g.add_edge(NodeId(7), NodeId(1), Weight(2), 2.0);
+        g.add_edge(NodeId(5), NodeId(6), Weight(4), 4.0);
+        g.add_edge(NodeId(11), NodeId(8), Weight(3), 3.0);
         g.freeze();
         assert_eq!(12, g.get_num_nodes());
     }
@@ -445,19 +1270,55 @@ mod tests {
     #[test]
     fn skips_loops() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 1, 3, 3.0);
-        g.add_edge(4, 4, 2, 2.0);
-        g.add_edge(2, 5, 4, 4.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(4), NodeId(4), Weight(2), 2.0);
+        g.add_edge(NodeId(2), NodeId(5), Weight(4), 4.0);
         g.freeze();
         assert_eq!(2, g.get_num_edges());
     }
 
     #[test]
-    fn skips_zero_weight_edges() {
+    fn keeps_zero_weight_edges() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 1, 5, 5.0);
-        g.add_edge(1, 2, 0, 0.0);
-        g.add_edge(2, 3, 3, 3.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(5), 5.0);
+        assert_eq!(1, g.add_edge(NodeId(1), NodeId(2), Weight(0), 0.0));
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 3.0);
+        // the zero weight edge is lighter than the duplicate
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        g.freeze();
+        assert_eq!(3, g.get_num_edges());
+        assert_eq!(
+            Weight(0),
+            g.get_edges_between(NodeId(1), NodeId(2))[0].weight
+        );
+    }
+
+    #[test]
+    fn sources_and_sinks() {
+        // 0 -> 1 <-> 2 -> 3 and 4 <- 5 -> 6
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge_bidir(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(5), NodeId(4), Weight(1), 1.0);
+        g.add_edge(NodeId(5), NodeId(6), Weight(1), 1.0);
+        assert_eq!(vec![NodeId(0), NodeId(5)], g.sources());
+        assert_eq!(vec![NodeId(3), NodeId(4), NodeId(6)], g.sinks());
+        g.freeze();
+        assert_eq!(vec![NodeId(0), NodeId(5)], g.sources());
+        assert_eq!(vec![NodeId(3), NodeId(4), NodeId(6)], g.sinks());
+        assert!(InputGraph::new().sources().is_empty());
+    }
+
+    #[test]
+    fn skips_infinite_weight_edges() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(5), 5.0);
+        assert_eq!(0, g.add_edge(NodeId(1), NodeId(2), WEIGHT_MAX, 0.0));
+        assert_eq!(
+            1,
+            g.add_edge(NodeId(2), NodeId(3), WEIGHT_MAX - Weight(1), 3.0)
+        );
         g.freeze();
         assert_eq!(2, g.get_num_edges());
     }
@@ -465,12 +1326,12 @@ mod tests {
     #[test]
     fn skips_duplicate_edges() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 1, 7, 7.0);
-        g.add_edge(2, 3, 5, 5.0);
-        g.add_edge(0, 2, 3, 3.0);
-        g.add_edge(0, 1, 2, 2.0);
-        g.add_edge(4, 6, 9, 9.0);
-        g.add_edge(0, 1, 4, 4.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(7), 7.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(5), 5.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(3), 3.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(2), 2.0);
+        g.add_edge(NodeId(4), NodeId(6), Weight(9), 9.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(4), 4.0);
         g.freeze();
         assert_eq!(4, g.get_num_edges());
         // edges should be sorted and duplicates should be removed keeping only the ones with
@@ -480,21 +1341,100 @@ mod tests {
             .iter()
             .map(|e| e.weight)
             .collect::<Vec<Weight>>();
-        assert_eq!(vec![2, 3, 5, 9], weights);
+        assert_eq!(vec![Weight(2), Weight(3), Weight(5), Weight(9)], weights);
+    }
+
+    #[cfg(feature = "petgraph")]
+    #[test]
+    fn from_petgraph() {
+        // 0 -> 1 -> 2, 0 -> 2 and node 3 without edges
+        let mut graph = petgraph::Graph::<&str, f64>::new();
+        let a = graph.add_node("a");
+        let b = graph.add_node("b");
+        let c = graph.add_node("c");
+        let d = graph.add_node("d");
+        graph.add_edge(a, b, 1.5);
+        graph.add_edge(b, c, 2.0);
+        graph.add_edge(a, c, 4.0);
+        // skipped
+        graph.add_edge(c, a, -1.0);
+        let (g, node_indices) = InputGraph::from_petgraph(&graph, |w| *w);
+        assert_eq!(vec![a, b, c, d], node_indices);
+        assert_eq!(4, g.get_num_nodes());
+        assert_eq!(3, g.get_num_edges());
+        assert_eq!(
+            Weight(2),
+            g.get_edges_between(NodeId(0), NodeId(1))[0].weight
+        );
+        assert_eq!(1.5, g.get_edges_between(NodeId(0), NodeId(1))[0].length);
+        assert_eq!(
+            Weight(4),
+            g.get_edges_between(NodeId(0), NodeId(2))[0].weight
+        );
+        assert!(g.get_edges_between(NodeId(2), NodeId(0)).is_empty());
+        let path = crate::calc_path(&crate::prepare(&g), NodeId(0), NodeId(2)).unwrap();
+        assert_eq!(Weight(4), path.get_weight());
+
+        // undirected edges are added in both directions
+        let mut graph = petgraph::graph::UnGraph::<(), u32>::new_undirected();
+        let a = graph.add_node(());
+        let b = graph.add_node(());
+        graph.add_edge(a, b, 3);
+        let (g, _) = InputGraph::from_petgraph(&graph, |w| *w as f64);
+        assert_eq!(2, g.get_num_edges());
+        assert_eq!(
+            Weight(3),
+            g.get_edges_between(NodeId(1), NodeId(0))[0].weight
+        );
+    }
+
+    #[test]
+    fn merge_parallel_edges() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(7), 7.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(5), 5.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(2), 2.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(4), 4.0);
+        assert_eq!(2, g.merge_parallel_edges());
+        assert_eq!(0, g.merge_parallel_edges());
+        let weights: Vec<Weight> = g
+            .get_edges_between(NodeId(0), NodeId(1))
+            .iter()
+            .map(|e| e.weight)
+            .collect();
+        assert_eq!(vec![Weight(2)], weights);
+        // edges can still be added afterwards
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        assert_eq!(1, g.merge_parallel_edges());
+        g.freeze();
+        assert_eq!(2, g.get_num_edges());
+        assert_eq!(
+            Weight(1),
+            g.get_edges_between(NodeId(2), NodeId(3))[0].weight
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn panic_if_frozen_merge_parallel_edges() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(7), 7.0);
+        g.freeze();
+        g.merge_parallel_edges();
     }
 
     #[test]
     fn skips_duplicate_edges_more() {
         let mut g = InputGraph::new();
-        g.add_edge(1, 3, 43, 43.0);
-        g.add_edge(3, 2, 90, 90.0);
-        g.add_edge(3, 2, 88, 88.0);
-        g.add_edge(2, 3, 87, 87.0);
-        g.add_edge(3, 0, 75, 75.0);
-        g.add_edge(0, 2, 45, 45.0);
-        g.add_edge(1, 3, 71, 71.0);
-        g.add_edge(4, 3, 5, 5.0);
-        g.add_edge(1, 3, 91, 91.0);
+        g.add_edge(NodeId(1), NodeId(3), Weight(43), 43.0);
+        g.add_edge(NodeId(3), NodeId(2), Weight(90), 90.0);
+        g.add_edge(NodeId(3), NodeId(2), Weight(88), 88.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(87), 87.0);
+        g.add_edge(NodeId(3), NodeId(0), Weight(75), 75.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(45), 45.0);
+        g.add_edge(NodeId(1), NodeId(3), Weight(71), 71.0);
+        g.add_edge(NodeId(4), NodeId(3), Weight(5), 5.0);
+        g.add_edge(NodeId(1), NodeId(3), Weight(91), 91.0);
         g.freeze();
         assert_eq!(6, g.get_num_edges());
         let weights = g
@@ -502,6 +1442,16 @@ mod tests {
             .iter()
             .map(|e| e.weight)
             .collect::<Vec<Weight>>();
-        assert_eq!(vec![45, 43, 87, 75, 88, 5], weights);
+        assert_eq!(
+            vec![
+                Weight(45),
+                Weight(43),
+                Weight(87),
+                Weight(75),
+                Weight(88),
+                Weight(5)
+            ],
+            weights
+        );
     }
 }
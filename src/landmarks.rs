@@ -0,0 +1,274 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::collections::BinaryHeap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{add_weights, is_infinite, NodeId, Weight, WEIGHT_MAX, WEIGHT_ZERO};
+use crate::heap_item::HeapItem;
+use crate::input_graph::InputGraph;
+use crate::preparation_graph::PreparationGraph;
+
+/// A set of landmark nodes along with the shortest path weights from and to every node of the
+/// graph. Using the triangle inequality these weights yield lower bounds for the weight of the
+/// shortest path between any two nodes, which can be used to guide a Dijkstra search towards its
+/// target (the so called ALT algorithm, see `PathCalculator::calc_path_alt`).
+///
+/// Selecting the landmarks only requires two Dijkstra searches on the full graph per landmark,
+/// which is much cheaper than the contraction needed for a `FastGraph`.
+#[derive(Serialize, Deserialize)]
+pub struct Landmarks {
+    graph: PreparationGraph,
+    landmarks: Vec<NodeId>,
+    /// the weights from the landmarks to all nodes, indexed by landmark
+    weights_from: Vec<Vec<Weight>>,
+    /// the weights from all nodes to the landmarks, indexed by landmark
+    weights_to: Vec<Vec<Weight>>,
+}
+
+impl Landmarks {
+    /// Selects `num_landmarks` landmarks for the given graph using the 'farthest' heuristic: the
+    /// first landmark is the node farthest away from node 0 and every following landmark is the
+    /// node whose distance to the closest landmark selected so far is the largest. Nodes that
+    /// cannot be reached from any landmark yet are preferred, so every connected component gets
+    /// a landmark if possible.
+    pub fn select(input_graph: &InputGraph, num_landmarks: usize) -> Self {
+        let graph = PreparationGraph::from_input_graph(input_graph);
+        let num_nodes = graph.get_num_nodes();
+        let mut landmarks = Landmarks {
+            graph,
+            landmarks: vec![],
+            weights_from: vec![],
+            weights_to: vec![],
+        };
+        if num_nodes == 0 {
+            return landmarks;
+        }
+        // the distance of each node to its closest landmark
+        let mut min_weights = Landmarks::calc_weights(&landmarks.graph, NodeId(0), true);
+        while landmarks.landmarks.len() < num_landmarks.min(num_nodes) {
+            let landmark = (0..num_nodes)
+                .map(NodeId)
+                .filter(|n| !landmarks.landmarks.contains(n))
+                .max_by_key(|&n| (min_weights[n.0], std::cmp::Reverse(n)))
+                .unwrap();
+            let is_first = landmarks.landmarks.is_empty();
+            landmarks.add(landmark);
+            let weights_from = landmarks.weights_from.last().unwrap();
+            if is_first {
+                min_weights = weights_from.clone();
+            } else {
+                for (min_weight, &weight) in min_weights.iter_mut().zip(weights_from.iter()) {
+                    *min_weight = (*min_weight).min(weight);
+                }
+            }
+        }
+        landmarks
+    }
+
+    /// Uses the given nodes as landmarks (in the given order) instead of selecting them and
+    /// calculates the shortest path weights from and to every node of the given graph for each of
+    /// them. Panics if one of the landmarks does not exist.
+    pub fn new(input_graph: &InputGraph, landmarks: &[NodeId]) -> Self {
+        let graph = PreparationGraph::from_input_graph(input_graph);
+        let num_nodes = graph.get_num_nodes();
+        let mut result = Landmarks {
+            graph,
+            landmarks: Vec::with_capacity(landmarks.len()),
+            weights_from: Vec::with_capacity(landmarks.len()),
+            weights_to: Vec::with_capacity(landmarks.len()),
+        };
+        for &landmark in landmarks {
+            if landmark.0 >= num_nodes {
+                panic!(
+                    "invalid landmark {}, must be in [0, {})",
+                    landmark, num_nodes
+                );
+            }
+            result.add(landmark);
+        }
+        result
+    }
+
+    pub fn get_landmarks(&self) -> &Vec<NodeId> {
+        &self.landmarks
+    }
+
+    pub fn get_num_nodes(&self) -> usize {
+        self.graph.get_num_nodes()
+    }
+
+    pub(crate) fn get_graph(&self) -> &PreparationGraph {
+        &self.graph
+    }
+
+    /// Returns the shortest path weights from the `i`-th landmark to all nodes, indexed by node.
+    /// The weight is `WEIGHT_MAX` for nodes that cannot be reached from the landmark.
+    pub fn get_weights_from(&self, i: usize) -> &[Weight] {
+        &self.weights_from[i]
+    }
+
+    /// Returns the shortest path weights from all nodes to the `i`-th landmark, indexed by node.
+    /// The weight is `WEIGHT_MAX` for nodes from which the landmark cannot be reached.
+    pub fn get_weights_to(&self, i: usize) -> &[Weight] {
+        &self.weights_to[i]
+    }
+
+    /// Returns a lower bound for the weight of the shortest path from `source` to `target`
+    pub fn lower_bound(&self, source: NodeId, target: NodeId) -> Weight {
+        let mut bound = WEIGHT_ZERO;
+        for i in 0..self.landmarks.len() {
+            // d(l, t) <= d(l, s) + d(s, t)
+            let from_source = self.weights_from[i][source.0];
+            let from_target = self.weights_from[i][target.0];
+            if !is_infinite(from_source) && !is_infinite(from_target) {
+                bound = bound.max(from_target.saturating_sub(from_source));
+            }
+            // d(s, l) <= d(s, t) + d(t, l)
+            let to_source = self.weights_to[i][source.0];
+            let to_target = self.weights_to[i][target.0];
+            if !is_infinite(to_source) && !is_infinite(to_target) {
+                bound = bound.max(to_source.saturating_sub(to_target));
+            }
+        }
+        bound
+    }
+
+    fn add(&mut self, landmark: NodeId) {
+        self.weights_from
+            .push(Landmarks::calc_weights(&self.graph, landmark, true));
+        self.weights_to
+            .push(Landmarks::calc_weights(&self.graph, landmark, false));
+        self.landmarks.push(landmark);
+    }
+
+    /// Runs a full Dijkstra search from (`fwd`) or to (`!fwd`) the given node and returns the
+    /// shortest path weights of all nodes, `WEIGHT_MAX` for nodes that are not connected
+    fn calc_weights(graph: &PreparationGraph, node: NodeId, fwd: bool) -> Vec<Weight> {
+        let mut weights = vec![WEIGHT_MAX; graph.get_num_nodes()];
+        let mut heap = BinaryHeap::new();
+        weights[node.0] = WEIGHT_ZERO;
+        heap.push(HeapItem::new(WEIGHT_ZERO, node));
+        while let Some(curr) = heap.pop() {
+            if curr.weight > weights[curr.node_id.0] {
+                continue;
+            }
+            let edges = if fwd {
+                graph.get_out_edges(curr.node_id)
+            } else {
+                graph.get_in_edges(curr.node_id)
+            };
+            for arc in edges {
+                let weight = add_weights(curr.weight, arc.weight);
+                if weight < weights[arc.adj_node.0] {
+                    weights[arc.adj_node.0] = weight;
+                    heap.push(HeapItem::new(weight, arc.adj_node));
+                }
+            }
+        }
+        weights
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn select_farthest() {
+        // 0 -> 1 -> 2 -> 3 -> 4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        let landmarks = Landmarks::select(&g, 2);
+        // node 4 is the farthest from node 0, and node 0 cannot be reached from node 4
+        assert_eq!(&vec![NodeId(4), NodeId(0)], landmarks.get_landmarks());
+        assert_eq!(Weight(3), landmarks.lower_bound(NodeId(0), NodeId(3)));
+        assert_eq!(Weight(2), landmarks.lower_bound(NodeId(1), NodeId(3)));
+        assert_eq!(Weight(0), landmarks.lower_bound(NodeId(3), NodeId(3)));
+    }
+
+    #[test]
+    fn select_more_landmarks_than_nodes() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(0), Weight(3), 3.0);
+        g.freeze();
+        let landmarks = Landmarks::select(&g, 5);
+        assert_eq!(&vec![NodeId(1), NodeId(0)], landmarks.get_landmarks());
+        assert_eq!(Weight(3), landmarks.lower_bound(NodeId(0), NodeId(1)));
+        assert_eq!(Weight(3), landmarks.lower_bound(NodeId(1), NodeId(0)));
+    }
+
+    #[test]
+    fn given_landmarks() {
+        // 0 -> 1 -> 2 -> 3, node 4 has no edges
+        let w = Weight;
+        let g = InputGraph::from_adjacency_list(&[
+            vec![(NodeId(1), w(1))],
+            vec![(NodeId(2), w(2))],
+            vec![(NodeId(3), w(3))],
+            vec![],
+            vec![],
+        ]);
+        let landmarks = Landmarks::new(&g, &[NodeId(1), NodeId(3)]);
+        assert_eq!(&vec![NodeId(1), NodeId(3)], landmarks.get_landmarks());
+        let max = WEIGHT_MAX;
+        assert_eq!(&[max, w(0), w(2), w(5), max], landmarks.get_weights_from(0));
+        assert_eq!(&[w(1), w(0), max, max, max], landmarks.get_weights_to(0));
+        assert_eq!(&[max, max, max, w(0), max], landmarks.get_weights_from(1));
+        assert_eq!(&[w(6), w(5), w(3), w(0), max], landmarks.get_weights_to(1));
+        assert_eq!(Weight(5), landmarks.lower_bound(NodeId(1), NodeId(3)));
+        assert_eq!(Weight(6), landmarks.lower_bound(NodeId(0), NodeId(3)));
+        assert_eq!(Weight(2), landmarks.lower_bound(NodeId(1), NodeId(2)));
+        // nodes that are not connected to any landmark only get the trivial bound
+        assert_eq!(Weight(0), landmarks.lower_bound(NodeId(4), NodeId(3)));
+    }
+
+    #[test]
+    fn no_landmarks() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.freeze();
+        let landmarks = Landmarks::new(&g, &[]);
+        assert!(landmarks.get_landmarks().is_empty());
+        assert_eq!(Weight(0), landmarks.lower_bound(NodeId(0), NodeId(1)));
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid landmark 2, must be in [0, 2)")]
+    fn invalid_landmark() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.freeze();
+        Landmarks::new(&g, &[NodeId(2)]);
+    }
+
+    #[test]
+    fn select_on_empty_graph() {
+        let mut g = InputGraph::new();
+        g.freeze();
+        let landmarks = Landmarks::select(&g, 3);
+        assert!(landmarks.get_landmarks().is_empty());
+    }
+}
@@ -17,50 +17,149 @@
  * under the License.
  */
 
-#[macro_use]
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+#[cfg_attr(feature = "std", macro_use)]
 extern crate log;
 
-use serde::{Deserialize, Deserializer, Serialize, Serializer};
+#[cfg(feature = "std")]
+use core::sync::atomic::AtomicBool;
+
+use alloc::vec::Vec;
+
+use serde::ser::Error;
+use serde::{Deserializer, Serialize, Serializer};
 
 pub use crate::constants::*;
+#[cfg(feature = "std")]
+pub use crate::dijkstra::Dijkstra;
 pub use crate::fast_graph::FastGraph;
+pub use crate::fast_graph::FastGraphDiff;
+pub use crate::fast_graph::FastGraphDiffEdge;
 pub use crate::fast_graph::FastGraphEdge;
+pub use crate::fast_graph::RankExplanation;
 pub use crate::fast_graph32::FastGraph32;
+#[cfg(feature = "std")]
+pub use crate::fast_graph_builder::ContractionState;
+#[cfg(feature = "std")]
 pub use crate::fast_graph_builder::FastGraphBuilder;
+#[cfg(feature = "std")]
+pub use crate::fast_graph_builder::OrderStrategy;
+#[cfg(feature = "std")]
 pub use crate::fast_graph_builder::Params;
+#[cfg(feature = "std")]
+pub use crate::fast_graph_builder::ParamsResult;
+#[cfg(feature = "std")]
 pub use crate::fast_graph_builder::ParamsWithOrder;
+#[cfg(feature = "std")]
+pub use crate::fast_graph_builder::PreparationError;
+#[cfg(feature = "std")]
+pub use crate::fast_graph_builder::PreparationReport;
+#[cfg(feature = "std")]
 pub use crate::input_graph::Edge;
+#[cfg(feature = "std")]
 pub use crate::input_graph::InputGraph;
+#[cfg(feature = "std")]
+pub use crate::input_graph::ParseError;
+#[cfg(feature = "std")]
+pub use crate::input_graph::RoundingMode;
+#[cfg(feature = "std")]
+pub use crate::landmarks::Landmarks;
 pub use crate::path_calculator::PathCalculator;
+pub use crate::path_calculator::QueryError;
+pub use crate::path_calculator::QueryStats;
+#[cfg(feature = "std")]
+pub use crate::preparation_graph::PreparationGraph;
 pub use crate::shortest_path::ShortestPath;
+#[cfg(feature = "std")]
+pub use crate::time_dependent::TimeDependentGraph;
+#[cfg(feature = "std")]
+pub use crate::time_dependent::WeightProfile;
+pub use crate::valid_flags::ValidFlags;
+#[cfg(feature = "std")]
+pub use crate::verification::VerificationError;
+#[cfg(feature = "wasm")]
+pub use crate::wasm::WasmPathCalculator;
 
 mod constants;
-#[cfg(test)]
+#[cfg(feature = "std")]
 mod dijkstra;
 mod fast_graph;
 mod fast_graph32;
+#[cfg(feature = "std")]
 mod fast_graph_builder;
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod floyd_warshall;
 mod heap_item;
+mod indexed_heap;
+#[cfg(feature = "std")]
 mod input_graph;
+#[cfg(feature = "std")]
+mod landmarks;
+#[cfg(feature = "std")]
 mod node_contractor;
 mod path_calculator;
+#[cfg(feature = "std")]
 mod preparation_graph;
 mod shortest_path;
+#[cfg(feature = "std")]
+mod spatial_index;
+#[cfg(feature = "std")]
+mod time_dependent;
+mod unpack_cache;
 mod valid_flags;
+#[cfg(feature = "std")]
+mod verification;
+#[cfg(feature = "wasm")]
+mod wasm;
+#[cfg(feature = "std")]
 mod witness_search;
 
+// A prepared graph is meant to be shared between threads (e.g. using an `Arc`) while every thread
+// uses its own `PathCalculator`, so make sure this keeps compiling.
+const _: fn() = || {
+    fn assert_send_sync<T: Send + Sync>() {}
+    fn assert_send<T: Send>() {}
+    assert_send_sync::<FastGraph>();
+    assert_send::<PathCalculator>();
+};
+
+#[cfg(feature = "std")]
 /// Prepares the given `InputGraph` for fast shortest path calculations.
 pub fn prepare(input_graph: &InputGraph) -> FastGraph {
     FastGraphBuilder::build(input_graph)
 }
 
+#[cfg(feature = "std")]
 /// Like `prepare()`, but allows specifying some parameters used for the graph preparation.
 pub fn prepare_with_params(input_graph: &InputGraph, params: &Params) -> FastGraph {
     FastGraphBuilder::build_with_params(input_graph, params)
 }
 
+#[cfg(feature = "std")]
+/// Like `prepare_with_params()`, but also returns a `PreparationReport` with the duration of the
+/// preparation, the number of shortcuts that were added and some statistics of the contraction.
+pub fn prepare_with_params_reported(
+    input_graph: &InputGraph,
+    params: &Params,
+) -> (FastGraph, PreparationReport) {
+    FastGraphBuilder::build_with_params_reported(input_graph, params)
+}
+
+#[cfg(feature = "std")]
+/// Like `prepare_with_params()`, but the preparation is aborted with
+/// `PreparationError::Cancelled` once `cancel` is set to true. Share the flag using an
+/// `Arc<AtomicBool>` to cancel the preparation from another thread.
+pub fn prepare_with_params_cancellable(
+    input_graph: &InputGraph,
+    params: &Params,
+    cancel: &AtomicBool,
+) -> Result<FastGraph, PreparationError> {
+    FastGraphBuilder::build_with_params_cancellable(input_graph, params, cancel)
+}
+
+#[cfg(feature = "std")]
 /// Prepares the given input graph using a fixed node ordering, which can be any permutation
 /// of the node ids. This can be used to speed up the graph preparation if you have done
 /// it for a similar graph with an equal number of nodes. For example if you have changed some
@@ -69,6 +168,7 @@ pub fn prepare_with_order(input_graph: &InputGraph, order: &[NodeId]) -> Result<
     FastGraphBuilder::build_with_order(input_graph, order)
 }
 
+#[cfg(feature = "std")]
 /// Like `prepare_with_order()`, but allows specifying some parameters used for the graph preparation
 pub fn prepare_with_order_with_params(
     input_graph: &InputGraph,
@@ -78,6 +178,95 @@ pub fn prepare_with_order_with_params(
     FastGraphBuilder::build_with_order_with_params(input_graph, order, params)
 }
 
+#[cfg(feature = "std")]
+/// Prepares the given input graph based on a partition of its nodes into cells, e.g. obtained
+/// from a graph partitioner. `partition[node]` is the cell of each node. The interior nodes of
+/// the cells are contracted first and the nodes at the cell boundaries are contracted last, so
+/// they form an overlay on top of the hierarchy. Returns an error if the partition does not have
+/// an entry for every node. See `FastGraphBuilder::build_partitioned`.
+pub fn prepare_partitioned(
+    input_graph: &InputGraph,
+    partition: &[CellId],
+) -> Result<FastGraph, String> {
+    FastGraphBuilder::build_partitioned(input_graph, partition, &Params::default())
+}
+
+#[cfg(feature = "std")]
+/// Like `prepare_partitioned()`, but allows specifying some parameters used for the graph
+/// preparation
+pub fn prepare_partitioned_with_params(
+    input_graph: &InputGraph,
+    partition: &[CellId],
+    params: &Params,
+) -> Result<FastGraph, String> {
+    FastGraphBuilder::build_partitioned(input_graph, partition, params)
+}
+
+#[cfg(feature = "std")]
+/// Prepares the given input graph like `prepare_with_params()`, but uses the given priorities
+/// (one per node) as the initial node priorities instead of calculating them, e.g. to reuse
+/// estimates from a previous run. Unlike the fixed node ordering of `prepare_with_order()` the
+/// priorities are only hints, because the priorities of the neighbors of every contracted node
+/// are still recalculated during the contraction. Returns an error if there is not exactly one
+/// priority per node. See `FastGraphBuilder::build_with_initial_priorities`.
+pub fn prepare_with_initial_priorities(
+    input_graph: &InputGraph,
+    priorities: &[i64],
+    params: &Params,
+) -> Result<FastGraph, String> {
+    FastGraphBuilder::build_with_initial_priorities(input_graph, priorities, params)
+}
+
+#[cfg(feature = "std")]
+/// Prepares several input graphs that share the same topology but use different edge weights,
+/// e.g. the road distance and the travel time for the same road network. The node ordering is
+/// only calculated for the first graph, and the other graphs are prepared using this ordering
+/// (see `prepare_with_order()`), which is much faster than preparing each of them separately.
+/// The prepared graphs are returned in the same order as the input graphs. Returns an error if
+/// the graphs do not have the same number of nodes.
+pub fn prepare_profiles(input_graphs: &[&InputGraph]) -> Result<Vec<FastGraph>, String> {
+    prepare_profiles_with_params(
+        input_graphs,
+        &Params::default(),
+        &ParamsWithOrder::default(),
+    )
+}
+
+#[cfg(feature = "std")]
+/// Like `prepare_profiles()`, but allows specifying the parameters used to prepare the first graph
+/// (`params`) and the other graphs (`params_with_order`)
+pub fn prepare_profiles_with_params(
+    input_graphs: &[&InputGraph],
+    params: &Params,
+    params_with_order: &ParamsWithOrder,
+) -> Result<Vec<FastGraph>, String> {
+    let Some((first, others)) = input_graphs.split_first() else {
+        return Ok(vec![]);
+    };
+    for (i, input_graph) in others.iter().enumerate() {
+        if input_graph.get_num_nodes() != first.get_num_nodes() {
+            return Err(format!(
+                "All profiles must have the same number of nodes, but profile {} has {} nodes and \
+                 profile 0 has {} nodes",
+                i + 1,
+                input_graph.get_num_nodes(),
+                first.get_num_nodes()
+            ));
+        }
+    }
+    let first_graph = prepare_with_params(first, params);
+    let order = first_graph.get_node_ordering();
+    let mut fast_graphs = vec![first_graph];
+    for input_graph in others {
+        fast_graphs.push(prepare_with_order_with_params(
+            input_graph,
+            &order,
+            params_with_order,
+        )?);
+    }
+    Ok(fast_graphs)
+}
+
 /// Calculates the shortest path from `source` to `target`.
 pub fn calc_path(fast_graph: &FastGraph, source: NodeId, target: NodeId) -> Option<ShortestPath> {
     let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
@@ -106,33 +295,160 @@ pub fn create_calculator(fast_graph: &FastGraph) -> PathCalculator {
     PathCalculator::new(fast_graph.get_num_nodes())
 }
 
+#[cfg(feature = "std")]
+/// Calculates the shortest path weights from and to every node of the given graph for each of
+/// the given landmarks, see `Landmarks::new()`. Besides the ALT queries of
+/// `PathCalculator::calc_path_alt` the weights and the resulting lower bounds
+/// (`Landmarks::lower_bound`) can be used to implement other goal directed searches, and no CH
+/// preparation is required.
+pub fn compute_landmark_distances(input_graph: &InputGraph, landmarks: &[NodeId]) -> Landmarks {
+    Landmarks::new(input_graph, landmarks)
+}
+
 /// Returns the node ordering of a prepared graph. This can be used to run the preparation with
 /// `prepare_with_order()`.
 pub fn get_node_ordering(fast_graph: &FastGraph) -> Vec<NodeId> {
     fast_graph.get_node_ordering()
 }
 
+#[cfg(feature = "std")]
+/// Reads a node ordering that was written using `FastGraph::save_ordering`, e.g. to pass it to
+/// `prepare_with_order()`. See `FastGraph::load_ordering` for the possible errors.
+pub fn load_ordering(filename: &str) -> std::io::Result<Vec<NodeId>> {
+    FastGraph::load_ordering(filename)
+}
+
+#[cfg(feature = "std")]
+/// Checks that the given `FastGraph` yields the same shortest path weights as the `InputGraph` it
+/// was prepared from. For this a plain Dijkstra search is run on the input graph for
+/// `num_samples` random (source, target) pairs. The pairs are drawn using `random_node`, which
+/// must return a random number in [0, n) when called with n, e.g. `|n| rng.gen_range(0, n)`.
+/// Returns the first query for which the weights do not agree.
+pub fn verify<F>(
+    fast_graph: &FastGraph,
+    input_graph: &InputGraph,
+    num_samples: usize,
+    random_node: &mut F,
+) -> Result<(), VerificationError>
+where
+    F: FnMut(usize) -> usize,
+{
+    verification::verify(fast_graph, input_graph, num_samples, random_node)
+}
+
+#[cfg(feature = "std")]
+/// Estimates the diameter of the given graph, i.e. the largest shortest path weight between any
+/// two nodes (where the target is reachable from the source), using the double-sweep heuristic:
+/// For each of the `samples` random sources (drawn using `random_node` like for `verify()`) we
+/// find the node that is farthest away from it and then the node that is farthest away from
+/// this node. The result is the largest weight found in any of these searches. This is only a
+/// lower bound of the actual diameter, but for road networks it is usually close to it. Every
+/// sample runs two searches that explore all reachable nodes (see
+/// `PathCalculator::reachable_iter`), so this takes time linear in the number of samples and the
+/// size of the graph. Returns 0 for an empty graph or if no samples are taken.
+pub fn estimate_diameter<F>(fast_graph: &FastGraph, samples: usize, random_node: &mut F) -> Weight
+where
+    F: FnMut(usize) -> usize,
+{
+    let num_nodes = fast_graph.get_num_nodes();
+    if num_nodes == 0 {
+        return WEIGHT_ZERO;
+    }
+    let mut calc = create_calculator(fast_graph);
+    let mut farthest = |source: NodeId| -> (NodeId, Weight) {
+        calc.reachable_iter(fast_graph, source, WEIGHT_MAX)
+            .last()
+            .unwrap()
+    };
+    let mut diameter = WEIGHT_ZERO;
+    for _ in 0..samples {
+        let (node, first_weight) = farthest(NodeId(random_node(num_nodes)));
+        let (_, second_weight) = farthest(node);
+        diameter = diameter.max(first_weight).max(second_weight);
+    }
+    diameter
+}
+
+#[cfg(feature = "std")]
+/// Prepares the given graph once for each of the given `Params` configurations and runs the
+/// shortest path queries between the given (source, target) pairs on each of the prepared
+/// graphs, e.g. to find suitable parameters for a specific graph. The i-th entry of the result
+/// contains the preparation time, the number of shortcuts and the mean query time for the i-th
+/// configuration. The timings depend on the machine and on what else is running, so it is worth
+/// using a few thousand queries and to repeat the comparison when the differences are small.
+pub fn compare_params(
+    input_graph: &InputGraph,
+    configs: &[Params],
+    query_pairs: &[(NodeId, NodeId)],
+) -> Vec<ParamsResult> {
+    configs
+        .iter()
+        .map(|params| {
+            let (fast_graph, report) = prepare_with_params_reported(input_graph, params);
+            let mut calc = create_calculator(&fast_graph);
+            let mut total_weight = WEIGHT_ZERO;
+            let mut num_not_found = 0;
+            let start = std::time::Instant::now();
+            for &(source, target) in query_pairs {
+                match calc.calc_path(&fast_graph, source, target) {
+                    Some(path) => total_weight = add_weights(total_weight, path.get_weight()),
+                    None => num_not_found += 1,
+                }
+            }
+            let query_time = start.elapsed();
+            ParamsResult {
+                preparation_time: report.duration,
+                shortcuts_added: report.shortcuts_added,
+                mean_query_time: query_time
+                    .checked_div(query_pairs.len() as u32)
+                    .unwrap_or_default(),
+                total_weight,
+                num_not_found,
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "std")]
+/// Checks that preparing the given `InputGraph` with `prepare_with_order()`, using the node
+/// ordering found by `prepare()`, yields the same shortest path weights as `prepare()` for
+/// `num_samples` random queries. The queries are drawn using `random_node` like for `verify()`.
+/// Panics if the weights of any query do not agree.
+pub fn assert_order_roundtrip<F>(input_graph: &InputGraph, num_samples: usize, random_node: &mut F)
+where
+    F: FnMut(usize) -> usize,
+{
+    verification::assert_order_roundtrip(input_graph, num_samples, random_node)
+}
+
 /// When serializing a `FastGraph` in a larger struct, use `#[serde(serialize_with =
 /// "fast_paths::serialize_32`)]` to transform the graph to a 32-bit representation. This will use
 /// 50% more RAM than serializing without transformation, but the resulting size will be 50% less.
-/// It will panic if the graph has more than 2^32 nodes or edges or values for weight.
+/// It will return an error if the graph has more than 2^32 nodes or edges or values for weight.
 pub fn serialize_32<S: Serializer>(fg: &FastGraph, s: S) -> Result<S::Ok, S::Error> {
-    FastGraph32::new(fg).serialize(s)
+    FastGraph32::try_new(fg)
+        .map_err(S::Error::custom)?
+        .serialize(s)
 }
 
 /// When deserializing a `FastGraph` in a larger struct, use `#[serde(deserialize_with =
 /// "fast_paths::deserialize_32`)]` to transform the graph from a 32-bit representation to the
 /// current platform's supported size. This is necessary when serializing on a 64-bit system and
-/// deserializing on a 32-bit system, such as WASM.
+/// deserializing on a 32-bit system, such as WASM. The values are converted while they are read,
+/// so unlike `FastGraph32::convert_to_usize` this does not need any extra RAM, and when used with a
+/// deserializer reading from a file the 32-bit graph is never held in memory as a whole.
 pub fn deserialize_32<'de, D: Deserializer<'de>>(d: D) -> Result<FastGraph, D::Error> {
-    let fg32 = <FastGraph32>::deserialize(d)?;
-    Ok(fg32.convert_to_usize())
+    fast_graph32::deserialize_to_usize(d)
 }
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod tests {
+    use std::collections::{HashMap, HashSet};
     use std::error::Error;
     use std::fs::{remove_file, File};
+    use std::io::BufReader;
+    use std::sync::Arc;
+    use std::thread;
     use std::time::SystemTime;
 
     use rand::rngs::StdRng;
@@ -149,6 +465,169 @@ mod tests {
     use super::*;
     use crate::fast_graph_builder::ParamsWithOrder;
 
+    #[test]
+    fn large_finite_weights() {
+        // 0 -> 1 -> 2 -> 3
+        //  \________/
+        let max = WEIGHT_MAX - Weight(1);
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(max.0 / 2), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(max.0 / 2), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), max, 1.0);
+        g.freeze();
+        for order in [[0, 1, 2, 3], [1, 2, 0, 3], [3, 2, 1, 0]] {
+            let order: Vec<NodeId> = order.iter().copied().map(NodeId).collect();
+            let fast_graph = prepare_with_order(&g, &order).unwrap();
+            let mut calc = create_calculator(&fast_graph);
+            // the largest finite weight is a valid path weight
+            assert_eq!(
+                Weight(max.0 / 2),
+                calc.calc_path(&fast_graph, NodeId(0), NodeId(1))
+                    .unwrap()
+                    .get_weight()
+            );
+            assert_eq!(
+                max,
+                calc.calc_path(&fast_graph, NodeId(0), NodeId(2))
+                    .unwrap()
+                    .get_weight()
+            );
+            assert_eq!(
+                Weight(max.0 / 2 + 1),
+                calc.calc_path(&fast_graph, NodeId(1), NodeId(3))
+                    .unwrap()
+                    .get_weight()
+            );
+            // the weight from 0 to 3 does not fit into a weight, so 3 is treated as unreachable
+            // instead of overflowing
+            assert_eq!(None, calc.calc_path(&fast_graph, NodeId(0), NodeId(3)));
+            let reachable: Vec<NodeId> = calc
+                .reachable_iter(&fast_graph, NodeId(0), WEIGHT_MAX)
+                .map(|(node, _)| node)
+                .collect();
+            assert_eq!(vec![NodeId(0), NodeId(1), NodeId(2)], reachable);
+        }
+    }
+
+    #[test]
+    fn weight_sums_beyond_weight_max() {
+        // 0 -> 1 -> 2 -> 3, every edge has the largest finite weight
+        let max = WEIGHT_MAX - Weight(1);
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), max, 1.0);
+        g.add_edge(NodeId(1), NodeId(2), max, 1.0);
+        g.add_edge(NodeId(2), NodeId(3), max, 1.0);
+        g.freeze();
+        let mut rng = create_rng();
+        for order in [[0, 1, 2, 3], [1, 2, 0, 3], [3, 2, 1, 0], [0, 3, 1, 2]] {
+            let order: Vec<NodeId> = order.iter().copied().map(NodeId).collect();
+            let fast_graph = prepare_with_order(&g, &order).unwrap();
+            // there are no shortcuts, because their weight would be infinite
+            assert_eq!(0, fast_graph.get_num_shortcut_out_edges());
+            assert_eq!(0, fast_graph.get_num_shortcut_in_edges());
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &g, 100, &mut |n| rng.gen_range(0, n)).unwrap();
+            let mut calc = create_calculator(&fast_graph);
+            for source in 0..4 {
+                for target in 0..4 {
+                    let expected = match target as isize - source as isize {
+                        0 => Some(WEIGHT_ZERO),
+                        1 => Some(max),
+                        _ => None,
+                    };
+                    assert_eq!(
+                        expected,
+                        calc.calc_path(&fast_graph, NodeId(source), NodeId(target))
+                            .map(|p| p.get_weight())
+                    );
+                }
+                let reachable: Vec<(NodeId, Weight)> = calc
+                    .reachable_iter(&fast_graph, NodeId(source), WEIGHT_MAX)
+                    .collect();
+                assert_eq!(if source < 3 { 2 } else { 1 }, reachable.len());
+            }
+        }
+    }
+
+    #[test]
+    fn isolated_nodes() {
+        // nodes 0, 3, 6 and 9 have no edges at all, the others form a connected component
+        // 1 <-> 2 -> 4 -> 5 -> 7 -> 8 -> 1
+        let w = Weight;
+        let adj = vec![
+            vec![],
+            vec![(NodeId(2), w(3))],
+            vec![(NodeId(1), w(3)), (NodeId(4), w(1))],
+            vec![],
+            vec![(NodeId(5), w(2))],
+            vec![(NodeId(7), w(4))],
+            vec![],
+            vec![(NodeId(8), w(1))],
+            vec![(NodeId(1), w(5))],
+            vec![],
+        ];
+        let g = InputGraph::from_adjacency_list(&adj);
+        assert_eq!(10, g.get_num_nodes());
+        let isolated = [0, 3, 6, 9];
+        let mut fw = FloydWarshall::new(g.get_num_nodes());
+        fw.prepare(&g);
+        let order: Vec<NodeId> = [9, 3, 5, 0, 8, 1, 6, 2, 7, 4]
+            .iter()
+            .copied()
+            .map(NodeId)
+            .collect();
+        let fast_graphs = vec![
+            prepare(&g),
+            prepare_with_params(&g, &Params::with_rank_explanations(true)),
+            prepare_with_order(&g, &order).unwrap(),
+            prepare_partitioned(&g, &[0, 0, 1, 1, 0, 1, 0, 1, 1, 0]).unwrap(),
+        ];
+        for fast_graph in fast_graphs {
+            let bytes = bincode::serialize(&fast_graph).unwrap();
+            let deserialized: FastGraph = bincode::deserialize(&bytes).unwrap();
+            let converted = FastGraph32::new(&fast_graph).convert_to_usize();
+            for fast_graph in [fast_graph, deserialized, converted] {
+                assert_eq!(10, fast_graph.get_num_nodes());
+                fast_graph.check_invariants().unwrap();
+                let mut calc = create_calculator(&fast_graph);
+                for source in 0..10 {
+                    for target in 0..10 {
+                        let path = calc.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                        if source == target {
+                            let path = path.unwrap();
+                            assert_eq!(WEIGHT_ZERO, path.get_weight());
+                            assert_eq!(&vec![NodeId(source)], path.get_nodes());
+                        } else if isolated.contains(&source) || isolated.contains(&target) {
+                            assert_eq!(None, path);
+                        } else {
+                            assert_eq!(
+                                fw.calc_weight(NodeId(source), NodeId(target)),
+                                path.unwrap().get_weight()
+                            );
+                        }
+                    }
+                }
+                for &node in &isolated {
+                    if let Some(explanation) = fast_graph.explain_rank(NodeId(node)) {
+                        assert_eq!(0, explanation.num_edges);
+                        assert_eq!(0, explanation.num_shortcuts);
+                    }
+                    let reachable: Vec<(NodeId, Weight)> = calc
+                        .reachable_iter(&fast_graph, NodeId(node), WEIGHT_MAX)
+                        .collect();
+                    assert_eq!(vec![(NodeId(node), WEIGHT_ZERO)], reachable);
+                    let path = calc.calc_path_multiple_sources_and_targets(
+                        &fast_graph,
+                        vec![(NodeId(node), WEIGHT_ZERO)],
+                        vec![(NodeId(1), WEIGHT_ZERO), (NodeId(5), WEIGHT_ZERO)],
+                    );
+                    assert_eq!(None, path);
+                }
+            }
+        }
+    }
+
     #[test]
     fn routing_on_random_graph() {
         const REPEATS: usize = 100;
@@ -157,6 +636,363 @@ mod tests {
         }
     }
 
+    #[test]
+    fn routing_with_uniqueness_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let mut fw = FloydWarshall::new(num_nodes);
+            fw.prepare(&input_graph);
+            for _ in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                match path_calculator.calc_path_with_uniqueness(
+                    &fast_graph,
+                    NodeId(source),
+                    NodeId(target),
+                ) {
+                    Some((path, unique)) => {
+                        assert_eq!(
+                            fw.calc_weight(NodeId(source), NodeId(target)),
+                            path.get_weight()
+                        );
+                        if !unique {
+                            assert!(
+                                count_shortest_paths(
+                                    &input_graph,
+                                    &fw,
+                                    NodeId(source),
+                                    NodeId(target)
+                                ) > 1,
+                                "The shortest path from {} to {} is unique\n Failing graph:\n{:?}",
+                                source,
+                                target,
+                                input_graph
+                            );
+                        }
+                    }
+                    None => assert_eq!(WEIGHT_MAX, fw.calc_weight(NodeId(source), NodeId(target))),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn unpack_segments_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            for _ in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                let Some(path) =
+                    path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target))
+                else {
+                    continue;
+                };
+                let compressed = path_calculator
+                    .calc_path_compressed(&fast_graph, NodeId(source), NodeId(target))
+                    .unwrap();
+                let compressed = ShortestPath::new(
+                    NodeId(source),
+                    NodeId(target),
+                    path.get_weight(),
+                    compressed,
+                );
+                let mut nodes = vec![NodeId(source)];
+                for i in 0..compressed.get_nodes().len() - 1 {
+                    nodes.extend_from_slice(&compressed.unpack_segment(&fast_graph, i)[1..]);
+                }
+                assert_eq!(path.get_nodes(), &nodes);
+            }
+        }
+    }
+
+    #[test]
+    fn nearest_source_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            for _ in 0..NUM_QUERIES {
+                let num_sources = rng.gen_range(1, 5);
+                let sources: Vec<NodeId> = (0..num_sources)
+                    .map(|_| NodeId(rng.gen_range(0, num_nodes)))
+                    .collect();
+                let target = rng.gen_range(0, num_nodes);
+                let best_weight = sources
+                    .iter()
+                    .filter_map(|&s| path_calculator.calc_path(&fast_graph, s, NodeId(target)))
+                    .map(|p| p.get_weight())
+                    .min();
+                match path_calculator.calc_path_nearest_source(
+                    &fast_graph,
+                    &sources,
+                    NodeId(target),
+                ) {
+                    Some((source, path)) => {
+                        assert!(sources.contains(&source));
+                        assert_eq!(source, path.get_source());
+                        assert_eq!(NodeId(target), path.get_target());
+                        assert_eq!(best_weight, Some(path.get_weight()));
+                        assert_eq!(
+                            Some(path.get_weight()),
+                            path_calculator
+                                .calc_path(&fast_graph, source, NodeId(target))
+                                .map(|p| p.get_weight())
+                        );
+                    }
+                    None => assert_eq!(None, best_weight),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn compare_params() {
+        let mut rng = create_rng();
+        let input_graph = InputGraph::random(&mut rng, 100, 2.0);
+        let num_nodes = input_graph.get_num_nodes();
+        let query_pairs: Vec<(NodeId, NodeId)> = (0..100)
+            .map(|_| {
+                (
+                    NodeId(rng.gen_range(0, num_nodes)),
+                    NodeId(rng.gen_range(0, num_nodes)),
+                )
+            })
+            .collect();
+        let configs = vec![
+            Params::default(),
+            Params::with_shortcut_pruning(true),
+            Params::with_max_hierarchy_levels(0),
+        ];
+        let results = super::compare_params(&input_graph, &configs, &query_pairs);
+        assert_eq!(3, results.len());
+        for result in &results {
+            assert_eq!(results[0].total_weight, result.total_weight);
+            assert_eq!(results[0].num_not_found, result.num_not_found);
+        }
+        let fast_graph = prepare(&input_graph);
+        assert_eq!(
+            fast_graph.get_num_shortcut_out_edges() + fast_graph.get_num_shortcut_in_edges(),
+            results[0].shortcuts_added
+        );
+        assert!(results[1].shortcuts_added <= results[0].shortcuts_added);
+        // without any hierarchy levels nothing is contracted
+        assert_eq!(0, results[2].shortcuts_added);
+
+        let results = super::compare_params(&input_graph, &configs[..1], &[]);
+        assert_eq!(std::time::Duration::ZERO, results[0].mean_query_time);
+        assert_eq!(Weight(0), results[0].total_weight);
+    }
+
+    #[test]
+    fn estimate_diameter() {
+        // 0 -> 1 -> 2 -> 3 -> 4, 5 is isolated
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(2), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(3), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(4), 1.0);
+        g.add_edge(NodeId(5), NodeId(4), Weight(100), 1.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        // starting at 0 we find 4 at distance 10, but there are no paths starting at 4, so the
+        // second search does not find anything farther away
+        assert_eq!(
+            Weight(10),
+            super::estimate_diameter(&fast_graph, 1, &mut |_| 0)
+        );
+        assert_eq!(
+            Weight(0),
+            super::estimate_diameter(&fast_graph, 1, &mut |_| 4)
+        );
+        assert_eq!(
+            Weight(100),
+            super::estimate_diameter(&fast_graph, 1, &mut |_| 5)
+        );
+        assert_eq!(
+            Weight(0),
+            super::estimate_diameter(&fast_graph, 0, &mut |_| 0)
+        );
+        assert_eq!(
+            Weight(0),
+            super::estimate_diameter(&FastGraph::new(0), 10, &mut |_| 0)
+        );
+    }
+
+    #[test]
+    fn estimate_diameter_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut fw = FloydWarshall::new(num_nodes);
+            fw.prepare(&input_graph);
+            let weights: Vec<Weight> = (0..num_nodes)
+                .flat_map(|s| (0..num_nodes).map(move |t| (s, t)))
+                .map(|(s, t)| fw.calc_weight(NodeId(s), NodeId(t)))
+                .filter(|&weight| weight < WEIGHT_MAX)
+                .collect();
+            let diameter = *weights.iter().max().unwrap();
+            let estimate = super::estimate_diameter(&fast_graph, 10, &mut |n| rng.gen_range(0, n));
+            // the estimate is the weight of an actual shortest path
+            assert!(estimate <= diameter);
+            assert!(weights.contains(&estimate));
+        }
+    }
+
+    #[test]
+    fn scaled_weights_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let mut scaled_graph = input_graph.clone();
+            scaled_graph.thaw();
+            scaled_graph.scale_weights(10.0);
+            scaled_graph.freeze();
+            let fast_graph = prepare(&input_graph);
+            let scaled_fast_graph = prepare(&scaled_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            for _ in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                let path = path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                let scaled_path =
+                    path_calculator.calc_path(&scaled_fast_graph, NodeId(source), NodeId(target));
+                assert_eq!(
+                    path.as_ref().map(|p| Weight(10 * p.get_weight().0)),
+                    scaled_path.as_ref().map(|p| p.get_weight())
+                );
+                assert_eq!(
+                    path.map(|p| p.get_weight_f64()),
+                    scaled_path.map(|p| p.get_weight_f64())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn cost_adjusted_routing_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            for _ in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                let path = path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                // scaling all weights by the same factor is additive, so the shortest path stays
+                // the same
+                let adjusted_path = path_calculator.calc_path_with_cost_adjust(
+                    &fast_graph,
+                    NodeId(source),
+                    NodeId(target),
+                    |_edge, weight| Weight(3 * weight.0),
+                );
+                assert_eq!(
+                    path.as_ref().map(|p| Weight(3 * p.get_weight().0)),
+                    adjusted_path.as_ref().map(|p| p.get_weight())
+                );
+                assert_eq!(
+                    path.map(|p| p.get_nodes().clone()),
+                    adjusted_path.map(|p| p.get_nodes().clone())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn bounded_routing_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            for _ in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                let max_weight = rng.gen_range(0, 100);
+                let path = path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                let bounded_path = path_calculator.calc_path_bounded(
+                    &fast_graph,
+                    NodeId(source),
+                    NodeId(target),
+                    Weight(max_weight),
+                );
+                match path {
+                    Some(p) if p.get_weight() <= Weight(max_weight) => {
+                        assert_eq!(Some(p), bounded_path)
+                    }
+                    _ => assert_eq!(None, bounded_path),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn checked_routing_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            for _ in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                let path = path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                let checked_path =
+                    path_calculator.calc_path_checked(&fast_graph, NodeId(source), NodeId(target));
+                assert_eq!(path, checked_path);
+            }
+        }
+    }
+
     fn run_test_on_random_graph() {
         const NUM_NODES: usize = 50;
         const NUM_QUERIES: usize = 1_000;
@@ -166,6 +1002,7 @@ mod tests {
         let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
         debug!("random graph: \n {:?}", input_graph);
         let fast_graph = prepare(&input_graph);
+        fast_graph.check_invariants().unwrap();
         let mut path_calculator = create_calculator(&fast_graph);
 
         let dijkstra_graph = PreparationGraph::from_input_graph(&input_graph);
@@ -179,14 +1016,14 @@ mod tests {
             let source = rng.gen_range(0, input_graph.get_num_nodes());
             let target = rng.gen_range(0, input_graph.get_num_nodes());
             let path_fast = path_calculator
-                .calc_path(&fast_graph, source, target)
-                .unwrap_or(ShortestPath::none(source, target));
+                .calc_path(&fast_graph, NodeId(source), NodeId(target))
+                .unwrap_or(ShortestPath::none(NodeId(source), NodeId(target)));
             let path_dijkstra = dijkstra
-                .calc_path(&dijkstra_graph, source, target)
-                .unwrap_or(ShortestPath::none(source, target));
+                .calc_path(&dijkstra_graph, NodeId(source), NodeId(target))
+                .unwrap_or(ShortestPath::none(NodeId(source), NodeId(target)));
             let weight_fast = path_fast.get_weight();
             let weight_dijkstra = path_dijkstra.get_weight();
-            let weight_fw = fw.calc_weight(source, target);
+            let weight_fw = fw.calc_weight(NodeId(source), NodeId(target));
             assert_eq!(
                 weight_fw, weight_fast,
                 "\nNo agreement for routing query from: {} to: {}\nFloyd-Warshall: {}\nCH: {}\
@@ -214,11 +1051,98 @@ mod tests {
     }
 
     #[test]
-    fn routing_with_multiple_sources_and_targets_on_random_graph() {
+    fn unpack_cache_on_random_graph() {
+        const NUM_QUERIES: usize = 1_000;
+        let mut rng = create_rng();
+        let input_graph = InputGraph::random(&mut rng, 200, 2.0);
+        let num_nodes = input_graph.get_num_nodes();
+        let fast_graph = prepare(&input_graph);
+        let mut path_calculator = create_calculator(&fast_graph);
+        // the cache is small, so shortcuts are evicted frequently
+        let mut cached_calculator = PathCalculator::new(num_nodes).with_unpack_cache(20);
+        for _ in 0..NUM_QUERIES {
+            let source = rng.gen_range(0, num_nodes);
+            let target = rng.gen_range(0, num_nodes);
+            let path = path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target));
+            let cached_path =
+                cached_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target));
+            assert_eq!(path.is_some(), cached_path.is_some());
+            if let (Some(path), Some(cached_path)) = (path, cached_path) {
+                assert_eq!(path.get_weight(), cached_path.get_weight());
+                assert_eq!(path.get_nodes(), cached_path.get_nodes());
+                assert_eq!(path.get_edge_data(), cached_path.get_edge_data());
+            }
+        }
+    }
+
+    #[test]
+    fn zero_weight_edges_on_random_graph() {
         const REPEATS: usize = 20;
+        const NUM_QUERIES: usize = 200;
+        let mut rng = create_rng();
         for _ in 0..REPEATS {
-            const NUM_NODES: usize = 50;
-            const NUM_QUERIES: usize = 1_000;
+            let mut input_graph = InputGraph::random(&mut rng, 50, 2.0);
+            let num_nodes = input_graph.get_num_nodes();
+            input_graph.thaw();
+            // short chains of zero weight transfer edges, some of them also in the opposite
+            // direction, so there are zero weight cycles as well
+            for _ in 0..10 {
+                let mut node = rng.gen_range(0, num_nodes);
+                for _ in 0..rng.gen_range(1, 4) {
+                    let next = rng.gen_range(0, num_nodes);
+                    if rng.gen_bool(0.3) {
+                        input_graph.add_edge_bidir(NodeId(node), NodeId(next), Weight(0), 0.0);
+                    } else {
+                        input_graph.add_edge(NodeId(node), NodeId(next), Weight(0), 0.0);
+                    }
+                    node = next;
+                }
+            }
+            input_graph.freeze();
+            let dijkstra_graph = PreparationGraph::from_input_graph(&input_graph);
+            let mut dijkstra = Dijkstra::new(num_nodes);
+            for params in &[Params::default(), Params::with_shortcut_pruning(true)] {
+                let fast_graph = prepare_with_params(&input_graph, params);
+                fast_graph.check_invariants().unwrap();
+                let mut path_calculator = create_calculator(&fast_graph);
+                for _ in 0..NUM_QUERIES {
+                    let source = rng.gen_range(0, num_nodes);
+                    let target = rng.gen_range(0, num_nodes);
+                    let path_fast =
+                        path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                    let path_dijkstra =
+                        dijkstra.calc_path(&dijkstra_graph, NodeId(source), NodeId(target));
+                    assert_eq!(
+                        path_dijkstra.as_ref().map(|p| p.get_weight()),
+                        path_fast.as_ref().map(|p| p.get_weight()),
+                        "\nNo agreement for routing query from: {} to: {}\n Failing graph:\n{:?}",
+                        source,
+                        target,
+                        input_graph
+                    );
+                    if let Some(path) = path_fast {
+                        let nodes = path.get_nodes();
+                        assert_eq!(NodeId(source), nodes[0]);
+                        assert_eq!(NodeId(target), *nodes.last().unwrap());
+                        let weight = nodes.windows(2).fold(WEIGHT_ZERO, |weight, pair| {
+                            let edge = input_graph.get_edges_between(pair[0], pair[1])[0];
+                            add_weights(weight, edge.weight)
+                        });
+                        assert_eq!(path.get_weight(), weight);
+                        let unique_nodes: HashSet<&NodeId> = nodes.iter().collect();
+                        assert_eq!(nodes.len(), unique_nodes.len());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn routing_with_multiple_sources_and_targets_on_random_graph() {
+        const REPEATS: usize = 20;
+        for _ in 0..REPEATS {
+            const NUM_NODES: usize = 50;
+            const NUM_QUERIES: usize = 1_000;
             const MEAN_DEGREE: f32 = 2.0;
             const NUM_SOURCES: usize = 3;
             const NUM_TARGETS: usize = 3;
@@ -246,6 +1170,10 @@ mod tests {
                     sources.clone(),
                     targets.clone(),
                 );
+                assert_eq!(
+                    fast_path,
+                    path_calculator.calc_path_multi(&fast_graph, &sources, &targets)
+                );
                 let mut dijkstra_paths: Vec<(Option<ShortestPath>, Weight, Weight)> = vec![];
                 for (source, source_weight) in &sources {
                     for (target, target_weight) in &targets {
@@ -271,7 +1199,7 @@ mod tests {
                 let shortest_dijkstra_weight = found_dijkstras
                     .iter()
                     .map(|(p, source_weight, target_weight)| {
-                        p.get_weight() + source_weight + target_weight
+                        p.get_weight() + *source_weight + *target_weight
                     })
                     .min();
 
@@ -296,53 +1224,1539 @@ mod tests {
                 let matching_dijkstras: Vec<(ShortestPath, Weight, Weight)> = found_dijkstras
                     .into_iter()
                     .filter(|(p, source_weight, target_weight)| {
-                        p.get_weight() + source_weight + target_weight == f.get_weight()
+                        p.get_weight() + *source_weight + *target_weight == f.get_weight()
                             && p.get_source() == f.get_source()
                             && p.get_target() == f.get_target()
                     })
                     .collect();
 
-                assert!(
-                    matching_dijkstras.len() > 0,
-                    "There has to be at least one Dijkstra path with source,target and weight equal to fast_path"
-                );
+                assert!(
+                    matching_dijkstras.len() > 0,
+                    "There has to be at least one Dijkstra path with source,target and weight equal to fast_path"
+                );
+
+                // one of the matching Dijkstra's should have the same nodes as fast_path, but in
+                // some rare cases this might not be true, because there are multiple shortest paths.
+                if !matching_dijkstras
+                    .into_iter()
+                    .any(|(p, _, _)| p.get_nodes() == f.get_nodes())
+                {
+                    num_different_paths += 1;
+                }
+            }
+            if num_paths_not_found as f32 > 0.5 * NUM_QUERIES as f32 {
+                panic!(
+                    "too many paths not found: {}, out of {}",
+                    num_paths_not_found, NUM_QUERIES
+                )
+            }
+            if num_different_paths as f32 > 0.1 * NUM_QUERIES as f32 {
+                panic!(
+                    "too many different paths: {}, out of {}, a few different paths can be expected \
+                        because of unambiguous shortest paths, but if there are too many something is \
+                        wrong",
+                    num_different_paths, NUM_QUERIES
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn approximate_routing_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 1_000;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let dijkstra_graph = PreparationGraph::from_input_graph(&input_graph);
+            let mut dijkstra = Dijkstra::new(input_graph.get_num_nodes());
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, input_graph.get_num_nodes());
+                let target = rng.gen_range(0, input_graph.get_num_nodes());
+                let epsilon = [0.0, 0.1, 0.5, 2.0][rng.gen_range(0, 4)];
+                let path = path_calculator.calc_path_approx(
+                    &fast_graph,
+                    NodeId(source),
+                    NodeId(target),
+                    epsilon,
+                );
+                let path_dijkstra =
+                    dijkstra.calc_path(&dijkstra_graph, NodeId(source), NodeId(target));
+                match (path, path_dijkstra) {
+                    (Some(path), Some(path_dijkstra)) => {
+                        let exact_weight = path_dijkstra.get_weight();
+                        assert!(path.get_weight() >= exact_weight);
+                        assert!(
+                            path.get_weight().0 as f64 <= (1.0 + epsilon) * exact_weight.0 as f64,
+                            "weight {} exceeds (1 + {}) * {}",
+                            path.get_weight(),
+                            epsilon,
+                            exact_weight
+                        );
+                        if epsilon == 0.0 {
+                            assert_eq!(exact_weight, path.get_weight());
+                        }
+                        let nodes = path.get_nodes();
+                        assert_eq!(NodeId(source), nodes[0]);
+                        assert_eq!(NodeId(target), nodes[nodes.len() - 1]);
+                    }
+                    (None, None) => {}
+                    (path, path_dijkstra) => panic!(
+                        "path found by only one of the searches: {:?} vs. {:?}",
+                        path, path_dijkstra
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn alt_routing_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 1_000;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let landmarks = Landmarks::select(&input_graph, rng.gen_range(0, 5));
+            let mut path_calculator = PathCalculator::new(num_nodes);
+            let dijkstra_graph = PreparationGraph::from_input_graph(&input_graph);
+            let mut dijkstra = Dijkstra::new(num_nodes);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                let path =
+                    path_calculator.calc_path_alt(&landmarks, NodeId(source), NodeId(target));
+                let path_dijkstra =
+                    dijkstra.calc_path(&dijkstra_graph, NodeId(source), NodeId(target));
+                match (path, path_dijkstra) {
+                    (Some(path), Some(path_dijkstra)) => {
+                        assert_eq!(path_dijkstra.get_weight(), path.get_weight());
+                        assert!(
+                            landmarks.lower_bound(NodeId(source), NodeId(target))
+                                <= path.get_weight()
+                        );
+                        let nodes = path.get_nodes();
+                        assert_eq!(NodeId(source), nodes[0]);
+                        assert_eq!(NodeId(target), nodes[nodes.len() - 1]);
+                        assert_eq!(nodes.len() - 1, path.get_edge_data().len());
+                    }
+                    (None, None) => {}
+                    (path, path_dijkstra) => panic!(
+                        "path found by only one of the searches: {:?} vs. {:?}",
+                        path, path_dijkstra
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn landmark_distances_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let landmark_nodes: Vec<NodeId> = (0..rng.gen_range(0, 5))
+                .map(|_| NodeId(rng.gen_range(0, num_nodes)))
+                .collect();
+            let landmarks = compute_landmark_distances(&input_graph, &landmark_nodes);
+            assert_eq!(&landmark_nodes, landmarks.get_landmarks());
+            let mut fw = FloydWarshall::new(num_nodes);
+            fw.prepare(&input_graph);
+            for (i, &landmark) in landmark_nodes.iter().enumerate() {
+                for node in (0..num_nodes).map(NodeId) {
+                    assert_eq!(
+                        fw.calc_weight(landmark, node),
+                        landmarks.get_weights_from(i)[node.0]
+                    );
+                    assert_eq!(
+                        fw.calc_weight(node, landmark),
+                        landmarks.get_weights_to(i)[node.0]
+                    );
+                }
+            }
+            for source in (0..num_nodes).map(NodeId) {
+                for target in (0..num_nodes).map(NodeId) {
+                    assert!(
+                        landmarks.lower_bound(source, target) <= fw.calc_weight(source, target)
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reachable_iter_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let mut fw = FloydWarshall::new(num_nodes);
+            fw.prepare(&input_graph);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let max_weight = rng.gen_range(0, 300);
+                let reachable: Vec<(NodeId, Weight)> = path_calculator
+                    .reachable_iter(&fast_graph, NodeId(source), Weight(max_weight))
+                    .collect();
+                assert!(reachable.windows(2).all(|w| w[0].1 <= w[1].1));
+                let mut expected: Vec<(NodeId, Weight)> = (0..num_nodes)
+                    .map(|node| (NodeId(node), fw.calc_weight(NodeId(source), NodeId(node))))
+                    .filter(|(_, weight)| *weight <= Weight(max_weight))
+                    .collect();
+                let mut found = reachable.clone();
+                expected.sort_unstable();
+                found.sort_unstable();
+                assert_eq!(
+                    expected, found,
+                    "\nwrong reachable nodes for source: {}, max_weight: {}\n Failing graph:\n{:?}",
+                    source, max_weight, input_graph
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn shortest_path_tree_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let edge_weights: HashMap<(NodeId, NodeId), Weight> = input_graph
+                .get_edges()
+                .iter()
+                .map(|e| ((e.from, e.to), e.weight))
+                .collect();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let mut fw = FloydWarshall::new(num_nodes);
+            fw.prepare(&input_graph);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let max_weight = if rng.gen_bool(0.5) {
+                    Some(Weight(rng.gen_range(0, 300)))
+                } else {
+                    None
+                };
+                let tree = path_calculator.calc_shortest_path_tree(
+                    &fast_graph,
+                    NodeId(source),
+                    max_weight,
+                );
+                assert_eq!(num_nodes, tree.len());
+                assert_eq!(None, tree[source]);
+                for node in 0..num_nodes {
+                    let weight = fw.calc_weight(NodeId(source), NodeId(node));
+                    match tree[node] {
+                        Some(parent) => {
+                            assert!(weight <= max_weight.unwrap_or(WEIGHT_MAX));
+                            assert_eq!(
+                                weight,
+                                fw.calc_weight(NodeId(source), parent)
+                                    + edge_weights[&(parent, NodeId(node))],
+                                "\nwrong parent for node: {}, source: {}\n Failing graph:\n{:?}",
+                                node,
+                                source,
+                                input_graph
+                            );
+                        }
+                        None => assert!(
+                            node == source
+                                || weight == WEIGHT_MAX
+                                || weight > max_weight.unwrap_or(WEIGHT_MAX)
+                        ),
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn edge_data_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 1_000;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let random_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let mut input_graph = InputGraph::new();
+            for e in random_graph.get_edges() {
+                let data = (e.from.0 * NUM_NODES + e.to.0) as EdgeData;
+                input_graph.add_edge_with_data(e.from, e.to, e.weight, e.length, data);
+            }
+            input_graph.freeze();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, input_graph.get_num_nodes());
+                let target = rng.gen_range(0, input_graph.get_num_nodes());
+                if let Some(path) =
+                    path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target))
+                {
+                    let expected_data: Vec<EdgeData> = path
+                        .get_nodes()
+                        .windows(2)
+                        .map(|w| (w[0].0 * NUM_NODES + w[1].0) as EdgeData)
+                        .collect();
+                    assert_eq!(&expected_data, path.get_edge_data());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn routing_without_stall_on_demand_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 1_000;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let mut path_calculator_no_stall = create_calculator(&fast_graph);
+            path_calculator_no_stall.set_stall_on_demand(false);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, input_graph.get_num_nodes());
+                let target = rng.gen_range(0, input_graph.get_num_nodes());
+                let path = path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                let path_no_stall =
+                    path_calculator_no_stall.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                assert_eq!(
+                    path.map(|p| p.get_weight()),
+                    path_no_stall.map(|p| p.get_weight()),
+                    "\nNo agreement for routing query from: {} to: {}\n Failing graph:\n{:?}",
+                    source,
+                    target,
+                    input_graph
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn routing_with_multiple_threads_on_random_graph() {
+        const NUM_THREADS: usize = 8;
+        const NUM_NODES: usize = 100;
+        const NUM_QUERIES: usize = 500;
+        const MEAN_DEGREE: f32 = 2.0;
+        let mut rng = create_rng();
+        let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+        let num_nodes = input_graph.get_num_nodes();
+        let fast_graph = Arc::new(prepare(&input_graph));
+        let queries: Vec<Vec<(NodeId, NodeId)>> = (0..NUM_THREADS)
+            .map(|_| {
+                (0..NUM_QUERIES)
+                    .map(|_| {
+                        (
+                            NodeId(rng.gen_range(0, num_nodes)),
+                            NodeId(rng.gen_range(0, num_nodes)),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut calc = create_calculator(&fast_graph);
+        let expected: Vec<Vec<Option<Weight>>> = queries
+            .iter()
+            .map(|qs| {
+                qs.iter()
+                    .map(|&(s, t)| calc.calc_path(&fast_graph, s, t).map(|p| p.get_weight()))
+                    .collect()
+            })
+            .collect();
+        let handles: Vec<_> = queries
+            .into_iter()
+            .map(|qs| {
+                let fast_graph = Arc::clone(&fast_graph);
+                thread::spawn(move || {
+                    // the down edges are indexed lazily by whichever thread comes first
+                    assert!(fast_graph.get_down_edges(NodeId(0)).len() <= num_nodes);
+                    let mut calc = create_calculator(&fast_graph);
+                    qs.iter()
+                        .map(|&(s, t)| calc.calc_path(&fast_graph, s, t).map(|p| p.get_weight()))
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect();
+        for (handle, expected) in handles.into_iter().zip(expected) {
+            assert_eq!(expected, handle.join().unwrap());
+        }
+    }
+
+    #[test]
+    fn routing_with_adaptive_witness_hops_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let params = Params::with_adaptive_witness_hops(vec![
+                (0.0, rng.gen_range(0, 3)),
+                (rng.gen_range(0.0, 1.0), rng.gen_range(1, 5)),
+            ]);
+            let fast_graph = prepare_with_params(&input_graph, &params);
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn dijkstra_on_prepared_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let fast_graph = prepare(&input_graph);
+            // only the prepared graph is needed to run Dijkstra, and it restores all the edges of
+            // the input graph
+            let dijkstra_graph = fast_graph.to_preparation_graph();
+            let mut restored_edges = vec![];
+            let mut input_edges = vec![];
+            for node in (0..fast_graph.get_num_nodes()).map(NodeId) {
+                for arc in dijkstra_graph.get_out_edges(node) {
+                    restored_edges.push((node, arc.adj_node, arc.weight));
+                }
+            }
+            for e in input_graph.get_edges() {
+                input_edges.push((e.from, e.to, e.weight));
+            }
+            restored_edges.sort();
+            input_edges.sort();
+            assert_eq!(input_edges, restored_edges);
+            let mut dijkstra = Dijkstra::new(fast_graph.get_num_nodes());
+            let mut path_calculator = create_calculator(&fast_graph);
+            for _j in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, fast_graph.get_num_nodes());
+                let target = rng.gen_range(0, fast_graph.get_num_nodes());
+                assert_eq!(
+                    path_calculator
+                        .calc_path(&fast_graph, NodeId(source), NodeId(target))
+                        .map(|p| p.get_weight()),
+                    dijkstra
+                        .calc_path(&dijkstra_graph, NodeId(source), NodeId(target))
+                        .map(|p| p.get_weight())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn add_node_and_edges_on_random_graph() {
+        const REPEATS: usize = 50;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 100;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let mut fast_graph = prepare(&input_graph);
+            let mut adj = vec![vec![]; input_graph.get_num_nodes()];
+            for edge in input_graph.get_edges() {
+                adj[edge.from.0].push((edge.to, edge.weight));
+            }
+            for _j in 0..rng.gen_range(1, 4) {
+                let new_node = adj.len();
+                adj.push(vec![]);
+                let mut new_edges = vec![];
+                for _k in 0..rng.gen_range(0, 6) {
+                    let node = NodeId(rng.gen_range(0, new_node));
+                    let weight = Weight(rng.gen_range(1, 100));
+                    if rng.gen_bool(0.5) {
+                        new_edges.push((node, NodeId(new_node), weight));
+                    } else {
+                        new_edges.push((NodeId(new_node), node, weight));
+                    }
+                }
+                // a detour via an existing edge that is lighter than the direct edge, so the
+                // direct edge is replaced by a shortcut if the detour is via a lower ranked node
+                let edges = input_graph.get_edges();
+                if !edges.is_empty() && rng.gen_bool(0.5) {
+                    let e = &edges[rng.gen_range(0, edges.len())];
+                    let weight = Weight(rng.gen_range(1, 100));
+                    let direct_weight = e.weight + weight + Weight(rng.gen_range(1, 10));
+                    if rng.gen_bool(0.5) {
+                        new_edges.push((e.to, NodeId(new_node), weight));
+                        new_edges.push((e.from, NodeId(new_node), direct_weight));
+                    } else {
+                        new_edges.push((NodeId(new_node), e.from, weight));
+                        new_edges.push((NodeId(new_node), e.to, direct_weight));
+                    }
+                }
+                for &(from, to, weight) in &new_edges {
+                    adj[from.0].push((to, weight));
+                }
+                assert_eq!(
+                    NodeId(new_node),
+                    fast_graph.add_node_and_edges(&new_edges).unwrap()
+                );
+            }
+            // the new nodes are part of the extended graph even if they have no edges
+            let extended_graph = InputGraph::from_adjacency_list(&adj);
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &extended_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+            // the original edges that were replaced by shortcuts to and from the new nodes are kept
+            assert_eq!(
+                extended_graph.unit_test_output_string(),
+                fast_graph.to_input_graph().unit_test_output_string()
+            );
+            let original_graph = PreparationGraph::from_input_graph(&extended_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            for edge in fast_graph.get_replaced_edges() {
+                let expected =
+                    calc_weight_max_hops(&original_graph, edge.base_node, edge.adj_node, 1);
+                let path = path_calculator.calc_path_max_hops(
+                    &fast_graph,
+                    edge.base_node,
+                    edge.adj_node,
+                    1,
+                );
+                assert_eq!(expected, path.map(|p| p.get_weight()));
+            }
+            // the shortcuts to and from the new nodes are unpacked correctly
+            for _j in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, adj.len());
+                let target = rng.gen_range(0, adj.len());
+                if let Some(path) =
+                    path_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target))
+                {
+                    let weight: Weight = path
+                        .get_nodes()
+                        .windows(2)
+                        .map(|w| {
+                            adj[w[0].0]
+                                .iter()
+                                .filter(|(to, _)| *to == w[1])
+                                .map(|(_, weight)| *weight)
+                                .min()
+                                .unwrap()
+                        })
+                        .sum();
+                    assert_eq!(path.get_weight(), weight);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn routing_with_shortcut_pruning_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            // a small settled nodes limit makes the witness searches miss some witnesses
+            let params = Params {
+                shortcut_pruning: true,
+                ..Params::new(0.1, 500, 2, rng.gen_range(0, 3))
+            };
+            let fast_graph = prepare_with_params(&input_graph, &params);
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn order_roundtrip_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            assert_order_roundtrip(&input_graph, 1_000, &mut |n| rng.gen_range(0, n));
+        }
+    }
+
+    #[test]
+    fn order_roundtrip_on_empty_graph() {
+        let mut input_graph = InputGraph::new();
+        input_graph.freeze();
+        assert_order_roundtrip(&input_graph, 10, &mut |_| unreachable!());
+    }
+
+    #[test]
+    fn prepare_profiles_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_EDGES: usize = 100;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let mut dist_graph = InputGraph::new();
+            let mut time_graph = InputGraph::new();
+            for _ in 0..NUM_EDGES {
+                let from = rng.gen_range(0, NUM_NODES);
+                let to = rng.gen_range(0, NUM_NODES);
+                let dist = rng.gen_range(1, 100);
+                dist_graph.add_edge(NodeId(from), NodeId(to), Weight(dist), dist as f64);
+                time_graph.add_edge(
+                    NodeId(from),
+                    NodeId(to),
+                    Weight(rng.gen_range(1, 100)),
+                    dist as f64,
+                );
+            }
+            dist_graph.freeze();
+            time_graph.freeze();
+            let fast_graphs = prepare_profiles(&[&dist_graph, &time_graph]).unwrap();
+            assert_eq!(2, fast_graphs.len());
+            assert_eq!(
+                get_node_ordering(&fast_graphs[0]),
+                get_node_ordering(&fast_graphs[1])
+            );
+            fast_graphs[0].check_invariants().unwrap();
+            verify(&fast_graphs[0], &dist_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+            fast_graphs[1].check_invariants().unwrap();
+            verify(&fast_graphs[1], &time_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn prepare_profiles_with_different_number_of_nodes() {
+        let mut g1 = InputGraph::new();
+        g1.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g1.freeze();
+        let mut g2 = InputGraph::new();
+        g2.add_edge(NodeId(0), NodeId(2), Weight(1), 1.0);
+        g2.freeze();
+        assert!(prepare_profiles(&[&g1, &g2]).is_err());
+        assert_eq!(1, prepare_profiles(&[&g1]).unwrap().len());
+        assert!(prepare_profiles(&[]).unwrap().is_empty());
+    }
+
+    #[test]
+    fn routing_with_max_hierarchy_levels_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let params = Params::with_max_hierarchy_levels(rng.gen_range(0, 10));
+            let fast_graph = prepare_with_params(&input_graph, &params);
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+            let full_graph = prepare(&input_graph);
+            let mut calc = create_calculator(&fast_graph);
+            let mut full_calc = create_calculator(&full_graph);
+            let num_nodes = input_graph.get_num_nodes();
+            for _ in 0..100 {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                assert_eq!(
+                    full_calc
+                        .calc_path(&full_graph, NodeId(source), NodeId(target))
+                        .map(|p| p.get_weight()),
+                    calc.calc_path(&fast_graph, NodeId(source), NodeId(target))
+                        .map(|p| p.get_weight())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn prepare_partitioned_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_cells = rng.gen_range(1, 6);
+            let partition: Vec<CellId> = (0..input_graph.get_num_nodes())
+                .map(|_| rng.gen_range(0, num_cells))
+                .collect();
+            let fast_graph = prepare_partitioned(&input_graph, &partition).unwrap();
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn prepare_from_fast_graph_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let fast_graph = prepare(&input_graph);
+            let restored_graph = fast_graph.to_input_graph();
+            assert_eq!(input_graph.get_num_nodes(), restored_graph.get_num_nodes());
+            assert_eq!(
+                input_graph.unit_test_output_string(),
+                restored_graph.unit_test_output_string()
+            );
+            let fast_graph = prepare_with_params(&restored_graph, &Params::new(0.5, 100, 5, 50));
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn contraction_budget_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let budget = rng.gen_range(0.0, 1.0);
+            let (fast_graph, report) = prepare_with_params_reported(
+                &input_graph,
+                &Params {
+                    contraction_budget: budget,
+                    ..Params::new(0.1, 100, 5, 50)
+                },
+            );
+            assert!(report.rounds <= (budget * input_graph.get_num_nodes() as f64) as usize);
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn shortcut_accounting_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let (fast_graph, report) =
+                prepare_with_params_reported(&input_graph, &Params::with_shortcut_accounting(true));
+            // the accounting does not change the preparation
+            assert_eq!(
+                get_node_ordering(&prepare(&input_graph)),
+                get_node_ordering(&fast_graph)
+            );
+            assert_eq!(input_graph.get_num_nodes(), report.shortcuts_per_node.len());
+            let top = report.top_shortcut_nodes(5);
+            assert!(top.len() <= 5);
+            assert!(top.windows(2).all(|w| w[0].1 >= w[1].1));
+            let max_shortcuts = report.shortcuts_per_node.iter().max().copied();
+            assert_eq!(max_shortcuts.filter(|&n| n > 0), top.first().map(|t| t.1));
+        }
+    }
+
+    #[test]
+    fn spatial_priority_on_random_graph() {
+        const REPEATS: usize = 20;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let (input_graph, coordinates) = create_geo_random_graph(&mut rng, 8);
+            let grid_size = rng.gen_range(0.001, 0.1);
+            let fast_graph = prepare_with_params(
+                &input_graph,
+                &Params {
+                    spatial_grid_size: Some(grid_size),
+                    node_coordinates: coordinates,
+                    ..Params::new(0.1, 100, 5, 50)
+                },
+            );
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn prepare_with_initial_priorities_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            // use the ranks of a previous preparation as hints, plus some noise
+            let ranks = prepare(&input_graph).ranks;
+            let priorities: Vec<i64> = ranks
+                .iter()
+                .map(|&rank| 100 * rank as i64 + rng.gen_range(-200, 200))
+                .collect();
+            let fast_graph =
+                prepare_with_initial_priorities(&input_graph, &priorities, &Params::default())
+                    .unwrap();
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn routing_with_sampled_query_cost_order_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let params = Params::with_order_strategy(OrderStrategy::SampledQueryCost {
+                samples: rng.gen_range(0, 20),
+            });
+            let fast_graph = prepare_with_params(&input_graph, &params);
+            fast_graph.check_invariants().unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
+    }
+
+    #[test]
+    fn time_dependent_routing_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 30;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let mut input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            input_graph.thaw();
+            for _ in 0..NUM_NODES {
+                let from = rng.gen_range(0, num_nodes);
+                let to = rng.gen_range(0, num_nodes);
+                let mut profile = vec![];
+                let mut time = 0;
+                let mut weight: usize = rng.gen_range(1, 20);
+                for _ in 0..rng.gen_range(1, 5) {
+                    profile.push((Weight(time), Weight(weight)));
+                    let dt = rng.gen_range(1, 10);
+                    time += dt;
+                    // respect the FIFO property
+                    weight = (weight + rng.gen_range(0, 20)).saturating_sub(dt).max(1);
+                }
+                input_graph.add_edge_profile(NodeId(from), NodeId(to), profile);
+            }
+            input_graph.freeze();
+            let td_graph = TimeDependentGraph::new(&input_graph);
+            let mut path_calculator = PathCalculator::new(num_nodes);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                let departure = rng.gen_range(0, 40);
+                let expected =
+                    calc_earliest_arrivals(&input_graph, NodeId(source), Weight(departure))[target];
+                let path = path_calculator.calc_path_at(
+                    &td_graph,
+                    NodeId(source),
+                    NodeId(target),
+                    Weight(departure),
+                );
+                match path {
+                    Some(path) => {
+                        assert_eq!(expected, Weight(departure) + path.get_weight());
+                        // the weight of the path must match the weights of its edges
+                        let mut time = Weight(departure);
+                        for w in path.get_nodes().windows(2) {
+                            time += get_weight_at(&input_graph, w[0], w[1], time).unwrap();
+                        }
+                        assert_eq!(expected, time);
+                    }
+                    None => assert_eq!(WEIGHT_MAX, expected),
+                }
+            }
+        }
+    }
+
+    /// Calculates the earliest arrival times for all nodes by relaxing all edges until no arrival
+    /// time changes anymore (label correcting)
+    fn calc_earliest_arrivals(
+        input_graph: &InputGraph,
+        source: NodeId,
+        departure: Weight,
+    ) -> Vec<Weight> {
+        let mut arrivals = vec![WEIGHT_MAX; input_graph.get_num_nodes()];
+        arrivals[source.0] = departure;
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for e in input_graph.get_edges() {
+                if arrivals[e.from.0] == WEIGHT_MAX {
+                    continue;
+                }
+                let weight = get_weight_at(input_graph, e.from, e.to, arrivals[e.from.0]).unwrap();
+                if arrivals[e.from.0] + weight < arrivals[e.to.0] {
+                    arrivals[e.to.0] = arrivals[e.from.0] + weight;
+                    changed = true;
+                }
+            }
+        }
+        arrivals
+    }
+
+    fn get_weight_at(
+        input_graph: &InputGraph,
+        from: NodeId,
+        to: NodeId,
+        time: Weight,
+    ) -> Option<Weight> {
+        if let Some(profile) = input_graph.get_edge_profile(from, to) {
+            return Some(profile.weight_at(time));
+        }
+        input_graph
+            .get_edges()
+            .iter()
+            .find(|e| e.from == from && e.to == to)
+            .map(|e| e.weight)
+    }
+
+    #[test]
+    fn all_shortest_paths_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 30;
+        const NUM_EDGES: usize = 90;
+        const NUM_QUERIES: usize = 100;
+        const LIMIT: usize = 20;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            // small weights, so there are many shortest paths with equal weight
+            let mut input_graph = InputGraph::new();
+            for _ in 0..NUM_EDGES {
+                let weight = rng.gen_range(1, 4);
+                input_graph.add_edge(
+                    NodeId(rng.gen_range(0, NUM_NODES)),
+                    NodeId(rng.gen_range(0, NUM_NODES)),
+                    Weight(weight),
+                    weight as f64,
+                );
+            }
+            input_graph.freeze();
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut fw = FloydWarshall::new(num_nodes);
+            fw.prepare(&input_graph);
+            let mut edge_weights = HashMap::new();
+            for e in input_graph.get_edges() {
+                edge_weights.insert((e.from, e.to), e.weight);
+            }
+            let mut path_calculator = create_calculator(&fast_graph);
+            for _ in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                let paths = path_calculator.calc_all_shortest_paths(
+                    &fast_graph,
+                    NodeId(source),
+                    NodeId(target),
+                    LIMIT,
+                );
+                let weight = fw.calc_weight(NodeId(source), NodeId(target));
+                let num_paths =
+                    count_shortest_paths(&input_graph, &fw, NodeId(source), NodeId(target));
+                assert_eq!(num_paths.min(LIMIT), paths.len());
+                let mut distinct_nodes = HashSet::new();
+                for path in paths {
+                    assert_eq!(weight, path.get_weight());
+                    let nodes = path.get_nodes();
+                    assert_eq!(NodeId(source), nodes[0]);
+                    assert_eq!(NodeId(target), nodes[nodes.len() - 1]);
+                    let path_weight: Weight =
+                        nodes.windows(2).map(|w| edge_weights[&(w[0], w[1])]).sum();
+                    assert_eq!(weight, path_weight);
+                    assert!(distinct_nodes.insert(nodes.clone()));
+                }
+            }
+        }
+    }
+
+    /// Counts the shortest paths from source to target, ignoring the edges the shortest paths
+    /// cannot use
+    fn count_shortest_paths(
+        input_graph: &InputGraph,
+        fw: &FloydWarshall,
+        source: NodeId,
+        target: NodeId,
+    ) -> usize {
+        if fw.calc_weight(source, target) == WEIGHT_MAX {
+            return 0;
+        }
+        if source == target {
+            return 1;
+        }
+        input_graph
+            .get_edges()
+            .iter()
+            .filter(|e| {
+                e.from == source
+                    && fw.calc_weight(e.to, target) != WEIGHT_MAX
+                    && e.weight + fw.calc_weight(e.to, target) == fw.calc_weight(source, target)
+            })
+            .map(|e| count_shortest_paths(input_graph, fw, e.to, target))
+            .sum()
+    }
+
+    #[test]
+    fn equal_weight_paths_are_reproducible() {
+        // a grid with unit weights has lots of shortest paths with equal weight
+        const SIZE: usize = 12;
+        const NUM_QUERIES: usize = 200;
+        let mut input_graph = InputGraph::new();
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let node = row * SIZE + col;
+                if col + 1 < SIZE {
+                    input_graph.add_edge_bidir(NodeId(node), NodeId(node + 1), Weight(1), 1.0);
+                }
+                if row + 1 < SIZE {
+                    input_graph.add_edge_bidir(NodeId(node), NodeId(node + SIZE), Weight(1), 1.0);
+                }
+            }
+        }
+        input_graph.freeze();
+        let fast_graph = prepare(&input_graph);
+        let mut rng = create_rng();
+        let mut reused_calculator = create_calculator(&fast_graph);
+        for _i in 0..NUM_QUERIES {
+            let source = rng.gen_range(0, SIZE * SIZE);
+            let target = rng.gen_range(0, SIZE * SIZE);
+            // other queries with the same calculator must not change the result
+            let other_source = rng.gen_range(0, SIZE * SIZE);
+            let other_target = rng.gen_range(0, SIZE * SIZE);
+            reused_calculator.calc_path(&fast_graph, NodeId(other_source), NodeId(other_target));
+            let expected = calc_path(&fast_graph, NodeId(source), NodeId(target)).unwrap();
+            for _j in 0..3 {
+                let path = reused_calculator
+                    .calc_path(&fast_graph, NodeId(source), NodeId(target))
+                    .unwrap();
+                assert_eq!(expected.get_nodes(), path.get_nodes());
+                assert_eq!(expected.get_weight(), path.get_weight());
+            }
+        }
+    }
+
+    #[test]
+    fn set_source_and_calc_to_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let mut reference_calculator = create_calculator(&fast_graph);
+            let mut source = rng.gen_range(0, num_nodes);
+            path_calculator.set_source(&fast_graph, NodeId(source));
+            for _i in 0..NUM_QUERIES {
+                // change the source or run other queries with the same calculator from time to
+                // time, which must not affect the results of calc_to
+                if rng.gen_bool(0.1) {
+                    source = rng.gen_range(0, num_nodes);
+                    path_calculator.set_source(&fast_graph, NodeId(source));
+                } else if rng.gen_bool(0.1) {
+                    let s = rng.gen_range(0, num_nodes);
+                    let t = rng.gen_range(0, num_nodes);
+                    path_calculator.calc_path(&fast_graph, NodeId(s), NodeId(t));
+                }
+                let target = rng.gen_range(0, num_nodes);
+                let expected =
+                    reference_calculator.calc_path(&fast_graph, NodeId(source), NodeId(target));
+                let path = path_calculator.calc_to(&fast_graph, NodeId(target));
+                assert_eq!(
+                    expected.as_ref().map(|p| p.get_weight()),
+                    path.as_ref().map(|p| p.get_weight())
+                );
+                if let Some(path) = path {
+                    assert_eq!(NodeId(source), path.get_source());
+                    assert_eq!(NodeId(target), path.get_target());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn one_to_many_routing_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 100;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let mut reference_calculator = create_calculator(&fast_graph);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let targets: Vec<NodeId> = (0..rng.gen_range(0, 10))
+                    .map(|_| NodeId(rng.gen_range(0, num_nodes)))
+                    .collect();
+                let paths =
+                    path_calculator.calc_paths_one_to_many(&fast_graph, NodeId(source), &targets);
+                assert_eq!(targets.len(), paths.len());
+                for (target, path) in targets.iter().zip(paths) {
+                    let expected =
+                        reference_calculator.calc_path(&fast_graph, NodeId(source), *target);
+                    assert_eq!(
+                        expected.as_ref().map(|p| p.get_weight()),
+                        path.as_ref().map(|p| p.get_weight())
+                    );
+                    if let Some(path) = path {
+                        let nodes = path.get_nodes();
+                        assert_eq!(NodeId(source), nodes[0]);
+                        assert_eq!(*target, nodes[nodes.len() - 1]);
+                        assert_eq!(NodeId(source), path.get_source());
+                        assert_eq!(*target, path.get_target());
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn weighted_one_to_many_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 100;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let mut reference_calculator = create_calculator(&fast_graph);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let source_weight = rng.gen_range(0, 10);
+                let targets: Vec<(NodeId, Weight)> = (0..rng.gen_range(0, 10))
+                    .map(|_| {
+                        (
+                            NodeId(rng.gen_range(0, num_nodes)),
+                            Weight(rng.gen_range(0, 10)),
+                        )
+                    })
+                    .collect();
+                let weights = path_calculator.calc_weights_one_to_many_weighted(
+                    &fast_graph,
+                    NodeId(source),
+                    Weight(source_weight),
+                    &targets,
+                );
+                assert_eq!(targets.len(), weights.len());
+                for (&target, weight) in targets.iter().zip(weights) {
+                    let expected = reference_calculator.calc_path_multi(
+                        &fast_graph,
+                        &[(NodeId(source), Weight(source_weight))],
+                        &[target],
+                    );
+                    assert_eq!(expected.map(|p| p.get_weight()), weight);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn k_nearest_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 100;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let dijkstra_graph = PreparationGraph::from_input_graph(&input_graph);
+            let mut dijkstra = Dijkstra::new(num_nodes);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let candidates: Vec<NodeId> = (0..rng.gen_range(0, 20))
+                    .map(|_| NodeId(rng.gen_range(0, num_nodes)))
+                    .collect();
+                let k = rng.gen_range(0, 8);
+                let result =
+                    path_calculator.calc_k_nearest(&fast_graph, NodeId(source), &candidates, k);
+                let mut expected: Vec<(NodeId, Weight)> = candidates
+                    .iter()
+                    .cloned()
+                    .collect::<HashSet<NodeId>>()
+                    .into_iter()
+                    .filter_map(|c| {
+                        dijkstra
+                            .calc_path(&dijkstra_graph, NodeId(source), c)
+                            .map(|p| (c, p.get_weight()))
+                    })
+                    .collect();
+                expected.sort_by_key(|&(node, weight)| (weight, node));
+                expected.truncate(k);
+                assert_eq!(expected, result);
+            }
+        }
+    }
+
+    #[test]
+    fn routing_with_core_nodes_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 1_000;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let num_core_nodes = rng.gen_range(0, num_nodes + 1);
+            let core_nodes: Vec<NodeId> = (0..num_core_nodes)
+                .map(|_| NodeId(rng.gen_range(0, num_nodes)))
+                .collect();
+            let fast_graph =
+                prepare_with_params(&input_graph, &Params::with_core_nodes(core_nodes.clone()));
+            let num_core = core_nodes.iter().collect::<HashSet<_>>().len();
+            for node in core_nodes {
+                assert!(fast_graph.ranks[node.0] >= num_nodes - num_core);
+            }
+            let mut path_calculator = create_calculator(&fast_graph);
+            let mut fw = FloydWarshall::new(num_nodes);
+            fw.prepare(&input_graph);
+            for _i in 0..NUM_QUERIES {
+                let source = rng.gen_range(0, num_nodes);
+                let target = rng.gen_range(0, num_nodes);
+                let weight_fast = path_calculator
+                    .calc_path(&fast_graph, NodeId(source), NodeId(target))
+                    .map_or(WEIGHT_MAX, |p| p.get_weight());
+                assert_eq!(
+                    fw.calc_weight(NodeId(source), NodeId(target)),
+                    weight_fast,
+                    "\nNo agreement for routing query from: {} to: {}\n Failing graph:\n{:?}",
+                    source,
+                    target,
+                    input_graph
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn routing_with_max_hops_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+            let original_graph = PreparationGraph::from_input_graph(&input_graph);
+            let mut queries: Vec<(usize, usize, usize)> = (0..NUM_QUERIES)
+                .map(|_| {
+                    let source = rng.gen_range(0, num_nodes);
+                    let target = rng.gen_range(0, num_nodes);
+                    (source, target, rng.gen_range(0, 10))
+                })
+                .collect();
+            // the edges replaced by shortcuts are the only single hop paths between their nodes
+            for edge in fast_graph.get_replaced_edges() {
+                queries.push((edge.base_node.0, edge.adj_node.0, 1));
+            }
+            for (source, target, max_hops) in queries {
+                let expected =
+                    calc_weight_max_hops(&original_graph, NodeId(source), NodeId(target), max_hops);
+                let path = path_calculator.calc_path_max_hops(
+                    &fast_graph,
+                    NodeId(source),
+                    NodeId(target),
+                    max_hops,
+                );
+                assert_eq!(
+                    expected,
+                    path.as_ref().map(|p| p.get_weight()),
+                    "\nNo agreement for max hops query from: {} to: {} max_hops: {}\
+                     \n Failing graph:\n{:?}",
+                    source,
+                    target,
+                    max_hops,
+                    input_graph
+                );
+                if let Some(p) = path {
+                    assert!(p.get_nodes().len() - 1 <= max_hops);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn routing_avoiding_nodes_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const NUM_BLOCKED: usize = 5;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _ in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
+
+            let mut blocked: HashSet<NodeId> = (0..NUM_BLOCKED)
+                .map(|_| NodeId(rng.gen_range(0, num_nodes)))
+                .collect();
+            let mut queries: Vec<(usize, usize)> = (0..NUM_QUERIES)
+                .map(|_| (rng.gen_range(0, num_nodes), rng.gen_range(0, num_nodes)))
+                .collect();
+            // blocking the shortest path between the nodes of an edge that was replaced by a
+            // shortcut might make this edge the best remaining option
+            if let Some(e) = fast_graph.get_replaced_edges().first() {
+                let path = calc_path(&fast_graph, e.base_node, e.adj_node).unwrap();
+                blocked.insert(path.get_nodes()[1]);
+                queries.push((e.base_node.0, e.adj_node.0));
+            }
+            let mut dijkstra_graph = PreparationGraph::new(num_nodes);
+            for e in input_graph.get_edges() {
+                if !blocked.contains(&e.from) && !blocked.contains(&e.to) {
+                    dijkstra_graph.add_edge(e.from, e.to, e.weight, e.length);
+                }
+            }
+            let mut dijkstra = Dijkstra::new(num_nodes);
+            for (source, target) in queries {
+                let path_fast = path_calculator.calc_path_avoiding(
+                    &fast_graph,
+                    NodeId(source),
+                    NodeId(target),
+                    &blocked,
+                );
+                let path_dijkstra =
+                    if blocked.contains(&NodeId(source)) || blocked.contains(&NodeId(target)) {
+                        None
+                    } else {
+                        dijkstra.calc_path(&dijkstra_graph, NodeId(source), NodeId(target))
+                    };
+                assert_eq!(
+                    path_dijkstra.map(|p| p.get_weight()),
+                    path_fast.as_ref().map(|p| p.get_weight()),
+                    "\nNo agreement for routing query from: {} to: {} avoiding: {:?}\
+                     \n Failing graph:\n{:?}",
+                    source,
+                    target,
+                    blocked,
+                    input_graph
+                );
+                if let Some(p) = path_fast {
+                    assert!(p.get_nodes().iter().all(|n| !blocked.contains(n)));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn routing_avoiding_edges_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const NUM_QUERIES: usize = 200;
+        const NUM_BLOCKED: usize = 10;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _ in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let num_nodes = input_graph.get_num_nodes();
+            let fast_graph = prepare(&input_graph);
+            let mut path_calculator = create_calculator(&fast_graph);
 
-                // one of the matching Dijkstra's should have the same nodes as fast_path, but in
-                // some rare cases this might not be true, because there are multiple shortest paths.
-                if !matching_dijkstras
-                    .into_iter()
-                    .any(|(p, _, _)| p.get_nodes() == f.get_nodes())
-                {
-                    num_different_paths += 1;
-                }
+            let edges = input_graph.get_edges();
+            let mut blocked: HashSet<(NodeId, NodeId)> = (0..NUM_BLOCKED)
+                .map(|_| edges[rng.gen_range(0, edges.len())])
+                .map(|e| (e.from, e.to))
+                .collect();
+            let mut queries: Vec<(usize, usize)> = (0..NUM_QUERIES)
+                .map(|_| (rng.gen_range(0, num_nodes), rng.gen_range(0, num_nodes)))
+                .collect();
+            // blocking the shortest path between the nodes of an edge that was replaced by a
+            // shortcut might make this edge the best remaining option
+            if let Some(e) = fast_graph.get_replaced_edges().first() {
+                let path = calc_path(&fast_graph, e.base_node, e.adj_node).unwrap();
+                blocked.insert((path.get_nodes()[0], path.get_nodes()[1]));
+                queries.push((e.base_node.0, e.adj_node.0));
             }
-            if num_paths_not_found as f32 > 0.5 * NUM_QUERIES as f32 {
-                panic!(
-                    "too many paths not found: {}, out of {}",
-                    num_paths_not_found, NUM_QUERIES
-                )
+            let mut dijkstra_graph = PreparationGraph::new(num_nodes);
+            for e in edges {
+                if !blocked.contains(&(e.from, e.to)) {
+                    dijkstra_graph.add_edge(e.from, e.to, e.weight, e.length);
+                }
             }
-            if num_different_paths as f32 > 0.1 * NUM_QUERIES as f32 {
-                panic!(
-                    "too many different paths: {}, out of {}, a few different paths can be expected \
-                        because of unambiguous shortest paths, but if there are too many something is \
-                        wrong",
-                    num_different_paths, NUM_QUERIES
+            let mut dijkstra = Dijkstra::new(num_nodes);
+            for (source, target) in queries {
+                let path_fast = path_calculator.calc_path_avoiding_edges(
+                    &fast_graph,
+                    NodeId(source),
+                    NodeId(target),
+                    &blocked,
                 );
+                let path_dijkstra =
+                    dijkstra.calc_path(&dijkstra_graph, NodeId(source), NodeId(target));
+                assert_eq!(
+                    path_dijkstra.map(|p| p.get_weight()),
+                    path_fast.as_ref().map(|p| p.get_weight()),
+                    "\nNo agreement for routing query from: {} to: {} avoiding: {:?}\
+                     \n Failing graph:\n{:?}",
+                    source,
+                    target,
+                    blocked,
+                    input_graph
+                );
+                if let Some(p) = path_fast {
+                    assert!(p
+                        .get_nodes()
+                        .windows(2)
+                        .all(|w| !blocked.contains(&(w[0], w[1]))));
+                }
+            }
+        }
+    }
+
+    /// Bellman-Ford with a limited number of rounds, each round adds one more hop
+    fn calc_weight_max_hops(
+        graph: &PreparationGraph,
+        source: NodeId,
+        target: NodeId,
+        max_hops: usize,
+    ) -> Option<Weight> {
+        let mut weights = vec![WEIGHT_MAX; graph.get_num_nodes()];
+        weights[source.0] = Weight(0);
+        for _ in 0..max_hops {
+            let mut next_weights = weights.clone();
+            for (node, node_weight) in weights.iter().enumerate() {
+                if *node_weight == WEIGHT_MAX {
+                    continue;
+                }
+                for arc in graph.get_out_edges(NodeId(node)) {
+                    let weight = *node_weight + arc.weight;
+                    if weight < next_weights[arc.adj_node.0] {
+                        next_weights[arc.adj_node.0] = weight;
+                    }
+                }
             }
+            weights = next_weights;
         }
+        if weights[target.0] == WEIGHT_MAX {
+            None
+        } else {
+            Some(weights[target.0])
+        }
+    }
+
+    #[test]
+    fn verify_on_random_graph() {
+        let mut rng = create_rng();
+        let input_graph = InputGraph::random(&mut rng, 50, 2.0);
+        let fast_graph = prepare(&input_graph);
+        fast_graph.check_invariants().unwrap();
+        verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+            rng.gen_range(0, n)
+        })
+        .unwrap();
+    }
+
+    #[test]
+    fn verify_detects_mismatch() {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        g.thaw();
+        g.add_edge(NodeId(0), NodeId(2), Weight(5), 5.0);
+        g.freeze();
+        // we query 0->2 and the fast graph does not know the new edge
+        let mut nodes = vec![0, 2].into_iter().cycle();
+        let result = verify(&fast_graph, &g, 10, &mut |_| nodes.next().unwrap());
+        assert_eq!(
+            Err(VerificationError::WeightMismatch {
+                source: NodeId(0),
+                target: NodeId(2),
+                weight_fast: Some(Weight(7)),
+                weight_dijkstra: Some(Weight(5)),
+            }),
+            result
+        );
+
+        g.thaw();
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.freeze();
+        assert_eq!(
+            Err(VerificationError::InvalidNodeCount {
+                fast_graph: 3,
+                input_graph: 4,
+            }),
+            verify(&fast_graph, &g, 10, &mut |_| 0)
+        );
     }
 
     fn gen_weighted_nodes(rng: &mut StdRng, max_node: usize, num: usize) -> Vec<(NodeId, Weight)> {
         (0..num)
             .map(|_| {
                 (
-                    rng.gen_range(0, max_node),
+                    NodeId(rng.gen_range(0, max_node)),
                     // sometimes use max weight
                     if rng.gen_range(0, 100) < 3 {
                         WEIGHT_MAX
                     } else {
-                        rng.gen_range(0, 100)
+                        Weight(rng.gen_range(0, 100))
                     },
                 )
             })
@@ -352,9 +2766,9 @@ mod tests {
     #[test]
     fn save_to_and_load_from_disk() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 5, 6, 6.0);
-        g.add_edge(5, 2, 1, 1.0);
-        g.add_edge(2, 3, 4, 4.0);
+        g.add_edge(NodeId(0), NodeId(5), Weight(6), 6.0);
+        g.add_edge(NodeId(5), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(4), 4.0);
         g.freeze();
         let fast_graph = prepare(&g);
         save_to_disk(&fast_graph, "example.fp").expect("writing to disk failed");
@@ -368,9 +2782,9 @@ mod tests {
     #[test]
     fn save_to_and_load_from_disk_32() {
         let mut g = InputGraph::new();
-        g.add_edge(0, 5, 6, 6.0);
-        g.add_edge(5, 2, 1, 1.0);
-        g.add_edge(2, 3, 4, 4.0);
+        g.add_edge(NodeId(0), NodeId(5), Weight(6), 6.0);
+        g.add_edge(NodeId(5), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(4), 4.0);
         g.freeze();
         let fast_graph = prepare(&g);
         save_to_disk32(&fast_graph, "example32.fp").expect("writing to disk failed");
@@ -379,6 +2793,47 @@ mod tests {
         assert_eq!(fast_graph.get_num_nodes(), loaded.get_num_nodes());
         assert_eq!(fast_graph.get_num_in_edges(), loaded.get_num_in_edges());
         assert_eq!(fast_graph.get_num_out_edges(), loaded.get_num_out_edges());
+        let path = calc_path(&loaded, NodeId(0), NodeId(3)).unwrap();
+        assert_eq!(Weight(11), path.get_weight());
+        assert_eq!(
+            &vec![NodeId(0), NodeId(5), NodeId(2), NodeId(3)],
+            path.get_nodes()
+        );
+    }
+
+    #[test]
+    fn serialize_32_fails_with_too_large_numbers() {
+        #[derive(Serialize)]
+        struct Wrapper {
+            #[serde(serialize_with = "serialize_32")]
+            fast_graph: FastGraph,
+        }
+        let mut fast_graph = FastGraph::new(1);
+        fast_graph.ranks = vec![5_000_000_000];
+        let result = bincode::serialize(&Wrapper { fast_graph });
+        assert!(result
+            .unwrap_err()
+            .to_string()
+            .contains("Could not convert 5000000000 to a 32-bit integer, field: ranks[0]"));
+    }
+
+    #[test]
+    fn serialize_into_buf_on_random_graph() {
+        const REPEATS: usize = 20;
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+        for _i in 0..REPEATS {
+            let mut rng = create_rng();
+            let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+            let fast_graph = prepare(&input_graph);
+            let mut buf = vec![];
+            fast_graph.serialize_into_buf(&mut buf);
+            let fast_graph = FastGraph::from_bytes(&buf).unwrap();
+            verify(&fast_graph, &input_graph, 1_000, &mut |n| {
+                rng.gen_range(0, n)
+            })
+            .unwrap();
+        }
     }
 
     #[test]
@@ -398,6 +2853,87 @@ mod tests {
         }
     }
 
+    #[test]
+    fn resume_preparation() {
+        const NUM_NODES: usize = 50;
+        const MEAN_DEGREE: f32 = 2.0;
+
+        let mut rng = create_rng();
+        let input_graph = InputGraph::random(&mut rng, NUM_NODES, MEAN_DEGREE);
+        let params = Params::default();
+        let mut checkpoints = vec![];
+        let fast_graph = FastGraphBuilder::build_resumable(&input_graph, &params, None, 10, |s| {
+            checkpoints.push((s.get_num_contracted_nodes(), bincode::serialize(s).unwrap()))
+        })
+        .unwrap();
+        // without interruption we get the same result as usual
+        assert_eq!(
+            bincode::serialize(&prepare_with_params(&input_graph, &params)).unwrap(),
+            bincode::serialize(&fast_graph).unwrap()
+        );
+        assert_eq!(
+            (10..input_graph.get_num_nodes())
+                .step_by(10)
+                .collect::<Vec<_>>(),
+            checkpoints.iter().map(|(n, _)| *n).collect::<Vec<_>>()
+        );
+        for (_, checkpoint) in checkpoints {
+            let state: ContractionState = bincode::deserialize(&checkpoint).unwrap();
+            let resumed =
+                FastGraphBuilder::build_resumable(&input_graph, &params, Some(state), 10, |_| {})
+                    .unwrap();
+            resumed.check_invariants().unwrap();
+            verify(&resumed, &input_graph, 1_000, &mut |n| rng.gen_range(0, n)).unwrap();
+        }
+    }
+
+    #[test]
+    fn shrink_to_fit_bremen() {
+        let input_graph = InputGraph::from_file("meta/test_maps/bremen_dist.gr").unwrap();
+        let mut fast_graph = prepare(&input_graph);
+        let weight = calc_path(&fast_graph, NodeId(0), NodeId(1000)).map(|p| p.get_weight());
+        fast_graph.shrink_to_fit();
+        assert_eq!(fast_graph.ranks.capacity(), fast_graph.ranks.len());
+        assert_eq!(fast_graph.edges_fwd.capacity(), fast_graph.edges_fwd.len());
+        assert_eq!(
+            fast_graph.first_edge_ids_fwd.capacity(),
+            fast_graph.first_edge_ids_fwd.len()
+        );
+        assert_eq!(fast_graph.edges_bwd.capacity(), fast_graph.edges_bwd.len());
+        assert_eq!(
+            fast_graph.first_edge_ids_bwd.capacity(),
+            fast_graph.first_edge_ids_bwd.len()
+        );
+        assert_eq!(
+            weight,
+            calc_path(&fast_graph, NodeId(0), NodeId(1000)).map(|p| p.get_weight())
+        );
+    }
+
+    #[cfg(feature = "compression")]
+    #[test]
+    fn save_compressed_bremen() {
+        let input_graph = InputGraph::from_file("meta/test_maps/bremen_dist.gr").unwrap();
+        let fast_graph = prepare(&input_graph);
+        let uncompressed = bincode::serialize(&fast_graph).unwrap().len();
+        let file_name = "save_compressed_bremen.fp.zst";
+        for &level in &[3, 19] {
+            fast_graph.save_compressed(file_name, level).unwrap();
+            let compressed = std::fs::metadata(file_name).unwrap().len() as usize;
+            let loaded = FastGraph::load_compressed(file_name).unwrap();
+            println!(
+                "zstd level {}: {} bytes -> {} bytes (ratio {:.1})",
+                level,
+                uncompressed,
+                compressed,
+                uncompressed as f64 / compressed as f64
+            );
+            assert!(compressed * 4 < uncompressed);
+            assert!(fast_graph.diff(&loaded).is_empty());
+        }
+        remove_file(file_name).unwrap();
+    }
+
     #[ignore]
     #[test]
     fn run_performance_test_dist() {
@@ -406,13 +2942,89 @@ mod tests {
         // prep: 190ms, query: 16μs, out: 68494, in: 68426
         // todo: try to tune parameters
         run_performance_test(
-            &InputGraph::from_file("meta/test_maps/bremen_dist.gr"),
+            &InputGraph::from_file("meta/test_maps/bremen_dist.gr").unwrap(),
             &Params::new(0.1, 500, 2, 50),
             845493338,
             30265,
         )
     }
 
+    #[ignore]
+    #[test]
+    fn run_performance_test_dist_sampled_query_cost() {
+        println!("Running performance test for Bremen dist (sampled query cost order)");
+        // prep: 481ms, query: 16μs, out: 68476, in: 68480 (with 1000 samples: 3047ms, 15μs)
+        // so far this does not improve the query time noticeably compared to the default order
+        run_performance_test(
+            &InputGraph::from_file("meta/test_maps/bremen_dist.gr").unwrap(),
+            &Params {
+                order_strategy: OrderStrategy::SampledQueryCost { samples: 100 },
+                ..Params::new(0.1, 500, 2, 50)
+            },
+            845493338,
+            30265,
+        )
+    }
+
+    #[ignore]
+    #[test]
+    fn run_performance_test_geo_random_spatial_priority() {
+        println!("Running performance test for a geo-random graph (spatial priority)");
+        // default order -> prep: 3604ms, query: 149μs, out: 80319
+        // spatial order -> prep: 4577ms, query: 140μs, out: 81337 (grid size 0.01: 3156ms, 133μs)
+        // the query times vary by about 10% between runs, so so far this does not improve the
+        // query time noticeably compared to the default order
+        let mut rng = create_rng_with_seed(42);
+        let (input_graph, coordinates) = create_geo_random_graph(&mut rng, 100);
+        run_performance_test(&input_graph, &Params::new(0.1, 500, 2, 50), 7311644063, 0);
+        run_performance_test(
+            &input_graph,
+            &Params {
+                spatial_grid_size: Some(0.1),
+                node_coordinates: coordinates,
+                ..Params::new(0.1, 500, 2, 50)
+            },
+            7311644063,
+            0,
+        );
+    }
+
+    #[ignore]
+    #[test]
+    fn run_performance_test_dist_contraction_budget() {
+        println!("Running performance test for Bremen dist (contraction budget)");
+        // prep: 181ms, query: 66μs, out: 68538 (with a budget of 0.999: 194ms, 20μs, 68598)
+        // Bremen is too small for the budget to save preparation time, but the last ~400 nodes
+        // that are left in the core already make the queries about four times slower
+        run_performance_test(
+            &InputGraph::from_file("meta/test_maps/bremen_dist.gr").unwrap(),
+            &Params {
+                contraction_budget: 0.99,
+                ..Params::new(0.1, 500, 2, 50)
+            },
+            845493338,
+            30265,
+        )
+    }
+
+    #[ignore]
+    #[test]
+    fn run_performance_test_dist_adaptive_witness_hops() {
+        println!("Running performance test for Bremen dist (adaptive witness hops)");
+        // prep: 150ms, query: 14μs, out: 68818, in: 68733
+        // compared to run_performance_test_dist the preparation is ~20% faster and there are ~0.5%
+        // more shortcuts
+        run_performance_test(
+            &InputGraph::from_file("meta/test_maps/bremen_dist.gr").unwrap(),
+            &Params {
+                witness_hop_schedule: vec![(0.0, 2), (0.5, 4), (0.9, usize::MAX)],
+                ..Params::new(0.1, 500, 2, 50)
+            },
+            845493338,
+            30265,
+        )
+    }
+
     #[ignore]
     #[test]
     fn run_performance_test_time() {
@@ -421,7 +3033,37 @@ mod tests {
         // prep: 256ms, query: 11μs, out: 64825, in: 65027
         // todo: try to tune parameters
         run_performance_test(
-            &InputGraph::from_file("meta/test_maps/bremen_time.gr"),
+            &InputGraph::from_file("meta/test_maps/bremen_time.gr").unwrap(),
+            &Params::new(0.1, 100, 2, 100),
+            88104267255,
+            30265,
+        );
+    }
+
+    #[ignore]
+    #[test]
+    fn run_performance_test_dist_reordered_by_rank() {
+        println!("Running performance test for Bremen dist (nodes reordered by rank)");
+        // prep: 190ms, query: 15μs, out: 68494, in: 68426
+        // compared to run_performance_test_dist (16μs on the same machine) the queries are ~5%
+        // faster
+        run_performance_test_reordered_by_rank(
+            &InputGraph::from_file("meta/test_maps/bremen_dist.gr").unwrap(),
+            &Params::new(0.1, 500, 2, 50),
+            845493338,
+            30265,
+        )
+    }
+
+    #[ignore]
+    #[test]
+    fn run_performance_test_time_reordered_by_rank() {
+        println!("Running performance test for Bremen time (nodes reordered by rank)");
+        // prep: 256ms, query: 12μs, out: 64825, in: 65027
+        // compared to run_performance_test_time (12-13μs on the same machine) the difference is
+        // within the noise
+        run_performance_test_reordered_by_rank(
+            &InputGraph::from_file("meta/test_maps/bremen_time.gr").unwrap(),
             &Params::new(0.1, 100, 2, 100),
             88104267255,
             30265,
@@ -435,7 +3077,7 @@ mod tests {
         // prep: 1150ms, query: 53μs, out: 43849, in: 43700
         // todo: try to tune parameters
         run_performance_test(
-            &InputGraph::from_file("meta/test_maps/graph_ballard.gr"),
+            &InputGraph::from_file("meta/test_maps/graph_ballard.gr").unwrap(),
             &Params::new(0.1, 100, 3, 100),
             28409159409,
             14992,
@@ -449,7 +3091,7 @@ mod tests {
         // prep: 170ms, query: 23μs, out: 11478, in: 11236
         // todo: try to tune parameters
         run_performance_test(
-            &InputGraph::from_file("meta/test_maps/graph_23rd.gr"),
+            &InputGraph::from_file("meta/test_maps/graph_23rd.gr").unwrap(),
             &Params::new(0.1, 100, 3, 100),
             19438403873,
             20421,
@@ -463,13 +3105,155 @@ mod tests {
         // prep: 877ms, query: 26μs, out: 68777, in: 68161
         // todo: try to tune parameters
         run_performance_test(
-            &InputGraph::from_file("meta/test_maps/south_seattle_car.gr"),
+            &InputGraph::from_file("meta/test_maps/south_seattle_car.gr").unwrap(),
             &Params::new(0.1, 100, 10, 100),
             77479396,
             30805,
         );
     }
 
+    #[ignore]
+    #[test]
+    fn run_performance_test_south_seattle_car_unpack_cache() {
+        println!("Running performance test for South Seattle car (unpack cache)");
+        // query: 36μs without the cache, 39μs with a capacity of 10_000 (hits / misses: 369859 /
+        // 129676) and 39μs with a capacity of 100_000 (462165 / 37370). unpacking the shortcuts
+        // is cheap compared to the search, so the cache lookups cost more than they save here
+        let input_graph = InputGraph::from_file("meta/test_maps/south_seattle_car.gr").unwrap();
+        let mut fast_graph = FastGraph::new(1);
+        prepare_algo(
+            &mut |input_graph| {
+                fast_graph = prepare_with_params(input_graph, &Params::new(0.1, 100, 10, 100))
+            },
+            &input_graph,
+        );
+        print_fast_graph_stats(&fast_graph);
+        for &capacity in &[0, 10_000, 100_000] {
+            println!("unpack cache capacity ............. {}", capacity);
+            let mut path_calculator =
+                PathCalculator::new(fast_graph.get_num_nodes()).with_unpack_cache(capacity);
+            do_run_performance_test(
+                &mut |s, t| path_calculator.calc_path(&fast_graph, s, t),
+                input_graph.get_num_nodes(),
+                77479396,
+                30805,
+            );
+            if let Some((hits, misses)) = path_calculator.get_unpack_cache_stats() {
+                println!("unpack cache hits / misses ........ {} / {}", hits, misses);
+            }
+        }
+    }
+
+    #[ignore]
+    #[test]
+    fn run_performance_test_south_seattle_car_heap_capacity() {
+        println!("Running performance test for South Seattle car (heap capacity)");
+        // query: 39μs with a heap capacity of 0 and 297 (1% of the nodes), 37μs with 2976 (10%).
+        // the heaps keep their memory between queries, so the difference is within the noise
+        // for a calculator that is used for many queries
+        let input_graph = InputGraph::from_file("meta/test_maps/south_seattle_car.gr").unwrap();
+        let mut fast_graph = FastGraph::new(1);
+        prepare_algo(
+            &mut |input_graph| {
+                fast_graph = prepare_with_params(input_graph, &Params::new(0.1, 100, 10, 100))
+            },
+            &input_graph,
+        );
+        print_fast_graph_stats(&fast_graph);
+        let num_nodes = fast_graph.get_num_nodes();
+        for &capacity in &[0, num_nodes / 100, num_nodes / 10] {
+            println!("heap capacity ..................... {}", capacity);
+            let mut path_calculator = PathCalculator::with_capacity(num_nodes, capacity);
+            do_run_performance_test(
+                &mut |s, t| path_calculator.calc_path(&fast_graph, s, t),
+                input_graph.get_num_nodes(),
+                77479396,
+                30805,
+            );
+        }
+    }
+
+    #[ignore]
+    #[test]
+    fn run_performance_test_south_seattle_car_adaptive_witness_hops() {
+        println!("Running performance test for South Seattle car (adaptive witness hops)");
+        // prep: 815ms, query: 30μs, out: 72442, in: 71422
+        // compared to run_performance_test_south_seattle_car the preparation is ~10% faster and
+        // there are ~5% more shortcuts
+        run_performance_test(
+            &InputGraph::from_file("meta/test_maps/south_seattle_car.gr").unwrap(),
+            &Params {
+                witness_hop_schedule: vec![(0.0, 2), (0.5, 4), (0.9, usize::MAX)],
+                ..Params::new(0.1, 100, 10, 100)
+            },
+            77479396,
+            30805,
+        );
+    }
+
+    #[ignore]
+    #[test]
+    fn run_performance_test_south_seattle_car_shortcut_pruning() {
+        println!("Running performance test for South Seattle car (shortcut pruning)");
+        // prep: 3163ms, query: 38μs, out: 67989, in: 67663
+        // compared to run_performance_test_south_seattle_car (prep: 1042ms, query: 41μs, out:
+        // 68802, in: 68829 on the same machine) there are ~2% fewer shortcuts, but the
+        // preparation takes about three times as long
+        run_performance_test(
+            &InputGraph::from_file("meta/test_maps/south_seattle_car.gr").unwrap(),
+            &Params {
+                shortcut_pruning: true,
+                ..Params::new(0.1, 100, 10, 100)
+            },
+            77479396,
+            30805,
+        );
+    }
+
+    #[ignore]
+    #[test]
+    fn run_performance_test_input_graph_capacity() {
+        println!("Running performance test for building an input graph with 1M edges");
+        // new: 21ms, with_capacity: 21ms
+        // growing the edge vector is cheap compared to adding the edges, so presizing it mostly
+        // avoids the temporary memory overhead of the reallocations rather than saving time
+        const NUM_NODES: usize = 100_000;
+        const NUM_EDGES: usize = 1_000_000;
+        let mut rng = create_rng();
+        let edges: Vec<(NodeId, NodeId, Weight)> = (0..NUM_EDGES)
+            .map(|_| {
+                (
+                    NodeId(rng.gen_range(0, NUM_NODES)),
+                    NodeId(rng.gen_range(0, NUM_NODES)),
+                    Weight(rng.gen_range(1, 100)),
+                )
+            })
+            .collect();
+        let build = |mut g: InputGraph| {
+            for &(from, to, weight) in &edges {
+                g.add_edge(from, to, weight, weight.0 as f64);
+            }
+            g
+        };
+        let mut time_new = Stopwatch::new();
+        let mut time_with_capacity = Stopwatch::new();
+        for _ in 0..10 {
+            time_new.start();
+            let g = build(InputGraph::new());
+            time_new.stop();
+            drop(g);
+            time_with_capacity.start();
+            let g = build(InputGraph::with_capacity(NUM_NODES, NUM_EDGES));
+            time_with_capacity.stop();
+            drop(g);
+        }
+        println!(
+            "new: {}ms, with_capacity: {}ms",
+            time_new.elapsed_ms() / 10,
+            time_with_capacity.elapsed_ms() / 10
+        );
+    }
+
     #[ignore]
     #[test]
     fn run_performance_test_bremen_dist_fixed_ordering() {
@@ -477,7 +3261,7 @@ mod tests {
         // prep: 340ms, prep_order: 64ms, query: 16μs, out: 66646, in: 66725
         // todo: try to tune parameters
         run_performance_test_fixed_ordering(
-            &InputGraph::from_file("meta/test_maps/bremen_dist.gr"),
+            &InputGraph::from_file("meta/test_maps/bremen_dist.gr").unwrap(),
             &Params::default(),
             &ParamsWithOrder::default(),
             845493338,
@@ -492,7 +3276,7 @@ mod tests {
         // prep: 811ms, prep order: 138ms, query: 27μs, out: 68777, in: 68161
         // todo: try to tune parameters
         run_performance_test_fixed_ordering(
-            &InputGraph::from_file("meta/test_maps/south_seattle_car.gr"),
+            &InputGraph::from_file("meta/test_maps/south_seattle_car.gr").unwrap(),
             &Params::new(0.1, 100, 10, 100),
             &ParamsWithOrder::new(100),
             77479396,
@@ -507,11 +3291,21 @@ mod tests {
         expected_num_not_found: usize,
     ) {
         let mut fast_graph = FastGraph::new(1);
+        let mut report = None;
         prepare_algo(
-            &mut |input_graph| fast_graph = prepare_with_params(input_graph, params),
+            &mut |input_graph| {
+                let (graph, r) = prepare_with_params_reported(input_graph, params);
+                fast_graph = graph;
+                report = Some(r);
+            },
             &input_graph,
         );
         print_fast_graph_stats(&fast_graph);
+        let report = report.unwrap();
+        println!(
+            "contracted nodes / peak priority .. {} / {}",
+            report.rounds, report.peak_priority
+        );
         let mut path_calculator = PathCalculator::new(fast_graph.get_num_nodes());
         do_run_performance_test(
             &mut |s, t| path_calculator.calc_path(&fast_graph, s, t),
@@ -521,6 +3315,34 @@ mod tests {
         );
     }
 
+    fn run_performance_test_reordered_by_rank(
+        input_graph: &InputGraph,
+        params: &Params,
+        expected_checksum: usize,
+        expected_num_not_found: usize,
+    ) {
+        let mut fast_graph = FastGraph::new(1);
+        prepare_algo(
+            &mut |input_graph| fast_graph = prepare_with_params(input_graph, params),
+            input_graph,
+        );
+        print_fast_graph_stats(&fast_graph);
+        let (reordered, _mapping) = fast_graph.reorder_by_rank();
+        let mut path_calculator = PathCalculator::new(reordered.get_num_nodes());
+        do_run_performance_test(
+            &mut |s, t| {
+                path_calculator.calc_path(
+                    &reordered,
+                    NodeId(fast_graph.ranks[s.0]),
+                    NodeId(fast_graph.ranks[t.0]),
+                )
+            },
+            input_graph.get_num_nodes(),
+            expected_checksum,
+            expected_num_not_found,
+        );
+    }
+
     fn run_performance_test_fixed_ordering(
         input_graph: &InputGraph,
         params: &Params,
@@ -566,6 +3388,16 @@ mod tests {
             "number of in-edges  (fast graph) .. {}",
             fast_graph.get_num_in_edges()
         );
+        println!(
+            "  original out/in-edges ........... {} / {}",
+            fast_graph.get_num_original_out_edges(),
+            fast_graph.get_num_original_in_edges()
+        );
+        println!(
+            "  shortcut out/in-edges ........... {} / {}",
+            fast_graph.get_num_shortcut_out_edges(),
+            fast_graph.get_num_shortcut_in_edges()
+        );
     }
 
     pub fn prepare_algo<F>(preparation: &mut F, input_graph: &InputGraph)
@@ -608,10 +3440,10 @@ mod tests {
             let source = rng.gen_range(0, num_nodes);
             let target = rng.gen_range(0, num_nodes);
             time.start();
-            let path = calc_path(source, target);
+            let path = calc_path(NodeId(source), NodeId(target));
             time.stop();
             match path {
-                Some(path) => checksum += path.get_weight(),
+                Some(path) => checksum += path.get_weight().0,
                 None => num_not_found += 1,
             }
         }
@@ -630,6 +3462,41 @@ mod tests {
         );
     }
 
+    /// Creates a grid graph with the given number of rows and columns, where the nodes are placed
+    /// near the points of a grid with a spacing of 0.01 degrees and the weights are derived from
+    /// the distances between the nodes. Returns the graph and the (lat, lon) coordinates of the
+    /// nodes.
+    fn create_geo_random_graph(rng: &mut StdRng, size: usize) -> (InputGraph, Vec<(f64, f64)>) {
+        let coordinates: Vec<(f64, f64)> = (0..size * size)
+            .map(|node| {
+                let lat = 52.0 + 0.01 * (node / size) as f64 + rng.gen_range(-0.004, 0.004);
+                let lon = 8.0 + 0.01 * (node % size) as f64 + rng.gen_range(-0.004, 0.004);
+                (lat, lon)
+            })
+            .collect();
+        let mut input_graph = InputGraph::new();
+        let mut add_edge = |from: NodeId, to: NodeId, rng: &mut StdRng| {
+            let (lat_from, lon_from) = coordinates[from.0];
+            let (lat_to, lon_to) = coordinates[to.0];
+            let dist = ((lat_to - lat_from).powi(2) + (lon_to - lon_from).powi(2)).sqrt();
+            let weight = Weight((dist * 100_000.0 * rng.gen_range(1.0, 1.5)) as usize + 1);
+            input_graph.add_edge_bidir(from, to, weight, 1.0);
+        };
+        for row in 0..size {
+            for col in 0..size {
+                let node = row * size + col;
+                if col + 1 < size {
+                    add_edge(NodeId(node), NodeId(node + 1), rng);
+                }
+                if row + 1 < size {
+                    add_edge(NodeId(node), NodeId(node + size), rng);
+                }
+            }
+        }
+        input_graph.freeze();
+        (input_graph, coordinates)
+    }
+
     fn create_rng() -> StdRng {
         let seed = create_seed();
         create_rng_with_seed(seed)
@@ -673,10 +3540,10 @@ mod tests {
     /// Loads a graph from disk that was saved in 32bit representation, i.e. using save_to_disk32. The
     /// graph will use usize to store integers, so most commonly either 32 or 64bits per integer
     /// depending on the system in use.
-    /// Note: Using this method requires an extra +50% RAM while loading the graph.
+    /// The 32bit values are converted one by one while reading the file, so this method does not
+    /// need extra RAM.
     fn load_from_disk32(file_name: &str) -> Result<FastGraph, Box<dyn Error>> {
-        let file = File::open(file_name)?;
-        let r: Result<FastGraph32, Box<dyn Error>> = Ok(bincode::deserialize_from(file)?);
-        r.map(|g| g.convert_to_usize())
+        let file = BufReader::new(File::open(file_name)?);
+        Ok(FastGraph::load_streaming(file)?)
     }
 }
@@ -17,31 +17,73 @@
  * under the License.
  */
 
-use crate::constants::{Length, NodeId, Weight};
+use crate::constants::{add_weights, Length, NodeId, Weight};
+use crate::dijkstra::Dijkstra;
+use crate::fast_graph::RankExplanation;
 use crate::fast_graph_builder::Params;
 use crate::preparation_graph::PreparationGraph;
 use crate::witness_search::WitnessSearch;
 
 /// removes all edges incident to `node` from the graph and adds shortcuts between all neighbors
-/// of `node` such that all shortest paths are preserved
+/// of `node` such that all shortest paths are preserved. returns the number of shortcuts, including
+/// the ones that only reduced the weight of an existing edge.
 pub fn contract_node(
     graph: &mut PreparationGraph,
     witness_search: &mut WitnessSearch,
     node: NodeId,
     max_settled_nodes: usize,
-) {
-    handle_shortcuts(graph, witness_search, node, add_shortcut, max_settled_nodes);
+) -> usize {
+    let mut num_shortcuts = 0;
+    handle_shortcuts(
+        graph,
+        witness_search,
+        node,
+        |graph, shortcut| {
+            add_shortcut(graph, shortcut);
+            num_shortcuts += 1;
+        },
+        max_settled_nodes,
+    );
     graph.disconnect(node);
+    num_shortcuts
 }
 
+/// Like `contract_node`, but shortcuts for which the (limited) witness search did not find a
+/// witness are only added if `pruning_search` does not find a witness without any limits either,
+/// see `Params::shortcut_pruning`
+pub fn contract_node_with_pruning(
+    graph: &mut PreparationGraph,
+    witness_search: &mut WitnessSearch,
+    pruning_search: &mut WitnessSearch,
+    node: NodeId,
+    max_settled_nodes: usize,
+) -> usize {
+    let mut num_shortcuts = 0;
+    handle_shortcuts_with_pruning(
+        graph,
+        witness_search,
+        Some(pruning_search),
+        node,
+        |graph, shortcut| {
+            add_shortcut(graph, shortcut);
+            num_shortcuts += 1;
+        },
+        max_settled_nodes,
+    );
+    graph.disconnect(node);
+    num_shortcuts
+}
+
+/// Calculates the relevance of the given node and returns it along with the values it is
+/// calculated from. The bias and priority of the result are left at zero.
 pub fn calc_relevance(
     graph: &mut PreparationGraph,
     params: &Params,
     witness_search: &mut WitnessSearch,
     node: NodeId,
-    level: NodeId,
+    level: usize,
     max_settled_nodes: usize,
-) -> f32 {
+) -> RankExplanation {
     let mut num_shortcuts = 0;
     handle_shortcuts(
         graph,
@@ -55,25 +97,144 @@ pub fn calc_relevance(
     let num_edges = graph.get_out_edges(node).len() + graph.get_in_edges(node).len();
     let relevance = (params.hierarchy_depth_factor * level as f32)
         + (params.edge_quotient_factor * num_shortcuts as f32 + 1.0) / (num_edges as f32 + 1.0);
-    relevance * 1000.0
+    RankExplanation {
+        num_shortcuts,
+        num_edges,
+        level,
+        relevance: relevance * 1000.0,
+        bias: 0,
+        priority: 0,
+    }
+}
+
+/// Runs `samples` shortest path queries between random pairs of the given nodes and counts how
+/// often each node occurs on the shortest paths (excluding their start and end nodes). The
+/// counts are normalized such that a node that is on every path gets a cost of 1000, which is in
+/// the same range as the priorities returned by `calc_relevance`. `random_state` is the state of
+/// the random number generator and is updated.
+pub fn calc_query_costs(
+    graph: &PreparationGraph,
+    nodes: &[NodeId],
+    samples: usize,
+    random_state: &mut u64,
+) -> Vec<usize> {
+    let mut counts = vec![0; graph.get_num_nodes()];
+    if nodes.len() < 2 || samples == 0 {
+        return vec![0; graph.get_num_nodes()];
+    }
+    let mut dijkstra = Dijkstra::new(graph.get_num_nodes());
+    for _ in 0..samples {
+        let source = nodes[next_random(random_state) as usize % nodes.len()];
+        let target = nodes[next_random(random_state) as usize % nodes.len()];
+        if let Some(path) = dijkstra.calc_path(graph, source, target) {
+            let path_nodes = path.get_nodes();
+            for &node in path_nodes
+                .iter()
+                .skip(1)
+                .take(path_nodes.len().saturating_sub(2))
+            {
+                counts[node.0] += 1;
+            }
+        }
+    }
+    counts.iter().map(|count| count * 1000 / samples).collect()
+}
+
+/// Puts the nodes into the cells of a grid with the given cell size and orders the (non-empty)
+/// cells along a Z-order curve. The returned cost of each node is the position of its cell in this
+/// order, normalized such that the costs range from 0 to (less than) 1000, which is in the same
+/// range as the priorities returned by `calc_relevance`. Nodes within the same cell get the same
+/// cost and nearby cells usually get similar costs.
+pub fn calc_spatial_costs(coordinates: &[(f64, f64)], grid_size: f64) -> Vec<usize> {
+    if coordinates.is_empty() {
+        return vec![];
+    }
+    if grid_size.is_nan() || grid_size <= 0.0 {
+        panic!("The grid size must be positive, but was: {}", grid_size);
+    }
+    let min_lat = coordinates
+        .iter()
+        .map(|c| c.0)
+        .fold(f64::INFINITY, f64::min);
+    let min_lon = coordinates
+        .iter()
+        .map(|c| c.1)
+        .fold(f64::INFINITY, f64::min);
+    let cells: Vec<u64> = coordinates
+        .iter()
+        .map(|&(lat, lon)| {
+            let x = ((lat - min_lat) / grid_size) as u32;
+            let y = ((lon - min_lon) / grid_size) as u32;
+            interleave_bits(x) | (interleave_bits(y) << 1)
+        })
+        .collect();
+    let mut sorted_cells = cells.clone();
+    sorted_cells.sort_unstable();
+    sorted_cells.dedup();
+    cells
+        .iter()
+        .map(|cell| sorted_cells.binary_search(cell).unwrap() * 1000 / sorted_cells.len())
+        .collect()
+}
+
+/// Spreads the bits of the given value such that there is a zero bit between each of them
+fn interleave_bits(value: u32) -> u64 {
+    let mut result = 0;
+    for i in 0..32 {
+        result |= ((value as u64 >> i) & 1) << (2 * i);
+    }
+    result
+}
+
+/// A simple xorshift random number generator, so the preparation stays deterministic
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
 }
 
 pub fn handle_shortcuts<F>(
     graph: &mut PreparationGraph,
     witness_search: &mut WitnessSearch,
     node: NodeId,
+    handle_shortcut: F,
+    max_settled_nodes: usize,
+) where
+    F: FnMut(&mut PreparationGraph, Shortcut),
+{
+    handle_shortcuts_with_pruning(
+        graph,
+        witness_search,
+        None,
+        node,
+        handle_shortcut,
+        max_settled_nodes,
+    );
+}
+
+fn handle_shortcuts_with_pruning<F>(
+    graph: &mut PreparationGraph,
+    witness_search: &mut WitnessSearch,
+    mut pruning_search: Option<&mut WitnessSearch>,
+    node: NodeId,
     mut handle_shortcut: F,
     max_settled_nodes: usize,
 ) where
     F: FnMut(&mut PreparationGraph, Shortcut),
 {
-    for i in 0..graph.in_edges[node].len() {
-        let in_node = graph.in_edges[node][i].adj_node;
+    for i in 0..graph.in_edges[node.0].len() {
+        let in_node = graph.in_edges[node.0][i].adj_node;
         witness_search.init(in_node, node);
-        for j in 0..graph.out_edges[node].len() {
-            let weight = graph.in_edges[node][i].weight + graph.out_edges[node][j].weight;
-            let length = graph.in_edges[node][i].length + graph.out_edges[node][j].length;
-            let out_node = graph.out_edges[node][j].adj_node;
+        // the pruning search is only initialized once it is needed
+        let mut pruning_search_initialized = false;
+        for j in 0..graph.out_edges[node.0].len() {
+            let weight = add_weights(
+                graph.in_edges[node.0][i].weight,
+                graph.out_edges[node.0][j].weight,
+            );
+            let length = graph.in_edges[node.0][i].length + graph.out_edges[node.0][j].length;
+            let out_node = graph.out_edges[node.0][j].adj_node;
             // no need to find the actual weight of a witness path as long as we can be sure
             // that there is some witness with weight smaller or equal to the removed direct
             // path
@@ -82,6 +243,16 @@ pub fn handle_shortcuts<F>(
             if max_witness_weight <= weight {
                 continue;
             }
+            if let Some(pruning_search) = pruning_search.as_deref_mut() {
+                if !pruning_search_initialized {
+                    pruning_search.init(in_node, node);
+                    pruning_search_initialized = true;
+                }
+                // this search has no settled nodes limit, so it finds a witness if there is one
+                if pruning_search.find_max_weight(graph, out_node, weight, usize::MAX) <= weight {
+                    continue;
+                }
+            }
             handle_shortcut(
                 graph,
                 Shortcut::new(in_node, out_node, node, weight, length),
@@ -138,16 +309,16 @@ mod tests {
         // 0 -> 2 -> 3
         // 1 ->/ \-> 4
         let mut g = PreparationGraph::new(5);
-        g.add_edge(0, 2, 1, 1.0);
-        g.add_edge(1, 2, 2, 2.0);
-        g.add_edge(2, 3, 3, 3.0);
-        g.add_edge(2, 4, 1, 1.0);
-        let shortcuts = calc_shortcuts(&mut g, 2);
+        g.add_edge(NodeId(0), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 2.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 3.0);
+        g.add_edge(NodeId(2), NodeId(4), Weight(1), 1.0);
+        let shortcuts = calc_shortcuts(&mut g, NodeId(2));
         let expected_shortcuts = vec![
-            Shortcut::new(0, 3, 2, 4, 4.0),
-            Shortcut::new(0, 4, 2, 2, 2.0),
-            Shortcut::new(1, 3, 2, 5, 5.0),
-            Shortcut::new(1, 4, 2, 3, 3.0),
+            Shortcut::new(NodeId(0), NodeId(3), NodeId(2), Weight(4), 4.0),
+            Shortcut::new(NodeId(0), NodeId(4), NodeId(2), Weight(2), 2.0),
+            Shortcut::new(NodeId(1), NodeId(3), NodeId(2), Weight(5), 5.0),
+            Shortcut::new(NodeId(1), NodeId(4), NodeId(2), Weight(3), 3.0),
         ];
         assert_eq!(expected_shortcuts, shortcuts);
     }
@@ -157,11 +328,11 @@ mod tests {
         // 0 -> 1 -> 2
         //  \-> 3 ->/
         let mut g = PreparationGraph::new(4);
-        g.add_edge(0, 1, 1, 1.0);
-        g.add_edge(1, 2, 1, 1.0);
-        g.add_edge(0, 3, 1, 1.0);
-        g.add_edge(3, 2, 1, 1.0);
-        let shortcuts = calc_shortcuts(&mut g, 1);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(2), Weight(1), 1.0);
+        let shortcuts = calc_shortcuts(&mut g, NodeId(1));
         assert_eq!(0, shortcuts.len());
     }
 
@@ -171,39 +342,123 @@ mod tests {
         // |  /
         // 3 -
         let mut g = PreparationGraph::new(4);
-        g.add_edge(0, 1, 10, 10.0);
-        g.add_edge(1, 2, 1, 1.0);
-        g.add_edge(0, 3, 1, 1.0);
-        g.add_edge(3, 1, 1, 1.0);
-        let _shortcuts = calc_shortcuts(&mut g, 1);
+        g.add_edge(NodeId(0), NodeId(1), Weight(10), 10.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(1), Weight(1), 1.0);
+        let _shortcuts = calc_shortcuts(&mut g, NodeId(1));
         // performance: there is no need for a shortcut 0->1->2, because there is already the
         // (required) shortcut 3->1->2
-        let _expected_shortcuts = vec![Shortcut::new(3, 2, 1, 2, 2.0)];
+        let _expected_shortcuts = vec![Shortcut::new(
+            NodeId(3),
+            NodeId(2),
+            NodeId(1),
+            Weight(2),
+            2.0,
+        )];
         // todo: handle this case for better performance (less shortcuts)
         //        assert_eq!(expected_shortcuts, handler.shortcuts);
     }
 
+    #[test]
+    fn contract_node_with_pruning() {
+        // 0 -> 1 -> 2
+        //  \-> 3 ->/
+        let create_graph = || {
+            let mut g = PreparationGraph::new(4);
+            g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+            g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+            g.add_edge(NodeId(0), NodeId(3), Weight(1), 1.0);
+            g.add_edge(NodeId(3), NodeId(2), Weight(1), 1.0);
+            g
+        };
+        // the witness search does not settle any nodes, so it does not find the witness 0->3->2
+        let mut g = create_graph();
+        let mut witness_search = WitnessSearch::new(g.get_num_nodes());
+        assert_eq!(
+            1,
+            node_contractor::contract_node(&mut g, &mut witness_search, NodeId(1), 0)
+        );
+        assert_eq!(2, g.get_out_edges(NodeId(0)).len());
+
+        // ... but the pruning search does
+        let mut g = create_graph();
+        let mut pruning_search = WitnessSearch::new(g.get_num_nodes());
+        assert_eq!(
+            0,
+            node_contractor::contract_node_with_pruning(
+                &mut g,
+                &mut witness_search,
+                &mut pruning_search,
+                NodeId(1),
+                0,
+            )
+        );
+        assert_eq!(1, g.get_out_edges(NodeId(0)).len());
+        assert_eq!(NodeId(3), g.get_out_edges(NodeId(0))[0].adj_node);
+    }
+
     #[test]
     fn contract_node() {
         // 0 -> 1 -> 2
         // |  /   \  |
         // 3 --->--- 4
         let mut g = PreparationGraph::new(5);
-        g.add_edge(0, 1, 1, 1.0);
-        g.add_edge(1, 2, 1, 1.0);
-        g.add_edge(0, 3, 1, 1.0);
-        g.add_edge(3, 1, 5, 5.0);
-        g.add_edge(1, 4, 4, 4.0);
-        g.add_edge(3, 4, 3, 3.0);
-        g.add_edge(4, 2, 1, 1.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(1), Weight(5), 5.0);
+        g.add_edge(NodeId(1), NodeId(4), Weight(4), 4.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(3), 3.0);
+        g.add_edge(NodeId(4), NodeId(2), Weight(1), 1.0);
         let mut witness_search = WitnessSearch::new(g.get_num_nodes());
-        node_contractor::contract_node(&mut g, &mut witness_search, 1, usize::MAX);
+        node_contractor::contract_node(&mut g, &mut witness_search, NodeId(1), usize::MAX);
         // there should be a shortcut 0->2, but no shortcuts 0->4, 3->2
         // node 1 should be properly disconnected
-        assert_eq!(0, g.get_out_edges(1).len());
-        assert_eq!(0, g.get_in_edges(1).len());
-        assert_eq!(2, g.get_out_edges(0).len());
-        assert_eq!(2, g.get_in_edges(2).len());
+        assert_eq!(0, g.get_out_edges(NodeId(1)).len());
+        assert_eq!(0, g.get_in_edges(NodeId(1)).len());
+        assert_eq!(2, g.get_out_edges(NodeId(0)).len());
+        assert_eq!(2, g.get_in_edges(NodeId(2)).len());
+    }
+
+    #[test]
+    fn calc_query_costs() {
+        // 0 -> 1 -> 2
+        let mut g = PreparationGraph::new(3);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        let mut random_state = 42;
+        let costs =
+            node_contractor::calc_query_costs(&g, &[NodeId(0), NodeId(2)], 100, &mut random_state);
+        // only the paths from 0 to 2 contain an inner node
+        assert_eq!(0, costs[0]);
+        assert!(costs[1] > 0 && costs[1] < 1000);
+        assert_eq!(0, costs[2]);
+        assert_ne!(42, random_state);
+        assert_eq!(
+            vec![0; 3],
+            node_contractor::calc_query_costs(&g, &[NodeId(1)], 100, &mut random_state)
+        );
+    }
+
+    #[test]
+    fn calc_spatial_costs() {
+        // two nodes in the same cell, one in the next cell and one further away
+        let coordinates = vec![(0.0, 0.0), (0.5, 0.5), (1.5, 0.0), (5.0, 5.0)];
+        let costs = node_contractor::calc_spatial_costs(&coordinates, 1.0);
+        assert_eq!(vec![0, 0, 333, 666], costs);
+        // a single cell
+        assert_eq!(
+            vec![0, 0, 0, 0],
+            node_contractor::calc_spatial_costs(&coordinates, 10.0)
+        );
+        assert!(node_contractor::calc_spatial_costs(&[], 1.0).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "The grid size must be positive")]
+    fn calc_spatial_costs_invalid_grid_size() {
+        node_contractor::calc_spatial_costs(&[(0.0, 0.0)], 0.0);
     }
 
     #[test]
@@ -214,18 +469,18 @@ mod tests {
         //      |
         //      4
         let mut g = PreparationGraph::new(6);
-        g.add_edge(0, 1, 1, 1.0);
-        g.add_edge(1, 2, 1, 1.0);
-        g.add_edge(2, 5, 1, 1.0);
-        g.add_edge(3, 1, 1, 1.0);
-        g.add_edge(1, 4, 1, 1.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(5), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(4), Weight(1), 1.0);
         let mut witness_search = WitnessSearch::new(g.get_num_nodes());
         let priorities = vec![
             calc_relevance(
                 &mut g,
                 &Params::default(),
                 &mut witness_search,
-                0,
+                NodeId(0),
                 0,
                 usize::MAX,
             ),
@@ -233,7 +488,7 @@ mod tests {
                 &mut g,
                 &Params::default(),
                 &mut witness_search,
-                1,
+                NodeId(1),
                 0,
                 usize::MAX,
             ),
@@ -241,7 +496,7 @@ mod tests {
                 &mut g,
                 &Params::default(),
                 &mut witness_search,
-                2,
+                NodeId(2),
                 0,
                 usize::MAX,
             ),
@@ -249,7 +504,7 @@ mod tests {
                 &mut g,
                 &Params::default(),
                 &mut witness_search,
-                3,
+                NodeId(3),
                 0,
                 usize::MAX,
             ),
@@ -257,7 +512,7 @@ mod tests {
                 &mut g,
                 &Params::default(),
                 &mut witness_search,
-                4,
+                NodeId(4),
                 0,
                 usize::MAX,
             ),
@@ -265,12 +520,18 @@ mod tests {
                 &mut g,
                 &Params::default(),
                 &mut witness_search,
-                5,
+                NodeId(5),
                 0,
                 usize::MAX,
             ),
         ];
         println!("{:?}", priorities);
+        // contracting node 1 requires the shortcuts 0->2, 0->4, 3->2 and 3->4
+        assert_eq!(4, priorities[1].num_shortcuts);
+        assert_eq!(4, priorities[1].num_edges);
+        assert_eq!(0, priorities[0].num_shortcuts);
+        assert_eq!(1, priorities[0].num_edges);
+        assert!(priorities[1].relevance > priorities[0].relevance);
     }
 
     fn calc_shortcuts(g: &mut PreparationGraph, node: NodeId) -> Vec<Shortcut> {
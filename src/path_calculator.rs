@@ -17,16 +17,44 @@
  * under the License.
  */
 
-use std::collections::BinaryHeap;
+use alloc::collections::BTreeMap;
+#[cfg(feature = "std")]
+use alloc::collections::BinaryHeap;
+use alloc::vec;
+use alloc::vec::Vec;
+#[cfg(feature = "std")]
+use core::cmp::Reverse;
+use core::error::Error;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::{HashMap, HashSet};
+#[cfg(feature = "std")]
+use std::time::Instant;
 
+use crate::constants::EdgeData;
+#[cfg(feature = "std")]
+use crate::constants::TimeOfDay;
 use crate::constants::Weight;
 use crate::constants::INVALID_EDGE;
+#[cfg(feature = "std")]
+use crate::constants::INVALID_EDGE_DATA;
 use crate::constants::INVALID_NODE;
 use crate::constants::WEIGHT_MAX;
+use crate::constants::WEIGHT_ZERO;
+use crate::constants::{add_weights, is_infinite};
 use crate::constants::{EdgeId, NodeId};
 use crate::fast_graph::FastGraph;
+use crate::fast_graph::FastGraphEdge;
 use crate::heap_item::HeapItem;
+use crate::indexed_heap::IndexedHeap;
+#[cfg(feature = "std")]
+use crate::landmarks::Landmarks;
+#[cfg(feature = "std")]
+use crate::preparation_graph::{Arc, PreparationGraph};
 use crate::shortest_path::ShortestPath;
+#[cfg(feature = "std")]
+use crate::time_dependent::TimeDependentGraph;
+use crate::unpack_cache::{UnpackCache, UnpackedEdge};
 use crate::valid_flags::ValidFlags;
 
 pub struct PathCalculator {
@@ -35,30 +63,361 @@ pub struct PathCalculator {
     data_bwd: Vec<Data>,
     valid_flags_fwd: ValidFlags,
     valid_flags_bwd: ValidFlags,
-    heap_fwd: BinaryHeap<HeapItem>,
-    heap_bwd: BinaryHeap<HeapItem>,
+    heap_fwd: IndexedHeap,
+    heap_bwd: IndexedHeap,
+    stall_on_demand: bool,
+    /// the search is aborted once this point in time is reached, see `calc_path_with_deadline`
+    #[cfg(feature = "std")]
+    deadline: Option<Instant>,
+    #[cfg(feature = "std")]
+    deadline_exceeded: bool,
+    /// the search ignores paths with a larger weight, see `calc_path_bounded`
+    max_weight: Weight,
+    /// `None` unless enabled, see `enable_edge_usage_counting`
+    edge_usage_counts: Option<Vec<u64>>,
+    /// the source set by `set_source`
+    source: Option<NodeId>,
+    /// true if the forward data contains the complete upward search from `source`, i.e. no other
+    /// query has used the forward data since
+    source_search_done: bool,
+    /// the statistics of the last query, see `last_query_stats`
+    stats: QueryStats,
+    /// true if the last search found another meeting node with the same weight as the best one,
+    /// see `calc_path_with_uniqueness`
+    equal_meeting_node_found: bool,
+    /// `None` unless enabled, see `with_unpack_cache`
+    unpack_cache: Option<UnpackCache>,
+}
+
+/// Counters that describe the work done by a single query, see
+/// `PathCalculator::last_query_stats`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    /// the number of nodes taken from the queue of the forward search, including the nodes that
+    /// were pruned by stall on demand
+    pub forward_settled: usize,
+    /// the number of nodes taken from the queue of the backward search, including the nodes that
+    /// were pruned by stall on demand
+    pub backward_settled: usize,
+    /// the number of edges that were looked at when expanding the settled nodes
+    pub edges_relaxed: usize,
 }
 
 impl PathCalculator {
     pub fn new(num_nodes: usize) -> Self {
+        PathCalculator::with_capacity(num_nodes, 0)
+    }
+
+    /// Like `new`, but the forward and backward heaps can hold `heap_capacity` nodes each before
+    /// they need to grow. The heaps keep their memory between queries, so this only avoids the
+    /// reallocations during the first queries, which matters if the latency of these queries is
+    /// important or many short-lived calculators are created. A few percent of the number of
+    /// nodes is usually enough, because the searches only explore a small part of the graph.
+    pub fn with_capacity(num_nodes: usize, heap_capacity: usize) -> Self {
         PathCalculator {
             num_nodes,
             data_fwd: (0..num_nodes).map(|_i| Data::new()).collect(),
             data_bwd: (0..num_nodes).map(|_i| Data::new()).collect(),
             valid_flags_fwd: ValidFlags::new(num_nodes),
             valid_flags_bwd: ValidFlags::new(num_nodes),
-            heap_fwd: BinaryHeap::new(),
-            heap_bwd: BinaryHeap::new(),
+            heap_fwd: IndexedHeap::with_capacity(num_nodes, heap_capacity),
+            heap_bwd: IndexedHeap::with_capacity(num_nodes, heap_capacity),
+            stall_on_demand: true,
+            #[cfg(feature = "std")]
+            deadline: None,
+            #[cfg(feature = "std")]
+            deadline_exceeded: false,
+            max_weight: WEIGHT_MAX,
+            edge_usage_counts: None,
+            source: None,
+            source_search_done: false,
+            stats: QueryStats::default(),
+            equal_meeting_node_found: false,
+            unpack_cache: None,
+        }
+    }
+
+    /// Enables a cache for the original edges of up to `capacity` shortcuts, so shortcuts that
+    /// are part of many shortest paths (like the ones along highways) do not need to be unpacked
+    /// recursively for every path they are part of. This trades memory for speed: every cached
+    /// shortcut stores all of its original edges. Unpacking is usually cheap compared to the
+    /// search, so the cache only pays off if the paths are long and the same shortcuts are used
+    /// over and over again, which should be checked with a benchmark. The cache is
+    /// disabled by default, a capacity of zero disables it as well. The cache refers to the edge
+    /// ids of the graph, so it must be cleared using `clear_unpack_cache` before the calculator
+    /// is used for another graph.
+    pub fn with_unpack_cache(mut self, capacity: usize) -> Self {
+        self.unpack_cache = if capacity > 0 {
+            Some(UnpackCache::new(capacity))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Removes all shortcuts from the unpack cache (see `with_unpack_cache`), if it is enabled
+    pub fn clear_unpack_cache(&mut self) {
+        if let Some(cache) = &mut self.unpack_cache {
+            cache.clear();
+        }
+    }
+
+    /// Returns how often a shortcut was found in the unpack cache (see `with_unpack_cache`) and
+    /// how often it had to be unpacked recursively since the cache was enabled or cleared, or
+    /// `None` if the cache is disabled
+    pub fn get_unpack_cache_stats(&self) -> Option<(usize, usize)> {
+        self.unpack_cache
+            .as_ref()
+            .map(|cache| cache.get_hits_and_misses())
+    }
+
+    /// Enables or disables the stall on demand optimization, which prunes nodes from the search
+    /// that can be reached with a smaller weight via a higher ranked node. It is enabled by
+    /// default and usually reduces the number of settled nodes considerably, so disabling it is
+    /// mostly useful for benchmarking.
+    pub fn set_stall_on_demand(&mut self, stall_on_demand: bool) {
+        self.stall_on_demand = stall_on_demand;
+    }
+
+    /// Starts counting how often each edge of the `FastGraph` (original edges and shortcuts) is
+    /// used by the shortest paths found by this calculator, see `get_edge_usage_counts`. Only the
+    /// edges of the compressed path are counted, i.e. the edges found by the search, not the
+    /// edges a shortcut is unpacked into. Calling this again resets the counts.
+    pub fn enable_edge_usage_counting(&mut self) {
+        self.edge_usage_counts = Some(vec![]);
+    }
+
+    /// Returns how often each edge was used since `enable_edge_usage_counting` was called. The
+    /// count of out-edge `i` (`graph.edges_fwd[i]`) is stored at index `i` and the count of in-edge
+    /// `i` (`graph.edges_bwd[i]`) at index `graph.get_num_out_edges() + i`. Edges that come after
+    /// the last edge that was used might be missing, and the result is empty if counting is not
+    /// enabled.
+    pub fn get_edge_usage_counts(&self) -> Vec<u64> {
+        self.edge_usage_counts.clone().unwrap_or_default()
+    }
+
+    /// Returns the statistics of the last query, which is useful to compare search strategies or
+    /// to tune the `Params` used for the preparation. The counters are reset at the beginning of
+    /// every query that runs the bidirectional search (`calc_path` and its variants) and by
+    /// `calc_paths_one_to_many`, `set_source` and `calc_to`. For `calc_to` the forward search is
+    /// only counted if it had to be repeated, see `set_source`.
+    pub fn last_query_stats(&self) -> QueryStats {
+        self.stats
+    }
+
+    /// Writes to all the memory used by this calculator, so the first queries do not have to pay
+    /// for page faults. Since the memory is allocated up front (but only mapped into memory once
+    /// it is used) calling this once after creating the calculator keeps the latency of the first
+    /// queries close to the one of later queries. To also warm up the CPU caches and the memory of
+    /// the `FastGraph` it is recommended to run a few (dummy) queries between random nodes as
+    /// well. This does not change the results of any following queries.
+    pub fn warmup(&mut self) {
+        for data in self.data_fwd.iter_mut().chain(self.data_bwd.iter_mut()) {
+            *data = Data::new();
         }
+        self.valid_flags_fwd.reset();
+        self.source_search_done = false;
+        self.valid_flags_bwd.reset();
+        self.heap_fwd.reset();
+        self.heap_bwd.reset();
     }
 
+    /// Calculates the shortest path from `start` to `end`. If there are several shortest paths the
+    /// same one is returned for every run of the same query on the same graph: nodes with equal
+    /// weight are settled in order of their id, a node keeps the lowest-id predecessor among all
+    /// predecessors that reach it with minimal weight and among all meeting nodes with minimal
+    /// weight the one with the lowest id is used. Unpacking the shortcuts of the resulting path is
+    /// deterministic as well, because every shortcut replaces exactly two fixed edges.
     pub fn calc_path(
         &mut self,
         graph: &FastGraph,
         start: NodeId,
         end: NodeId,
     ) -> Option<ShortestPath> {
-        self.calc_path_multiple_sources_and_targets(graph, vec![(start, 0)], vec![(end, 0)])
+        self.calc_path_multi(graph, &[(start, WEIGHT_ZERO)], &[(end, WEIGHT_ZERO)])
+    }
+
+    /// Like `calc_path`, but the path starts with the weight `source_weight` at `source` and
+    /// `target_weight` is added when it arrives at `target`, e.g. to account for the cost of
+    /// getting to the source and from the target. The weight of the returned path includes both
+    /// offsets, just like for `calc_path_multiple_sources_and_targets`.
+    pub fn calc_path_with_offsets(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        source_weight: Weight,
+        target: NodeId,
+        target_weight: Weight,
+    ) -> Option<ShortestPath> {
+        self.calc_path_multi(
+            graph,
+            &[(source, source_weight)],
+            &[(target, target_weight)],
+        )
+    }
+
+    /// Calculates the shortest path from `source` to `target` and the one from `target` back to
+    /// `source`, e.g. for round trips. On directed graphs the two paths can differ or one of
+    /// them might not exist. Both searches run on the buffers of this calculator one after the
+    /// other, so nothing is allocated besides the returned paths.
+    pub fn calc_path_both_directions(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        target: NodeId,
+    ) -> (Option<ShortestPath>, Option<ShortestPath>) {
+        let forward = self.calc_path(graph, source, target);
+        let backward = self.calc_path(graph, target, source);
+        (forward, backward)
+    }
+
+    /// Calculates the shortest path from `source` to `target` in the transposed graph, i.e. the
+    /// graph with all edges reversed, without building a separate `FastGraph` for it. Since the
+    /// `FastGraph` stores the in-edges of every node as well, this is the same as the path from
+    /// `target` to `source` that `calc_path` returns, just in the opposite order, and the search
+    /// runs on the same arrays. The edge data (see `ShortestPath::get_edge_data`) is reversed
+    /// along with the nodes, so it still belongs to the edges between consecutive nodes.
+    pub fn calc_path_reverse(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        target: NodeId,
+    ) -> Option<ShortestPath> {
+        self.calc_path(graph, target, source)
+            .map(|path| path.reverse())
+    }
+
+    /// Like `calc_path`, but returns `None` if the weight of the shortest path exceeds
+    /// `max_weight`. Both search directions stop once they reach nodes with a larger weight than
+    /// `max_weight`, so this is much faster than `calc_path` for targets that are far away, e.g.
+    /// to check whether a target is within a budget. If the shortest path weight is at most
+    /// `max_weight` the same path as for `calc_path` is returned.
+    pub fn calc_path_bounded(
+        &mut self,
+        graph: &FastGraph,
+        start: NodeId,
+        end: NodeId,
+        max_weight: Weight,
+    ) -> Option<ShortestPath> {
+        self.max_weight = max_weight;
+        let path = self.calc_path(graph, start, end);
+        self.max_weight = WEIGHT_MAX;
+        path
+    }
+
+    /// Like `calc_path`, but the search uses the weights returned by `adjust` instead of the
+    /// stored edge weights, e.g. to penalize certain edge categories in experiments. `adjust` is
+    /// called with the edge and its stored weight whenever the edge is relaxed and the returned
+    /// weight is used for this edge in the search. The weight of the returned path is the sum of
+    /// these adjusted weights.
+    ///
+    /// Note that `adjust` is called for the edges of the prepared graph, which include shortcuts,
+    /// and the hierarchy was built for the stored weights. The result is only guaranteed to be
+    /// the shortest path if the adjustment never decreases a weight and it is additive, i.e. the
+    /// adjusted weight of each shortcut equals the sum of the adjusted weights of the edges it
+    /// replaces. Otherwise the search can miss shorter paths, or even return no path at all if
+    /// an adjusted weight is infinite.
+    pub fn calc_path_with_cost_adjust<F>(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        target: NodeId,
+        adjust: F,
+    ) -> Option<ShortestPath>
+    where
+        F: Fn(&FastGraphEdge, Weight) -> Weight,
+    {
+        let (meeting_node, weight) = self.search(
+            graph,
+            &[(source, WEIGHT_ZERO)],
+            &[(target, WEIGHT_ZERO)],
+            &mut |_graph, _edge_id, _fwd| true,
+            &adjust,
+            0.0,
+        )?;
+        Some(self.create_path(graph, meeting_node, weight))
+    }
+
+    /// Like `calc_path`, but also returns whether the shortest path is unique. The path is
+    /// considered unique unless the search finds another meeting node (see
+    /// `ShortestPath::get_meeting_node`) that yields the same weight, which means that there is
+    /// another shortest path with a different highest ranked node. This costs nothing extra, but
+    /// it does not catch all alternatives: equally short paths through the same highest ranked
+    /// node are not detected, and neither are paths whose shortcuts were omitted during the
+    /// preparation because an equally short witness path was found. So `false` reliably means
+    /// that there are several shortest paths, while `true` only means that no other one was found.
+    pub fn calc_path_with_uniqueness(
+        &mut self,
+        graph: &FastGraph,
+        start: NodeId,
+        end: NodeId,
+    ) -> Option<(ShortestPath, bool)> {
+        let path = self.calc_path(graph, start, end)?;
+        Some((path, !self.equal_meeting_node_found))
+    }
+
+    /// Like `calc_path`, but in debug builds (with `debug_assertions`) the returned path is checked
+    /// against the original edges of the graph: every two consecutive nodes of the path must be
+    /// connected by an original (non-shortcut) edge and the weight of the path must be the sum of
+    /// the weights of these edges, otherwise this panics. This catches errors in the graph or the
+    /// shortcut unpacking early, but takes time proportional to the number of edges adjacent to
+    /// the nodes of the path. In release builds this is identical to `calc_path`.
+    pub fn calc_path_checked(
+        &mut self,
+        graph: &FastGraph,
+        start: NodeId,
+        end: NodeId,
+    ) -> Option<ShortestPath> {
+        let path = self.calc_path(graph, start, end);
+        #[cfg(debug_assertions)]
+        if let Some(path) = &path {
+            PathCalculator::check_path(graph, path);
+        }
+        path
+    }
+
+    /// Calculates the shortest path between the nodes closest to the given (lat, lon) locations,
+    /// see `FastGraph::nearest_node`. Returns `None` if the graph has no coordinates or there is
+    /// no path.
+    #[cfg(feature = "std")]
+    pub fn calc_path_coords(
+        &mut self,
+        graph: &FastGraph,
+        from: (f64, f64),
+        to: (f64, f64),
+    ) -> Option<ShortestPath> {
+        let source = graph.nearest_node(from.0, from.1)?;
+        let target = graph.nearest_node(to.0, to.1)?;
+        self.calc_path(graph, source, target)
+    }
+
+    /// Like `calc_path`, but the search is aborted if it is still running at the given
+    /// `deadline`, in which case `QueryError::DeadlineExceeded` is returned. The deadline is
+    /// checked regularly during the search, so the query might take slightly longer than the
+    /// deadline allows. Unpacking the path found by the search is not interrupted.
+    #[cfg(feature = "std")]
+    pub fn calc_path_with_deadline(
+        &mut self,
+        graph: &FastGraph,
+        start: NodeId,
+        end: NodeId,
+        deadline: Instant,
+    ) -> Result<Option<ShortestPath>, QueryError> {
+        self.deadline = Some(deadline);
+        self.deadline_exceeded = false;
+        let result = self.search(
+            graph,
+            &[(start, WEIGHT_ZERO)],
+            &[(end, WEIGHT_ZERO)],
+            &mut |_graph, _edge_id, _fwd| true,
+            &PathCalculator::stored_weight,
+            0.0,
+        );
+        self.deadline = None;
+        if self.deadline_exceeded {
+            return Err(QueryError::DeadlineExceeded);
+        }
+        Ok(result.map(|(meeting_node, weight)| self.create_path(graph, meeting_node, weight)))
     }
 
     pub fn calc_path_multiple_sources_and_targets(
@@ -67,361 +426,3497 @@ impl PathCalculator {
         starts: Vec<(NodeId, Weight)>,
         ends: Vec<(NodeId, Weight)>,
     ) -> Option<ShortestPath> {
+        self.calc_path_multi(graph, &starts, &ends)
+    }
+
+    /// Like `calc_path_multiple_sources_and_targets`, but takes the sources and targets as slices,
+    /// so they do not need to be moved into a new `Vec` for every query, e.g. when a server runs
+    /// many multi-source queries with the same (or reused) buffers.
+    pub fn calc_path_multi(
+        &mut self,
+        graph: &FastGraph,
+        sources: &[(NodeId, Weight)],
+        targets: &[(NodeId, Weight)],
+    ) -> Option<ShortestPath> {
+        self.calc_path_with_edge_filter(graph, sources, targets, &mut |_graph, _edge_id, _fwd| true)
+    }
+
+    /// Calculates the shortest path from the closest of the given `sources` to `target` and
+    /// returns this source along with the path, e.g. to find the nearest facility for a query
+    /// point. This runs a single search just like `calc_path_multi` with all weights set to zero.
+    /// If several sources are equally close the one chosen by the search is returned. Returns
+    /// `None` if `sources` is empty or none of them can reach `target`.
+    pub fn calc_path_nearest_source(
+        &mut self,
+        graph: &FastGraph,
+        sources: &[NodeId],
+        target: NodeId,
+    ) -> Option<(NodeId, ShortestPath)> {
+        if sources.is_empty() {
+            return None;
+        }
+        let starts: Vec<(NodeId, Weight)> = sources
+            .iter()
+            .map(|&source| (source, WEIGHT_ZERO))
+            .collect();
+        let path = self.calc_path_multi(graph, &starts, &[(target, WEIGHT_ZERO)])?;
+        Some((path.get_source(), path))
+    }
+
+    /// Calculates the shortest paths from `source` to each of the `targets`. The i-th entry of the
+    /// result is the path to the i-th target, or `None` if it cannot be reached from `source`.
+    ///
+    /// The forward search from `source` is run only once and is shared by all targets. For each
+    /// target we then run a backward search that stops as soon as it cannot improve the best
+    /// path found so far, which is usually much cheaper than running `calc_path` for each target.
+    pub fn calc_paths_one_to_many(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        targets: &[NodeId],
+    ) -> Vec<Option<ShortestPath>> {
         assert_eq!(
             graph.get_num_nodes(),
             self.num_nodes,
             "given graph has invalid node count"
         );
-        assert!(!starts.is_empty(), "there has to be at least one start");
-        assert!(!ends.is_empty(), "there has to be at least one end");
-        for (start_node, _) in &starts {
-            assert!(*start_node < self.num_nodes, "invalid start node");
+        assert!(source.0 < self.num_nodes, "invalid source node");
+        for target in targets {
+            assert!(target.0 < self.num_nodes, "invalid target node");
+        }
+        self.stats = QueryStats::default();
+        self.run_upward_search(graph, source);
+        targets
+            .iter()
+            .map(|&target| self.calc_path_to_forward_search(graph, target))
+            .collect()
+    }
+
+    /// Like `calc_paths_one_to_many`, but only calculates the weights of the shortest paths and
+    /// includes access weights, just like `calc_path_multiple_sources_and_targets`: the i-th
+    /// entry of the result is `source_weight` plus the weight of the shortest path from `source`
+    /// to the i-th target plus the access weight of this target. Targets that cannot be reached
+    /// from `source` or whose access weight is `WEIGHT_MAX` yield `None`, and so do all targets if
+    /// `source_weight` is `WEIGHT_MAX`. No paths are unpacked, so this is cheaper than
+    /// `calc_paths_one_to_many` when only the weights are needed.
+    pub fn calc_weights_one_to_many_weighted(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        source_weight: Weight,
+        targets: &[(NodeId, Weight)],
+    ) -> Vec<Option<Weight>> {
+        assert_eq!(
+            graph.get_num_nodes(),
+            self.num_nodes,
+            "given graph has invalid node count"
+        );
+        assert!(source.0 < self.num_nodes, "invalid source node");
+        for (target, _) in targets {
+            assert!(target.0 < self.num_nodes, "invalid target node");
         }
-        for (end_node, _) in &ends {
-            assert!(*end_node < self.num_nodes, "invalid end node");
+        self.stats = QueryStats::default();
+        if is_infinite(source_weight) {
+            return vec![None; targets.len()];
         }
-        self.heap_fwd.clear();
-        self.heap_bwd.clear();
-        self.valid_flags_fwd.invalidate_all();
-        self.valid_flags_bwd.invalidate_all();
+        self.run_upward_search(graph, source);
+        targets
+            .iter()
+            .map(|&(target, target_weight)| {
+                if is_infinite(target_weight) {
+                    return None;
+                }
+                let (_, weight) = self.search_to_forward_search(graph, target)?;
+                let weight = add_weights(add_weights(source_weight, weight), target_weight);
+                if is_infinite(weight) {
+                    None
+                } else {
+                    Some(weight)
+                }
+            })
+            .collect()
+    }
 
-        let mut best_weight = WEIGHT_MAX;
-        let mut meeting_node = INVALID_NODE;
+    /// Runs the forward search from `source` and keeps its result, so the paths from `source` to
+    /// different targets can be calculated one after another using `calc_to`. This is useful if
+    /// the targets are not known in advance, otherwise use `calc_paths_one_to_many`. The search is
+    /// only repeated if the source changes or if another query was run in between.
+    pub fn set_source(&mut self, graph: &FastGraph, source: NodeId) {
+        assert_eq!(
+            graph.get_num_nodes(),
+            self.num_nodes,
+            "given graph has invalid node count"
+        );
+        assert!(source.0 < self.num_nodes, "invalid source node");
+        self.stats = QueryStats::default();
+        if self.source == Some(source) && self.source_search_done {
+            return;
+        }
+        self.run_upward_search(graph, source);
+        self.source = Some(source);
+        self.source_search_done = true;
+    }
 
-        for (start_node, start_weight) in &starts {
-            for (end_node, end_weight) in &ends {
-                if *start_node == *end_node
-                    && *start_weight < WEIGHT_MAX
-                    && *end_weight < WEIGHT_MAX
-                    && *start_weight + *end_weight < best_weight
-                {
-                    best_weight = *start_weight + *end_weight;
-                    meeting_node = *end_node;
+    /// Calculates the shortest path from the source set using `set_source` to `target`. The
+    /// given graph must be the one that was passed to `set_source`. Panics if no source was set.
+    pub fn calc_to(&mut self, graph: &FastGraph, target: NodeId) -> Option<ShortestPath> {
+        let source = self
+            .source
+            .expect("the source must be set using set_source before calling calc_to");
+        assert!(target.0 < self.num_nodes, "invalid target node");
+        self.set_source(graph, source);
+        self.calc_path_to_forward_search(graph, target)
+    }
+
+    /// Explores the complete upward search space of `source`, which is small, so we do not need
+    /// any stopping criterion
+    fn run_upward_search(&mut self, graph: &FastGraph, source: NodeId) {
+        self.clear_fwd();
+        self.update_node_fwd(source, WEIGHT_ZERO, source, INVALID_EDGE);
+        self.heap_fwd
+            .push_or_decrease(HeapItem::new(WEIGHT_ZERO, source));
+        while let Some(curr) = self.heap_fwd.pop() {
+            self.stats.forward_settled += 1;
+            if self.stall_on_demand
+                && self.is_stallable_fwd(
+                    graph,
+                    curr,
+                    &mut |_graph, _edge_id, _fwd| true,
+                    &PathCalculator::stored_weight,
+                )
+            {
+                continue;
+            }
+            let begin = graph.begin_out_edges(curr.node_id);
+            let end = graph.end_out_edges(curr.node_id);
+            self.stats.edges_relaxed += end - begin;
+            for edge_id in begin..end {
+                let adj = graph.edges_fwd[edge_id].adj_node;
+                let weight = add_weights(curr.weight, graph.edges_fwd[edge_id].weight);
+                if self.improves_fwd(adj, weight, curr) {
+                    self.update_node_fwd(adj, weight, curr.node_id, edge_id);
+                    self.heap_fwd.push_or_decrease(HeapItem::new(weight, adj));
                 }
             }
         }
+    }
 
-        for (node, weight) in starts {
-            if weight < self.get_weight_fwd(node) {
-                // this is a bit of a hack, we store the start node as parent even though it is not
-                // the parent. this way we can easily obtain the target node when we unpack the path
-                // later
-                self.update_node_fwd(node, weight, node, INVALID_EDGE);
-                self.heap_fwd.push(HeapItem::new(weight, node));
+    /// Resets the forward search, this also invalidates the search run by `set_source`
+    fn clear_fwd(&mut self) {
+        self.heap_fwd.clear();
+        self.valid_flags_fwd.invalidate_all();
+        self.source_search_done = false;
+    }
+
+    /// Returns the (up to) `k` candidates that are closest to `source` along with the weights of
+    /// their shortest paths, sorted by weight. Candidates that cannot be reached from `source` are
+    /// ignored, so the result has less than `k` entries if not enough candidates are reachable.
+    ///
+    /// For each candidate we first run a backward search and store its weights at the nodes of
+    /// the search space ('buckets'). Then we run a forward search from `source` and scan the
+    /// buckets of the settled nodes. Once the weight of the next node of the forward search is
+    /// larger than the weight found for k of the candidates none of these weights can improve
+    /// anymore, so the search stops without exploring the remaining search space.
+    #[cfg(feature = "std")]
+    pub fn calc_k_nearest(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        candidates: &[NodeId],
+        k: usize,
+    ) -> Vec<(NodeId, Weight)> {
+        assert_eq!(
+            graph.get_num_nodes(),
+            self.num_nodes,
+            "given graph has invalid node count"
+        );
+        assert!(source.0 < self.num_nodes, "invalid source node");
+        let mut unique_candidates = vec![];
+        let mut seen = HashSet::new();
+        for &candidate in candidates {
+            assert!(candidate.0 < self.num_nodes, "invalid candidate node");
+            if seen.insert(candidate) {
+                unique_candidates.push(candidate);
             }
         }
-        for (node, weight) in ends {
-            if weight < self.get_weight_bwd(node) {
-                // ... same here
-                self.update_node_bwd(node, weight, node, INVALID_EDGE);
-                self.heap_bwd.push(HeapItem::new(weight, node));
-            }
+        if k == 0 || unique_candidates.is_empty() {
+            return vec![];
         }
 
-        loop {
-            if self.heap_fwd.is_empty() && self.heap_bwd.is_empty() {
-                break;
-            }
-            loop {
-                if self.heap_fwd.is_empty() {
-                    break;
-                }
-                let curr = self.heap_fwd.pop().unwrap();
-                if self.is_settled_fwd(curr.node_id) {
-                    continue;
-                }
-                if curr.weight > best_weight {
-                    break;
-                }
-                // stall on demand optimization
-                if self.is_stallable_fwd(graph, curr) {
-                    continue;
-                }
-                let begin = graph.begin_out_edges(curr.node_id);
-                let end = graph.end_out_edges(curr.node_id);
-                for edge_id in begin..end {
-                    let adj = graph.edges_fwd[edge_id].adj_node;
-                    let edge_weight = graph.edges_fwd[edge_id].weight;
-                    let weight = curr.weight + edge_weight;
-                    if weight < self.get_weight_fwd(adj) {
-                        self.update_node_fwd(adj, weight, curr.node_id, edge_id);
-                        self.heap_fwd.push(HeapItem::new(weight, adj));
-                    }
-                }
-                self.data_fwd[curr.node_id].settled = true;
-                if self.valid_flags_bwd.is_valid(curr.node_id)
-                    && curr.weight + self.get_weight_bwd(curr.node_id) < best_weight
+        // the buckets contain (candidate index, weight from the node to the candidate) pairs
+        let mut buckets: HashMap<NodeId, Vec<(usize, Weight)>> = HashMap::new();
+        for (index, &candidate) in unique_candidates.iter().enumerate() {
+            self.heap_bwd.clear();
+            self.valid_flags_bwd.invalidate_all();
+            self.update_node_bwd(candidate, WEIGHT_ZERO, INVALID_NODE, INVALID_EDGE);
+            self.heap_bwd
+                .push_or_decrease(HeapItem::new(WEIGHT_ZERO, candidate));
+            while let Some(curr) = self.heap_bwd.pop() {
+                buckets
+                    .entry(curr.node_id)
+                    .or_default()
+                    .push((index, curr.weight));
+                for edge_id in graph.begin_in_edges(curr.node_id)..graph.end_in_edges(curr.node_id)
                 {
-                    best_weight = curr.weight + self.get_weight_bwd(curr.node_id);
-                    meeting_node = curr.node_id;
-                }
-                break;
-            }
-
-            loop {
-                if self.heap_bwd.is_empty() {
-                    break;
-                }
-                let curr = self.heap_bwd.pop().unwrap();
-                if self.is_settled_bwd(curr.node_id) {
-                    continue;
-                }
-                if curr.weight > best_weight {
-                    break;
-                }
-                // stall on demand optimization
-                if self.is_stallable_bwd(graph, curr) {
-                    continue;
-                }
-                let begin = graph.begin_in_edges(curr.node_id);
-                let end = graph.end_in_edges(curr.node_id);
-                for edge_id in begin..end {
                     let adj = graph.edges_bwd[edge_id].adj_node;
-                    let edge_weight = graph.edges_bwd[edge_id].weight;
-                    let weight = curr.weight + edge_weight;
+                    let weight = add_weights(curr.weight, graph.edges_bwd[edge_id].weight);
                     if weight < self.get_weight_bwd(adj) {
                         self.update_node_bwd(adj, weight, curr.node_id, edge_id);
-                        self.heap_bwd.push(HeapItem::new(weight, adj));
+                        self.heap_bwd.push_or_decrease(HeapItem::new(weight, adj));
                     }
                 }
-                self.data_bwd[curr.node_id].settled = true;
-                if self.valid_flags_fwd.is_valid(curr.node_id)
-                    && curr.weight + self.get_weight_fwd(curr.node_id) < best_weight
-                {
-                    best_weight = curr.weight + self.get_weight_fwd(curr.node_id);
-                    meeting_node = curr.node_id;
-                }
-                break;
             }
         }
 
-        if meeting_node == INVALID_NODE {
-            None
-        } else {
-            assert!(best_weight < WEIGHT_MAX);
-            let nodes = self.extract_nodes(graph, meeting_node);
-            assert!(!nodes.is_empty());
-            Some(ShortestPath::new(
-                nodes[0],
-                nodes[nodes.len() - 1],
-                best_weight,
-                nodes,
-            ))
-        }
-    }
-
-    fn is_stallable_fwd(&self, graph: &FastGraph, curr: HeapItem) -> bool {
-        let begin = graph.begin_in_edges(curr.node_id);
-        let end = graph.end_in_edges(curr.node_id);
-        for edge_id in begin..end {
-            let adj = graph.edges_bwd[edge_id].adj_node;
-            let adj_weight = self.get_weight_fwd(adj);
-            if adj_weight == WEIGHT_MAX {
-                continue;
+        let mut weights = vec![WEIGHT_MAX; unique_candidates.len()];
+        let mut kth_weight = WEIGHT_MAX;
+        self.clear_fwd();
+        self.update_node_fwd(source, WEIGHT_ZERO, INVALID_NODE, INVALID_EDGE);
+        self.heap_fwd
+            .push_or_decrease(HeapItem::new(WEIGHT_ZERO, source));
+        while let Some(curr) = self.heap_fwd.pop() {
+            if curr.weight > kth_weight {
+                break;
             }
-            let edge_weight = graph.edges_bwd[edge_id].weight;
-            if adj_weight + edge_weight < curr.weight {
-                return true;
+            let mut improved = false;
+            if let Some(bucket) = buckets.get(&curr.node_id) {
+                for &(index, weight) in bucket {
+                    if add_weights(curr.weight, weight) < weights[index] {
+                        weights[index] = add_weights(curr.weight, weight);
+                        improved = true;
+                    }
+                }
+            }
+            if improved && k <= weights.len() {
+                let mut sorted_weights = weights.clone();
+                kth_weight = *sorted_weights.select_nth_unstable(k - 1).1;
+            }
+            for edge_id in graph.begin_out_edges(curr.node_id)..graph.end_out_edges(curr.node_id) {
+                let adj = graph.edges_fwd[edge_id].adj_node;
+                let weight = add_weights(curr.weight, graph.edges_fwd[edge_id].weight);
+                if weight < self.get_weight_fwd(adj) {
+                    self.update_node_fwd(adj, weight, curr.node_id, edge_id);
+                    self.heap_fwd.push_or_decrease(HeapItem::new(weight, adj));
+                }
             }
         }
-        false
+
+        let mut result: Vec<(NodeId, Weight)> = unique_candidates
+            .into_iter()
+            .zip(weights)
+            .filter(|(_, weight)| *weight < WEIGHT_MAX)
+            .collect();
+        result.sort_by_key(|&(node, weight)| (weight, node));
+        result.truncate(k);
+        result
     }
 
-    fn is_stallable_bwd(&self, graph: &FastGraph, curr: HeapItem) -> bool {
-        let begin = graph.begin_out_edges(curr.node_id);
-        let end = graph.end_out_edges(curr.node_id);
-        for edge_id in begin..end {
-            let adj = graph.edges_fwd[edge_id].adj_node;
-            let adj_weight = self.get_weight_bwd(adj);
-            if adj_weight == WEIGHT_MAX {
+    /// Runs a backward search from `target` that meets the (completed) forward search and returns
+    /// the resulting shortest path
+    fn calc_path_to_forward_search(
+        &mut self,
+        graph: &FastGraph,
+        target: NodeId,
+    ) -> Option<ShortestPath> {
+        let (meeting_node, weight) = self.search_to_forward_search(graph, target)?;
+        Some(self.create_path(graph, meeting_node, weight))
+    }
+
+    /// Runs the backward search from `target` until it cannot improve the path to the nodes
+    /// reached by the forward search anymore and returns the meeting node and weight of the
+    /// shortest path, if there is one
+    fn search_to_forward_search(
+        &mut self,
+        graph: &FastGraph,
+        target: NodeId,
+    ) -> Option<(NodeId, Weight)> {
+        self.heap_bwd.clear();
+        self.valid_flags_bwd.invalidate_all();
+        self.update_node_bwd(target, WEIGHT_ZERO, target, INVALID_EDGE);
+        self.heap_bwd
+            .push_or_decrease(HeapItem::new(WEIGHT_ZERO, target));
+        let mut best_weight = WEIGHT_MAX;
+        let mut meeting_node = INVALID_NODE;
+        while let Some(curr) = self.heap_bwd.pop() {
+            if curr.weight > best_weight {
+                break;
+            }
+            self.stats.backward_settled += 1;
+            if self.stall_on_demand
+                && self.is_stallable_bwd(
+                    graph,
+                    curr,
+                    &mut |_graph, _edge_id, _fwd| true,
+                    &PathCalculator::stored_weight,
+                )
+            {
                 continue;
             }
-            let edge_weight = graph.edges_fwd[edge_id].weight;
-            if adj_weight + edge_weight < curr.weight {
-                return true;
+            let begin = graph.begin_in_edges(curr.node_id);
+            let end = graph.end_in_edges(curr.node_id);
+            self.stats.edges_relaxed += end - begin;
+            for edge_id in begin..end {
+                let adj = graph.edges_bwd[edge_id].adj_node;
+                let weight = add_weights(curr.weight, graph.edges_bwd[edge_id].weight);
+                if self.improves_bwd(adj, weight, curr) {
+                    self.update_node_bwd(adj, weight, curr.node_id, edge_id);
+                    self.heap_bwd.push_or_decrease(HeapItem::new(weight, adj));
+                }
+            }
+            let weight = add_weights(curr.weight, self.get_weight_fwd(curr.node_id));
+            if PathCalculator::improves_meeting(weight, curr.node_id, best_weight, meeting_node) {
+                best_weight = weight;
+                meeting_node = curr.node_id;
             }
         }
-        false
+        if meeting_node == INVALID_NODE {
+            return None;
+        }
+        Some((meeting_node, best_weight))
     }
 
-    fn extract_nodes(&self, graph: &FastGraph, meeting_node: NodeId) -> Vec<NodeId> {
-        assert_ne!(meeting_node, INVALID_NODE);
-        assert!(self.valid_flags_fwd.is_valid(meeting_node));
-        assert!(self.valid_flags_bwd.is_valid(meeting_node));
-        let mut result = Vec::new();
+    /// Calculates the shortest path from `source` to `target` in the hierarchy, but unlike
+    /// `calc_path` this does not unpack the shortcuts. The returned nodes are the start and end
+    /// nodes of the (shortcut) edges found by the search, so consecutive nodes are not
+    /// necessarily connected by an edge of the original graph. Shortcuts of interest can be
+    /// unpacked later using `ShortestPath::unpack_segment` (or `calc_path` for the corresponding
+    /// segment). Returns `None` if there is no path.
+    pub fn calc_path_compressed(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        target: NodeId,
+    ) -> Option<Vec<NodeId>> {
+        let (meeting_node, _) = self.search(
+            graph,
+            &[(source, WEIGHT_ZERO)],
+            &[(target, WEIGHT_ZERO)],
+            &mut |_graph, _edge_id, _fwd| true,
+            &PathCalculator::stored_weight,
+            0.0,
+        )?;
+        self.count_edge_usages(graph, meeting_node);
+        let mut result = vec![meeting_node];
         let mut node = meeting_node;
-        while self.data_fwd[node].inc_edge != INVALID_EDGE {
-            PathCalculator::unpack_fwd(graph, &mut result, self.data_fwd[node].inc_edge, true);
-            node = self.data_fwd[node].parent;
+        while self.data_fwd[node.0].inc_edge != INVALID_EDGE {
+            node = self.data_fwd[node.0].parent;
+            result.push(node);
         }
         result.reverse();
         node = meeting_node;
-        while self.data_bwd[node].inc_edge != INVALID_EDGE {
-            PathCalculator::unpack_bwd(graph, &mut result, self.data_bwd[node].inc_edge, false);
-            node = self.data_bwd[node].parent;
+        while self.data_bwd[node.0].inc_edge != INVALID_EDGE {
+            node = self.data_bwd[node.0].parent;
+            result.push(node);
         }
-        // we stored the target node as 'parent' of the root of the shortest tree, so we can use it
-        // here
-        result.push(node);
-        result
+        Some(result)
     }
 
-    pub fn unpack_fwd(graph: &FastGraph, nodes: &mut Vec<NodeId>, edge_id: EdgeId, reverse: bool) {
-        if !graph.edges_fwd[edge_id].is_shortcut() {
-            nodes.push(graph.edges_fwd[edge_id].base_node);
-            return;
-        }
-        if reverse {
-            PathCalculator::unpack_fwd(
-                graph,
-                nodes,
-                graph.edges_fwd[edge_id].replaced_out_edge,
-                reverse,
+    /// Calculates a path from `source` to `target` whose weight is at most `1 + epsilon` times the
+    /// weight of the shortest path. The bidirectional search usually needs to continue for a
+    /// while after the two search directions met to prove that the best path found so far is
+    /// optimal. Here, each direction stops as soon as the weight of its next node times
+    /// `1 + epsilon` exceeds the weight of the best path, which can save a considerable amount of
+    /// time for long paths. Every node that was not settled has a weight of at least
+    /// `best_weight / (1 + epsilon)`, so the true shortest path cannot be shorter than that.
+    /// With `epsilon = 0` this is the same as `calc_path`.
+    pub fn calc_path_approx(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        target: NodeId,
+        epsilon: f64,
+    ) -> Option<ShortestPath> {
+        assert!(epsilon >= 0.0, "epsilon must not be negative");
+        let (meeting_node, weight) = self.search(
+            graph,
+            &[(source, WEIGHT_ZERO)],
+            &[(target, WEIGHT_ZERO)],
+            &mut |_graph, _edge_id, _fwd| true,
+            &PathCalculator::stored_weight,
+            epsilon,
+        )?;
+        Some(self.create_path(graph, meeting_node, weight))
+    }
+
+    /// The search of one direction can stop once it reaches nodes with a weight larger than this
+    fn stop_weight(best_weight: Weight, epsilon: f64) -> Weight {
+        if epsilon == 0.0 || best_weight == WEIGHT_MAX {
+            best_weight
+        } else {
+            Weight((best_weight.0 as f64 / (1.0 + epsilon)) as usize)
+        }
+    }
+
+    /// Calculates the shortest path tree rooted at `source`. For every node the result contains
+    /// its parent in the tree, i.e. the previous node on a shortest path from `source`, or `None`
+    /// if the node cannot be reached from `source` or the weight of its shortest path exceeds
+    /// `max_weight`. The entry of `source` itself is `None` as well.
+    ///
+    /// This runs an upward search from `source` followed by a sweep over all nodes in descending
+    /// rank order (like the PHAST algorithm), so it always takes O(nodes + edges) time, even when
+    /// `max_weight` is small.
+    pub fn calc_shortest_path_tree(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        max_weight: Option<Weight>,
+    ) -> Vec<Option<NodeId>> {
+        assert_eq!(
+            graph.get_num_nodes(),
+            self.num_nodes,
+            "given graph has invalid node count"
+        );
+        assert!(source.0 < self.num_nodes, "invalid source node");
+        let max_weight = max_weight.unwrap_or(WEIGHT_MAX);
+        let mut weights = vec![WEIGHT_MAX; self.num_nodes];
+        let mut parents = vec![None; self.num_nodes];
+
+        // upward search
+        self.clear_fwd();
+        self.update_node_fwd(source, WEIGHT_ZERO, INVALID_NODE, INVALID_EDGE);
+        self.heap_fwd
+            .push_or_decrease(HeapItem::new(WEIGHT_ZERO, source));
+        while let Some(curr) = self.heap_fwd.pop() {
+            weights[curr.node_id.0] = curr.weight;
+            for edge_id in graph.begin_out_edges(curr.node_id)..graph.end_out_edges(curr.node_id) {
+                let adj = graph.edges_fwd[edge_id].adj_node;
+                let weight = add_weights(curr.weight, graph.edges_fwd[edge_id].weight);
+                if weight <= max_weight && weight < self.get_weight_fwd(adj) {
+                    self.update_node_fwd(adj, weight, curr.node_id, edge_id);
+                    self.heap_fwd.push_or_decrease(HeapItem::new(weight, adj));
+                }
+            }
+        }
+
+        // downward sweep, every node can be reached via an in-edge coming from a node with higher
+        // rank, so when we process the nodes in descending rank order the weights of these are
+        // final already
+        for &node in graph.get_node_ordering().iter().rev() {
+            if self.valid_flags_fwd.is_valid(node) && node != source {
+                let inc_edge = self.data_fwd[node.0].inc_edge;
+                parents[node.0] = Some(PathCalculator::last_base_node_fwd(graph, inc_edge));
+            }
+            for edge_id in graph.begin_in_edges(node)..graph.end_in_edges(node) {
+                let adj = graph.edges_bwd[edge_id].adj_node;
+                if is_infinite(weights[adj.0]) {
+                    continue;
+                }
+                let weight = add_weights(weights[adj.0], graph.edges_bwd[edge_id].weight);
+                if weight <= max_weight && weight < weights[node.0] {
+                    weights[node.0] = weight;
+                    parents[node.0] = Some(PathCalculator::last_base_node_bwd(graph, edge_id));
+                }
+            }
+        }
+        parents
+    }
+
+    /// Returns the nodes that can be reached from `source` with a weight of at most `max_weight`
+    /// along with their shortest path weight. Unlike `calc_shortest_path_tree` the nodes are
+    /// found lazily one at a time in order of increasing weight, so no memory is needed for the
+    /// result and the search stops as soon as the iterator is dropped, which makes early exits
+    /// cheap.
+    ///
+    /// This runs Dijkstra's algorithm on all edges of the hierarchy (upwards and downwards),
+    /// which yields the same weights as the input graph. The index of the downward edges is built
+    /// the first time this is called for a graph, which takes O(edges) time and memory.
+    #[cfg(feature = "std")]
+    pub fn reachable_iter<'a>(
+        &'a mut self,
+        graph: &'a FastGraph,
+        source: NodeId,
+        max_weight: Weight,
+    ) -> impl Iterator<Item = (NodeId, Weight)> + 'a {
+        assert_eq!(
+            graph.get_num_nodes(),
+            self.num_nodes,
+            "given graph has invalid node count"
+        );
+        assert!(source.0 < self.num_nodes, "invalid source node");
+        self.clear_fwd();
+        self.update_node_fwd(source, WEIGHT_ZERO, INVALID_NODE, INVALID_EDGE);
+        self.heap_fwd
+            .push_or_decrease(HeapItem::new(WEIGHT_ZERO, source));
+        ReachableIter {
+            calc: self,
+            graph,
+            max_weight,
+        }
+    }
+
+    /// Returns the source node of the last original edge of the given out-edge
+    fn last_base_node_fwd(graph: &FastGraph, edge_id: EdgeId) -> NodeId {
+        let mut edge = &graph.edges_fwd[edge_id];
+        while edge.is_shortcut() {
+            edge = &graph.edges_fwd[edge.replaced_out_edge];
+        }
+        edge.base_node
+    }
+
+    /// Returns the source node of the last original edge of the given in-edge
+    fn last_base_node_bwd(graph: &FastGraph, edge_id: EdgeId) -> NodeId {
+        let edge = &graph.edges_bwd[edge_id];
+        if edge.is_shortcut() {
+            PathCalculator::last_base_node_fwd(graph, edge.replaced_out_edge)
+        } else {
+            // original edges stored as in-edges point from the adj_node to the base_node
+            edge.adj_node
+        }
+    }
+
+    /// Calculates the shortest path from `start` to `end` that does not visit any of the `blocked`
+    /// nodes.
+    ///
+    /// The blocked nodes are excluded from the upward/downward search of the hierarchy and
+    /// shortcuts are only used if none of the nodes they skip is blocked, which requires unpacking
+    /// them during the search. If blocked nodes are encountered at all the hierarchy can no longer
+    /// guarantee that the shortest path is found and we need to fall back to a Dijkstra search on
+    /// the original edges of the graph, which is a lot slower (O(edges) just to set it up), so
+    /// this method is only fast if the blocked nodes are not close to the shortest path's search
+    /// space. The fallback search uses all edges of the input graph, including the ones that were
+    /// replaced by shortcuts during the preparation (see `FastGraph::get_replaced_edges`).
+    #[cfg(feature = "std")]
+    pub fn calc_path_avoiding(
+        &mut self,
+        graph: &FastGraph,
+        start: NodeId,
+        end: NodeId,
+        blocked: &HashSet<NodeId>,
+    ) -> Option<ShortestPath> {
+        if blocked.contains(&start) || blocked.contains(&end) {
+            return None;
+        }
+        self.calc_path_with_base_edge_filter(graph, start, end, |edge| {
+            !blocked.contains(&edge.base_node) && !blocked.contains(&edge.adj_node)
+        })
+    }
+
+    /// Calculates the shortest path from `start` to `end` that does not use any of the
+    /// `blocked_edges`, which are given as (from, to) pairs of the original (directed) edges.
+    ///
+    /// Blocked edges might be part of a shortcut, so shortcuts are unpacked when they are
+    /// considered during the search and rejected if they contain a blocked edge. Just like for
+    /// `calc_path_avoiding` we need to fall back to a (much slower) Dijkstra search on the original
+    /// edges of the graph in case any blocked edges are encountered during the search. This
+    /// includes the edges that were replaced by shortcuts during the preparation, so e.g. a
+    /// detour that was never used before the blocked edges were closed is still found.
+    #[cfg(feature = "std")]
+    pub fn calc_path_avoiding_edges(
+        &mut self,
+        graph: &FastGraph,
+        start: NodeId,
+        end: NodeId,
+        blocked_edges: &HashSet<(NodeId, NodeId)>,
+    ) -> Option<ShortestPath> {
+        self.calc_path_with_base_edge_filter(graph, start, end, |edge| {
+            !blocked_edges.contains(&(edge.base_node, edge.adj_node))
+        })
+    }
+
+    /// Calculates the shortest path from `start` to `end` that only uses edges accepted by
+    /// `edge_ok`, e.g. to try out custom routing rules. `edge_ok` is called with the original
+    /// edges of the graph, where `base_node` is the source and `adj_node` is the target of the
+    /// edge (also for edges stored as in-edges), and must always return the same result for the
+    /// same edge.
+    ///
+    /// A shortcut can only be used if all the original edges it consists of are accepted, so
+    /// every shortcut considered by the search is unpacked (recursively), which makes the search
+    /// several times slower than `calc_path`, depending on the number of original edges per
+    /// shortcut. Just like for `calc_path_avoiding` we need to fall back to a Dijkstra search on
+    /// the original edges (which takes O(edges) just to set it up) in case any edge is rejected
+    /// during the search, so this is only fast if the rejected edges are rare and not close to
+    /// the search space of the query. Like for `calc_path_avoiding` the fallback search also uses
+    /// the edges that were replaced by shortcuts during the preparation, so the result is the
+    /// shortest path among all paths of the input graph whose edges are accepted.
+    #[cfg(feature = "std")]
+    pub fn calc_path_filtered<F>(
+        &mut self,
+        graph: &FastGraph,
+        start: NodeId,
+        end: NodeId,
+        edge_ok: F,
+    ) -> Option<ShortestPath>
+    where
+        F: Fn(&FastGraphEdge) -> bool,
+    {
+        self.calc_path_with_base_edge_filter(graph, start, end, edge_ok)
+    }
+
+    /// Calculates the shortest path from `start` to `end` using only shortcuts whose base edges
+    /// are all accepted by `accept_base_edge`, falls back to a Dijkstra search on the original
+    /// edges in case any edge was rejected
+    #[cfg(feature = "std")]
+    fn calc_path_with_base_edge_filter<F>(
+        &mut self,
+        graph: &FastGraph,
+        start: NodeId,
+        end: NodeId,
+        accept_base_edge: F,
+    ) -> Option<ShortestPath>
+    where
+        F: Fn(&FastGraphEdge) -> bool,
+    {
+        let mut rejected_edge = false;
+        let path = self.calc_path_with_edge_filter(
+            graph,
+            &[(start, WEIGHT_ZERO)],
+            &[(end, WEIGHT_ZERO)],
+            &mut |graph, edge_id, fwd| {
+                let accepted = if fwd {
+                    PathCalculator::accept_base_edges_fwd(graph, edge_id, &accept_base_edge)
+                } else {
+                    PathCalculator::accept_base_edges_bwd(graph, edge_id, &accept_base_edge)
+                };
+                rejected_edge |= !accepted;
+                accepted
+            },
+        );
+        if !rejected_edge {
+            // the search was not affected by the filter, so this is the actual shortest path
+            return path;
+        }
+        // the path found so far (if any) is still valid, but there might be a shorter one that
+        // cannot be found using the hierarchy
+        let weight_limit = path.as_ref().map_or(WEIGHT_MAX, |p| p.get_weight());
+        let original_graph = PreparationGraph::from_fast_graph(graph);
+        self.calc_path_on_original_graph(
+            &original_graph,
+            start,
+            end,
+            weight_limit,
+            accept_base_edge,
+        )
+        .map(|p| p.with_weight_scale(graph.get_weight_scale()))
+        .or(path)
+    }
+
+    /// Runs Dijkstra's algorithm on the given graph, but only considers edges accepted by
+    /// `accept_edge` and paths with weight smaller than `weight_limit`.
+    #[cfg(feature = "std")]
+    fn calc_path_on_original_graph<F>(
+        &mut self,
+        graph: &PreparationGraph,
+        start: NodeId,
+        end: NodeId,
+        weight_limit: Weight,
+        accept_edge: F,
+    ) -> Option<ShortestPath>
+    where
+        F: Fn(&FastGraphEdge) -> bool,
+    {
+        self.clear_fwd();
+        self.update_node_fwd(start, WEIGHT_ZERO, INVALID_NODE, INVALID_EDGE);
+        self.heap_fwd
+            .push_or_decrease(HeapItem::new(WEIGHT_ZERO, start));
+        while let Some(curr) = self.heap_fwd.pop() {
+            if curr.node_id == end {
+                let mut nodes = vec![];
+                let mut node = end;
+                while node != INVALID_NODE {
+                    nodes.push(node);
+                    node = self.data_fwd[node.0].parent;
+                }
+                nodes.reverse();
+                let edge_data = PathCalculator::find_edge_data(graph, &nodes);
+                return Some(
+                    ShortestPath::new(start, end, curr.weight, nodes).with_edge_data(edge_data),
+                );
+            }
+            for arc in graph.get_out_edges(curr.node_id) {
+                let weight = add_weights(curr.weight, arc.weight);
+                if weight < weight_limit
+                    && weight < self.get_weight_fwd(arc.adj_node)
+                    && accept_edge(&FastGraphEdge::new_with_data(
+                        curr.node_id,
+                        arc.adj_node,
+                        arc.weight,
+                        arc.length,
+                        INVALID_EDGE,
+                        INVALID_EDGE,
+                        arc.data,
+                    ))
+                {
+                    self.update_node_fwd(arc.adj_node, weight, curr.node_id, INVALID_EDGE);
+                    self.heap_fwd
+                        .push_or_decrease(HeapItem::new(weight, arc.adj_node));
+                }
+            }
+        }
+        None
+    }
+
+    /// Calculates the shortest path from `start` to `end` on the graph stored in the given
+    /// `Landmarks` using Dijkstra's algorithm guided by the landmark lower bounds (ALT). This does
+    /// not require a `FastGraph`, but is usually much slower than `calc_path`.
+    #[cfg(feature = "std")]
+    pub fn calc_path_alt(
+        &mut self,
+        landmarks: &Landmarks,
+        start: NodeId,
+        end: NodeId,
+    ) -> Option<ShortestPath> {
+        let graph = landmarks.get_graph();
+        assert_eq!(
+            graph.get_num_nodes(),
+            self.num_nodes,
+            "given graph has invalid node count"
+        );
+        self.clear_fwd();
+        self.update_node_fwd(start, WEIGHT_ZERO, INVALID_NODE, INVALID_EDGE);
+        self.heap_fwd
+            .push_or_decrease(HeapItem::new(landmarks.lower_bound(start, end), start));
+        while let Some(curr) = self.heap_fwd.pop() {
+            let curr_weight = self.get_weight_fwd(curr.node_id);
+            if curr.node_id == end {
+                let mut nodes = vec![];
+                let mut node = end;
+                while node != INVALID_NODE {
+                    nodes.push(node);
+                    node = self.data_fwd[node.0].parent;
+                }
+                nodes.reverse();
+                let edge_data = PathCalculator::find_edge_data(graph, &nodes);
+                return Some(
+                    ShortestPath::new(start, end, curr_weight, nodes).with_edge_data(edge_data),
+                );
+            }
+            for arc in graph.get_out_edges(curr.node_id) {
+                let weight = add_weights(curr_weight, arc.weight);
+                if weight < self.get_weight_fwd(arc.adj_node) {
+                    self.update_node_fwd(arc.adj_node, weight, curr.node_id, INVALID_EDGE);
+                    // the lower bounds are consistent, so every node is settled only once
+                    let estimate = add_weights(weight, landmarks.lower_bound(arc.adj_node, end));
+                    self.heap_fwd
+                        .push_or_decrease(HeapItem::new(estimate, arc.adj_node));
+                }
+            }
+        }
+        None
+    }
+
+    /// Calculates the quickest path from `start` to `end` when departing at `departure`, using the
+    /// time-dependent weights of the edges that were added with a profile, see
+    /// `InputGraph::add_edge_profile`. The weight of the path is the time from the departure until
+    /// the arrival at `end`. This runs Dijkstra's algorithm on the original edges, so it is much
+    /// slower than `calc_path`. It yields the correct result because all profiles fulfill the FIFO
+    /// property.
+    #[cfg(feature = "std")]
+    pub fn calc_path_at(
+        &mut self,
+        graph: &TimeDependentGraph,
+        start: NodeId,
+        end: NodeId,
+        departure: TimeOfDay,
+    ) -> Option<ShortestPath> {
+        assert_eq!(
+            graph.get_num_nodes(),
+            self.num_nodes,
+            "given graph has invalid node count"
+        );
+        self.clear_fwd();
+        self.update_node_fwd(start, WEIGHT_ZERO, INVALID_NODE, INVALID_EDGE);
+        self.heap_fwd
+            .push_or_decrease(HeapItem::new(WEIGHT_ZERO, start));
+        while let Some(curr) = self.heap_fwd.pop() {
+            if curr.node_id == end {
+                let mut nodes = vec![];
+                let mut node = end;
+                while node != INVALID_NODE {
+                    nodes.push(node);
+                    node = self.data_fwd[node.0].parent;
+                }
+                nodes.reverse();
+                let edge_data = PathCalculator::find_edge_data(graph.get_graph(), &nodes);
+                return Some(
+                    ShortestPath::new(start, end, curr.weight, nodes).with_edge_data(edge_data),
+                );
+            }
+            let out_edges = graph.get_graph().get_out_edges(curr.node_id);
+            for (index, arc) in out_edges.iter().enumerate() {
+                let edge_weight = graph.get_weight(curr.node_id, index, departure + curr.weight);
+                let weight = add_weights(curr.weight, edge_weight);
+                if weight < self.get_weight_fwd(arc.adj_node) {
+                    self.update_node_fwd(arc.adj_node, weight, curr.node_id, INVALID_EDGE);
+                    self.heap_fwd
+                        .push_or_decrease(HeapItem::new(weight, arc.adj_node));
+                }
+            }
+        }
+        None
+    }
+
+    /// Returns true if `accept_base_edge` accepts all the base edges of the given out-edge
+    #[cfg(feature = "std")]
+    fn accept_base_edges_fwd<F>(graph: &FastGraph, edge_id: EdgeId, accept_base_edge: &F) -> bool
+    where
+        F: Fn(&FastGraphEdge) -> bool,
+    {
+        let edge = &graph.edges_fwd[edge_id];
+        if !edge.is_shortcut() {
+            return accept_base_edge(edge);
+        }
+        PathCalculator::accept_base_edges_bwd(graph, edge.replaced_in_edge, accept_base_edge)
+            && PathCalculator::accept_base_edges_fwd(
+                graph,
+                edge.replaced_out_edge,
+                accept_base_edge,
+            )
+    }
+
+    /// Returns true if `accept_base_edge` accepts all the base edges of the given in-edge
+    #[cfg(feature = "std")]
+    fn accept_base_edges_bwd<F>(graph: &FastGraph, edge_id: EdgeId, accept_base_edge: &F) -> bool
+    where
+        F: Fn(&FastGraphEdge) -> bool,
+    {
+        let edge = &graph.edges_bwd[edge_id];
+        if !edge.is_shortcut() {
+            // in-edges point from the adj node to the base node
+            return accept_base_edge(&FastGraphEdge {
+                base_node: edge.adj_node,
+                adj_node: edge.base_node,
+                ..edge.clone()
+            });
+        }
+        PathCalculator::accept_base_edges_bwd(graph, edge.replaced_in_edge, accept_base_edge)
+            && PathCalculator::accept_base_edges_fwd(
+                graph,
+                edge.replaced_out_edge,
+                accept_base_edge,
+            )
+    }
+
+    /// Calculates the shortest path, but only uses edges that are accepted by `accept_edge`,
+    /// which is called with the edge id and a flag telling whether the edge is an out-edge (true)
+    /// or in-edge (false).
+    fn calc_path_with_edge_filter<F>(
+        &mut self,
+        graph: &FastGraph,
+        starts: &[(NodeId, Weight)],
+        ends: &[(NodeId, Weight)],
+        accept_edge: &mut F,
+    ) -> Option<ShortestPath>
+    where
+        F: FnMut(&FastGraph, EdgeId, bool) -> bool,
+    {
+        let (meeting_node, best_weight) = self.search(
+            graph,
+            starts,
+            ends,
+            accept_edge,
+            &PathCalculator::stored_weight,
+            0.0,
+        )?;
+        Some(self.create_path(graph, meeting_node, best_weight))
+    }
+
+    fn create_path(
+        &mut self,
+        graph: &FastGraph,
+        meeting_node: NodeId,
+        weight: Weight,
+    ) -> ShortestPath {
+        self.count_edge_usages(graph, meeting_node);
+        let (nodes, edge_data) = self.extract_nodes(graph, meeting_node);
+        assert!(!nodes.is_empty());
+        ShortestPath::new_with_meeting_node(
+            nodes[0],
+            nodes[nodes.len() - 1],
+            weight,
+            nodes,
+            meeting_node,
+        )
+        .with_edge_data(edge_data)
+        .with_weight_scale(graph.get_weight_scale())
+    }
+
+    /// Increases the usage counts of the edges of the path through the given meeting node, if
+    /// edge usage counting is enabled
+    fn count_edge_usages(&mut self, graph: &FastGraph, meeting_node: NodeId) {
+        let counts = match &mut self.edge_usage_counts {
+            Some(counts) => counts,
+            None => return,
+        };
+        let num_edges = graph.get_num_out_edges() + graph.get_num_in_edges();
+        if counts.len() < num_edges {
+            counts.resize(num_edges, 0);
+        }
+        let mut node = meeting_node;
+        while self.data_fwd[node.0].inc_edge != INVALID_EDGE {
+            counts[self.data_fwd[node.0].inc_edge] += 1;
+            node = self.data_fwd[node.0].parent;
+        }
+        node = meeting_node;
+        while self.data_bwd[node.0].inc_edge != INVALID_EDGE {
+            counts[graph.get_num_out_edges() + self.data_bwd[node.0].inc_edge] += 1;
+            node = self.data_bwd[node.0].parent;
+        }
+    }
+
+    /// Runs the bidirectional search and returns the meeting node and the weight of the shortest
+    /// path, if there is one. For `epsilon > 0` the search stops early and the returned weight
+    /// can be up to `1 + epsilon` times larger than the actual shortest path weight.
+    fn search<F, W>(
+        &mut self,
+        graph: &FastGraph,
+        starts: &[(NodeId, Weight)],
+        ends: &[(NodeId, Weight)],
+        accept_edge: &mut F,
+        adjust_weight: &W,
+        epsilon: f64,
+    ) -> Option<(NodeId, Weight)>
+    where
+        F: FnMut(&FastGraph, EdgeId, bool) -> bool,
+        W: Fn(&FastGraphEdge, Weight) -> Weight,
+    {
+        assert_eq!(
+            graph.get_num_nodes(),
+            self.num_nodes,
+            "given graph has invalid node count"
+        );
+        assert!(!starts.is_empty(), "there has to be at least one start");
+        assert!(!ends.is_empty(), "there has to be at least one end");
+        for (start_node, _) in starts {
+            assert!(start_node.0 < self.num_nodes, "invalid start node");
+        }
+        for (end_node, _) in ends {
+            assert!(end_node.0 < self.num_nodes, "invalid end node");
+        }
+        self.clear_fwd();
+        self.heap_bwd.clear();
+        self.valid_flags_bwd.invalidate_all();
+        self.stats = QueryStats::default();
+        self.equal_meeting_node_found = false;
+
+        let mut best_weight = WEIGHT_MAX;
+        let mut meeting_node = INVALID_NODE;
+        #[cfg(feature = "std")]
+        let mut iterations: usize = 0;
+
+        for (start_node, start_weight) in starts {
+            for (end_node, end_weight) in ends {
+                let weight = add_weights(*start_weight, *end_weight);
+                if *start_node == *end_node {
+                    self.check_equal_meeting_node(weight, *end_node, best_weight, meeting_node);
+                }
+                if *start_node == *end_node
+                    && PathCalculator::improves_meeting(
+                        weight,
+                        *end_node,
+                        best_weight,
+                        meeting_node,
+                    )
+                {
+                    best_weight = weight;
+                    meeting_node = *end_node;
+                }
+            }
+        }
+
+        for &(node, weight) in starts {
+            if weight < self.get_weight_fwd(node) {
+                // this is a bit of a hack, we store the start node as parent even though it is not
+                // the parent. this way we can easily obtain the target node when we unpack the path
+                // later
+                self.update_node_fwd(node, weight, node, INVALID_EDGE);
+                self.heap_fwd.push_or_decrease(HeapItem::new(weight, node));
+            }
+        }
+        for &(node, weight) in ends {
+            if weight < self.get_weight_bwd(node) {
+                // ... same here
+                self.update_node_bwd(node, weight, node, INVALID_EDGE);
+                self.heap_bwd.push_or_decrease(HeapItem::new(weight, node));
+            }
+        }
+
+        loop {
+            if self.heap_fwd.is_empty() && self.heap_bwd.is_empty() {
+                break;
+            }
+            // checking the time is relatively expensive, so we do not do this in every iteration
+            #[cfg(feature = "std")]
+            {
+                iterations += 1;
+                if let Some(deadline) = self.deadline {
+                    if iterations.is_multiple_of(DEADLINE_CHECK_INTERVAL)
+                        && Instant::now() >= deadline
+                    {
+                        self.deadline_exceeded = true;
+                        log::trace!(
+                            "query exceeded its deadline after settling {} nodes",
+                            self.stats.forward_settled + self.stats.backward_settled
+                        );
+                        return None;
+                    }
+                }
+            }
+            loop {
+                if self.heap_fwd.is_empty() {
+                    break;
+                }
+                let curr = self.heap_fwd.pop().unwrap();
+                if curr.weight > PathCalculator::stop_weight(best_weight, epsilon)
+                    || curr.weight > self.max_weight
+                {
+                    break;
+                }
+                self.stats.forward_settled += 1;
+                // stall on demand optimization
+                if self.stall_on_demand
+                    && self.is_stallable_fwd(graph, curr, accept_edge, adjust_weight)
+                {
+                    continue;
+                }
+                let begin = graph.begin_out_edges(curr.node_id);
+                let end = graph.end_out_edges(curr.node_id);
+                self.stats.edges_relaxed += end - begin;
+                for edge_id in begin..end {
+                    let adj = graph.edges_fwd[edge_id].adj_node;
+                    let edge = &graph.edges_fwd[edge_id];
+                    let weight = add_weights(curr.weight, adjust_weight(edge, edge.weight));
+                    if self.improves_fwd(adj, weight, curr) && accept_edge(graph, edge_id, true) {
+                        self.update_node_fwd(adj, weight, curr.node_id, edge_id);
+                        self.heap_fwd.push_or_decrease(HeapItem::new(weight, adj));
+                    }
+                }
+                let weight = add_weights(curr.weight, self.get_weight_bwd(curr.node_id));
+                self.check_equal_meeting_node(weight, curr.node_id, best_weight, meeting_node);
+                if PathCalculator::improves_meeting(weight, curr.node_id, best_weight, meeting_node)
+                {
+                    best_weight = weight;
+                    meeting_node = curr.node_id;
+                }
+                break;
+            }
+
+            loop {
+                if self.heap_bwd.is_empty() {
+                    break;
+                }
+                let curr = self.heap_bwd.pop().unwrap();
+                if curr.weight > PathCalculator::stop_weight(best_weight, epsilon)
+                    || curr.weight > self.max_weight
+                {
+                    break;
+                }
+                self.stats.backward_settled += 1;
+                // stall on demand optimization
+                if self.stall_on_demand
+                    && self.is_stallable_bwd(graph, curr, accept_edge, adjust_weight)
+                {
+                    continue;
+                }
+                let begin = graph.begin_in_edges(curr.node_id);
+                let end = graph.end_in_edges(curr.node_id);
+                self.stats.edges_relaxed += end - begin;
+                for edge_id in begin..end {
+                    let adj = graph.edges_bwd[edge_id].adj_node;
+                    let edge = &graph.edges_bwd[edge_id];
+                    let weight = add_weights(curr.weight, adjust_weight(edge, edge.weight));
+                    if self.improves_bwd(adj, weight, curr) && accept_edge(graph, edge_id, false) {
+                        self.update_node_bwd(adj, weight, curr.node_id, edge_id);
+                        self.heap_bwd.push_or_decrease(HeapItem::new(weight, adj));
+                    }
+                }
+                let weight = add_weights(curr.weight, self.get_weight_fwd(curr.node_id));
+                self.check_equal_meeting_node(weight, curr.node_id, best_weight, meeting_node);
+                if PathCalculator::improves_meeting(weight, curr.node_id, best_weight, meeting_node)
+                {
+                    best_weight = weight;
+                    meeting_node = curr.node_id;
+                }
+                break;
+            }
+        }
+
+        // the arguments are only evaluated if trace logging is enabled
+        log::trace!(
+            "query from {:?} to {:?}: settled nodes fwd: {}, bwd: {}, relaxed edges: {}, \
+             (meeting node, weight): {:?}",
+            starts,
+            ends,
+            self.stats.forward_settled,
+            self.stats.backward_settled,
+            self.stats.edges_relaxed,
+            (meeting_node != INVALID_NODE).then_some((meeting_node, best_weight))
+        );
+        if meeting_node == INVALID_NODE || best_weight > self.max_weight {
+            None
+        } else {
+            assert!(best_weight < WEIGHT_MAX);
+            Some((meeting_node, best_weight))
+        }
+    }
+
+    /// Calculates up to `limit` different shortest paths from `source` to `target`, i.e. paths
+    /// with distinct node sequences that all have the weight of the shortest path. Since there
+    /// can be exponentially many of them, only the first `limit` paths found are returned, in no
+    /// particular order. Only paths that visit every node at most once are considered, which
+    /// matters if there are cycles of zero weight edges. The result is empty if there is no path.
+    ///
+    /// The hierarchy only keeps one of multiple equally short paths, so this calculates the
+    /// weight of the shortest path using the hierarchy and then runs a forward and a backward
+    /// Dijkstra search on the original edges of the graph. An edge lies on a shortest path if the
+    /// weights to its source and from its target add up to the weight of the shortest path. Just
+    /// like the fallback search of `calc_path_avoiding` this takes O(edges) time, so it is much
+    /// slower than `calc_path`.
+    #[cfg(feature = "std")]
+    pub fn calc_all_shortest_paths(
+        &mut self,
+        graph: &FastGraph,
+        source: NodeId,
+        target: NodeId,
+        limit: usize,
+    ) -> Vec<ShortestPath> {
+        if limit == 0 {
+            return vec![];
+        }
+        let weight = match self.calc_path(graph, source, target) {
+            Some(path) => path.get_weight(),
+            None => return vec![],
+        };
+        let original_graph = PreparationGraph::from_fast_graph(graph);
+        self.calc_weights_on_original_graph(&original_graph, source, weight, true);
+        self.calc_weights_on_original_graph(&original_graph, target, weight, false);
+        let is_on_shortest_path = |calc: &PathCalculator, from: NodeId, arc: &Arc| {
+            calc.valid_flags_fwd.is_valid(from)
+                && calc.valid_flags_bwd.is_valid(arc.adj_node)
+                && add_weights(
+                    add_weights(calc.data_fwd[from.0].weight, arc.weight),
+                    calc.data_bwd[arc.adj_node.0].weight,
+                ) == weight
+        };
+        // we enumerate the paths along the edges on shortest paths using a depth-first search.
+        // these edges only form cycles if their weights are zero, but to be safe we never visit
+        // a node twice. the i-th entry of next_arcs is the index of the next out-edge of nodes[i]
+        // we need to look at.
+        let mut paths = vec![];
+        let mut nodes = vec![source];
+        let mut next_arcs = vec![0];
+        while let Some(&node) = nodes.last() {
+            if node == target {
+                let edge_data = PathCalculator::find_edge_data(&original_graph, &nodes);
+                paths.push(
+                    ShortestPath::new(source, target, weight, nodes.clone())
+                        .with_edge_data(edge_data)
+                        .with_weight_scale(graph.get_weight_scale()),
+                );
+                if paths.len() == limit {
+                    break;
+                }
+                nodes.pop();
+                next_arcs.pop();
+                continue;
+            }
+            let arcs = original_graph.get_out_edges(node);
+            let next_arc = next_arcs.last_mut().unwrap();
+            while *next_arc < arcs.len()
+                && (!is_on_shortest_path(self, node, &arcs[*next_arc])
+                    || nodes.contains(&arcs[*next_arc].adj_node))
+            {
+                *next_arc += 1;
+            }
+            if *next_arc < arcs.len() {
+                nodes.push(arcs[*next_arc].adj_node);
+                *next_arc += 1;
+                next_arcs.push(0);
+            } else {
+                nodes.pop();
+                next_arcs.pop();
+            }
+        }
+        paths
+    }
+
+    /// Runs Dijkstra's algorithm from (`fwd`) or to (`!fwd`) the given node on the given graph
+    /// and stores the weights of all nodes up to `max_weight` in `data_fwd` or `data_bwd`
+    #[cfg(feature = "std")]
+    fn calc_weights_on_original_graph(
+        &mut self,
+        graph: &PreparationGraph,
+        node: NodeId,
+        max_weight: Weight,
+        fwd: bool,
+    ) {
+        let (data, valid_flags, heap) = if fwd {
+            (
+                &mut self.data_fwd,
+                &mut self.valid_flags_fwd,
+                &mut self.heap_fwd,
+            )
+        } else {
+            (
+                &mut self.data_bwd,
+                &mut self.valid_flags_bwd,
+                &mut self.heap_bwd,
+            )
+        };
+        heap.clear();
+        valid_flags.invalidate_all();
+        valid_flags.set_valid(node);
+        data[node.0].weight = WEIGHT_ZERO;
+        heap.push_or_decrease(HeapItem::new(WEIGHT_ZERO, node));
+        while let Some(curr) = heap.pop() {
+            let arcs = if fwd {
+                graph.get_out_edges(curr.node_id)
+            } else {
+                graph.get_in_edges(curr.node_id)
+            };
+            for arc in arcs {
+                let weight = add_weights(curr.weight, arc.weight);
+                if weight <= max_weight
+                    && (!valid_flags.is_valid(arc.adj_node) || weight < data[arc.adj_node.0].weight)
+                {
+                    valid_flags.set_valid(arc.adj_node);
+                    data[arc.adj_node.0].weight = weight;
+                    heap.push_or_decrease(HeapItem::new(weight, arc.adj_node));
+                }
+            }
+        }
+    }
+
+    /// Calculates the shortest path from `start` to `end` that consists of at most `max_hops`
+    /// edges. The hops are counted on the original edges, i.e. after unpacking all shortcuts,
+    /// which means `max_hops` equals the maximum number of nodes on the path minus one.
+    ///
+    /// If the (unrestricted) shortest path does not exceed `max_hops` this is as fast as
+    /// `calc_path`. Otherwise we fall back to a hop-aware search on the original edges of the
+    /// graph, which does not benefit from the hierarchy and is considerably slower: it takes
+    /// O(edges) just to set up and its search space can grow up to O(nodes * max_hops). The
+    /// hop-aware search uses all edges of the input graph, including the ones that were replaced
+    /// by shortcuts during the preparation (see `FastGraph::get_replaced_edges`).
+    #[cfg(feature = "std")]
+    pub fn calc_path_max_hops(
+        &mut self,
+        graph: &FastGraph,
+        start: NodeId,
+        end: NodeId,
+        max_hops: usize,
+    ) -> Option<ShortestPath> {
+        let path = self.calc_path(graph, start, end)?;
+        if path.get_nodes().len() - 1 <= max_hops {
+            return Some(path);
+        }
+        // the shortest path has too many edges, so now we need to look for a path with more
+        // weight but less edges
+        let original_graph = PreparationGraph::from_fast_graph(graph);
+        PathCalculator::calc_path_max_hops_on_original_graph(&original_graph, start, end, max_hops)
+            .map(|p| p.with_weight_scale(graph.get_weight_scale()))
+    }
+
+    #[cfg(feature = "std")]
+    fn calc_path_max_hops_on_original_graph(
+        graph: &PreparationGraph,
+        start: NodeId,
+        end: NodeId,
+        max_hops: usize,
+    ) -> Option<ShortestPath> {
+        // Every node can be reached via multiple labels that differ in weight and number of hops.
+        // Labels are settled in order of increasing weight, so a label is dominated if we already
+        // settled a label with a smaller or equal number of hops at the same node.
+        let mut labels = vec![HopLabel {
+            node: start,
+            parent: INVALID_LABEL,
+        }];
+        let mut min_settled_hops = vec![usize::MAX; graph.get_num_nodes()];
+        let mut heap = BinaryHeap::new();
+        heap.push(Reverse((WEIGHT_ZERO, 0, 0)));
+        while let Some(Reverse((weight, hops, label))) = heap.pop() {
+            let node = labels[label].node;
+            if hops >= min_settled_hops[node.0] {
+                continue;
+            }
+            min_settled_hops[node.0] = hops;
+            if node == end {
+                let mut nodes = vec![];
+                let mut l = label;
+                while l != INVALID_LABEL {
+                    nodes.push(labels[l].node);
+                    l = labels[l].parent;
+                }
+                nodes.reverse();
+                let edge_data = PathCalculator::find_edge_data(graph, &nodes);
+                return Some(
+                    ShortestPath::new(start, end, weight, nodes).with_edge_data(edge_data),
+                );
+            }
+            if hops == max_hops {
+                continue;
+            }
+            for arc in graph.get_out_edges(node) {
+                if hops + 1 >= min_settled_hops[arc.adj_node.0] {
+                    continue;
+                }
+                labels.push(HopLabel {
+                    node: arc.adj_node,
+                    parent: label,
+                });
+                heap.push(Reverse((
+                    add_weights(weight, arc.weight),
+                    hops + 1,
+                    labels.len() - 1,
+                )));
+            }
+        }
+        None
+    }
+
+    /// The edge weight used by the search unless it is adjusted, see `calc_path_with_cost_adjust`
+    fn stored_weight(_edge: &FastGraphEdge, weight: Weight) -> Weight {
+        weight
+    }
+
+    fn is_stallable_fwd<F, W>(
+        &self,
+        graph: &FastGraph,
+        curr: HeapItem,
+        accept_edge: &mut F,
+        adjust_weight: &W,
+    ) -> bool
+    where
+        F: FnMut(&FastGraph, EdgeId, bool) -> bool,
+        W: Fn(&FastGraphEdge, Weight) -> Weight,
+    {
+        let begin = graph.begin_in_edges(curr.node_id);
+        let end = graph.end_in_edges(curr.node_id);
+        for edge_id in begin..end {
+            let adj = graph.edges_bwd[edge_id].adj_node;
+            let adj_weight = self.get_weight_fwd(adj);
+            if is_infinite(adj_weight) {
+                continue;
+            }
+            let edge = &graph.edges_bwd[edge_id];
+            if add_weights(adj_weight, adjust_weight(edge, edge.weight)) < curr.weight
+                && accept_edge(graph, edge_id, false)
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    fn is_stallable_bwd<F, W>(
+        &self,
+        graph: &FastGraph,
+        curr: HeapItem,
+        accept_edge: &mut F,
+        adjust_weight: &W,
+    ) -> bool
+    where
+        F: FnMut(&FastGraph, EdgeId, bool) -> bool,
+        W: Fn(&FastGraphEdge, Weight) -> Weight,
+    {
+        let begin = graph.begin_out_edges(curr.node_id);
+        let end = graph.end_out_edges(curr.node_id);
+        for edge_id in begin..end {
+            let adj = graph.edges_fwd[edge_id].adj_node;
+            let adj_weight = self.get_weight_bwd(adj);
+            if is_infinite(adj_weight) {
+                continue;
+            }
+            let edge = &graph.edges_fwd[edge_id];
+            if add_weights(adj_weight, adjust_weight(edge, edge.weight)) < curr.weight
+                && accept_edge(graph, edge_id, true)
+            {
+                return true;
+            }
+        }
+        false
+    }
+
+    /// Looks up the user data of the edges between consecutive nodes of a path found on the
+    /// original edges. There is only one original edge between any two nodes.
+    #[cfg(feature = "std")]
+    fn find_edge_data(graph: &PreparationGraph, nodes: &[NodeId]) -> Vec<EdgeData> {
+        nodes
+            .windows(2)
+            .map(|w| {
+                graph
+                    .get_out_edges(w[0])
+                    .iter()
+                    .find(|arc| arc.adj_node == w[1])
+                    .map_or(INVALID_EDGE_DATA, |arc| arc.data)
+            })
+            .collect()
+    }
+
+    /// Returns the nodes of the shortest path along with the user data of its edges
+    fn extract_nodes(
+        &mut self,
+        graph: &FastGraph,
+        meeting_node: NodeId,
+    ) -> (Vec<NodeId>, Vec<EdgeData>) {
+        assert_ne!(meeting_node, INVALID_NODE);
+        assert!(self.valid_flags_fwd.is_valid(meeting_node));
+        assert!(self.valid_flags_bwd.is_valid(meeting_node));
+        let mut unpack_cache = self.unpack_cache.take();
+        let mut result = Vec::new();
+        let mut edge_data = Vec::new();
+        let mut zero_weight_edge_found = false;
+        let mut handle_edge = |from, data, weight| {
+            result.push(from);
+            edge_data.push(data);
+            zero_weight_edge_found |= weight == WEIGHT_ZERO;
+        };
+        let mut node = meeting_node;
+        while self.data_fwd[node.0].inc_edge != INVALID_EDGE {
+            PathCalculator::unpack_edges_cached(
+                graph,
+                &mut unpack_cache,
+                self.data_fwd[node.0].inc_edge,
+                true,
+                true,
+                &mut handle_edge,
             );
-            PathCalculator::unpack_bwd(
+            node = self.data_fwd[node.0].parent;
+        }
+        result.reverse();
+        edge_data.reverse();
+        let mut handle_edge = |from, data, weight| {
+            result.push(from);
+            edge_data.push(data);
+            zero_weight_edge_found |= weight == WEIGHT_ZERO;
+        };
+        node = meeting_node;
+        while self.data_bwd[node.0].inc_edge != INVALID_EDGE {
+            PathCalculator::unpack_edges_cached(
                 graph,
-                nodes,
-                graph.edges_fwd[edge_id].replaced_in_edge,
-                reverse,
+                &mut unpack_cache,
+                self.data_bwd[node.0].inc_edge,
+                false,
+                false,
+                &mut handle_edge,
             );
+            node = self.data_bwd[node.0].parent;
+        }
+        // we stored the target node as 'parent' of the root of the shortest tree, so we can use it
+        // here
+        result.push(node);
+        self.unpack_cache = unpack_cache;
+        if zero_weight_edge_found {
+            PathCalculator::remove_cycles(&mut result, &mut edge_data);
+        }
+        (result, edge_data)
+    }
+
+    /// Removes the cycles from the given path, where the i-th entry of `edge_data` belongs to the
+    /// edge from the i-th to the (i+1)-th node. The path is a shortest path, so its cycles can only
+    /// consist of zero weight edges and removing them does not change its weight. Such cycles can
+    /// occur when the forward and backward searches both pass the same zero weight edges.
+    fn remove_cycles(nodes: &mut Vec<NodeId>, edge_data: &mut Vec<EdgeData>) {
+        let mut positions = BTreeMap::new();
+        let mut result_nodes = Vec::with_capacity(nodes.len());
+        let mut result_edge_data = Vec::with_capacity(edge_data.len());
+        for (i, &node) in nodes.iter().enumerate() {
+            if let Some(&position) = positions.get(&node) {
+                for removed in result_nodes.drain(position + 1..) {
+                    positions.remove(&removed);
+                }
+                result_edge_data.truncate(position);
+            } else {
+                if i > 0 {
+                    result_edge_data.push(edge_data[i - 1]);
+                }
+                positions.insert(node, result_nodes.len());
+                result_nodes.push(node);
+            }
+        }
+        *nodes = result_nodes;
+        *edge_data = result_edge_data;
+    }
+
+    pub fn unpack_fwd(graph: &FastGraph, nodes: &mut Vec<NodeId>, edge_id: EdgeId, reverse: bool) {
+        PathCalculator::unpack_fwd_edges(graph, edge_id, reverse, &mut |from, _data, _weight| {
+            nodes.push(from)
+        });
+    }
+
+    pub fn unpack_bwd(graph: &FastGraph, nodes: &mut Vec<NodeId>, edge_id: EdgeId, reverse: bool) {
+        PathCalculator::unpack_bwd_edges(graph, edge_id, reverse, &mut |from, _data, _weight| {
+            nodes.push(from)
+        });
+    }
+
+    /// Like `unpack_fwd_edges` for an out-edge (`fwd`) or `unpack_bwd_edges` for an in-edge, but
+    /// shortcuts are taken from the given cache if possible and added to it otherwise
+    fn unpack_edges_cached<F>(
+        graph: &FastGraph,
+        cache: &mut Option<UnpackCache>,
+        edge_id: EdgeId,
+        fwd: bool,
+        reverse: bool,
+        handle_edge: &mut F,
+    ) where
+        F: FnMut(NodeId, EdgeData, Weight),
+    {
+        let is_shortcut = if fwd {
+            graph.edges_fwd[edge_id].is_shortcut()
         } else {
-            PathCalculator::unpack_bwd(
-                graph,
-                nodes,
-                graph.edges_fwd[edge_id].replaced_in_edge,
-                reverse,
+            graph.edges_bwd[edge_id].is_shortcut()
+        };
+        let cache = match cache {
+            Some(cache) if is_shortcut => cache,
+            _ => {
+                if fwd {
+                    PathCalculator::unpack_fwd_edges(graph, edge_id, reverse, handle_edge);
+                } else {
+                    PathCalculator::unpack_bwd_edges(graph, edge_id, reverse, handle_edge);
+                }
+                return;
+            }
+        };
+        let replay = |edges: &[UnpackedEdge], handle_edge: &mut F| {
+            if reverse {
+                edges
+                    .iter()
+                    .rev()
+                    .for_each(|&(from, data, weight)| handle_edge(from, data, weight));
+            } else {
+                edges
+                    .iter()
+                    .for_each(|&(from, data, weight)| handle_edge(from, data, weight));
+            }
+        };
+        if let Some(edges) = cache.get(edge_id, fwd) {
+            replay(edges, handle_edge);
+            return;
+        }
+        let mut edges = vec![];
+        let mut add_edge = |from, data, weight| edges.push((from, data, weight));
+        if fwd {
+            PathCalculator::unpack_fwd_edges(graph, edge_id, false, &mut add_edge);
+        } else {
+            PathCalculator::unpack_bwd_edges(graph, edge_id, false, &mut add_edge);
+        }
+        replay(&edges, handle_edge);
+        cache.insert(edge_id, fwd, edges);
+    }
+
+    /// Recursively unpacks the given out-edge and calls `handle_edge` with the source node, the
+    /// user data and the weight of every original edge, in the order of the path (or in reverse
+    /// order if `reverse` is set)
+    fn unpack_fwd_edges<F>(graph: &FastGraph, edge_id: EdgeId, reverse: bool, handle_edge: &mut F)
+    where
+        F: FnMut(NodeId, EdgeData, Weight),
+    {
+        let edge = &graph.edges_fwd[edge_id];
+        if !edge.is_shortcut() {
+            handle_edge(edge.base_node, edge.data, edge.weight);
+            return;
+        }
+        if reverse {
+            PathCalculator::unpack_fwd_edges(graph, edge.replaced_out_edge, reverse, handle_edge);
+            PathCalculator::unpack_bwd_edges(graph, edge.replaced_in_edge, reverse, handle_edge);
+        } else {
+            PathCalculator::unpack_bwd_edges(graph, edge.replaced_in_edge, reverse, handle_edge);
+            PathCalculator::unpack_fwd_edges(graph, edge.replaced_out_edge, reverse, handle_edge);
+        }
+    }
+
+    /// Like `unpack_fwd_edges`, but for an in-edge
+    fn unpack_bwd_edges<F>(graph: &FastGraph, edge_id: EdgeId, reverse: bool, handle_edge: &mut F)
+    where
+        F: FnMut(NodeId, EdgeData, Weight),
+    {
+        let edge = &graph.edges_bwd[edge_id];
+        if !edge.is_shortcut() {
+            // original edges stored as in-edges point from the adj_node to the base_node
+            handle_edge(edge.adj_node, edge.data, edge.weight);
+            return;
+        }
+        if reverse {
+            PathCalculator::unpack_fwd_edges(graph, edge.replaced_out_edge, reverse, handle_edge);
+            PathCalculator::unpack_bwd_edges(graph, edge.replaced_in_edge, reverse, handle_edge);
+        } else {
+            PathCalculator::unpack_bwd_edges(graph, edge.replaced_in_edge, reverse, handle_edge);
+            PathCalculator::unpack_fwd_edges(graph, edge.replaced_out_edge, reverse, handle_edge);
+        }
+    }
+
+    /// Panics if the given path does not consist of original edges of the graph or its weight is
+    /// not the sum of their weights, see `calc_path_checked`
+    #[cfg(debug_assertions)]
+    fn check_path(graph: &FastGraph, path: &ShortestPath) {
+        let mut weight = WEIGHT_ZERO;
+        for pair in path.get_nodes().windows(2) {
+            let edge_weight = PathCalculator::original_edge_weight(graph, pair[0], pair[1]);
+            assert!(
+                edge_weight.is_some(),
+                "There is no original edge from {} to {} for the path from {} to {}",
+                pair[0],
+                pair[1],
+                path.get_source(),
+                path.get_target()
             );
-            PathCalculator::unpack_fwd(
-                graph,
-                nodes,
-                graph.edges_fwd[edge_id].replaced_out_edge,
-                reverse,
+            weight = add_weights(weight, edge_weight.unwrap());
+        }
+        assert_eq!(
+            weight,
+            path.get_weight(),
+            "The weight of the path from {} to {} does not match the weight of its edges",
+            path.get_source(),
+            path.get_target()
+        );
+    }
+
+    /// Returns the smallest weight of the original edges from `from` to `to`. Such edges are
+    /// stored either as out-edge of `from` or as in-edge of `to`, depending on their ranks.
+    #[cfg(debug_assertions)]
+    fn original_edge_weight(graph: &FastGraph, from: NodeId, to: NodeId) -> Option<Weight> {
+        let out_edges = (graph.begin_out_edges(from)..graph.end_out_edges(from))
+            .map(|edge_id| &graph.edges_fwd[edge_id])
+            .filter(|edge| edge.adj_node == to);
+        let in_edges = (graph.begin_in_edges(to)..graph.end_in_edges(to))
+            .map(|edge_id| &graph.edges_bwd[edge_id])
+            .filter(|edge| edge.adj_node == from);
+        out_edges
+            .chain(in_edges)
+            .filter(|edge| !edge.is_shortcut())
+            .map(|edge| edge.weight)
+            .min()
+    }
+
+    fn update_node_fwd(&mut self, node: NodeId, weight: Weight, parent: NodeId, inc_edge: EdgeId) {
+        self.valid_flags_fwd.set_valid(node);
+        self.data_fwd[node.0].weight = weight;
+        self.data_fwd[node.0].parent = parent;
+        self.data_fwd[node.0].inc_edge = inc_edge;
+    }
+
+    fn update_node_bwd(&mut self, node: NodeId, weight: Weight, parent: NodeId, inc_edge: EdgeId) {
+        self.valid_flags_bwd.set_valid(node);
+        self.data_bwd[node.0].weight = weight;
+        self.data_bwd[node.0].parent = parent;
+        self.data_bwd[node.0].inc_edge = inc_edge;
+    }
+
+    /// Returns true if reaching `node` with `weight` via the settled node `parent` is better than
+    /// what the forward search found so far. At equal weight the lower parent id wins, see
+    /// `calc_path`. We only do this for edges with positive weight, because otherwise `node` might
+    /// already be settled and changing its parent could create a cycle.
+    fn improves_fwd(&self, node: NodeId, weight: Weight, parent: HeapItem) -> bool {
+        let curr_weight = self.get_weight_fwd(node);
+        weight < curr_weight
+            || (weight == curr_weight
+                && weight > parent.weight
+                && !is_infinite(weight)
+                && parent.node_id < self.data_fwd[node.0].parent)
+    }
+
+    fn improves_bwd(&self, node: NodeId, weight: Weight, parent: HeapItem) -> bool {
+        let curr_weight = self.get_weight_bwd(node);
+        weight < curr_weight
+            || (weight == curr_weight
+                && weight > parent.weight
+                && !is_infinite(weight)
+                && parent.node_id < self.data_bwd[node.0].parent)
+    }
+
+    /// Returns true if `node` is a better meeting node than `meeting_node`. At equal weight the
+    /// lower node id wins, see `calc_path`.
+    fn improves_meeting(
+        weight: Weight,
+        node: NodeId,
+        best_weight: Weight,
+        meeting_node: NodeId,
+    ) -> bool {
+        !is_infinite(weight) && (weight, node) < (best_weight, meeting_node)
+    }
+
+    /// Keeps track of whether there is another meeting node with the same weight as the best
+    /// meeting node found so far. Must be called before the best meeting node is updated.
+    fn check_equal_meeting_node(
+        &mut self,
+        weight: Weight,
+        node: NodeId,
+        best_weight: Weight,
+        meeting_node: NodeId,
+    ) {
+        if is_infinite(weight) {
+            return;
+        }
+        if weight < best_weight {
+            self.equal_meeting_node_found = false;
+        } else if weight == best_weight && node != meeting_node {
+            self.equal_meeting_node_found = true;
+        }
+    }
+
+    fn get_weight_fwd(&self, node: NodeId) -> Weight {
+        if self.valid_flags_fwd.is_valid(node) {
+            self.data_fwd[node.0].weight
+        } else {
+            WEIGHT_MAX
+        }
+    }
+
+    fn get_weight_bwd(&self, node: NodeId) -> Weight {
+        if self.valid_flags_bwd.is_valid(node) {
+            self.data_bwd[node.0].weight
+        } else {
+            WEIGHT_MAX
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+const DEADLINE_CHECK_INTERVAL: usize = 64;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum QueryError {
+    /// The query was aborted, because it did not finish before the given deadline
+    DeadlineExceeded,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryError::DeadlineExceeded => {
+                write!(f, "The query did not finish before the deadline")
+            }
+        }
+    }
+}
+
+impl Error for QueryError {}
+
+/// Yields the nodes settled by the search started in `PathCalculator::reachable_iter`
+#[cfg(feature = "std")]
+struct ReachableIter<'a> {
+    calc: &'a mut PathCalculator,
+    graph: &'a FastGraph,
+    max_weight: Weight,
+}
+
+#[cfg(feature = "std")]
+impl Iterator for ReachableIter<'_> {
+    type Item = (NodeId, Weight);
+
+    fn next(&mut self) -> Option<(NodeId, Weight)> {
+        let curr = self.calc.heap_fwd.pop()?;
+        let graph = self.graph;
+        let up_edges = (graph.begin_out_edges(curr.node_id)..graph.end_out_edges(curr.node_id))
+            .map(|edge_id| &graph.edges_fwd[edge_id]);
+        let down_edges = graph
+            .get_down_edges(curr.node_id)
+            .iter()
+            .map(|&edge_id| &graph.edges_bwd[edge_id]);
+        for (adj, edge_weight) in up_edges
+            .map(|e| (e.adj_node, e.weight))
+            .chain(down_edges.map(|e| (e.base_node, e.weight)))
+        {
+            let weight = add_weights(curr.weight, edge_weight);
+            if weight <= self.max_weight && weight < self.calc.get_weight_fwd(adj) {
+                self.calc
+                    .update_node_fwd(adj, weight, curr.node_id, INVALID_EDGE);
+                self.calc
+                    .heap_fwd
+                    .push_or_decrease(HeapItem::new(weight, adj));
+            }
+        }
+        Some((curr.node_id, curr.weight))
+    }
+}
+
+struct Data {
+    weight: Weight,
+    parent: NodeId,
+    inc_edge: usize,
+}
+
+impl Data {
+    fn new() -> Self {
+        Data {
+            weight: WEIGHT_MAX,
+            parent: INVALID_NODE,
+            inc_edge: INVALID_EDGE,
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+const INVALID_LABEL: usize = usize::MAX;
+
+#[cfg(feature = "std")]
+struct HopLabel {
+    node: NodeId,
+    parent: usize,
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use crate::fast_graph::FastGraphEdge;
+    use crate::input_graph::InputGraph;
+    use crate::{calc_path, prepare, prepare_with_order};
+    use std::sync::Mutex;
+    use std::thread::{self, ThreadId};
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn unpack_fwd_single() {
+        // 0 -> 1
+        let mut g = FastGraph::new(2);
+        g.edges_fwd.push(FastGraphEdge::new(
+            NodeId(0),
+            NodeId(1),
+            Weight(3),
+            3.0,
+            INVALID_EDGE,
+            INVALID_EDGE,
+        ));
+        let mut nodes = vec![];
+        PathCalculator::unpack_fwd(&g, &mut nodes, 0, false);
+        assert_eq!(nodes, vec![NodeId(0)]);
+    }
+
+    #[test]
+    fn unpack_fwd_simple() {
+        // 0 -> 1 -> 2
+        let mut g = FastGraph::new(3);
+        g.edges_fwd.push(FastGraphEdge::new(
+            NodeId(0),
+            NodeId(1),
+            Weight(2),
+            2.0,
+            INVALID_EDGE,
+            INVALID_EDGE,
+        ));
+        g.edges_fwd.push(FastGraphEdge::new(
+            NodeId(0),
+            NodeId(2),
+            Weight(5),
+            5.0,
+            0,
+            0,
+        ));
+        g.edges_bwd.push(FastGraphEdge::new(
+            NodeId(2),
+            NodeId(1),
+            Weight(3),
+            3.0,
+            INVALID_EDGE,
+            INVALID_EDGE,
+        ));
+        g.first_edge_ids_fwd = vec![0, 2, 0, 0];
+        let mut nodes = vec![];
+        PathCalculator::unpack_fwd(&g, &mut nodes, 1, false);
+        assert_eq!(nodes, vec![NodeId(1), NodeId(0)]);
+    }
+
+    #[test]
+    fn edge_data() {
+        // 0 -> 1 -> 2 -> 3
+        // |         |
+        // 4 -> 5 -> 6
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(1), 1.0, 10);
+        g.add_edge_with_data(NodeId(1), NodeId(2), Weight(1), 1.0, 12);
+        g.add_edge_with_data(NodeId(2), NodeId(3), Weight(1), 1.0, 23);
+        g.add_edge_with_data(NodeId(0), NodeId(4), Weight(2), 2.0, 4);
+        g.add_edge_with_data(NodeId(4), NodeId(5), Weight(2), 2.0, 45);
+        g.add_edge(NodeId(5), NodeId(6), Weight(2), 2.0);
+        g.add_edge_with_data(NodeId(6), NodeId(2), Weight(2), 2.0, 62);
+        g.freeze();
+        let fast_graph = prepare_with_order(
+            &g,
+            &[
+                NodeId(1),
+                NodeId(5),
+                NodeId(4),
+                NodeId(6),
+                NodeId(0),
+                NodeId(3),
+                NodeId(2),
+            ],
+        )
+        .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc.calc_path(&fast_graph, NodeId(0), NodeId(3)).unwrap();
+        assert_eq!(&vec![10, 12, 23], path.get_edge_data());
+        let path = calc.calc_path(&fast_graph, NodeId(3), NodeId(3)).unwrap();
+        assert!(path.get_edge_data().is_empty());
+        // the fallback search yields the edge data as well
+        let blocked: HashSet<NodeId> = [NodeId(1)].iter().cloned().collect();
+        let path = calc
+            .calc_path_avoiding(&fast_graph, NodeId(0), NodeId(3), &blocked)
+            .unwrap();
+        assert_eq!(
+            &vec![
+                NodeId(0),
+                NodeId(4),
+                NodeId(5),
+                NodeId(6),
+                NodeId(2),
+                NodeId(3)
+            ],
+            path.get_nodes()
+        );
+        assert_eq!(
+            &vec![4, 45, INVALID_EDGE_DATA, 62, 23],
+            path.get_edge_data()
+        );
+    }
+
+    #[test]
+    fn calc_path_at() {
+        // 0 -> 1 -> 3
+        //  \-> 2 ->/
+        // the edge 0->1 is congested between 10 and 20
+        let mut g = InputGraph::new();
+        g.add_edge_profile(
+            NodeId(0),
+            NodeId(1),
+            vec![
+                (Weight(5), Weight(2)),
+                (Weight(10), Weight(12)),
+                (Weight(20), Weight(12)),
+                (Weight(30), Weight(2)),
+            ],
+        );
+        g.add_edge(NodeId(1), NodeId(3), Weight(2), 2.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(3), 3.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 3.0);
+        g.freeze();
+        let td_graph = TimeDependentGraph::new(&g);
+        let mut calc = PathCalculator::new(td_graph.get_num_nodes());
+        let path = calc
+            .calc_path_at(&td_graph, NodeId(0), NodeId(3), Weight(0))
+            .unwrap();
+        assert_eq!(&vec![NodeId(0), NodeId(1), NodeId(3)], path.get_nodes());
+        assert_eq!(Weight(4), path.get_weight());
+        let path = calc
+            .calc_path_at(&td_graph, NodeId(0), NodeId(3), Weight(15))
+            .unwrap();
+        assert_eq!(&vec![NodeId(0), NodeId(2), NodeId(3)], path.get_nodes());
+        assert_eq!(Weight(6), path.get_weight());
+        // at time 7 the weight of 0->1 is 6, so the path via node 1 takes 8
+        assert_eq!(
+            Weight(6),
+            calc.calc_path_at(&td_graph, NodeId(0), NodeId(3), Weight(7))
+                .unwrap()
+                .get_weight()
+        );
+        assert_eq!(
+            Weight(4),
+            calc.calc_path_at(&td_graph, NodeId(0), NodeId(1), Weight(6))
+                .unwrap()
+                .get_weight()
+        );
+        assert_eq!(
+            None,
+            calc.calc_path_at(&td_graph, NodeId(3), NodeId(0), Weight(0))
+        );
+        // without departure time the edge gets the minimum weight of its profile
+        let fast_graph = prepare(&g);
+        assert_eq!(
+            Weight(4),
+            calc_path(&fast_graph, NodeId(0), NodeId(3))
+                .unwrap()
+                .get_weight()
+        );
+    }
+
+    #[test]
+    fn calc_path_with_deadline() {
+        // 0 -> 1 -> 2 -> ... -> 299
+        let mut g = InputGraph::new();
+        for i in 0..299 {
+            g.add_edge(NodeId(i), NodeId(i + 1), Weight(1), 1.0);
+        }
+        g.freeze();
+        // contracting the nodes in this order yields no shortcuts, so the search is long
+        let order: Vec<NodeId> = (0..300).map(NodeId).collect();
+        let fast_graph = prepare_with_order(&g, &order).unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let far_future = Instant::now() + Duration::from_secs(3600);
+        let path = calc
+            .calc_path_with_deadline(&fast_graph, NodeId(0), NodeId(299), far_future)
+            .unwrap()
+            .unwrap();
+        assert_eq!(Weight(299), path.get_weight());
+        assert_eq!(
+            Ok(None),
+            calc.calc_path_with_deadline(&fast_graph, NodeId(299), NodeId(0), far_future)
+        );
+        assert_eq!(
+            Err(QueryError::DeadlineExceeded),
+            calc.calc_path_with_deadline(&fast_graph, NodeId(0), NodeId(299), Instant::now())
+        );
+        // the deadline does not affect later queries
+        assert_eq!(
+            path,
+            calc.calc_path(&fast_graph, NodeId(0), NodeId(299)).unwrap()
+        );
+    }
+
+    #[test]
+    fn calc_path_coords() {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        g.freeze();
+        let mut fast_graph = prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_eq!(
+            None,
+            calc.calc_path_coords(&fast_graph, (52.0, 13.0), (52.0, 13.2))
+        );
+        fast_graph
+            .set_coordinates(vec![(52.0, 13.0), (52.0, 13.1), (52.0, 13.2)])
+            .unwrap();
+        let path = calc
+            .calc_path_coords(&fast_graph, (52.01, 13.01), (51.99, 13.21))
+            .unwrap();
+        assert_eq!(&vec![NodeId(0), NodeId(1), NodeId(2)], path.get_nodes());
+        assert_eq!(Weight(7), path.get_weight());
+        assert_eq!(
+            None,
+            calc.calc_path_coords(&fast_graph, (52.0, 13.2), (52.0, 13.0))
+        );
+    }
+
+    #[test]
+    fn warmup() {
+        // 0 -> 1 -> 2
+        // |         |
+        // 3 ------> 4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(4), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(2), 2.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(2), 2.0);
+        g.freeze();
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(1), NodeId(2), NodeId(3), NodeId(0), NodeId(4)])
+                .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        calc.warmup();
+        let path = calc.calc_path(&fast_graph, NodeId(0), NodeId(4)).unwrap();
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(4)],
+            path.get_nodes()
+        );
+        assert_eq!(Weight(3), path.get_weight());
+        calc.warmup();
+        assert_eq!(
+            path,
+            calc.calc_path(&fast_graph, NodeId(0), NodeId(4)).unwrap()
+        );
+        assert_eq!(None, calc.calc_path(&fast_graph, NodeId(4), NodeId(0)));
+        calc.warmup();
+        calc.warmup();
+        assert_eq!(
+            path,
+            calc.calc_path(&fast_graph, NodeId(0), NodeId(4)).unwrap()
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn calc_to_without_source() {
+        let fast_graph = FastGraph::new(2);
+        PathCalculator::new(2).calc_to(&fast_graph, NodeId(1));
+    }
+
+    #[test]
+    fn last_query_stats() {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.freeze();
+        let fast_graph = prepare_with_order(&g, &[NodeId(0), NodeId(2), NodeId(1)]).unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_eq!(QueryStats::default(), calc.last_query_stats());
+        // node 1 has the highest rank, so both searches settle their start node and node 1
+        calc.calc_path(&fast_graph, NodeId(0), NodeId(2)).unwrap();
+        let stats = calc.last_query_stats();
+        assert_eq!(create_stats(2, 2, 2), stats);
+        // the counters are reset for every query
+        calc.calc_path(&fast_graph, NodeId(0), NodeId(2)).unwrap();
+        assert_eq!(stats, calc.last_query_stats());
+        calc.calc_path(&fast_graph, NodeId(1), NodeId(1)).unwrap();
+        assert_eq!(create_stats(1, 1, 0), calc.last_query_stats());
+        calc.set_source(&fast_graph, NodeId(0));
+        assert_eq!(create_stats(2, 0, 1), calc.last_query_stats());
+        // the forward search is reused, so it is not counted again
+        calc.calc_to(&fast_graph, NodeId(2)).unwrap();
+        assert_eq!(create_stats(0, 2, 1), calc.last_query_stats());
+    }
+
+    fn create_stats(
+        forward_settled: usize,
+        backward_settled: usize,
+        edges_relaxed: usize,
+    ) -> QueryStats {
+        QueryStats {
+            forward_settled,
+            backward_settled,
+            edges_relaxed,
+        }
+    }
+
+    #[test]
+    fn reachable_iter() {
+        // 0 -> 1 -> 2 -> 3
+        //      |
+        //      4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 2.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(4), Weight(4), 4.0);
+        g.freeze();
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(3), NodeId(1), NodeId(4), NodeId(0), NodeId(2)])
+                .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let reachable: Vec<(NodeId, Weight)> = calc
+            .reachable_iter(&fast_graph, NodeId(0), Weight(5))
+            .collect();
+        assert_eq!(
+            vec![
+                (NodeId(0), Weight(0)),
+                (NodeId(1), Weight(1)),
+                (NodeId(2), Weight(3)),
+                (NodeId(4), Weight(5))
+            ],
+            reachable
+        );
+        // the caller can stop early
+        let first: Vec<(NodeId, Weight)> = calc
+            .reachable_iter(&fast_graph, NodeId(0), Weight(100))
+            .take(2)
+            .collect();
+        assert_eq!(vec![(NodeId(0), Weight(0)), (NodeId(1), Weight(1))], first);
+        assert_eq!(
+            vec![(NodeId(2), Weight(0)), (NodeId(3), Weight(3))],
+            calc.reachable_iter(&fast_graph, NodeId(2), WEIGHT_MAX)
+                .collect::<Vec<_>>()
+        );
+        // the calculator can still be used for other queries afterwards
+        assert_eq!(
+            Weight(6),
+            calc.calc_path(&fast_graph, NodeId(0), NodeId(3))
+                .unwrap()
+                .get_weight()
+        );
+    }
+
+    #[test]
+    fn calc_path_compressed() {
+        // 0 -> 1 -> 2 -> 3
+        //      |
+        //      4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        // contracting node 1 and 2 yields the shortcuts 0->2 and 0->3
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(1), NodeId(2), NodeId(0), NodeId(3), NodeId(4)])
+                .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_eq!(
+            Some(vec![NodeId(0), NodeId(3)]),
+            calc.calc_path_compressed(&fast_graph, NodeId(0), NodeId(3))
+        );
+        assert_eq!(
+            Some(vec![NodeId(0), NodeId(4)]),
+            calc.calc_path_compressed(&fast_graph, NodeId(0), NodeId(4))
+        );
+        assert_eq!(
+            Some(vec![NodeId(1), NodeId(2), NodeId(3)]),
+            calc.calc_path_compressed(&fast_graph, NodeId(1), NodeId(3))
+        );
+        assert_eq!(
+            Some(vec![NodeId(2)]),
+            calc.calc_path_compressed(&fast_graph, NodeId(2), NodeId(2))
+        );
+        assert_eq!(
+            None,
+            calc.calc_path_compressed(&fast_graph, NodeId(3), NodeId(0))
+        );
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+            calc.calc_path(&fast_graph, NodeId(0), NodeId(3))
+                .unwrap()
+                .get_nodes()
+        );
+    }
+
+    #[test]
+    fn calc_all_shortest_paths() {
+        //   1
+        //  / \
+        // 0---3 -> 4
+        //  \ /
+        //   2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(2), 2.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let paths = calc.calc_all_shortest_paths(&fast_graph, NodeId(0), NodeId(4), 10);
+        let mut nodes: Vec<Vec<NodeId>> = paths.iter().map(|p| p.get_nodes().clone()).collect();
+        nodes.sort();
+        assert_eq!(
+            vec![
+                vec![NodeId(0), NodeId(1), NodeId(3), NodeId(4)],
+                vec![NodeId(0), NodeId(2), NodeId(3), NodeId(4)],
+                vec![NodeId(0), NodeId(3), NodeId(4)]
+            ],
+            nodes
+        );
+        assert!(paths.iter().all(|p| p.get_weight() == Weight(3)));
+        assert_eq!(
+            2,
+            calc.calc_all_shortest_paths(&fast_graph, NodeId(0), NodeId(4), 2)
+                .len()
+        );
+        assert!(calc
+            .calc_all_shortest_paths(&fast_graph, NodeId(0), NodeId(4), 0)
+            .is_empty());
+        assert!(calc
+            .calc_all_shortest_paths(&fast_graph, NodeId(4), NodeId(0), 10)
+            .is_empty());
+        let paths = calc.calc_all_shortest_paths(&fast_graph, NodeId(1), NodeId(4), 10);
+        assert_eq!(1, paths.len());
+        assert_eq!(&vec![NodeId(1), NodeId(3), NodeId(4)], paths[0].get_nodes());
+        let paths = calc.calc_all_shortest_paths(&fast_graph, NodeId(2), NodeId(2), 10);
+        assert_eq!(1, paths.len());
+        assert_eq!(&vec![NodeId(2)], paths[0].get_nodes());
+    }
+
+    #[test]
+    fn unpack_cache() {
+        // 0 -> 1 -> 2 -> 3 -> 4, nodes 1 and 3 are contracted first, so there are the shortcuts
+        // 0->2 and 2->4
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(1), 1.0, 10);
+        g.add_edge_with_data(NodeId(1), NodeId(2), Weight(1), 1.0, 11);
+        g.add_edge_with_data(NodeId(2), NodeId(3), Weight(1), 1.0, 12);
+        g.add_edge_with_data(NodeId(3), NodeId(4), Weight(1), 1.0, 13);
+        g.freeze();
+        let fast_graph =
+            crate::prepare_with_order(&g, &[NodeId(1), NodeId(3), NodeId(0), NodeId(4), NodeId(2)])
+                .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_eq!(None, calc.get_unpack_cache_stats());
+        let mut cached_calc = PathCalculator::new(fast_graph.get_num_nodes()).with_unpack_cache(10);
+        assert_eq!(Some((0, 0)), cached_calc.get_unpack_cache_stats());
+        for _ in 0..2 {
+            let path = calc.calc_path(&fast_graph, NodeId(0), NodeId(4)).unwrap();
+            let cached_path = cached_calc
+                .calc_path(&fast_graph, NodeId(0), NodeId(4))
+                .unwrap();
+            assert_eq!(
+                &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+                cached_path.get_nodes()
             );
+            assert_eq!(path.get_nodes(), cached_path.get_nodes());
+            assert_eq!(&vec![10, 11, 12, 13], cached_path.get_edge_data());
         }
+        // both shortcuts are unpacked for the first query and taken from the cache for the second
+        assert_eq!(Some((2, 2)), cached_calc.get_unpack_cache_stats());
+        cached_calc.clear_unpack_cache();
+        assert_eq!(Some((0, 0)), cached_calc.get_unpack_cache_stats());
+        let path = cached_calc
+            .calc_path(&fast_graph, NodeId(1), NodeId(4))
+            .unwrap();
+        assert_eq!(
+            &vec![NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+            path.get_nodes()
+        );
+        assert_eq!(Some((0, 1)), cached_calc.get_unpack_cache_stats());
+        let calc = PathCalculator::new(fast_graph.get_num_nodes()).with_unpack_cache(0);
+        assert_eq!(None, calc.get_unpack_cache_stats());
     }
 
-    pub fn unpack_bwd(graph: &FastGraph, nodes: &mut Vec<NodeId>, edge_id: EdgeId, reverse: bool) {
-        if !graph.edges_bwd[edge_id].is_shortcut() {
-            nodes.push(graph.edges_bwd[edge_id].adj_node);
-            return;
+    #[test]
+    fn calc_path_zero_weight_transfers() {
+        // 0 -> 1 <-0-> 2 <-0-> 3 -> 4, 0 -> 4
+        // the stations 1, 2 and 3 are connected by zero weight transfer edges
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(2), 2.0);
+        g.add_edge_bidir(NodeId(1), NodeId(2), Weight(0), 0.0);
+        g.add_edge_bidir(NodeId(2), NodeId(3), Weight(0), 0.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(3), 3.0);
+        g.add_edge(NodeId(0), NodeId(4), Weight(6), 6.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc.calc_path(&fast_graph, NodeId(0), NodeId(4)).unwrap();
+        assert_eq!(Weight(5), path.get_weight());
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3), NodeId(4)],
+            path.get_nodes()
+        );
+        let path = calc.calc_path(&fast_graph, NodeId(3), NodeId(1)).unwrap();
+        assert_eq!(Weight(0), path.get_weight());
+        assert_eq!(&vec![NodeId(3), NodeId(2), NodeId(1)], path.get_nodes());
+        assert!(calc.calc_path(&fast_graph, NodeId(4), NodeId(1)).is_none());
+    }
+
+    #[test]
+    fn calc_all_shortest_paths_zero_weight_cycle() {
+        // 0 -> 1 <-0-> 2 -> 3, where 1 and 2 are connected by zero weight edges
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge_bidir(NodeId(1), NodeId(2), Weight(0), 0.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let paths = calc.calc_all_shortest_paths(&fast_graph, NodeId(0), NodeId(3), 10);
+        let mut nodes: Vec<Vec<NodeId>> = paths.iter().map(|p| p.get_nodes().clone()).collect();
+        nodes.sort();
+        assert_eq!(
+            vec![
+                vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+                vec![NodeId(0), NodeId(2), NodeId(3)]
+            ],
+            nodes
+        );
+        assert!(paths.iter().all(|p| p.get_weight() == Weight(2)));
+    }
+
+    #[test]
+    fn get_weight_f64() {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.set_weight_scale(100.0);
+        g.add_edge_f64(NodeId(0), NodeId(1), 1.25, 1.25);
+        g.add_edge_f64(NodeId(1), NodeId(2), 0.5, 0.5);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        assert_eq!(100.0, fast_graph.get_weight_scale());
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc.calc_path(&fast_graph, NodeId(0), NodeId(2)).unwrap();
+        assert_eq!(Weight(175), path.get_weight());
+        assert_eq!(1.75, path.get_weight_f64());
+        let mut blocked = HashSet::new();
+        blocked.insert((NodeId(0), NodeId(1)));
+        assert!(calc
+            .calc_path_avoiding_edges(&fast_graph, NodeId(0), NodeId(2), &blocked)
+            .is_none());
+        assert_eq!(
+            0.5,
+            calc.calc_path_max_hops(&fast_graph, NodeId(1), NodeId(2), 1)
+                .unwrap()
+                .get_weight_f64()
+        );
+        assert_eq!(
+            f64::INFINITY,
+            ShortestPath::none(NodeId(0), NodeId(2)).get_weight_f64()
+        );
+    }
+
+    #[test]
+    fn with_capacity() {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        for &capacity in &[0, 1, 100] {
+            let mut calc = PathCalculator::with_capacity(fast_graph.get_num_nodes(), capacity);
+            let path = calc.calc_path(&fast_graph, NodeId(0), NodeId(2)).unwrap();
+            assert_eq!(Weight(7), path.get_weight());
+            assert_eq!(&vec![NodeId(0), NodeId(1), NodeId(2)], path.get_nodes());
         }
-        if reverse {
-            PathCalculator::unpack_fwd(
-                graph,
-                nodes,
-                graph.edges_bwd[edge_id].replaced_out_edge,
-                reverse,
+    }
+
+    #[test]
+    fn calc_path_with_offsets() {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(3), 3.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(4), 4.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc
+            .calc_path_with_offsets(&fast_graph, NodeId(0), Weight(5), NodeId(2), Weight(10))
+            .unwrap();
+        assert_eq!(Weight(22), path.get_weight());
+        assert_eq!(&vec![NodeId(0), NodeId(1), NodeId(2)], path.get_nodes());
+        let path = calc
+            .calc_path_with_offsets(&fast_graph, NodeId(1), Weight(2), NodeId(1), Weight(1))
+            .unwrap();
+        assert_eq!(Weight(3), path.get_weight());
+        assert_eq!(&vec![NodeId(1)], path.get_nodes());
+        assert!(calc
+            .calc_path_with_offsets(&fast_graph, NodeId(2), Weight(0), NodeId(0), Weight(0))
+            .is_none());
+    }
+
+    #[test]
+    fn source_equals_target() {
+        // 0 -> 1 -> 2 -> 3
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 2.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 3.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        let landmarks = Landmarks::select(&g, 2);
+        let td_graph = TimeDependentGraph::new(&g);
+        let far_future = Instant::now() + Duration::from_secs(3600);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        for n in 0..4 {
+            let node = NodeId(n);
+            let singular = Some(ShortestPath::singular(node));
+            assert_eq!(singular, calc_path(&fast_graph, node, node));
+            assert_eq!(singular, calc.calc_path(&fast_graph, node, node));
+            assert_eq!(
+                singular,
+                calc.calc_path_bounded(&fast_graph, node, node, WEIGHT_ZERO)
             );
-            PathCalculator::unpack_bwd(
-                graph,
-                nodes,
-                graph.edges_bwd[edge_id].replaced_in_edge,
-                reverse,
+            assert_eq!(
+                singular,
+                calc.calc_path_with_cost_adjust(&fast_graph, node, node, |_edge, weight| weight)
             );
-        } else {
-            PathCalculator::unpack_bwd(
-                graph,
-                nodes,
-                graph.edges_bwd[edge_id].replaced_in_edge,
-                reverse,
+            assert_eq!(singular, calc.calc_path_checked(&fast_graph, node, node));
+            assert_eq!(
+                singular,
+                calc.calc_path_approx(&fast_graph, node, node, 0.5)
             );
-            PathCalculator::unpack_fwd(
-                graph,
-                nodes,
-                graph.edges_bwd[edge_id].replaced_out_edge,
-                reverse,
+            assert_eq!(
+                singular,
+                calc.calc_path_avoiding(&fast_graph, node, node, &HashSet::new())
+            );
+            assert_eq!(
+                singular,
+                calc.calc_path_avoiding_edges(&fast_graph, node, node, &HashSet::new())
+            );
+            assert_eq!(
+                singular,
+                calc.calc_path_filtered(&fast_graph, node, node, |_edge| false)
+            );
+            assert_eq!(singular, calc.calc_path_alt(&landmarks, node, node));
+            assert_eq!(
+                singular,
+                calc.calc_path_max_hops(&fast_graph, node, node, 0)
+            );
+            assert_eq!(
+                Ok(singular.clone()),
+                calc.calc_path_with_deadline(&fast_graph, node, node, far_future)
+            );
+            assert_eq!(
+                Some((ShortestPath::singular(node), true)),
+                calc.calc_path_with_uniqueness(&fast_graph, node, node)
+            );
+            assert_eq!(
+                (singular.clone(), singular.clone()),
+                calc.calc_path_both_directions(&fast_graph, node, node)
+            );
+            assert_eq!(
+                Some(vec![node]),
+                calc.calc_path_compressed(&fast_graph, node, node)
+            );
+            assert_eq!(
+                vec![ShortestPath::singular(node)],
+                calc.calc_all_shortest_paths(&fast_graph, node, node, 3)
+            );
+            assert_eq!(
+                vec![singular.clone()],
+                calc.calc_paths_one_to_many(&fast_graph, node, &[node])
+            );
+            assert_eq!(
+                Some((node, ShortestPath::singular(node))),
+                calc.calc_path_nearest_source(&fast_graph, &[node], node)
+            );
+            assert_eq!(
+                vec![(node, WEIGHT_ZERO)],
+                calc.calc_k_nearest(&fast_graph, node, &[node], 1)
+            );
+            calc.set_source(&fast_graph, node);
+            assert_eq!(singular, calc.calc_to(&fast_graph, node));
+            assert_eq!(
+                None,
+                calc.calc_shortest_path_tree(&fast_graph, node, None)[node.0]
+            );
+            assert_eq!(
+                Some((node, WEIGHT_ZERO)),
+                calc.reachable_iter(&fast_graph, node, WEIGHT_ZERO).next()
+            );
+            // the time-dependent path starts at the departure time, but its weight is the
+            // travel time
+            assert_eq!(
+                singular,
+                calc.calc_path_at(&td_graph, node, node, Weight(7))
+            );
+            // the offsets are part of the weight, just like for paths between different nodes
+            let path = calc
+                .calc_path_with_offsets(&fast_graph, node, Weight(2), node, Weight(3))
+                .unwrap();
+            assert_eq!(&vec![node], path.get_nodes());
+            assert_eq!(Weight(5), path.get_weight());
+            let path = calc
+                .calc_path_multiple_sources_and_targets(
+                    &fast_graph,
+                    vec![(node, Weight(4))],
+                    vec![(node, Weight(1))],
+                )
+                .unwrap();
+            assert_eq!(&vec![node], path.get_nodes());
+            assert_eq!(Weight(5), path.get_weight());
+            assert_eq!(
+                vec![Some(Weight(7))],
+                calc.calc_weights_one_to_many_weighted(
+                    &fast_graph,
+                    node,
+                    Weight(2),
+                    &[(node, Weight(5))]
+                )
             );
         }
     }
 
-    fn update_node_fwd(&mut self, node: NodeId, weight: Weight, parent: NodeId, inc_edge: EdgeId) {
-        self.valid_flags_fwd.set_valid(node);
-        self.data_fwd[node].settled = false;
-        self.data_fwd[node].weight = weight;
-        self.data_fwd[node].parent = parent;
-        self.data_fwd[node].inc_edge = inc_edge;
+    #[test]
+    fn calc_path_reverse() {
+        // 0 -> 1 -> 2
+        // |         |
+        // \--<--3<--/
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(1), 1.0, 10);
+        g.add_edge_with_data(NodeId(1), NodeId(2), Weight(2), 2.0, 11);
+        g.add_edge_with_data(NodeId(2), NodeId(3), Weight(3), 3.0, 12);
+        g.add_edge_with_data(NodeId(3), NodeId(0), Weight(4), 4.0, 13);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        // in the transposed graph we can only go 0 -> 3 -> 2 -> 1
+        let path = calc
+            .calc_path_reverse(&fast_graph, NodeId(0), NodeId(2))
+            .unwrap();
+        assert_eq!(NodeId(0), path.get_source());
+        assert_eq!(NodeId(2), path.get_target());
+        assert_eq!(Weight(7), path.get_weight());
+        assert_eq!(&vec![NodeId(0), NodeId(3), NodeId(2)], path.get_nodes());
+        assert_eq!(&vec![13, 12], path.get_edge_data());
+        for source in 0..4 {
+            for target in 0..4 {
+                let reversed = calc
+                    .calc_path_reverse(&fast_graph, NodeId(source), NodeId(target))
+                    .unwrap();
+                let path = calc
+                    .calc_path(&fast_graph, NodeId(target), NodeId(source))
+                    .unwrap();
+                assert_eq!(path.get_weight(), reversed.get_weight());
+                let mut nodes = path.get_nodes().clone();
+                nodes.reverse();
+                assert_eq!(&nodes, reversed.get_nodes());
+            }
+        }
     }
 
-    fn update_node_bwd(&mut self, node: NodeId, weight: Weight, parent: NodeId, inc_edge: EdgeId) {
-        self.valid_flags_bwd.set_valid(node);
-        self.data_bwd[node].settled = false;
-        self.data_bwd[node].weight = weight;
-        self.data_bwd[node].parent = parent;
-        self.data_bwd[node].inc_edge = inc_edge;
+    #[test]
+    fn calc_path_both_directions() {
+        // 0 -> 1 -> 2 -> 3
+        // |              |
+        // \------<-------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 2.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 3.0);
+        g.add_edge(NodeId(3), NodeId(0), Weight(10), 10.0);
+        g.add_edge_bidir(NodeId(4), NodeId(5), Weight(1), 1.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let (forward, backward) = calc.calc_path_both_directions(&fast_graph, NodeId(1), NodeId(3));
+        let forward = forward.unwrap();
+        assert_eq!(Weight(5), forward.get_weight());
+        assert_eq!(&vec![NodeId(1), NodeId(2), NodeId(3)], forward.get_nodes());
+        let backward = backward.unwrap();
+        assert_eq!(Weight(11), backward.get_weight());
+        assert_eq!(&vec![NodeId(3), NodeId(0), NodeId(1)], backward.get_nodes());
+        let (forward, backward) = calc.calc_path_both_directions(&fast_graph, NodeId(2), NodeId(2));
+        assert_eq!(&vec![NodeId(2)], forward.unwrap().get_nodes());
+        assert_eq!(&vec![NodeId(2)], backward.unwrap().get_nodes());
+        assert_eq!(
+            (None, None),
+            calc.calc_path_both_directions(&fast_graph, NodeId(0), NodeId(5))
+        );
+    }
+
+    #[test]
+    fn edge_usage_counts() {
+        // 0 -> 1 -> 2 -> 3
+        //      |
+        //      4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        // contracting node 1 and 2 yields the shortcuts 0->2 and 0->3
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(1), NodeId(2), NodeId(0), NodeId(3), NodeId(4)])
+                .unwrap();
+        let num_out_edges = fast_graph.get_num_out_edges();
+        let edge_index = |from: NodeId, to: NodeId| {
+            let fwd = fast_graph
+                .edges_fwd
+                .iter()
+                .position(|e| e.base_node == from && e.adj_node == to);
+            let bwd = fast_graph
+                .edges_bwd
+                .iter()
+                .position(|e| e.adj_node == from && e.base_node == to);
+            fwd.or(bwd.map(|i| num_out_edges + i)).unwrap()
+        };
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        calc.calc_path(&fast_graph, NodeId(0), NodeId(3));
+        assert!(calc.get_edge_usage_counts().is_empty());
+
+        calc.enable_edge_usage_counting();
+        calc.calc_path(&fast_graph, NodeId(0), NodeId(3));
+        calc.calc_path(&fast_graph, NodeId(0), NodeId(3));
+        calc.calc_path_compressed(&fast_graph, NodeId(1), NodeId(3));
+        calc.calc_path(&fast_graph, NodeId(3), NodeId(0));
+        let counts = calc.get_edge_usage_counts();
+        assert_eq!(num_out_edges + fast_graph.get_num_in_edges(), counts.len());
+        let mut expected = vec![0; counts.len()];
+        // only the shortcut is counted, not the edges it replaces
+        expected[edge_index(NodeId(0), NodeId(3))] = 2;
+        expected[edge_index(NodeId(1), NodeId(2))] = 1;
+        expected[edge_index(NodeId(2), NodeId(3))] = 1;
+        assert_eq!(expected, counts);
+
+        calc.enable_edge_usage_counting();
+        assert!(calc.get_edge_usage_counts().is_empty());
     }
 
-    fn is_settled_fwd(&self, node: NodeId) -> bool {
-        self.valid_flags_fwd.is_valid(node) && self.data_fwd[node].settled
+    #[test]
+    fn meeting_node() {
+        // 0 -> 1 -> 2 -> 3
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.freeze();
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(0), NodeId(3), NodeId(2), NodeId(1)]).unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc.calc_path(&fast_graph, NodeId(0), NodeId(3)).unwrap();
+        assert_eq!(Some(NodeId(1)), path.get_meeting_node());
+        let path = calc.calc_path(&fast_graph, NodeId(2), NodeId(3)).unwrap();
+        assert_eq!(Some(NodeId(2)), path.get_meeting_node());
+        let path = calc.calc_path(&fast_graph, NodeId(2), NodeId(2)).unwrap();
+        assert_eq!(Some(NodeId(2)), path.get_meeting_node());
+        assert!(calc.calc_path(&fast_graph, NodeId(3), NodeId(0)).is_none());
+        assert_eq!(
+            None,
+            ShortestPath::none(NodeId(3), NodeId(0)).get_meeting_node()
+        );
     }
 
-    fn is_settled_bwd(&self, node: NodeId) -> bool {
-        self.valid_flags_bwd.is_valid(node) && self.data_bwd[node].settled
+    #[test]
+    fn calc_path_with_cost_adjust() {
+        // 0 -> 1 -> 2
+        //  \-------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(3), 1.0);
+        g.freeze();
+        // node 1 is contracted last, so there are no shortcuts
+        let fast_graph = prepare_with_order(&g, &[NodeId(0), NodeId(2), NodeId(1)]).unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_eq!(
+            calc.calc_path(&fast_graph, NodeId(0), NodeId(2)),
+            calc.calc_path_with_cost_adjust(&fast_graph, NodeId(0), NodeId(2), |_edge, weight| {
+                weight
+            })
+        );
+        let path = calc
+            .calc_path_with_cost_adjust(&fast_graph, NodeId(0), NodeId(2), |edge, weight| {
+                if edge.base_node == NodeId(1) || edge.adj_node == NodeId(1) {
+                    weight + Weight(5)
+                } else {
+                    weight
+                }
+            })
+            .unwrap();
+        assert_eq!(&vec![NodeId(0), NodeId(2)], path.get_nodes());
+        assert_eq!(Weight(3), path.get_weight());
     }
 
-    fn get_weight_fwd(&self, node: NodeId) -> Weight {
-        if self.valid_flags_fwd.is_valid(node) {
-            self.data_fwd[node].weight
-        } else {
-            WEIGHT_MAX
-        }
+    /// Collects the messages logged by the thread that set `thread`
+    struct TestLogger {
+        thread: Mutex<Option<ThreadId>>,
+        messages: Mutex<Vec<String>>,
     }
 
-    fn get_weight_bwd(&self, node: NodeId) -> Weight {
-        if self.valid_flags_bwd.is_valid(node) {
-            self.data_bwd[node].weight
-        } else {
-            WEIGHT_MAX
+    impl log::Log for TestLogger {
+        fn enabled(&self, _metadata: &log::Metadata) -> bool {
+            *self.thread.lock().unwrap() == Some(thread::current().id())
         }
+
+        fn log(&self, record: &log::Record) {
+            if self.enabled(record.metadata()) {
+                self.messages
+                    .lock()
+                    .unwrap()
+                    .push(format!("{}", record.args()));
+            }
+        }
+
+        fn flush(&self) {}
     }
-}
 
-struct Data {
-    settled: bool,
-    weight: Weight,
-    parent: NodeId,
-    inc_edge: usize,
-}
+    #[test]
+    fn trace_logging() {
+        static LOGGER: TestLogger = TestLogger {
+            thread: Mutex::new(None),
+            messages: Mutex::new(vec![]),
+        };
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 1.0);
+        g.freeze();
+        let fast_graph = prepare_with_order(&g, &[NodeId(0), NodeId(2), NodeId(1)]).unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
 
-impl Data {
-    fn new() -> Self {
-        Data {
-            settled: false,
-            weight: WEIGHT_MAX,
-            parent: INVALID_NODE,
-            inc_edge: INVALID_EDGE,
+        let _ = log::set_logger(&LOGGER);
+        *LOGGER.thread.lock().unwrap() = Some(thread::current().id());
+        log::set_max_level(log::LevelFilter::Trace);
+        calc.calc_path(&fast_graph, NodeId(0), NodeId(2));
+        calc.calc_path(&fast_graph, NodeId(2), NodeId(0));
+        log::set_max_level(log::LevelFilter::Off);
+        *LOGGER.thread.lock().unwrap() = None;
+
+        let messages = LOGGER.messages.lock().unwrap();
+        assert_eq!(
+            vec![
+                "query from [(0, 0)] to [(2, 0)]: settled nodes fwd: 2, bwd: 2, relaxed edges: 2, \
+                 (meeting node, weight): Some((1, 3))",
+                "query from [(2, 0)] to [(0, 0)]: settled nodes fwd: 1, bwd: 1, relaxed edges: 0, \
+                 (meeting node, weight): None",
+            ],
+            *messages
+        );
+    }
+
+    #[test]
+    fn calc_weights_one_to_many_weighted() {
+        // 0 -> 1 -> 2 -> 3
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(3), 1.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_eq!(
+            vec![
+                Some(Weight(10)),
+                Some(Weight(23)),
+                Some(Weight(17)),
+                None,
+                None,
+                None
+            ],
+            calc.calc_weights_one_to_many_weighted(
+                &fast_graph,
+                NodeId(1),
+                Weight(10),
+                &[
+                    (NodeId(1), Weight(0)),
+                    (NodeId(3), Weight(8)),
+                    (NodeId(2), Weight(5)),
+                    (NodeId(0), Weight(0)),
+                    (NodeId(2), WEIGHT_MAX),
+                    (NodeId(3), WEIGHT_MAX - Weight(10))
+                ]
+            )
+        );
+        assert_eq!(
+            vec![None, None],
+            calc.calc_weights_one_to_many_weighted(
+                &fast_graph,
+                NodeId(1),
+                WEIGHT_MAX,
+                &[(NodeId(1), Weight(0)), (NodeId(2), Weight(0))]
+            )
+        );
+        assert!(calc
+            .calc_weights_one_to_many_weighted(&fast_graph, NodeId(1), Weight(0), &[])
+            .is_empty());
+    }
+
+    #[test]
+    fn calc_path_nearest_source() {
+        // 0 -> 1 -> 2 -> 3 <- 4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(4), NodeId(3), Weight(5), 1.0);
+        g.freeze();
+        let fast_graph = prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let (source, path) = calc
+            .calc_path_nearest_source(&fast_graph, &[NodeId(0), NodeId(4)], NodeId(3))
+            .unwrap();
+        assert_eq!(NodeId(0), source);
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+            path.get_nodes()
+        );
+        assert_eq!(Weight(3), path.get_weight());
+        let (source, path) = calc
+            .calc_path_nearest_source(&fast_graph, &[NodeId(4), NodeId(1)], NodeId(3))
+            .unwrap();
+        assert_eq!(NodeId(1), source);
+        assert_eq!(Weight(2), path.get_weight());
+        let (source, path) = calc
+            .calc_path_nearest_source(&fast_graph, &[NodeId(4), NodeId(3)], NodeId(3))
+            .unwrap();
+        assert_eq!(NodeId(3), source);
+        assert_eq!(Weight(0), path.get_weight());
+        assert!(calc
+            .calc_path_nearest_source(&fast_graph, &[], NodeId(3))
+            .is_none());
+        assert!(calc
+            .calc_path_nearest_source(&fast_graph, &[NodeId(3)], NodeId(0))
+            .is_none());
+    }
+
+    #[test]
+    fn calc_path_bounded() {
+        // 0 -> 1 -> 2 -> ... -> 9
+        let mut g = InputGraph::new();
+        for i in 0..9 {
+            g.add_edge(NodeId(i), NodeId(i + 1), Weight(1), 1.0);
         }
+        g.freeze();
+        let fast_graph = prepare_with_order(
+            &g,
+            &[
+                NodeId(9),
+                NodeId(0),
+                NodeId(8),
+                NodeId(1),
+                NodeId(7),
+                NodeId(2),
+                NodeId(6),
+                NodeId(3),
+                NodeId(5),
+                NodeId(4),
+            ],
+        )
+        .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_eq!(
+            calc.calc_path(&fast_graph, NodeId(0), NodeId(9)),
+            calc.calc_path_bounded(&fast_graph, NodeId(0), NodeId(9), Weight(9))
+        );
+        let unbounded_stats = calc.last_query_stats();
+        assert!(calc
+            .calc_path_bounded(&fast_graph, NodeId(0), NodeId(9), Weight(8))
+            .is_none());
+        assert!(calc
+            .calc_path_bounded(&fast_graph, NodeId(0), NodeId(9), Weight(0))
+            .is_none());
+        let bounded_stats = calc.last_query_stats();
+        assert!(
+            bounded_stats.forward_settled + bounded_stats.backward_settled
+                < unbounded_stats.forward_settled + unbounded_stats.backward_settled
+        );
+        assert_eq!(
+            Weight(0),
+            calc.calc_path_bounded(&fast_graph, NodeId(3), NodeId(3), Weight(0))
+                .unwrap()
+                .get_weight()
+        );
+        assert!(calc
+            .calc_path_bounded(&fast_graph, NodeId(9), NodeId(0), WEIGHT_MAX)
+            .is_none());
+        // the bound is only used for a single query
+        assert_eq!(
+            Weight(9),
+            calc.calc_path(&fast_graph, NodeId(0), NodeId(9))
+                .unwrap()
+                .get_weight()
+        );
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use crate::fast_graph::FastGraphEdge;
+    #[test]
+    fn calc_path_with_uniqueness() {
+        //   1
+        //  / \
+        // 0   3 -> 4
+        //  \ /
+        //   2
+        let create_graph = |weight_02: Weight| {
+            let mut g = InputGraph::new();
+            g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+            g.add_edge(NodeId(1), NodeId(3), Weight(1), 1.0);
+            g.add_edge(NodeId(0), NodeId(2), weight_02, weight_02.0 as f64);
+            g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+            g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+            g.freeze();
+            g
+        };
+        // both paths meet at a different node
+        let fast_graph = prepare_with_order(
+            &create_graph(Weight(1)),
+            &[NodeId(0), NodeId(4), NodeId(3), NodeId(1), NodeId(2)],
+        )
+        .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let (path, unique) = calc
+            .calc_path_with_uniqueness(&fast_graph, NodeId(0), NodeId(4))
+            .unwrap();
+        assert_eq!(Weight(3), path.get_weight());
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(3), NodeId(4)],
+            path.get_nodes()
+        );
+        assert!(!unique);
+        let (_, unique) = calc
+            .calc_path_with_uniqueness(&fast_graph, NodeId(0), NodeId(1))
+            .unwrap();
+        assert!(unique);
+        let (_, unique) = calc
+            .calc_path_with_uniqueness(&fast_graph, NodeId(4), NodeId(4))
+            .unwrap();
+        assert!(unique);
+        assert!(calc
+            .calc_path_with_uniqueness(&fast_graph, NodeId(4), NodeId(0))
+            .is_none());
 
-    use super::*;
+        let fast_graph = prepare_with_order(
+            &create_graph(Weight(2)),
+            &[NodeId(0), NodeId(4), NodeId(3), NodeId(1), NodeId(2)],
+        )
+        .unwrap();
+        let (path, unique) = calc
+            .calc_path_with_uniqueness(&fast_graph, NodeId(0), NodeId(4))
+            .unwrap();
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(3), NodeId(4)],
+            path.get_nodes()
+        );
+        assert!(unique);
+
+        // here both paths go through node 3, which has the highest rank, so the alternative is
+        // not detected
+        let fast_graph = prepare_with_order(
+            &create_graph(Weight(1)),
+            &[NodeId(0), NodeId(4), NodeId(1), NodeId(2), NodeId(3)],
+        )
+        .unwrap();
+        let (path, unique) = calc
+            .calc_path_with_uniqueness(&fast_graph, NodeId(0), NodeId(4))
+            .unwrap();
+        assert_eq!(Weight(3), path.get_weight());
+        assert!(unique);
+    }
 
     #[test]
-    fn unpack_fwd_single() {
-        // 0 -> 1
-        let mut g = FastGraph::new(2);
-        g.edges_fwd
-            .push(FastGraphEdge::new(0, 1, 3, 3.0, INVALID_EDGE, INVALID_EDGE));
-        let mut nodes = vec![];
-        PathCalculator::unpack_fwd(&g, &mut nodes, 0, false);
-        assert_eq!(nodes, vec![0]);
+    fn calc_path_checked() {
+        let fast_graph = create_graph_with_shortcut();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc
+            .calc_path_checked(&fast_graph, NodeId(0), NodeId(3))
+            .unwrap();
+        assert_eq!(Weight(3), path.get_weight());
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+            path.get_nodes()
+        );
+        assert!(calc
+            .calc_path_checked(&fast_graph, NodeId(3), NodeId(0))
+            .is_none());
+        assert_eq!(
+            calc.calc_path(&fast_graph, NodeId(4), NodeId(2)),
+            calc.calc_path_checked(&fast_graph, NodeId(4), NodeId(2))
+        );
     }
 
+    #[cfg(debug_assertions)]
     #[test]
-    fn unpack_fwd_simple() {
+    #[should_panic(expected = "does not match the weight of its edges")]
+    fn calc_path_checked_with_wrong_shortcut_weight() {
+        let mut fast_graph = create_graph_with_shortcut();
+        // the shortcut 0->2 replaces two edges with weight 1
+        let shortcut = fast_graph
+            .edges_fwd
+            .iter()
+            .position(|e| e.base_node == NodeId(0) && e.adj_node == NodeId(2))
+            .unwrap();
+        assert!(fast_graph.edges_fwd[shortcut].is_shortcut());
+        fast_graph.edges_fwd[shortcut].weight = Weight(1);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        calc.calc_path_checked(&fast_graph, NodeId(0), NodeId(3));
+    }
+
+    fn create_graph_with_shortcut() -> FastGraph {
+        // 0 -> 1 -> 2 -> 3
+        // |         |
+        // 4 -> 5 -> 6
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(4), Weight(2), 2.0);
+        g.add_edge(NodeId(4), NodeId(5), Weight(2), 2.0);
+        g.add_edge(NodeId(5), NodeId(6), Weight(2), 2.0);
+        g.add_edge(NodeId(6), NodeId(2), Weight(2), 2.0);
+        g.freeze();
+        // contracting node 1 first yields the shortcut 0->2
+        prepare_with_order(
+            &g,
+            &[
+                NodeId(1),
+                NodeId(5),
+                NodeId(4),
+                NodeId(6),
+                NodeId(0),
+                NodeId(3),
+                NodeId(2),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn calc_path_max_hops() {
+        // 0 -> 1 -> 2 -> 3
+        //  \------------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(10), 10.0);
+        g.freeze();
+        let fast_graph =
+            prepare_with_order(&g, &[NodeId(0), NodeId(1), NodeId(2), NodeId(3)]).unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_max_hops_path(
+            &mut calc,
+            &fast_graph,
+            NodeId(0),
+            NodeId(3),
+            5,
+            Weight(3),
+            vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+        );
+        assert_max_hops_path(
+            &mut calc,
+            &fast_graph,
+            NodeId(0),
+            NodeId(3),
+            3,
+            Weight(3),
+            vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+        );
+        // the shortest path has too many hops, so we take the direct edge
+        assert_max_hops_path(
+            &mut calc,
+            &fast_graph,
+            NodeId(0),
+            NodeId(3),
+            2,
+            Weight(10),
+            vec![NodeId(0), NodeId(3)],
+        );
+        assert_max_hops_path(
+            &mut calc,
+            &fast_graph,
+            NodeId(0),
+            NodeId(3),
+            1,
+            Weight(10),
+            vec![NodeId(0), NodeId(3)],
+        );
+        assert_max_hops_path(
+            &mut calc,
+            &fast_graph,
+            NodeId(0),
+            NodeId(2),
+            2,
+            Weight(2),
+            vec![NodeId(0), NodeId(1), NodeId(2)],
+        );
+        assert_max_hops_path(
+            &mut calc,
+            &fast_graph,
+            NodeId(2),
+            NodeId(2),
+            0,
+            Weight(0),
+            vec![NodeId(2)],
+        );
+        // there is no path with less hops
+        assert!(calc
+            .calc_path_max_hops(&fast_graph, NodeId(1), NodeId(3), 1)
+            .is_none());
+        assert!(calc
+            .calc_path_max_hops(&fast_graph, NodeId(0), NodeId(2), 1)
+            .is_none());
+        assert!(calc
+            .calc_path_max_hops(&fast_graph, NodeId(3), NodeId(0), 5)
+            .is_none());
+    }
+
+    #[test]
+    fn calc_path_max_hops_with_replaced_edge() {
         // 0 -> 1 -> 2
-        let mut g = FastGraph::new(3);
-        g.edges_fwd
-            .push(FastGraphEdge::new(0, 1, 2, 2.0, INVALID_EDGE, INVALID_EDGE));
-        g.edges_fwd.push(FastGraphEdge::new(0, 2, 5, 5.0, 0, 0));
-        g.edges_bwd
-            .push(FastGraphEdge::new(2, 1, 3, 3.0, INVALID_EDGE, INVALID_EDGE));
-        g.first_edge_ids_fwd = vec![0, 2, 0, 0];
-        let mut nodes = vec![];
-        PathCalculator::unpack_fwd(&g, &mut nodes, 1, false);
-        assert_eq!(nodes, vec![1, 0]);
+        //  \-------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(5), 5.0);
+        g.freeze();
+        // contracting node 1 replaces the edge 0->2 with a shortcut, but it is still the only
+        // path with a single hop
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        assert_eq!(1, fast_graph.get_replaced_edges().len());
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        assert_max_hops_path(
+            &mut calc,
+            &fast_graph,
+            NodeId(0),
+            NodeId(2),
+            1,
+            Weight(5),
+            vec![NodeId(0), NodeId(2)],
+        );
+        assert_max_hops_path(
+            &mut calc,
+            &fast_graph,
+            NodeId(0),
+            NodeId(2),
+            2,
+            Weight(2),
+            vec![NodeId(0), NodeId(1), NodeId(2)],
+        );
+    }
+
+    #[test]
+    fn calc_path_avoiding_with_replaced_edge() {
+        // 0 -> 1 -> 2
+        //  \-------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(5), 5.0);
+        g.freeze();
+        // contracting node 1 replaces the edge 0->2 with a shortcut, but it is still the only
+        // path that does not visit node 1
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let blocked: HashSet<NodeId> = [NodeId(1)].iter().cloned().collect();
+        let path = calc
+            .calc_path_avoiding(&fast_graph, NodeId(0), NodeId(2), &blocked)
+            .unwrap();
+        assert_eq!(Weight(5), path.get_weight());
+        assert_eq!(&vec![NodeId(0), NodeId(2)], path.get_nodes());
+    }
+
+    #[test]
+    fn calc_path_avoiding_edges_with_replaced_edge() {
+        // 0 -> 1 -> 2
+        //  \-------/
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(5), 5.0);
+        g.freeze();
+        // contracting node 1 replaces the edge 0->2 with a shortcut, but it is the detour once
+        // the edge 0->1 is blocked
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let blocked: HashSet<(NodeId, NodeId)> = [(NodeId(0), NodeId(1))].iter().cloned().collect();
+        let path = calc
+            .calc_path_avoiding_edges(&fast_graph, NodeId(0), NodeId(2), &blocked)
+            .unwrap();
+        assert_eq!(Weight(5), path.get_weight());
+        assert_eq!(&vec![NodeId(0), NodeId(2)], path.get_nodes());
+    }
+
+    #[test]
+    fn calc_path_avoiding() {
+        // 0 -> 1 -> 2 -> 3
+        // |         |
+        // 4 -> 5 -> 6
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(4), Weight(2), 2.0);
+        g.add_edge(NodeId(4), NodeId(5), Weight(2), 2.0);
+        g.add_edge(NodeId(5), NodeId(6), Weight(2), 2.0);
+        g.add_edge(NodeId(6), NodeId(2), Weight(2), 2.0);
+        g.freeze();
+        // contracting node 1 first yields the shortcut 0->2 which skips node 1
+        let fast_graph = prepare_with_order(
+            &g,
+            &[
+                NodeId(1),
+                NodeId(5),
+                NodeId(4),
+                NodeId(6),
+                NodeId(0),
+                NodeId(3),
+                NodeId(2),
+            ],
+        )
+        .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc
+            .calc_path_avoiding(&fast_graph, NodeId(0), NodeId(3), &HashSet::new())
+            .unwrap();
+        assert_eq!(Weight(3), path.get_weight());
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+            path.get_nodes()
+        );
+
+        let blocked: HashSet<NodeId> = [NodeId(1)].iter().cloned().collect();
+        let path = calc
+            .calc_path_avoiding(&fast_graph, NodeId(0), NodeId(3), &blocked)
+            .unwrap();
+        assert_eq!(Weight(9), path.get_weight());
+        assert_eq!(
+            &vec![
+                NodeId(0),
+                NodeId(4),
+                NodeId(5),
+                NodeId(6),
+                NodeId(2),
+                NodeId(3)
+            ],
+            path.get_nodes()
+        );
+
+        let blocked: HashSet<NodeId> = [NodeId(1), NodeId(5)].iter().cloned().collect();
+        assert!(calc
+            .calc_path_avoiding(&fast_graph, NodeId(0), NodeId(3), &blocked)
+            .is_none());
+        // blocked source or target
+        let blocked: HashSet<NodeId> = [NodeId(3)].iter().cloned().collect();
+        assert!(calc
+            .calc_path_avoiding(&fast_graph, NodeId(0), NodeId(3), &blocked)
+            .is_none());
+        assert!(calc
+            .calc_path_avoiding(&fast_graph, NodeId(3), NodeId(3), &blocked)
+            .is_none());
+    }
+
+    #[test]
+    fn calc_path_avoiding_edges() {
+        // 0 -> 1 -> 2 -> 3
+        // |         |
+        // 4 -> 5 -> 6
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(4), Weight(2), 2.0);
+        g.add_edge(NodeId(4), NodeId(5), Weight(2), 2.0);
+        g.add_edge(NodeId(5), NodeId(6), Weight(2), 2.0);
+        g.add_edge(NodeId(6), NodeId(2), Weight(2), 2.0);
+        g.freeze();
+        // contracting node 1 first yields the shortcut 0->2 which contains the edge 1->2
+        let fast_graph = prepare_with_order(
+            &g,
+            &[
+                NodeId(1),
+                NodeId(5),
+                NodeId(4),
+                NodeId(6),
+                NodeId(0),
+                NodeId(3),
+                NodeId(2),
+            ],
+        )
+        .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let blocked: HashSet<(NodeId, NodeId)> = [(NodeId(1), NodeId(2))].iter().cloned().collect();
+        let path = calc
+            .calc_path_avoiding_edges(&fast_graph, NodeId(0), NodeId(3), &blocked)
+            .unwrap();
+        assert_eq!(Weight(9), path.get_weight());
+        assert_eq!(
+            &vec![
+                NodeId(0),
+                NodeId(4),
+                NodeId(5),
+                NodeId(6),
+                NodeId(2),
+                NodeId(3)
+            ],
+            path.get_nodes()
+        );
+        // edges are directed, blocking the opposite direction changes nothing
+        let blocked: HashSet<(NodeId, NodeId)> = [(NodeId(2), NodeId(1))].iter().cloned().collect();
+        let path = calc
+            .calc_path_avoiding_edges(&fast_graph, NodeId(0), NodeId(3), &blocked)
+            .unwrap();
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+            path.get_nodes()
+        );
+
+        let blocked: HashSet<(NodeId, NodeId)> = [(NodeId(1), NodeId(2)), (NodeId(2), NodeId(3))]
+            .iter()
+            .cloned()
+            .collect();
+        assert!(calc
+            .calc_path_avoiding_edges(&fast_graph, NodeId(0), NodeId(3), &blocked)
+            .is_none());
+        assert_eq!(
+            Weight(0),
+            calc.calc_path_avoiding_edges(&fast_graph, NodeId(3), NodeId(3), &blocked)
+                .unwrap()
+                .get_weight()
+        );
+    }
+
+    #[test]
+    fn calc_path_filtered() {
+        // 0 -> 1 -> 2 -> 3
+        // |         |
+        // 4 -> 5 -> 6
+        // the edge data is used as road class, the edges along the top are all of class 1
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(1), 1.0, 1);
+        g.add_edge_with_data(NodeId(1), NodeId(2), Weight(1), 1.0, 1);
+        g.add_edge_with_data(NodeId(2), NodeId(3), Weight(1), 1.0, 0);
+        g.add_edge_with_data(NodeId(0), NodeId(4), Weight(2), 2.0, 0);
+        g.add_edge_with_data(NodeId(4), NodeId(5), Weight(2), 2.0, 0);
+        g.add_edge_with_data(NodeId(5), NodeId(6), Weight(2), 2.0, 0);
+        g.add_edge_with_data(NodeId(6), NodeId(2), Weight(2), 2.0, 0);
+        g.freeze();
+        // contracting node 1 first yields the shortcut 0->2 which contains the edges 0->1->2
+        let fast_graph = prepare_with_order(
+            &g,
+            &[
+                NodeId(1),
+                NodeId(5),
+                NodeId(4),
+                NodeId(6),
+                NodeId(0),
+                NodeId(3),
+                NodeId(2),
+            ],
+        )
+        .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc
+            .calc_path_filtered(&fast_graph, NodeId(0), NodeId(3), |_| true)
+            .unwrap();
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+            path.get_nodes()
+        );
+        let path = calc
+            .calc_path_filtered(&fast_graph, NodeId(0), NodeId(3), |e| e.data != 1)
+            .unwrap();
+        assert_eq!(Weight(9), path.get_weight());
+        assert_eq!(
+            &vec![
+                NodeId(0),
+                NodeId(4),
+                NodeId(5),
+                NodeId(6),
+                NodeId(2),
+                NodeId(3)
+            ],
+            path.get_nodes()
+        );
+        // the edges are passed in their original direction
+        let path = calc
+            .calc_path_filtered(&fast_graph, NodeId(0), NodeId(3), |e| {
+                (e.base_node, e.adj_node) != (NodeId(1), NodeId(2))
+            })
+            .unwrap();
+        assert_eq!(
+            &vec![
+                NodeId(0),
+                NodeId(4),
+                NodeId(5),
+                NodeId(6),
+                NodeId(2),
+                NodeId(3)
+            ],
+            path.get_nodes()
+        );
+        let path = calc
+            .calc_path_filtered(&fast_graph, NodeId(0), NodeId(3), |e| e.weight < Weight(2))
+            .unwrap();
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+            path.get_nodes()
+        );
+        assert!(calc
+            .calc_path_filtered(&fast_graph, NodeId(0), NodeId(3), |e| e.adj_node
+                != NodeId(3))
+            .is_none());
+    }
+
+    #[test]
+    fn calc_path_filtered_with_replaced_edge() {
+        // 0 -> 1 -> 2
+        //  \-------/
+        // the edge data is used as road class, only the direct edge is of class 0
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(1), 1.0, 1);
+        g.add_edge_with_data(NodeId(1), NodeId(2), Weight(1), 1.0, 1);
+        g.add_edge_with_data(NodeId(0), NodeId(2), Weight(5), 5.0, 0);
+        g.freeze();
+        // contracting node 1 replaces the edge 0->2 with the shortcut 0->1->2
+        let fast_graph = prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let path = calc
+            .calc_path_filtered(&fast_graph, NodeId(0), NodeId(2), |e| e.data != 1)
+            .unwrap();
+        assert_eq!(Weight(5), path.get_weight());
+        assert_eq!(&vec![NodeId(0), NodeId(2)], path.get_nodes());
+        assert_eq!(&vec![0], path.get_edge_data());
+        // the replaced edge itself is passed to the filter as well
+        assert!(calc
+            .calc_path_filtered(&fast_graph, NodeId(0), NodeId(2), |e| e.data != 1
+                && e.weight < Weight(5))
+            .is_none());
+    }
+
+    fn assert_max_hops_path(
+        calc: &mut PathCalculator,
+        fast_graph: &FastGraph,
+        source: NodeId,
+        target: NodeId,
+        max_hops: usize,
+        weight: Weight,
+        nodes: Vec<NodeId>,
+    ) {
+        let path = calc
+            .calc_path_max_hops(fast_graph, source, target, max_hops)
+            .unwrap();
+        assert_eq!(weight, path.get_weight());
+        assert_eq!(&nodes, path.get_nodes());
     }
 }
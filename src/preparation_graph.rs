@@ -17,14 +17,25 @@
  * under the License.
  */
 
-use crate::constants::{Length, Weight};
-use crate::constants::{NodeId, INVALID_NODE};
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{EdgeData, Length, Weight, INVALID_EDGE_DATA};
+use crate::constants::{NodeId, INVALID_EDGE, INVALID_NODE};
+use crate::fast_graph::{FastGraph, FastGraphEdge};
 use crate::input_graph::InputGraph;
 
+/// The graph used during the preparation, which stores the in- and out-edges of every node. Use
+/// `FastGraph::to_preparation_graph` to get the original edges of a prepared graph, e.g. to run
+/// `Dijkstra` on them.
+#[derive(Serialize, Deserialize)]
 pub struct PreparationGraph {
     pub out_edges: Vec<Vec<Arc>>,
     pub in_edges: Vec<Vec<Arc>>,
     num_nodes: usize,
+    /// the original edges that were replaced by a shortcut with a smaller weight, see
+    /// `add_or_reduce_edge`
+    #[serde(default)]
+    replaced_edges: Vec<FastGraphEdge>,
 }
 
 impl PreparationGraph {
@@ -35,19 +46,48 @@ impl PreparationGraph {
             out_edges,
             in_edges,
             num_nodes,
+            replaced_edges: vec![],
         }
     }
 
     pub fn from_input_graph(input_graph: &InputGraph) -> Self {
         let mut graph = PreparationGraph::new(input_graph.get_num_nodes());
         for e in input_graph.get_edges() {
-            graph.add_edge(e.from, e.to, e.weight, e.length);
+            graph.add_edge_with_data(e.from, e.to, e.weight, e.length, e.data);
+        }
+        graph
+    }
+
+    /// Builds a graph containing all the original (non-shortcut) edges of the given `FastGraph`,
+    /// including the ones that were replaced by a shortcut with a smaller weight during the
+    /// preparation (see `FastGraph::get_replaced_edges`), so this is the graph that was prepared.
+    pub fn from_fast_graph(fast_graph: &FastGraph) -> Self {
+        let mut graph = PreparationGraph::new(fast_graph.get_num_nodes());
+        let original_edges = fast_graph.edges_fwd.iter().filter(|e| !e.is_shortcut());
+        for e in original_edges.chain(fast_graph.get_replaced_edges()) {
+            graph.add_edge_with_data(e.base_node, e.adj_node, e.weight, e.length, e.data);
+        }
+        // original edges stored as in-edges point from the adj_node to the base_node
+        for e in fast_graph.edges_bwd.iter().filter(|e| !e.is_shortcut()) {
+            graph.add_edge_with_data(e.adj_node, e.base_node, e.weight, e.length, e.data);
         }
         graph
     }
 
+    #[cfg(test)]
     pub fn add_edge(&mut self, from: NodeId, to: NodeId, weight: Weight, length: Length) {
-        self.add_edge_or_shortcut(from, to, weight, length, INVALID_NODE);
+        self.add_edge_with_data(from, to, weight, length, INVALID_EDGE_DATA);
+    }
+
+    pub fn add_edge_with_data(
+        &mut self,
+        from: NodeId,
+        to: NodeId,
+        weight: Weight,
+        length: Length,
+        data: EdgeData,
+    ) {
+        self.add_edge_or_shortcut(from, to, weight, length, INVALID_NODE, data);
     }
 
     pub fn add_edge_or_shortcut(
@@ -57,12 +97,17 @@ impl PreparationGraph {
         weight: Weight,
         length: Length,
         center_node: NodeId,
+        data: EdgeData,
     ) {
         self.assert_valid_node_id(to);
-        self.out_edges[from].push(Arc::new(to, weight, length, center_node));
-        self.in_edges[to].push(Arc::new(from, weight, length, center_node));
+        self.out_edges[from.0].push(Arc::new(to, weight, length, center_node, data));
+        self.in_edges[to.0].push(Arc::new(from, weight, length, center_node, data));
     }
 
+    /// Adds a shortcut from `from` to `to` via `center_node`, unless there already is an edge
+    /// from `from` to `to`. In this case the weight of the existing edge is reduced to the given
+    /// weight (if it is larger) and the edge becomes a shortcut. Original edges replaced this way
+    /// are kept, see `take_replaced_edges`.
     pub fn add_or_reduce_edge(
         &mut self,
         from: NodeId,
@@ -74,7 +119,7 @@ impl PreparationGraph {
         if self.reduce_edge(from, to, weight, center_node) {
             return;
         }
-        self.add_edge_or_shortcut(from, to, weight, length, center_node);
+        self.add_edge_or_shortcut(from, to, weight, length, center_node, INVALID_EDGE_DATA);
     }
 
     fn reduce_edge(
@@ -84,17 +129,30 @@ impl PreparationGraph {
         weight: Weight,
         center_node: NodeId,
     ) -> bool {
-        for out_edge in &mut self.out_edges[from] {
+        for out_edge in &mut self.out_edges[from.0] {
             if out_edge.adj_node == to {
                 if out_edge.weight <= weight {
                     return true;
                 }
-                for in_edge in &mut self.in_edges[to] {
+                if out_edge.center_node == INVALID_NODE {
+                    self.replaced_edges.push(FastGraphEdge::new_with_data(
+                        from,
+                        to,
+                        out_edge.weight,
+                        out_edge.length,
+                        INVALID_EDGE,
+                        INVALID_EDGE,
+                        out_edge.data,
+                    ));
+                }
+                for in_edge in &mut self.in_edges[to.0] {
                     if in_edge.adj_node == from {
                         out_edge.weight = weight;
                         in_edge.weight = weight;
                         out_edge.center_node = center_node;
                         in_edge.center_node = center_node;
+                        out_edge.data = INVALID_EDGE_DATA;
+                        in_edge.data = INVALID_EDGE_DATA;
                     }
                 }
                 return true;
@@ -107,25 +165,31 @@ impl PreparationGraph {
         self.num_nodes
     }
 
+    /// Returns the original edges that were replaced by a shortcut with a smaller weight so far
+    /// and removes them from this graph
+    pub fn take_replaced_edges(&mut self) -> Vec<FastGraphEdge> {
+        std::mem::take(&mut self.replaced_edges)
+    }
+
     pub fn disconnect(&mut self, node: NodeId) {
-        for i in 0..self.out_edges[node].len() {
-            let adj = self.out_edges[node][i].adj_node;
+        for i in 0..self.out_edges[node.0].len() {
+            let adj = self.out_edges[node.0][i].adj_node;
             self.remove_in_edge(adj, node);
         }
-        for i in 0..self.in_edges[node].len() {
-            let adj = self.in_edges[node][i].adj_node;
+        for i in 0..self.in_edges[node.0].len() {
+            let adj = self.in_edges[node.0][i].adj_node;
             self.remove_out_edge(adj, node);
         }
-        self.in_edges[node].clear();
-        self.out_edges[node].clear();
+        self.in_edges[node.0].clear();
+        self.out_edges[node.0].clear();
     }
 
     pub fn remove_out_edge(&mut self, node: NodeId, adj: NodeId) {
-        PreparationGraph::remove_edge_with_adj_node(&mut self.out_edges[node], adj);
+        PreparationGraph::remove_edge_with_adj_node(&mut self.out_edges[node.0], adj);
     }
 
     pub fn remove_in_edge(&mut self, node: NodeId, adj: NodeId) {
-        PreparationGraph::remove_edge_with_adj_node(&mut self.in_edges[node], adj);
+        PreparationGraph::remove_edge_with_adj_node(&mut self.in_edges[node.0], adj);
     }
 
     pub fn remove_edge_with_adj_node(edges: &mut Vec<Arc>, adj: NodeId) {
@@ -139,15 +203,15 @@ impl PreparationGraph {
     }
 
     pub fn get_out_edges(&self, node: NodeId) -> &Vec<Arc> {
-        &self.out_edges[node]
+        &self.out_edges[node.0]
     }
 
     pub fn get_in_edges(&self, node: NodeId) -> &Vec<Arc> {
-        &self.in_edges[node]
+        &self.in_edges[node.0]
     }
 
     fn assert_valid_node_id(&self, node: NodeId) {
-        if node >= self.num_nodes {
+        if node.0 >= self.num_nodes {
             panic!(
                 "invalid node id {}, must be in [0, {})",
                 node, self.num_nodes
@@ -156,21 +220,30 @@ impl PreparationGraph {
     }
 }
 
-#[derive(Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct Arc {
     pub adj_node: NodeId,
     pub weight: Weight,
     pub length: Length,
     pub center_node: NodeId,
+    /// the user data of the original edge, `INVALID_EDGE_DATA` for shortcuts
+    pub data: EdgeData,
 }
 
 impl Arc {
-    pub fn new(adj_node: NodeId, weight: Weight, length: Length, center_node: NodeId) -> Self {
+    pub fn new(
+        adj_node: NodeId,
+        weight: Weight,
+        length: Length,
+        center_node: NodeId,
+        data: EdgeData,
+    ) -> Self {
         Arc {
             adj_node,
             weight,
             length,
             center_node,
+            data,
         }
     }
 }
@@ -182,51 +255,90 @@ mod tests {
     #[test]
     fn add_and_remove_edges() {
         let mut g = PreparationGraph::new(4);
-        g.add_edge(0, 1, 1, 1.0);
-        g.add_edge(0, 2, 1, 1.0);
-        g.add_edge(0, 3, 1, 1.0);
-        g.add_edge(2, 3, 1, 1.0);
-        assert_eq!(adj_nodes(g.get_out_edges(0)), vec![1, 2, 3]);
-        assert_eq!(adj_nodes(g.get_in_edges(3)), vec![0, 2]);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        assert_eq!(
+            adj_nodes(g.get_out_edges(NodeId(0))),
+            vec![NodeId(1), NodeId(2), NodeId(3)]
+        );
+        assert_eq!(
+            adj_nodes(g.get_in_edges(NodeId(3))),
+            vec![NodeId(0), NodeId(2)]
+        );
 
-        g.remove_out_edge(0, 2);
-        assert_eq!(adj_nodes(g.get_out_edges(0)), vec![1, 3]);
-        assert_eq!(adj_nodes(g.get_in_edges(3)), vec![0, 2]);
+        g.remove_out_edge(NodeId(0), NodeId(2));
+        assert_eq!(
+            adj_nodes(g.get_out_edges(NodeId(0))),
+            vec![NodeId(1), NodeId(3)]
+        );
+        assert_eq!(
+            adj_nodes(g.get_in_edges(NodeId(3))),
+            vec![NodeId(0), NodeId(2)]
+        );
 
-        g.remove_in_edge(3, 0);
-        assert_eq!(adj_nodes(g.get_out_edges(0)), vec![1, 3]);
-        assert_eq!(adj_nodes(g.get_in_edges(3)), vec![2]);
+        g.remove_in_edge(NodeId(3), NodeId(0));
+        assert_eq!(
+            adj_nodes(g.get_out_edges(NodeId(0))),
+            vec![NodeId(1), NodeId(3)]
+        );
+        assert_eq!(adj_nodes(g.get_in_edges(NodeId(3))), vec![NodeId(2)]);
     }
 
     #[test]
     fn add_or_remove_edge() {
         // 0 -> 1
         let mut g = PreparationGraph::new(3);
-        g.add_edge(0, 1, 10, 10.0);
-        g.add_or_reduce_edge(0, 1, 6, 6.0, INVALID_NODE);
-        assert_eq!(1, g.get_out_edges(0).len());
-        assert_eq!(6, g.get_out_edges(0)[0].weight);
-        assert_eq!(1, g.get_in_edges(1).len());
-        assert_eq!(6, g.get_in_edges(1)[0].weight);
+        g.add_edge(NodeId(0), NodeId(1), Weight(10), 10.0);
+        g.add_or_reduce_edge(NodeId(0), NodeId(1), Weight(6), 6.0, INVALID_NODE);
+        assert_eq!(1, g.get_out_edges(NodeId(0)).len());
+        assert_eq!(Weight(6), g.get_out_edges(NodeId(0))[0].weight);
+        assert_eq!(1, g.get_in_edges(NodeId(1)).len());
+        assert_eq!(Weight(6), g.get_in_edges(NodeId(1))[0].weight);
+
+        // the original edge replaced by a shortcut is kept, reducing the shortcut again loses
+        // nothing
+        let mut g = PreparationGraph::new(3);
+        g.add_edge(NodeId(0), NodeId(1), Weight(10), 10.0);
+        g.add_or_reduce_edge(NodeId(0), NodeId(1), Weight(6), 6.0, NodeId(2));
+        g.add_or_reduce_edge(NodeId(0), NodeId(1), Weight(4), 4.0, NodeId(2));
+        let replaced = g.take_replaced_edges();
+        assert_eq!(1, replaced.len());
+        assert_eq!(
+            (NodeId(0), NodeId(1), Weight(10)),
+            (
+                replaced[0].base_node,
+                replaced[0].adj_node,
+                replaced[0].weight
+            )
+        );
+        assert!(g.take_replaced_edges().is_empty());
     }
 
     #[test]
     fn disconnect() {
         // 0 <-> 1 <-> 2
         let mut g = PreparationGraph::new(4);
-        g.add_edge(1, 0, 1, 1.0);
-        g.add_edge(1, 2, 1, 1.0);
-        g.add_edge(0, 1, 1, 1.0);
-        g.add_edge(2, 1, 1, 1.0);
-        assert_eq!(vec![0, 2], adj_nodes(g.get_out_edges(1)));
-        assert_eq!(vec![0, 2], adj_nodes(g.get_in_edges(1)));
-        g.disconnect(1);
-        assert_eq!(0, adj_nodes(g.get_out_edges(0)).len());
-        assert_eq!(0, adj_nodes(g.get_out_edges(1)).len());
-        assert_eq!(0, adj_nodes(g.get_out_edges(2)).len());
-        assert_eq!(0, adj_nodes(g.get_in_edges(0)).len());
-        assert_eq!(0, adj_nodes(g.get_in_edges(1)).len());
-        assert_eq!(0, adj_nodes(g.get_in_edges(2)).len());
+        g.add_edge(NodeId(1), NodeId(0), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(1), Weight(1), 1.0);
+        assert_eq!(
+            vec![NodeId(0), NodeId(2)],
+            adj_nodes(g.get_out_edges(NodeId(1)))
+        );
+        assert_eq!(
+            vec![NodeId(0), NodeId(2)],
+            adj_nodes(g.get_in_edges(NodeId(1)))
+        );
+        g.disconnect(NodeId(1));
+        assert_eq!(0, adj_nodes(g.get_out_edges(NodeId(0))).len());
+        assert_eq!(0, adj_nodes(g.get_out_edges(NodeId(1))).len());
+        assert_eq!(0, adj_nodes(g.get_out_edges(NodeId(2))).len());
+        assert_eq!(0, adj_nodes(g.get_in_edges(NodeId(0))).len());
+        assert_eq!(0, adj_nodes(g.get_in_edges(NodeId(1))).len());
+        assert_eq!(0, adj_nodes(g.get_in_edges(NodeId(2))).len());
     }
 
     fn adj_nodes(edges: &Vec<Arc>) -> Vec<NodeId> {
@@ -17,10 +17,27 @@
  * under the License.
  */
 
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::fmt;
+#[cfg(feature = "std")]
+use std::collections::HashMap;
+
+use crate::constants::add_weights;
+use crate::constants::EdgeData;
 use crate::constants::NodeId;
 use crate::constants::Weight;
 use crate::constants::WEIGHT_MAX;
 use crate::constants::WEIGHT_ZERO;
+use crate::fast_graph::FastGraph;
+#[cfg(feature = "std")]
+use crate::input_graph::InputGraph;
+use crate::path_calculator::PathCalculator;
+
+/// The maximum number of nodes shown when a path is displayed, longer paths are truncated
+const MAX_DISPLAYED_NODES: usize = 10;
 
 #[derive(Debug, Clone)]
 pub struct ShortestPath {
@@ -28,6 +45,9 @@ pub struct ShortestPath {
     target: NodeId,
     weight: Weight,
     nodes: Vec<NodeId>,
+    meeting_node: Option<NodeId>,
+    edge_data: Vec<EdgeData>,
+    weight_scale: f64,
 }
 
 impl PartialEq for ShortestPath {
@@ -44,15 +64,53 @@ impl ShortestPath {
             target,
             weight,
             nodes,
+            meeting_node: None,
+            edge_data: vec![],
+            weight_scale: 1.0,
+        }
+    }
+
+    /// Creates a path that was found by a bidirectional search where the forward and backward
+    /// searches met at `meeting_node`
+    pub fn new_with_meeting_node(
+        source: NodeId,
+        target: NodeId,
+        weight: Weight,
+        nodes: Vec<NodeId>,
+        meeting_node: NodeId,
+    ) -> Self {
+        ShortestPath {
+            source,
+            target,
+            weight,
+            nodes,
+            meeting_node: Some(meeting_node),
+            edge_data: vec![],
+            weight_scale: 1.0,
         }
     }
 
+    /// Sets the user data of the edges of this path, see `get_edge_data`
+    pub fn with_edge_data(mut self, edge_data: Vec<EdgeData>) -> Self {
+        self.edge_data = edge_data;
+        self
+    }
+
+    /// Sets the factor the weights of the graph were scaled with, see `get_weight_f64`
+    pub fn with_weight_scale(mut self, weight_scale: f64) -> Self {
+        self.weight_scale = weight_scale;
+        self
+    }
+
     pub fn singular(node: NodeId) -> Self {
         ShortestPath {
             source: node,
             target: node,
             weight: WEIGHT_ZERO,
             nodes: vec![node],
+            meeting_node: Some(node),
+            edge_data: vec![],
+            weight_scale: 1.0,
         }
     }
 
@@ -62,6 +120,9 @@ impl ShortestPath {
             target,
             weight: WEIGHT_MAX,
             nodes: vec![],
+            meeting_node: None,
+            edge_data: vec![],
+            weight_scale: 1.0,
         }
     }
 
@@ -77,11 +138,454 @@ impl ShortestPath {
         self.weight
     }
 
+    /// Returns the weight of this path divided by the weight scale of the graph (see
+    /// `InputGraph::set_weight_scale`), i.e. in the unit of the weights passed to
+    /// `InputGraph::add_edge_f64`. Returns infinity if no path was found.
+    pub fn get_weight_f64(&self) -> f64 {
+        if !self.is_found() {
+            return f64::INFINITY;
+        }
+        self.weight.0 as f64 / self.weight_scale
+    }
+
     pub fn get_nodes(&self) -> &Vec<NodeId> {
         &self.nodes
     }
 
+    /// Returns the user data of the original edges of this path (see
+    /// `InputGraph::add_edge_with_data`), in the order they are traversed, so there is one entry
+    /// for every pair of consecutive nodes. Edges that were added without data yield
+    /// `INVALID_EDGE_DATA`.
+    pub fn get_edge_data(&self) -> &Vec<EdgeData> {
+        &self.edge_data
+    }
+
+    /// Returns the node at which the forward and backward searches of the bidirectional search
+    /// met, i.e. the node with the highest rank on the path. This is the node itself for a
+    /// singular path, and `None` if no path was found or the path was not calculated by a
+    /// bidirectional search (like the fallback searches of `PathCalculator::calc_path_avoiding`).
+    pub fn get_meeting_node(&self) -> Option<NodeId> {
+        self.meeting_node
+    }
+
+    /// Returns the (lat, lon) coordinates of the nodes of this path (see
+    /// `FastGraph::set_coordinates`), e.g. to draw the path as a polyline on a map. The paths
+    /// returned by `PathCalculator::calc_path` are unpacked, so this includes the nodes that were
+    /// skipped by shortcuts. The graph must be the one the path was calculated for. Returns an
+    /// empty vector if the graph has no coordinates or no path was found.
+    pub fn to_coords(&self, fast_graph: &FastGraph) -> Vec<(f64, f64)> {
+        let coordinates = fast_graph.get_coordinates();
+        if coordinates.is_empty() {
+            return vec![];
+        }
+        self.nodes.iter().map(|&node| coordinates[node.0]).collect()
+    }
+
+    /// Unpacks the edge from the `i`-th to the `(i + 1)`-th node of this path into the original
+    /// edges it consists of and returns their nodes, including the two given ones. This is meant
+    /// for paths made of the nodes returned by `PathCalculator::calc_path_compressed` (e.g.
+    /// created using `ShortestPath::new`), whose consecutive nodes can be connected by shortcuts,
+    /// so a UI can unpack the path one segment at a time, e.g. when the user zooms in. For the
+    /// unpacked paths returned by `calc_path` this just returns the two nodes. The graph must be
+    /// the one the path was calculated for. Panics if `i + 1` is not a valid index of the nodes
+    /// or the graph has no edge between the two nodes.
+    pub fn unpack_segment(&self, fast_graph: &FastGraph, i: usize) -> Vec<NodeId> {
+        assert!(
+            i + 1 < self.nodes.len(),
+            "Invalid segment {} for a path with {} nodes",
+            i,
+            self.nodes.len()
+        );
+        let (from, to) = (self.nodes[i], self.nodes[i + 1]);
+        // the edge is stored at its lower ranked node, so it is either an out-edge of `from` or
+        // an in-edge of `to`
+        let out_edge = (fast_graph.begin_out_edges(from)..fast_graph.end_out_edges(from))
+            .filter(|&edge_id| fast_graph.edges_fwd[edge_id].adj_node == to)
+            .min_by_key(|&edge_id| fast_graph.edges_fwd[edge_id].weight);
+        let in_edge = (fast_graph.begin_in_edges(to)..fast_graph.end_in_edges(to))
+            .filter(|&edge_id| fast_graph.edges_bwd[edge_id].adj_node == from)
+            .min_by_key(|&edge_id| fast_graph.edges_bwd[edge_id].weight);
+        let mut nodes = vec![];
+        match (out_edge, in_edge) {
+            (Some(out_edge), Some(in_edge))
+                if fast_graph.edges_bwd[in_edge].weight < fast_graph.edges_fwd[out_edge].weight =>
+            {
+                PathCalculator::unpack_bwd(fast_graph, &mut nodes, in_edge, false)
+            }
+            (Some(out_edge), _) => {
+                PathCalculator::unpack_fwd(fast_graph, &mut nodes, out_edge, false)
+            }
+            (None, Some(in_edge)) => {
+                PathCalculator::unpack_bwd(fast_graph, &mut nodes, in_edge, false)
+            }
+            (None, None) => panic!("There is no edge from {} to {}", from, to),
+        }
+        nodes.push(to);
+        nodes
+    }
+
     pub fn is_found(&self) -> bool {
         self.weight != WEIGHT_MAX
     }
+
+    /// Joins this path with the given path that starts where this path ends, e.g. to build a
+    /// journey with multiple legs from separate queries. The nodes are merged without repeating
+    /// the shared node and the weights are added up. The edge data (see `get_edge_data`) is only
+    /// kept if both paths have it. Note that the result is not necessarily a shortest path from
+    /// the source of this path to the target of the other path. Returns an error if the target of
+    /// this path is not the source of the other path, if one of the paths was not found or if
+    /// their weight scales are different.
+    pub fn concat(&self, other: &ShortestPath) -> Result<ShortestPath, String> {
+        if self.target != other.source {
+            return Err(format!(
+                "The path to {} cannot be continued with a path from {}",
+                self.target, other.source
+            ));
+        }
+        if !self.is_found() || !other.is_found() {
+            return Err(String::from(
+                "Paths that were not found cannot be concatenated",
+            ));
+        }
+        if self.weight_scale != other.weight_scale {
+            return Err(format!(
+                "The weight scales of the paths are different: {} and {}",
+                self.weight_scale, other.weight_scale
+            ));
+        }
+        let mut nodes = self.nodes.clone();
+        nodes.extend_from_slice(&other.nodes[1..]);
+        let has_edge_data = |path: &ShortestPath| path.edge_data.len() + 1 == path.nodes.len();
+        let edge_data = if has_edge_data(self) && has_edge_data(other) {
+            let mut edge_data = self.edge_data.clone();
+            edge_data.extend_from_slice(&other.edge_data);
+            edge_data
+        } else {
+            vec![]
+        };
+        Ok(ShortestPath::new(
+            self.source,
+            other.target,
+            add_weights(self.weight, other.weight),
+            nodes,
+        )
+        .with_edge_data(edge_data)
+        .with_weight_scale(self.weight_scale))
+    }
+
+    /// Turns this path into the same path in the opposite direction, i.e. the path from the
+    /// target to the source in the graph with all edges reversed
+    pub(crate) fn reverse(mut self) -> ShortestPath {
+        core::mem::swap(&mut self.source, &mut self.target);
+        self.nodes.reverse();
+        self.edge_data.reverse();
+        self
+    }
+
+    /// Returns the nodes of this path without the nodes that are only passed through, e.g. to
+    /// display the path on a map. A node is passed through if it is not connected to any other
+    /// node than its predecessor and successor on the path (regardless of the edge directions),
+    /// so there is no way to turn off at this node. The first and last node are always kept. The
+    /// input graph must be the one the path was calculated for. This takes O(edges) time.
+    #[cfg(feature = "std")]
+    pub fn simplify(&self, input_graph: &InputGraph) -> Vec<NodeId> {
+        if self.nodes.len() <= 2 {
+            return self.nodes.clone();
+        }
+        // the distinct neighbors of the inner nodes of the path, we stop collecting them once
+        // there are more than two
+        let mut neighbors: HashMap<NodeId, Vec<NodeId>> = self.nodes[1..self.nodes.len() - 1]
+            .iter()
+            .map(|&node| (node, vec![]))
+            .collect();
+        let mut add_neighbor = |node: NodeId, neighbor: NodeId| {
+            if let Some(node_neighbors) = neighbors.get_mut(&node) {
+                if node != neighbor
+                    && node_neighbors.len() <= 2
+                    && !node_neighbors.contains(&neighbor)
+                {
+                    node_neighbors.push(neighbor);
+                }
+            }
+        };
+        for edge in input_graph.get_edges() {
+            add_neighbor(edge.from, edge.to);
+            add_neighbor(edge.to, edge.from);
+        }
+        let mut result = vec![self.nodes[0]];
+        for w in self.nodes.windows(3) {
+            let passed_through = neighbors[&w[1]]
+                .iter()
+                .all(|&neighbor| neighbor == w[0] || neighbor == w[2]);
+            if !passed_through {
+                result.push(w[1]);
+            }
+        }
+        result.push(self.nodes[self.nodes.len() - 1]);
+        result
+    }
+}
+
+impl fmt::Display for ShortestPath {
+    /// Formats the path like `0 -> 5 -> 2 (weight 7, 3 nodes)`. Only the first and the last node
+    /// are shown for paths with more than `MAX_DISPLAYED_NODES` nodes.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if !self.is_found() {
+            return write!(f, "no path from {} to {}", self.source, self.target);
+        }
+        let num_nodes = self.nodes.len();
+        let truncate = num_nodes > MAX_DISPLAYED_NODES;
+        let shown_nodes = if truncate {
+            &self.nodes[..MAX_DISPLAYED_NODES - 1]
+        } else {
+            &self.nodes[..]
+        };
+        let mut nodes: Vec<String> = shown_nodes.iter().map(|n| n.to_string()).collect();
+        if truncate {
+            nodes.push(String::from("..."));
+            nodes.push(self.nodes[num_nodes - 1].to_string());
+        }
+        write!(
+            f,
+            "{} (weight {}, {} nodes)",
+            nodes.join(" -> "),
+            self.weight,
+            num_nodes
+        )
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn simplify() {
+        // 0 -> 1 -> 2 -> 3 <-> 4 -> 5
+        //                 |    ^
+        //                 6    7
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.add_edge(NodeId(4), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(4), NodeId(5), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(6), Weight(1), 1.0);
+        g.add_edge(NodeId(7), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        let path = ShortestPath::new(
+            NodeId(0),
+            NodeId(5),
+            Weight(5),
+            vec![
+                NodeId(0),
+                NodeId(1),
+                NodeId(2),
+                NodeId(3),
+                NodeId(4),
+                NodeId(5),
+            ],
+        );
+        assert_eq!(
+            vec![NodeId(0), NodeId(3), NodeId(4), NodeId(5)],
+            path.simplify(&g)
+        );
+        let path = ShortestPath::new(
+            NodeId(1),
+            NodeId(3),
+            Weight(2),
+            vec![NodeId(1), NodeId(2), NodeId(3)],
+        );
+        assert_eq!(vec![NodeId(1), NodeId(3)], path.simplify(&g));
+        assert_eq!(
+            vec![NodeId(2)],
+            ShortestPath::singular(NodeId(2)).simplify(&g)
+        );
+        assert!(ShortestPath::none(NodeId(5), NodeId(0))
+            .simplify(&g)
+            .is_empty());
+    }
+
+    #[test]
+    fn to_coords() {
+        // 0 -> 1 -> 2, node 1 is contracted first, so there is a shortcut 0->2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.freeze();
+        let mut fast_graph =
+            crate::prepare_with_order(&g, &[NodeId(1), NodeId(0), NodeId(2)]).unwrap();
+        let path = crate::calc_path(&fast_graph, NodeId(0), NodeId(2)).unwrap();
+        assert!(path.to_coords(&fast_graph).is_empty());
+        fast_graph
+            .set_coordinates(vec![(52.0, 13.0), (52.1, 13.1), (52.2, 13.2)])
+            .unwrap();
+        assert_eq!(
+            vec![(52.0, 13.0), (52.1, 13.1), (52.2, 13.2)],
+            path.to_coords(&fast_graph)
+        );
+        assert!(ShortestPath::none(NodeId(0), NodeId(2))
+            .to_coords(&fast_graph)
+            .is_empty());
+    }
+
+    #[test]
+    fn unpack_segment() {
+        // 0 -> 1 -> 2 -> 3 -> 4, nodes 1 and 3 are contracted first, so there are the shortcuts
+        // 0->2 and 2->4
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.freeze();
+        let fast_graph =
+            crate::prepare_with_order(&g, &[NodeId(1), NodeId(3), NodeId(0), NodeId(4), NodeId(2)])
+                .unwrap();
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let compressed = calc
+            .calc_path_compressed(&fast_graph, NodeId(0), NodeId(4))
+            .unwrap();
+        assert_eq!(vec![NodeId(0), NodeId(2), NodeId(4)], compressed);
+        let path = ShortestPath::new(NodeId(0), NodeId(4), Weight(4), compressed);
+        assert_eq!(
+            vec![NodeId(0), NodeId(1), NodeId(2)],
+            path.unpack_segment(&fast_graph, 0)
+        );
+        assert_eq!(
+            vec![NodeId(2), NodeId(3), NodeId(4)],
+            path.unpack_segment(&fast_graph, 1)
+        );
+        // unpacked paths only consist of original edges
+        let path = calc.calc_path(&fast_graph, NodeId(0), NodeId(4)).unwrap();
+        assert_eq!(
+            vec![NodeId(1), NodeId(2)],
+            path.unpack_segment(&fast_graph, 1)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Invalid segment 1 for a path with 2 nodes")]
+    fn unpack_segment_invalid_index() {
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.freeze();
+        let fast_graph = crate::prepare(&g);
+        ShortestPath::new(NodeId(0), NodeId(1), Weight(1), vec![NodeId(0), NodeId(1)])
+            .unpack_segment(&fast_graph, 1);
+    }
+
+    #[test]
+    fn concat() {
+        // 0 -> 1 -> 2 -> 3
+        //  \--------/
+        let mut g = InputGraph::new();
+        g.add_edge_with_data(NodeId(0), NodeId(1), Weight(2), 2.0, 10);
+        g.add_edge_with_data(NodeId(1), NodeId(2), Weight(3), 3.0, 11);
+        g.add_edge_with_data(NodeId(2), NodeId(3), Weight(4), 4.0, 12);
+        g.add_edge_with_data(NodeId(0), NodeId(2), Weight(4), 4.0, 13);
+        g.freeze();
+        let fast_graph = crate::prepare(&g);
+        let mut calc = PathCalculator::new(fast_graph.get_num_nodes());
+        let leg1 = calc.calc_path(&fast_graph, NodeId(0), NodeId(1)).unwrap();
+        let leg2 = calc.calc_path(&fast_graph, NodeId(1), NodeId(3)).unwrap();
+        let journey = leg1.concat(&leg2).unwrap();
+        assert_eq!(NodeId(0), journey.get_source());
+        assert_eq!(NodeId(3), journey.get_target());
+        assert_eq!(leg1.get_weight() + leg2.get_weight(), journey.get_weight());
+        assert_eq!(Weight(9), journey.get_weight());
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+            journey.get_nodes()
+        );
+        assert_eq!(&vec![10, 11, 12], journey.get_edge_data());
+        // the journey via node 1 is longer than the shortest path
+        assert_eq!(
+            Weight(8),
+            calc.calc_path(&fast_graph, NodeId(0), NodeId(3))
+                .unwrap()
+                .get_weight()
+        );
+
+        let singular = ShortestPath::singular(NodeId(3));
+        let journey = journey.concat(&singular).unwrap();
+        assert_eq!(Weight(9), journey.get_weight());
+        assert_eq!(
+            &vec![NodeId(0), NodeId(1), NodeId(2), NodeId(3)],
+            journey.get_nodes()
+        );
+        assert_eq!(&vec![10, 11, 12], journey.get_edge_data());
+        // without edge data the result has no edge data either
+        let journey = leg1
+            .concat(&ShortestPath::new(
+                NodeId(1),
+                NodeId(4),
+                Weight(1),
+                vec![NodeId(1), NodeId(4)],
+            ))
+            .unwrap();
+        assert_eq!(&vec![NodeId(0), NodeId(1), NodeId(4)], journey.get_nodes());
+        assert!(journey.get_edge_data().is_empty());
+    }
+
+    #[test]
+    fn concat_errors() {
+        let leg1 = ShortestPath::new(NodeId(0), NodeId(1), Weight(2), vec![NodeId(0), NodeId(1)]);
+        let leg2 = ShortestPath::new(NodeId(2), NodeId(3), Weight(2), vec![NodeId(2), NodeId(3)]);
+        assert_eq!(
+            Err(String::from(
+                "The path to 1 cannot be continued with a path from 2"
+            )),
+            leg1.concat(&leg2)
+        );
+        assert!(leg1
+            .concat(&ShortestPath::none(NodeId(1), NodeId(3)))
+            .is_err());
+        assert!(ShortestPath::none(NodeId(3), NodeId(0))
+            .concat(&leg1)
+            .is_err());
+        let scaled = ShortestPath::new(NodeId(1), NodeId(3), Weight(2), vec![NodeId(1), NodeId(3)])
+            .with_weight_scale(10.0);
+        assert!(leg1.concat(&scaled).is_err());
+    }
+
+    #[test]
+    fn display() {
+        let path = ShortestPath::new(
+            NodeId(0),
+            NodeId(2),
+            Weight(7),
+            vec![NodeId(0), NodeId(5), NodeId(2)],
+        );
+        assert_eq!("0 -> 5 -> 2 (weight 7, 3 nodes)", path.to_string());
+        assert_eq!(
+            "4 (weight 0, 1 nodes)",
+            ShortestPath::singular(NodeId(4)).to_string()
+        );
+        assert_eq!(
+            "no path from 3 to 1",
+            ShortestPath::none(NodeId(3), NodeId(1)).to_string()
+        );
+        let path = ShortestPath::new(
+            NodeId(0),
+            NodeId(19),
+            Weight(19),
+            (0..20).map(NodeId).collect(),
+        );
+        assert_eq!(
+            "0 -> 1 -> 2 -> 3 -> 4 -> 5 -> 6 -> 7 -> 8 -> ... -> 19 (weight 19, 20 nodes)",
+            path.to_string()
+        );
+        let path = ShortestPath::new(
+            NodeId(0),
+            NodeId(9),
+            Weight(9),
+            (0..10).map(NodeId).collect(),
+        );
+        assert_eq!(
+            "0 -> 1 -> 2 -> 3 -> 4 -> 5 -> 6 -> 7 -> 8 -> 9 (weight 9, 10 nodes)",
+            path.to_string()
+        );
+    }
 }
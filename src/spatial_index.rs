@@ -0,0 +1,212 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use crate::constants::NodeId;
+
+/// A uniform grid over the node coordinates that is used to find the node closest to a given
+/// location. Distances are calculated on an equirectangular projection of the (lat, lon)
+/// coordinates, which is accurate enough for snapping as long as the graph does not span a
+/// large part of the globe.
+#[derive(Debug, Clone)]
+pub struct GridIndex {
+    cos_lat: f64,
+    min_x: f64,
+    min_y: f64,
+    cell_size: f64,
+    num_cols: usize,
+    num_rows: usize,
+    /// the nodes of each cell are stored in node_ids[first_ids[cell]..first_ids[cell + 1]]
+    first_ids: Vec<usize>,
+    node_ids: Vec<NodeId>,
+    points: Vec<(f64, f64)>,
+}
+
+impl GridIndex {
+    /// Builds the index for the given (lat, lon) coordinates, the coordinates of node i are
+    /// expected at index i. The grid has roughly one cell per node.
+    pub fn new(coordinates: &[(f64, f64)]) -> Self {
+        let num_nodes = coordinates.len();
+        let mean_lat = coordinates.iter().map(|c| c.0).sum::<f64>() / num_nodes.max(1) as f64;
+        let cos_lat = mean_lat.to_radians().cos();
+        let points: Vec<(f64, f64)> = coordinates
+            .iter()
+            .map(|&(lat, lon)| GridIndex::project(cos_lat, lat, lon))
+            .collect();
+        let min_x = points.iter().map(|p| p.0).fold(f64::INFINITY, f64::min);
+        let max_x = points.iter().map(|p| p.0).fold(f64::NEG_INFINITY, f64::max);
+        let min_y = points.iter().map(|p| p.1).fold(f64::INFINITY, f64::min);
+        let max_y = points.iter().map(|p| p.1).fold(f64::NEG_INFINITY, f64::max);
+        let (min_x, min_y, width, height) = if num_nodes == 0 {
+            (0.0, 0.0, 0.0, 0.0)
+        } else {
+            (min_x, min_y, max_x - min_x, max_y - min_y)
+        };
+        let mut cell_size = ((width * height) / num_nodes.max(1) as f64).sqrt();
+        if cell_size <= 0.0 {
+            // all nodes are located on a line (or at the same point)
+            cell_size = (width.max(height) / num_nodes.max(1) as f64).max(f64::MIN_POSITIVE);
+        }
+        let num_cols = (width / cell_size) as usize + 1;
+        let num_rows = (height / cell_size) as usize + 1;
+        let mut index = GridIndex {
+            cos_lat,
+            min_x,
+            min_y,
+            cell_size,
+            num_cols,
+            num_rows,
+            first_ids: vec![0; num_cols * num_rows + 1],
+            node_ids: vec![NodeId(0); num_nodes],
+            points,
+        };
+        let cells: Vec<usize> = index.points.iter().map(|&p| index.get_cell(p)).collect();
+        for &cell in &cells {
+            index.first_ids[cell + 1] += 1;
+        }
+        for i in 1..index.first_ids.len() {
+            index.first_ids[i] += index.first_ids[i - 1];
+        }
+        let mut next_ids = index.first_ids.clone();
+        for (node, &cell) in cells.iter().enumerate() {
+            index.node_ids[next_ids[cell]] = NodeId(node);
+            next_ids[cell] += 1;
+        }
+        index
+    }
+
+    /// Returns the node closest to the given location, or `None` if there are no nodes
+    pub fn find_nearest(&self, lat: f64, lon: f64) -> Option<NodeId> {
+        if self.points.is_empty() {
+            return None;
+        }
+        let query = GridIndex::project(self.cos_lat, lat, lon);
+        let (col, row) = self.get_col_and_row(query);
+        let mut best_node = None;
+        let mut best_dist = f64::INFINITY;
+        // we search the cells in rings around the cell of the query point. all points outside the
+        // first r rings are at least (r - 1) * cell_size away from the (clamped) query point
+        let max_ring = self.num_cols.max(self.num_rows);
+        for ring in 0..=max_ring {
+            if best_node.is_some() && best_dist <= (ring as f64 - 1.0) * self.cell_size {
+                break;
+            }
+            for (c, r) in self.get_ring(col, row, ring) {
+                let cell = r * self.num_cols + c;
+                for &node in &self.node_ids[self.first_ids[cell]..self.first_ids[cell + 1]] {
+                    let dist = GridIndex::dist(query, self.points[node.0]);
+                    if dist < best_dist || (dist == best_dist && Some(node) < best_node) {
+                        best_dist = dist;
+                        best_node = Some(node);
+                    }
+                }
+            }
+        }
+        best_node
+    }
+
+    /// Returns the cells whose column and row differ by exactly `ring` from the given ones
+    fn get_ring(&self, col: usize, row: usize, ring: usize) -> Vec<(usize, usize)> {
+        let mut cells = vec![];
+        let min_c = col.saturating_sub(ring);
+        let max_c = (col + ring).min(self.num_cols - 1);
+        let min_r = row.saturating_sub(ring);
+        let max_r = (row + ring).min(self.num_rows - 1);
+        for r in min_r..=max_r {
+            for c in min_c..=max_c {
+                if c.abs_diff(col) == ring || r.abs_diff(row) == ring {
+                    cells.push((c, r));
+                }
+            }
+        }
+        cells
+    }
+
+    fn get_cell(&self, point: (f64, f64)) -> usize {
+        let (col, row) = self.get_col_and_row(point);
+        row * self.num_cols + col
+    }
+
+    fn get_col_and_row(&self, point: (f64, f64)) -> (usize, usize) {
+        let col = ((point.0 - self.min_x) / self.cell_size).max(0.0) as usize;
+        let row = ((point.1 - self.min_y) / self.cell_size).max(0.0) as usize;
+        (col.min(self.num_cols - 1), row.min(self.num_rows - 1))
+    }
+
+    fn project(cos_lat: f64, lat: f64, lon: f64) -> (f64, f64) {
+        (lon * cos_lat, lat)
+    }
+
+    fn dist(a: (f64, f64), b: (f64, f64)) -> f64 {
+        ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    #[test]
+    fn find_nearest() {
+        let index = GridIndex::new(&[(52.0, 13.0), (52.1, 13.1), (52.2, 13.0), (52.05, 13.2)]);
+        assert_eq!(Some(NodeId(0)), index.find_nearest(52.0, 13.0));
+        assert_eq!(Some(NodeId(1)), index.find_nearest(52.11, 13.09));
+        assert_eq!(Some(NodeId(2)), index.find_nearest(53.0, 13.0));
+        assert_eq!(Some(NodeId(3)), index.find_nearest(52.05, 14.0));
+        assert_eq!(Some(NodeId(0)), index.find_nearest(51.0, 12.0));
+    }
+
+    #[test]
+    fn find_nearest_special_cases() {
+        assert_eq!(None, GridIndex::new(&[]).find_nearest(1.0, 2.0));
+        let index = GridIndex::new(&[(1.0, 2.0)]);
+        assert_eq!(Some(NodeId(0)), index.find_nearest(-5.0, 20.0));
+        // all nodes on a line
+        let index = GridIndex::new(&[(1.0, 2.0), (1.0, 3.0), (1.0, 4.0)]);
+        assert_eq!(Some(NodeId(1)), index.find_nearest(1.5, 3.1));
+        // duplicate coordinates yield the smallest node id
+        let index = GridIndex::new(&[(1.0, 2.0), (1.0, 3.0), (1.0, 3.0)]);
+        assert_eq!(Some(NodeId(1)), index.find_nearest(1.0, 3.0));
+    }
+
+    #[test]
+    fn find_nearest_random() {
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..20 {
+            let coordinates: Vec<(f64, f64)> = (0..rng.gen_range(1, 200))
+                .map(|_| (rng.gen_range(40.0, 41.0), rng.gen_range(10.0, 12.0)))
+                .collect();
+            let index = GridIndex::new(&coordinates);
+            for _ in 0..100 {
+                let lat = rng.gen_range(39.5, 41.5);
+                let lon = rng.gen_range(9.5, 12.5);
+                let nearest = index.find_nearest(lat, lon).unwrap();
+                let query = GridIndex::project(index.cos_lat, lat, lon);
+                let min_dist = index
+                    .points
+                    .iter()
+                    .map(|&p| GridIndex::dist(query, p))
+                    .fold(f64::INFINITY, f64::min);
+                assert_eq!(min_dist, GridIndex::dist(query, index.points[nearest.0]));
+            }
+        }
+    }
+}
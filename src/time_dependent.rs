@@ -0,0 +1,219 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use serde::{Deserialize, Serialize};
+
+use crate::constants::{NodeId, TimeOfDay, Weight};
+use crate::input_graph::InputGraph;
+use crate::preparation_graph::PreparationGraph;
+
+/// The weight of an edge as a function of the time the edge is entered. The weight is linearly
+/// interpolated between the given (time, weight) points and constant before the first and after
+/// the last point, the profile is not periodic.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct WeightProfile {
+    points: Vec<(TimeOfDay, Weight)>,
+}
+
+impl WeightProfile {
+    /// Creates a profile from the given (time, weight) points. The times must be strictly
+    /// increasing, all weights must be positive and the profile must fulfill the FIFO property:
+    /// entering the edge later must never mean leaving it earlier, i.e. the weight must not
+    /// decrease faster than the time increases. Otherwise time-dependent shortest path
+    /// calculations would no longer be correct.
+    pub fn new(points: Vec<(TimeOfDay, Weight)>) -> Result<Self, String> {
+        if points.is_empty() {
+            return Err(String::from("The profile must contain at least one point"));
+        }
+        if let Some(index) = points.iter().position(|&(_, weight)| weight < Weight(1)) {
+            return Err(format!("Zero weight at index {} is not allowed", index));
+        }
+        for i in 1..points.len() {
+            let (prev_time, prev_weight) = points[i - 1];
+            let (time, weight) = points[i];
+            if time <= prev_time {
+                return Err(format!(
+                    "The times must be strictly increasing, but the time at index {} is {} and \
+                     the time at index {} is {}",
+                    i - 1,
+                    prev_time,
+                    i,
+                    time
+                ));
+            }
+            if time + weight < prev_time + prev_weight {
+                return Err(format!(
+                    "The profile violates the FIFO property between index {} and {}: entering \
+                     at {} leads to an arrival at {}, but entering at {} leads to an arrival at {}",
+                    i - 1,
+                    i,
+                    prev_time,
+                    prev_time + prev_weight,
+                    time,
+                    time + weight
+                ));
+            }
+        }
+        Ok(WeightProfile { points })
+    }
+
+    /// Returns the weight of the edge when it is entered at the given time
+    pub fn weight_at(&self, time: TimeOfDay) -> Weight {
+        let index = self.points.partition_point(|&(t, _)| t <= time);
+        if index == 0 {
+            return self.points[0].1;
+        }
+        if index == self.points.len() {
+            return self.points[index - 1].1;
+        }
+        let (t0, w0) = self.points[index - 1];
+        let (t1, w1) = self.points[index];
+        // we round down, which keeps the FIFO property, because the time is an integer
+        let dt = (t1 - t0).0 as u128;
+        let elapsed = (time - t0).0 as u128;
+        if w1 >= w0 {
+            w0 + Weight(((w1 - w0).0 as u128 * elapsed / dt) as usize)
+        } else {
+            w0 - Weight(((w0 - w1).0 as u128 * elapsed).div_ceil(dt) as usize)
+        }
+    }
+
+    pub fn get_min_weight(&self) -> Weight {
+        self.points.iter().map(|&(_, weight)| weight).min().unwrap()
+    }
+
+    pub fn get_points(&self) -> &Vec<(TimeOfDay, Weight)> {
+        &self.points
+    }
+}
+
+/// The graph used for time-dependent shortest path calculations, see
+/// `PathCalculator::calc_path_at`. Edges that were added with a profile use its time-dependent
+/// weight, all other edges have a constant weight.
+#[derive(Serialize, Deserialize)]
+pub struct TimeDependentGraph {
+    graph: PreparationGraph,
+    profiles: Vec<WeightProfile>,
+    /// the index of the profile of each out-edge, same layout as graph.out_edges
+    profile_ids: Vec<Vec<Option<usize>>>,
+}
+
+impl TimeDependentGraph {
+    pub fn new(input_graph: &InputGraph) -> Self {
+        let graph = PreparationGraph::from_input_graph(input_graph);
+        let mut profiles = vec![];
+        let mut profile_ids = Vec::with_capacity(graph.get_num_nodes());
+        for node in (0..graph.get_num_nodes()).map(NodeId) {
+            let ids = graph
+                .get_out_edges(node)
+                .iter()
+                .map(|arc| {
+                    input_graph
+                        .get_edge_profile(node, arc.adj_node)
+                        .map(|profile| {
+                            profiles.push(profile.clone());
+                            profiles.len() - 1
+                        })
+                })
+                .collect();
+            profile_ids.push(ids);
+        }
+        TimeDependentGraph {
+            graph,
+            profiles,
+            profile_ids,
+        }
+    }
+
+    pub fn get_num_nodes(&self) -> usize {
+        self.graph.get_num_nodes()
+    }
+
+    pub(crate) fn get_graph(&self) -> &PreparationGraph {
+        &self.graph
+    }
+
+    /// Returns the weight of the `index`-th out-edge of `node` when it is entered at `time`
+    pub(crate) fn get_weight(&self, node: NodeId, index: usize, time: TimeOfDay) -> Weight {
+        match self.profile_ids[node.0][index] {
+            Some(profile_id) => self.profiles[profile_id].weight_at(time),
+            None => self.graph.get_out_edges(node)[index].weight,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weight_at() {
+        let profile = WeightProfile::new(vec![
+            (Weight(10), Weight(5)),
+            (Weight(20), Weight(15)),
+            (Weight(30), Weight(5)),
+        ])
+        .unwrap();
+        assert_eq!(Weight(5), profile.weight_at(Weight(0)));
+        assert_eq!(Weight(5), profile.weight_at(Weight(10)));
+        assert_eq!(Weight(10), profile.weight_at(Weight(15)));
+        assert_eq!(Weight(15), profile.weight_at(Weight(20)));
+        assert_eq!(Weight(14), profile.weight_at(Weight(21)));
+        assert_eq!(Weight(10), profile.weight_at(Weight(25)));
+        assert_eq!(Weight(5), profile.weight_at(Weight(30)));
+        assert_eq!(Weight(5), profile.weight_at(Weight(100)));
+        assert_eq!(Weight(5), profile.get_min_weight());
+    }
+
+    #[test]
+    fn weight_at_keeps_fifo() {
+        let profile = WeightProfile::new(vec![
+            (Weight(0), Weight(10)),
+            (Weight(3), Weight(8)),
+            (Weight(7), Weight(5)),
+        ])
+        .unwrap();
+        for time in 0..10 {
+            assert!(
+                Weight(time) + profile.weight_at(Weight(time))
+                    <= Weight(time + 1) + profile.weight_at(Weight(time + 1))
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_profiles() {
+        assert!(WeightProfile::new(vec![]).is_err());
+        assert!(WeightProfile::new(vec![(Weight(0), Weight(5)), (Weight(10), Weight(0))]).is_err());
+        assert!(
+            WeightProfile::new(vec![(Weight(10), Weight(5)), (Weight(10), Weight(6))]).is_err()
+        );
+        assert!(WeightProfile::new(vec![(Weight(10), Weight(5)), (Weight(5), Weight(6))]).is_err());
+        assert_eq!(
+            Err(String::from(
+                "The profile violates the FIFO property between index 0 and 1: entering at 0 \
+                 leads to an arrival at 20, but entering at 5 leads to an arrival at 15"
+            )),
+            WeightProfile::new(vec![(Weight(0), Weight(20)), (Weight(5), Weight(10))])
+        );
+        assert!(
+            WeightProfile::new(vec![(Weight(0), Weight(20)), (Weight(10), Weight(10))]).is_ok()
+        );
+    }
+}
@@ -0,0 +1,153 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use alloc::collections::BTreeMap;
+use alloc::vec::Vec;
+
+use crate::constants::{EdgeData, EdgeId, NodeId, Weight};
+
+/// The source node, user data and weight of an original edge
+pub type UnpackedEdge = (NodeId, EdgeData, Weight);
+
+/// A least recently used cache for the original edges that shortcuts are unpacked into, see
+/// `PathCalculator::with_unpack_cache`. The shortcuts are identified by their edge id and
+/// whether they are out-edges (`fwd`) or in-edges. Looking up and inserting shortcuts takes
+/// O(log(capacity)) time.
+pub struct UnpackCache {
+    capacity: usize,
+    /// the unpacked edges of each cached shortcut, along with the time it was last used
+    entries: BTreeMap<(EdgeId, bool), (u64, Vec<UnpackedEdge>)>,
+    /// the cached shortcuts ordered by the time they were last used
+    last_used: BTreeMap<u64, (EdgeId, bool)>,
+    time: u64,
+    hits: usize,
+    misses: usize,
+}
+
+impl UnpackCache {
+    /// Creates a cache that holds up to `capacity` shortcuts
+    pub fn new(capacity: usize) -> Self {
+        UnpackCache {
+            capacity,
+            entries: BTreeMap::new(),
+            last_used: BTreeMap::new(),
+            time: 0,
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the original edges of the given shortcut in the order of the path if it is
+    /// cached and marks it as the most recently used one
+    pub fn get(&mut self, edge_id: EdgeId, fwd: bool) -> Option<&Vec<UnpackedEdge>> {
+        match self.entries.get_mut(&(edge_id, fwd)) {
+            Some((last_used, edges)) => {
+                self.last_used.remove(last_used);
+                self.time += 1;
+                *last_used = self.time;
+                self.last_used.insert(self.time, (edge_id, fwd));
+                self.hits += 1;
+                Some(edges)
+            }
+            None => {
+                self.misses += 1;
+                None
+            }
+        }
+    }
+
+    /// Adds the given shortcut, the least recently used shortcut is removed if the cache is full
+    pub fn insert(&mut self, edge_id: EdgeId, fwd: bool, edges: Vec<UnpackedEdge>) {
+        if self.capacity == 0 || self.entries.contains_key(&(edge_id, fwd)) {
+            return;
+        }
+        if self.entries.len() == self.capacity {
+            if let Some((_, key)) = self.last_used.pop_first() {
+                self.entries.remove(&key);
+            }
+        }
+        self.time += 1;
+        self.entries.insert((edge_id, fwd), (self.time, edges));
+        self.last_used.insert(self.time, (edge_id, fwd));
+    }
+
+    /// Removes all shortcuts and resets the hit and miss counts
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.last_used.clear();
+        self.hits = 0;
+        self.misses = 0;
+    }
+
+    #[cfg(test)]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns the number of lookups that found (hits) and did not find (misses) the shortcut
+    pub fn get_hits_and_misses(&self) -> (usize, usize) {
+        (self.hits, self.misses)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use crate::constants::{NodeId, Weight};
+    use crate::unpack_cache::UnpackCache;
+
+    #[test]
+    fn evicts_least_recently_used() {
+        let mut cache = UnpackCache::new(2);
+        assert!(cache.get(0, true).is_none());
+        cache.insert(
+            0,
+            true,
+            vec![(NodeId(0), 10, Weight(1)), (NodeId(1), 11, Weight(2))],
+        );
+        cache.insert(0, false, vec![(NodeId(5), 12, Weight(3))]);
+        assert_eq!(2, cache.len());
+        // using 0/true makes 0/false the least recently used entry
+        assert_eq!(
+            Some(&vec![
+                (NodeId(0), 10, Weight(1)),
+                (NodeId(1), 11, Weight(2))
+            ]),
+            cache.get(0, true)
+        );
+        cache.insert(3, true, vec![(NodeId(3), 13, Weight(4))]);
+        assert_eq!(2, cache.len());
+        assert!(cache.get(0, false).is_none());
+        assert!(cache.get(0, true).is_some());
+        assert!(cache.get(3, true).is_some());
+        assert_eq!((3, 2), cache.get_hits_and_misses());
+        cache.clear();
+        assert_eq!(0, cache.len());
+        assert_eq!((0, 0), cache.get_hits_and_misses());
+    }
+
+    #[test]
+    fn zero_capacity() {
+        let mut cache = UnpackCache::new(0);
+        cache.insert(0, true, vec![(NodeId(0), 10, Weight(1))]);
+        assert_eq!(0, cache.len());
+        assert!(cache.get(0, true).is_none());
+    }
+}
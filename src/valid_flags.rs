@@ -17,18 +17,25 @@
  * under the License.
  */
 
-use std::u32::MAX;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::u32::MAX;
 
 use crate::constants::NodeId;
 
 /// Maintains a collection of N boolean flags that can efficiently be reset by incrementing a
-/// single integer
+/// single integer. A flag is valid if its stored value equals the current 'generation', so
+/// invalidating all flags simply means starting a new generation, which takes O(1) time (except
+/// for every 2^32 - 1 calls, when the flags need to be cleared once). This is useful for graph
+/// searches that run many times on the same graph and need to know which nodes were visited
+/// during the current search, without clearing the data of all nodes before each search.
 pub struct ValidFlags {
     valid_flags: Vec<u32>,
     valid_flag: u32,
 }
 
 impl ValidFlags {
+    /// Creates `num_nodes` flags, which are all invalid initially
     pub fn new(num_nodes: usize) -> Self {
         ValidFlags {
             valid_flags: vec![0; num_nodes],
@@ -36,14 +43,25 @@ impl ValidFlags {
         }
     }
 
+    /// Returns true if the flag of the given node was set since the last call of
+    /// `invalidate_all` (or `reset`)
     pub fn is_valid(&self, node: NodeId) -> bool {
-        self.valid_flags[node] == self.valid_flag
+        self.valid_flags[node.0] == self.valid_flag
     }
 
+    /// Marks the given node as valid until the next call of `invalidate_all` (or `reset`)
     pub fn set_valid(&mut self, node: NodeId) {
-        self.valid_flags[node] = self.valid_flag;
+        self.valid_flags[node.0] = self.valid_flag;
     }
 
+    /// Invalidates all flags by writing to each of them, unlike `invalidate_all` this takes
+    /// O(N) time, but makes sure the memory of all flags is resident afterwards
+    pub fn reset(&mut self) {
+        self.valid_flags.fill(0);
+        self.valid_flag = 1;
+    }
+
+    /// Marks all nodes as invalid in O(1) time
     pub fn invalidate_all(&mut self) {
         if self.valid_flag == MAX {
             self.valid_flags = vec![0; self.valid_flags.len()];
@@ -56,15 +74,28 @@ impl ValidFlags {
 
 #[cfg(test)]
 mod tests {
+    use crate::constants::NodeId;
     use crate::valid_flags::ValidFlags;
 
     #[test]
     fn set_valid_and_invalidate() {
         let mut flags = ValidFlags::new(5);
-        assert!(!flags.is_valid(3));
-        flags.set_valid(3);
-        assert!(flags.is_valid(3));
+        assert!(!flags.is_valid(NodeId(3)));
+        flags.set_valid(NodeId(3));
+        assert!(flags.is_valid(NodeId(3)));
         flags.invalidate_all();
-        assert!(!flags.is_valid(3));
+        assert!(!flags.is_valid(NodeId(3)));
+    }
+
+    #[test]
+    fn reset() {
+        let mut flags = ValidFlags::new(3);
+        flags.set_valid(NodeId(0));
+        flags.set_valid(NodeId(2));
+        flags.reset();
+        assert!(!flags.is_valid(NodeId(0)));
+        assert!(!flags.is_valid(NodeId(2)));
+        flags.set_valid(NodeId(1));
+        assert!(flags.is_valid(NodeId(1)));
     }
 }
@@ -0,0 +1,161 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use std::error::Error;
+use std::fmt;
+
+use crate::constants::{NodeId, Weight};
+use crate::dijkstra::Dijkstra;
+use crate::fast_graph::FastGraph;
+use crate::fast_graph_builder::FastGraphBuilder;
+use crate::input_graph::InputGraph;
+use crate::path_calculator::PathCalculator;
+use crate::preparation_graph::PreparationGraph;
+
+/// Compares the shortest path weights calculated on the given `FastGraph` with the ones calculated
+/// using Dijkstra's algorithm on the `InputGraph` for `num_samples` random queries. `random_node`
+/// must return a random number in [0, n) when called with n. Returns an error for the first query
+/// whose weights do not agree.
+pub fn verify<F>(
+    fast_graph: &FastGraph,
+    input_graph: &InputGraph,
+    num_samples: usize,
+    random_node: &mut F,
+) -> Result<(), VerificationError>
+where
+    F: FnMut(usize) -> usize,
+{
+    let num_nodes = input_graph.get_num_nodes();
+    if fast_graph.get_num_nodes() != num_nodes {
+        return Err(VerificationError::InvalidNodeCount {
+            fast_graph: fast_graph.get_num_nodes(),
+            input_graph: num_nodes,
+        });
+    }
+    if num_nodes == 0 {
+        return Ok(());
+    }
+    let mut path_calculator = PathCalculator::new(num_nodes);
+    let dijkstra_graph = PreparationGraph::from_input_graph(input_graph);
+    let mut dijkstra = Dijkstra::new(num_nodes);
+    for _ in 0..num_samples {
+        let source = NodeId(random_node(num_nodes));
+        let target = NodeId(random_node(num_nodes));
+        let weight_fast = path_calculator
+            .calc_path(fast_graph, source, target)
+            .map(|p| p.get_weight());
+        let weight_dijkstra = dijkstra
+            .calc_path(&dijkstra_graph, source, target)
+            .map(|p| p.get_weight());
+        if weight_fast != weight_dijkstra {
+            return Err(VerificationError::WeightMismatch {
+                source,
+                target,
+                weight_fast,
+                weight_dijkstra,
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Prepares the given `InputGraph` using `FastGraphBuilder::build`, prepares it again using the
+/// node ordering of the first graph and checks that both graphs yield the same shortest path
+/// weights for `num_samples` random queries. `random_node` must return a random number in [0, n)
+/// when called with n. Panics if the preparation with the fixed ordering fails or if the weights
+/// of any query do not agree.
+pub fn assert_order_roundtrip<F>(input_graph: &InputGraph, num_samples: usize, random_node: &mut F)
+where
+    F: FnMut(usize) -> usize,
+{
+    let fast_graph = FastGraphBuilder::build(input_graph);
+    let order = fast_graph.get_node_ordering();
+    let fast_graph_with_order = FastGraphBuilder::build_with_order(input_graph, &order)
+        .unwrap_or_else(|e| panic!("Preparation with the node ordering failed: {}", e));
+    assert_eq!(
+        order,
+        fast_graph_with_order.get_node_ordering(),
+        "The preparation with the node ordering yields a different node ordering"
+    );
+    let num_nodes = input_graph.get_num_nodes();
+    if num_nodes == 0 {
+        return;
+    }
+    let mut path_calculator = PathCalculator::new(num_nodes);
+    for _ in 0..num_samples {
+        let source = NodeId(random_node(num_nodes));
+        let target = NodeId(random_node(num_nodes));
+        let weight = path_calculator
+            .calc_path(&fast_graph, source, target)
+            .map(|p| p.get_weight());
+        let weight_with_order = path_calculator
+            .calc_path(&fast_graph_with_order, source, target)
+            .map(|p| p.get_weight());
+        assert_eq!(
+            weight, weight_with_order,
+            "No agreement for routing query from: {} to: {}, prepare: {:?}, \
+             prepare_with_order: {:?}",
+            source, target, weight, weight_with_order
+        );
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum VerificationError {
+    /// The fast graph and the input graph do not have the same number of nodes
+    InvalidNodeCount {
+        fast_graph: usize,
+        input_graph: usize,
+    },
+    /// The shortest path weight found on the fast graph differs from the one found by Dijkstra's
+    /// algorithm on the input graph, `None` means no path was found
+    WeightMismatch {
+        source: NodeId,
+        target: NodeId,
+        weight_fast: Option<Weight>,
+        weight_dijkstra: Option<Weight>,
+    },
+}
+
+impl fmt::Display for VerificationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VerificationError::InvalidNodeCount {
+                fast_graph,
+                input_graph,
+            } => write!(
+                f,
+                "The fast graph has {} nodes, but the input graph has {} nodes",
+                fast_graph, input_graph
+            ),
+            VerificationError::WeightMismatch {
+                source,
+                target,
+                weight_fast,
+                weight_dijkstra,
+            } => write!(
+                f,
+                "No agreement for routing query from: {} to: {}, Dijkstra: {:?}, CH: {:?}",
+                source, target, weight_dijkstra, weight_fast
+            ),
+        }
+    }
+}
+
+impl Error for VerificationError {}
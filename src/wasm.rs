@@ -0,0 +1,102 @@
+/*
+ * Licensed to the Apache Software Foundation (ASF) under one
+ * or more contributor license agreements.  See the NOTICE file
+ * distributed with this work for additional information
+ * regarding copyright ownership.  The ASF licenses this file
+ * to you under the Apache License, Version 2.0 (the
+ * "License"); you may not use this file except in compliance
+ * with the License.  You may obtain a copy of the License at
+ *
+ *   http://www.apache.org/licenses/LICENSE-2.0
+ *
+ * Unless required by applicable law or agreed to in writing,
+ * software distributed under the License is distributed on an
+ * "AS IS" BASIS, WITHOUT WARRANTIES OR CONDITIONS OF ANY
+ * KIND, either express or implied.  See the License for the
+ * specific language governing permissions and limitations
+ * under the License.
+ */
+
+use wasm_bindgen::prelude::wasm_bindgen;
+
+use crate::constants::NodeId;
+use crate::fast_graph::FastGraph;
+use crate::path_calculator::PathCalculator;
+
+/// A thin wrapper around a `FastGraph` and a `PathCalculator` that can be used from JavaScript
+/// when the crate is compiled to WebAssembly using wasm-bindgen. Only available with the `wasm`
+/// feature. Node ids are passed as `u32`, because JavaScript has no 64bit integers, and wasm32
+/// has a 32bit `usize` anyway.
+#[wasm_bindgen]
+pub struct WasmPathCalculator {
+    fast_graph: FastGraph,
+    path_calculator: PathCalculator,
+}
+
+#[wasm_bindgen]
+impl WasmPathCalculator {
+    /// Loads a graph that was serialized by bincode in its 32bit representation, e.g. using
+    /// `bincode::serialize(&FastGraph32::new(&graph))`, see `FastGraph::load_streaming`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<WasmPathCalculator, String> {
+        let fast_graph = FastGraph::load_streaming(bytes)
+            .map_err(|e| format!("Could not load the graph: {}", e))?;
+        let path_calculator = PathCalculator::new(fast_graph.get_num_nodes());
+        Ok(WasmPathCalculator {
+            fast_graph,
+            path_calculator,
+        })
+    }
+
+    /// Returns the number of nodes of the loaded graph
+    #[wasm_bindgen(js_name = getNumNodes)]
+    pub fn get_num_nodes(&self) -> u32 {
+        self.fast_graph.get_num_nodes() as u32
+    }
+
+    /// Calculates the shortest path from `source` to `target` and returns its nodes, starting
+    /// with `source`. Returns `None` (`undefined` in JavaScript) if there is no such path or if
+    /// one of the nodes does not exist.
+    #[wasm_bindgen(js_name = calcPath)]
+    pub fn calc_path(&mut self, source: u32, target: u32) -> Option<Vec<u32>> {
+        let num_nodes = self.fast_graph.get_num_nodes();
+        if source as usize >= num_nodes || target as usize >= num_nodes {
+            return None;
+        }
+        self.path_calculator
+            .calc_path(
+                &self.fast_graph,
+                NodeId(source as usize),
+                NodeId(target as usize),
+            )
+            .map(|path| path.get_nodes().iter().map(|node| node.0 as u32).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::constants::{NodeId, Weight};
+    use crate::fast_graph32::FastGraph32;
+    use crate::input_graph::InputGraph;
+
+    use super::*;
+
+    #[test]
+    fn calc_path() {
+        // 0 -> 1 -> 2
+        let mut g = InputGraph::new();
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(2), 2.0);
+        g.add_edge(NodeId(0), NodeId(2), Weight(5), 5.0);
+        g.freeze();
+        let bytes = bincode::serialize(&FastGraph32::new(&crate::prepare(&g))).unwrap();
+        let mut calc = WasmPathCalculator::new(&bytes).unwrap();
+        assert_eq!(3, calc.get_num_nodes());
+        assert_eq!(Some(vec![0, 1, 2]), calc.calc_path(0, 2));
+        assert_eq!(Some(vec![1]), calc.calc_path(1, 1));
+        assert_eq!(None, calc.calc_path(2, 0));
+        // node 3 does not exist
+        assert_eq!(None, calc.calc_path(0, 3));
+        assert!(WasmPathCalculator::new(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
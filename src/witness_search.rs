@@ -17,11 +17,10 @@
  * under the License.
  */
 
-use std::collections::BinaryHeap;
-
 use crate::constants::Weight;
-use crate::constants::{NodeId, INVALID_NODE, WEIGHT_MAX, WEIGHT_ZERO};
+use crate::constants::{add_weights, NodeId, INVALID_NODE, WEIGHT_MAX, WEIGHT_ZERO};
 use crate::heap_item::HeapItem;
+use crate::indexed_heap::IndexedHeap;
 use crate::preparation_graph::PreparationGraph;
 use crate::valid_flags::ValidFlags;
 
@@ -29,15 +28,16 @@ pub struct WitnessSearch {
     num_nodes: usize,
     data: Vec<Data>,
     valid_flags: ValidFlags,
-    heap: BinaryHeap<HeapItem>,
+    heap: IndexedHeap,
     start_node: NodeId,
     avoid_node: NodeId,
     settled_nodes: usize,
+    hop_limit: usize,
 }
 
 impl WitnessSearch {
     pub fn new(num_nodes: usize) -> Self {
-        let heap = BinaryHeap::new();
+        let heap = IndexedHeap::new(num_nodes);
         WitnessSearch {
             num_nodes,
             data: (0..num_nodes).map(|_i| Data::new()).collect(),
@@ -46,9 +46,18 @@ impl WitnessSearch {
             start_node: INVALID_NODE,
             avoid_node: INVALID_NODE,
             settled_nodes: 0,
+            hop_limit: usize::MAX,
         }
     }
 
+    /// Limits the number of edges of the witness paths, i.e. nodes that were reached via
+    /// `hop_limit` edges are not expanded any further. Smaller limits make the search faster, but
+    /// might miss some witnesses and thus yield more shortcuts. Only takes effect for the searches
+    /// started by the next call of `init`.
+    pub fn set_hop_limit(&mut self, hop_limit: usize) {
+        self.hop_limit = hop_limit;
+    }
+
     /// Initializes the witness search for a given start and avoid node. Calling this method
     /// resets/clears previously calculated data.
     pub fn init(&mut self, start: NodeId, avoid_node: NodeId) {
@@ -62,8 +71,9 @@ impl WitnessSearch {
 
         self.heap.clear();
         self.valid_flags.invalidate_all();
-        self.update_node(start, 0);
-        self.heap.push(HeapItem::new(0, start));
+        self.update_node(start, WEIGHT_ZERO, 0);
+        self.heap
+            .push_or_decrease(HeapItem::new(WEIGHT_ZERO, start));
         self.settled_nodes = 0;
     }
 
@@ -80,6 +90,9 @@ impl WitnessSearch {
     ///      any path with weight <= weight_limit has been found.
     ///   4) settled_nodes_limit nodes have been settled. the returned weight will be the best known
     ///      upper bound for the real shortest path weight at this point.
+    ///
+    /// Nodes that were reached using the hop limit (see `set_hop_limit`) are settled, but not
+    /// expanded.
     /// The shortest path tree established during the search will be re-used until the init
     /// function is called again.
     pub fn find_max_weight(
@@ -106,9 +119,9 @@ impl WitnessSearch {
             return WEIGHT_ZERO;
         }
         if self.valid_flags.is_valid(target)
-            && (self.data[target].settled || self.data[target].weight <= weight_limit)
+            && (self.data[target.0].settled || self.data[target.0].weight <= weight_limit)
         {
-            return self.data[target].weight;
+            return self.data[target.0].weight;
         }
         while !self.heap.is_empty() {
             if self.settled_nodes >= settled_nodes_limit {
@@ -119,28 +132,29 @@ impl WitnessSearch {
                 break;
             }
             self.heap.pop();
-            if self.is_settled(curr.node_id) {
-                // todo: since we are not using a special decrease key operation yet we need to
-                // filter out duplicate heap items here
-                continue;
-            }
             let mut found_target = false;
-            for i in 0..graph.out_edges[curr.node_id].len() {
-                let adj = graph.out_edges[curr.node_id][i].adj_node;
+            let hops = self.data[curr.node_id.0].hops + 1;
+            let num_out_edges = if hops > self.hop_limit {
+                0
+            } else {
+                graph.out_edges[curr.node_id.0].len()
+            };
+            for i in 0..num_out_edges {
+                let adj = graph.out_edges[curr.node_id.0][i].adj_node;
                 if adj == self.avoid_node {
                     continue;
                 }
-                let edge_weight = graph.out_edges[curr.node_id][i].weight;
-                let weight = curr.weight + edge_weight;
+                let edge_weight = graph.out_edges[curr.node_id.0][i].weight;
+                let weight = add_weights(curr.weight, edge_weight);
                 if weight < self.get_current_weight(adj) {
-                    self.update_node(adj, weight);
-                    self.heap.push(HeapItem::new(weight, adj));
+                    self.update_node(adj, weight, hops);
+                    self.heap.push_or_decrease(HeapItem::new(weight, adj));
                     if adj == target && weight <= weight_limit {
                         found_target = true;
                     }
                 }
             }
-            self.data[curr.node_id].settled = true;
+            self.data[curr.node_id.0].settled = true;
             self.settled_nodes += 1;
             if found_target || curr.node_id == target {
                 break;
@@ -149,19 +163,16 @@ impl WitnessSearch {
         self.get_current_weight(target)
     }
 
-    fn update_node(&mut self, node: NodeId, weight: Weight) {
+    fn update_node(&mut self, node: NodeId, weight: Weight, hops: usize) {
         self.valid_flags.set_valid(node);
-        self.data[node].settled = false;
-        self.data[node].weight = weight;
-    }
-
-    fn is_settled(&self, node: NodeId) -> bool {
-        self.valid_flags.is_valid(node) && self.data[node].settled
+        self.data[node.0].settled = false;
+        self.data[node.0].weight = weight;
+        self.data[node.0].hops = hops;
     }
 
     fn get_current_weight(&self, node: NodeId) -> Weight {
         if self.valid_flags.is_valid(node) {
-            self.data[node].weight
+            self.data[node.0].weight
         } else {
             WEIGHT_MAX
         }
@@ -171,6 +182,8 @@ impl WitnessSearch {
 struct Data {
     settled: bool,
     weight: Weight,
+    /// the number of edges of the path to this node
+    hops: usize,
 }
 
 impl Data {
@@ -179,6 +192,7 @@ impl Data {
         Data {
             settled: false,
             weight: WEIGHT_MAX,
+            hops: 0,
         }
     }
 }
@@ -193,55 +207,81 @@ mod tests {
         // |         |
         // 3 -> 4 -> 5
         let mut g = PreparationGraph::new(6);
-        g.add_edge(0, 1, 1, 1.0);
-        g.add_edge(1, 2, 1, 1.0);
-        g.add_edge(0, 3, 10, 10.0);
-        g.add_edge(3, 4, 1, 1.0);
-        g.add_edge(4, 5, 1, 1.0);
-        g.add_edge(5, 2, 1, 1.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(10), 10.0);
+        g.add_edge(NodeId(3), NodeId(4), Weight(1), 1.0);
+        g.add_edge(NodeId(4), NodeId(5), Weight(1), 1.0);
+        g.add_edge(NodeId(5), NodeId(2), Weight(1), 1.0);
         let mut ws = WitnessSearch::new(g.get_num_nodes());
-        ws.init(0, INVALID_NODE);
-        assert_eq!(2, ws.find_max_weight(&g, 2, 2, 100));
-        assert_eq!(2, ws.find_max_weight(&g, 2, 2, 100));
+        ws.init(NodeId(0), INVALID_NODE);
+        assert_eq!(Weight(2), ws.find_max_weight(&g, NodeId(2), Weight(2), 100));
+        assert_eq!(Weight(2), ws.find_max_weight(&g, NodeId(2), Weight(2), 100));
         assert_eq!(2, ws.settled_nodes);
-        ws.init(0, 1);
-        assert_eq!(13, ws.find_max_weight(&g, 2, 13, 100));
+        ws.init(NodeId(0), NodeId(1));
+        assert_eq!(
+            Weight(13),
+            ws.find_max_weight(&g, NodeId(2), Weight(13), 100)
+        );
         assert_eq!(4, ws.settled_nodes);
         // calling init again also resets settled nodes
-        ws.init(4, 3);
-        assert_eq!(2, ws.find_max_weight(&g, 2, 5, 100));
+        ws.init(NodeId(4), NodeId(3));
+        assert_eq!(Weight(2), ws.find_max_weight(&g, NodeId(2), Weight(5), 100));
         assert_eq!(2, ws.settled_nodes);
     }
 
+    #[test]
+    fn hop_limit() {
+        // 0 -> 1 -> 2 -> 3
+        //  \----------->/
+        let mut g = PreparationGraph::new(4);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(5), 5.0);
+        let mut ws = WitnessSearch::new(g.get_num_nodes());
+        ws.set_hop_limit(2);
+        ws.init(NodeId(0), INVALID_NODE);
+        // the shortest path has three edges, so we only find the direct edge
+        assert_eq!(Weight(5), ws.find_max_weight(&g, NodeId(3), Weight(3), 100));
+        assert_eq!(Weight(2), ws.find_max_weight(&g, NodeId(2), Weight(3), 100));
+        ws.set_hop_limit(3);
+        ws.init(NodeId(0), INVALID_NODE);
+        assert_eq!(Weight(3), ws.find_max_weight(&g, NodeId(3), Weight(3), 100));
+    }
+
     #[test]
     fn limit_weight() {
         // 0 -> 1 -> 2 -> 3 -> 4
         let mut g = PreparationGraph::new(5);
         for i in 0..4 {
-            g.add_edge(i, i + 1, 1, 1.0);
+            g.add_edge(NodeId(i), NodeId(i + 1), Weight(1), 1.0);
         }
         let mut ws = WitnessSearch::new(g.get_num_nodes());
-        ws.init(0, INVALID_NODE);
-        assert_eq!(3, ws.find_max_weight(&g, 3, 3, 100));
+        ws.init(NodeId(0), INVALID_NODE);
+        assert_eq!(Weight(3), ws.find_max_weight(&g, NodeId(3), Weight(3), 100));
         assert_eq!(3, ws.settled_nodes);
         // reset and reduce weight limit to 2. node 3 will not be settled, but we still get 3 as
         // upper bound for the weight of node 3
-        ws.init(0, INVALID_NODE);
-        assert_eq!(3, ws.find_max_weight(&g, 3, 2, 100));
+        ws.init(NodeId(0), INVALID_NODE);
+        assert_eq!(Weight(3), ws.find_max_weight(&g, NodeId(3), Weight(2), 100));
         assert_eq!(3, ws.settled_nodes);
         // .. but not for node 4
-        assert_eq!(WEIGHT_MAX, ws.find_max_weight(&g, 4, 2, 100));
+        assert_eq!(
+            WEIGHT_MAX,
+            ws.find_max_weight(&g, NodeId(4), Weight(2), 100)
+        );
         // if the weight has already be calculated no further search is required
-        assert_eq!(2, ws.find_max_weight(&g, 2, 2, 100));
-        assert_eq!(2, ws.find_max_weight(&g, 2, 2, 100));
-        assert_eq!(2, ws.find_max_weight(&g, 2, 2, 100));
+        assert_eq!(Weight(2), ws.find_max_weight(&g, NodeId(2), Weight(2), 100));
+        assert_eq!(Weight(2), ws.find_max_weight(&g, NodeId(2), Weight(2), 100));
+        assert_eq!(Weight(2), ws.find_max_weight(&g, NodeId(2), Weight(2), 100));
         // ... even when the weight limit is smaller than previously
-        assert_eq!(2, ws.find_max_weight(&g, 2, 1, 100));
+        assert_eq!(Weight(2), ws.find_max_weight(&g, NodeId(2), Weight(1), 100));
         assert_eq!(3, ws.settled_nodes);
         // we can extend the current search space
-        assert_eq!(4, ws.find_max_weight(&g, 4, 3, 100));
-        assert_eq!(4, ws.find_max_weight(&g, 4, 4, 100));
-        assert_eq!(4, ws.find_max_weight(&g, 4, 5, 100));
+        assert_eq!(Weight(4), ws.find_max_weight(&g, NodeId(4), Weight(3), 100));
+        assert_eq!(Weight(4), ws.find_max_weight(&g, NodeId(4), Weight(4), 100));
+        assert_eq!(Weight(4), ws.find_max_weight(&g, NodeId(4), Weight(5), 100));
         assert_eq!(4, ws.settled_nodes);
     }
 
@@ -250,40 +290,46 @@ mod tests {
         // 0 -> 1 -> 2 -> 3
         //  \----------->/
         let mut g = PreparationGraph::new(4);
-        g.add_edge(0, 1, 1, 1.0);
-        g.add_edge(1, 2, 1, 1.0);
-        g.add_edge(2, 3, 1, 1.0);
-        g.add_edge(0, 3, 4, 4.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(1), 1.0);
+        g.add_edge(NodeId(1), NodeId(2), Weight(1), 1.0);
+        g.add_edge(NodeId(2), NodeId(3), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(3), Weight(4), 4.0);
         let mut ws = WitnessSearch::new(g.get_num_nodes());
-        ws.init(0, INVALID_NODE);
+        ws.init(NodeId(0), INVALID_NODE);
         // the shortest path weight is 3, but since we set the limit to 10 the alternative path
         // 0->3 with weight 4 that we find earlier is 'good enough' and find_max_weight returns
         // early
-        assert_eq!(4, ws.find_max_weight(&g, 3, 10, 100));
+        assert_eq!(
+            Weight(4),
+            ws.find_max_weight(&g, NodeId(3), Weight(10), 100)
+        );
         assert_eq!(1, ws.settled_nodes);
         // calling the same again still does not trigger an expansion of the search tree
-        assert_eq!(4, ws.find_max_weight(&g, 3, 10, 100));
+        assert_eq!(
+            Weight(4),
+            ws.find_max_weight(&g, NodeId(3), Weight(10), 100)
+        );
         assert_eq!(1, ws.settled_nodes);
         // this is still true when we reduce the weight limit to the weight of the sub-optimal path
-        assert_eq!(4, ws.find_max_weight(&g, 3, 4, 100));
-        assert_eq!(4, ws.find_max_weight(&g, 3, 4, 100));
+        assert_eq!(Weight(4), ws.find_max_weight(&g, NodeId(3), Weight(4), 100));
+        assert_eq!(Weight(4), ws.find_max_weight(&g, NodeId(3), Weight(4), 100));
         assert_eq!(1, ws.settled_nodes);
         // when we further reduce the weight limit the search needs to be more accurate
-        assert_eq!(3, ws.find_max_weight(&g, 3, 3, 100));
+        assert_eq!(Weight(3), ws.find_max_weight(&g, NodeId(3), Weight(3), 100));
         // ... even though settling node 3 is still not necessary
         assert_eq!(3, ws.settled_nodes);
         // ... and repeating the search yields the same result
-        assert_eq!(3, ws.find_max_weight(&g, 3, 3, 100));
+        assert_eq!(Weight(3), ws.find_max_weight(&g, NodeId(3), Weight(3), 100));
         assert_eq!(3, ws.settled_nodes);
 
         // we can also limit the number of settled nodes
-        ws.init(0, INVALID_NODE);
+        ws.init(NodeId(0), INVALID_NODE);
         // ... here the weight limit is so large that the alternative path is returned anyway
-        assert_eq!(4, ws.find_max_weight(&g, 3, 100, 2));
+        assert_eq!(Weight(4), ws.find_max_weight(&g, NodeId(3), Weight(100), 2));
         assert_eq!(1, ws.settled_nodes);
         // ... here the weight limit does not allow the suboptimal weight to be returned, but once
         // the settled_nodes_limit is exceeded it is returned anyway
-        assert_eq!(4, ws.find_max_weight(&g, 3, 3, 2));
+        assert_eq!(Weight(4), ws.find_max_weight(&g, NodeId(3), Weight(3), 2));
         assert_eq!(2, ws.settled_nodes);
     }
 
@@ -292,24 +338,30 @@ mod tests {
         // 100 <- 99 <- ... <- 3 -> 2 -> 1
         //                      \-> 0 ->/
         let mut g = PreparationGraph::new(101);
-        g.add_edge(3, 2, 100, 100.0);
-        g.add_edge(2, 1, 100, 100.0);
-        g.add_edge(3, 0, 50, 50.0);
-        g.add_edge(0, 1, 50, 50.0);
+        g.add_edge(NodeId(3), NodeId(2), Weight(100), 100.0);
+        g.add_edge(NodeId(2), NodeId(1), Weight(100), 100.0);
+        g.add_edge(NodeId(3), NodeId(0), Weight(50), 50.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(50), 50.0);
         for i in 3..100 {
-            g.add_edge(i, i + 1, 1, 1.0);
+            g.add_edge(NodeId(i), NodeId(i + 1), Weight(1), 1.0);
         }
         let mut ws = WitnessSearch::new(g.get_num_nodes());
         // going from 3 to 1 while skipping 2 requires travelling along the large weight edge 3->0
         // this means many low weight edges will be visited first which slows down the search
-        ws.init(3, 2);
-        assert_eq!(100, ws.find_max_weight(&g, 1, 200, usize::MAX));
+        ws.init(NodeId(3), NodeId(2));
+        assert_eq!(
+            Weight(100),
+            ws.find_max_weight(&g, NodeId(1), Weight(200), usize::MAX)
+        );
         assert_eq!(51, ws.settled_nodes);
         // we can improve this by limiting the number of settled nodes. however, in this case
         // we won't find the witness and risk inserting an unnecessary shortcut. this might or might
         // not be ok depending on the situation.
-        ws.init(3, 2);
-        assert_eq!(WEIGHT_MAX, ws.find_max_weight(&g, 1, 200, 5));
+        ws.init(NodeId(3), NodeId(2));
+        assert_eq!(
+            WEIGHT_MAX,
+            ws.find_max_weight(&g, NodeId(1), Weight(200), 5)
+        );
         assert_eq!(5, ws.settled_nodes);
     }
 
@@ -318,19 +370,22 @@ mod tests {
         // 100 <- 99 <- ... <- 3 -> 2 -> 1
         //                      \-> 0 ->/
         let mut g = PreparationGraph::new(101);
-        g.add_edge(3, 2, 100, 100.0);
-        g.add_edge(2, 1, 100, 100.0);
-        g.add_edge(3, 0, 1, 1.0);
-        g.add_edge(0, 1, 99, 99.0);
+        g.add_edge(NodeId(3), NodeId(2), Weight(100), 100.0);
+        g.add_edge(NodeId(2), NodeId(1), Weight(100), 100.0);
+        g.add_edge(NodeId(3), NodeId(0), Weight(1), 1.0);
+        g.add_edge(NodeId(0), NodeId(1), Weight(99), 99.0);
         for i in 3..100 {
-            g.add_edge(i, i + 1, 1, 1.0);
+            g.add_edge(NodeId(i), NodeId(i + 1), Weight(1), 1.0);
         }
         let mut ws = WitnessSearch::new(g.get_num_nodes());
         // going from 3 to 1 while skipping 2 requires travelling the large weight edge 0->1 so
         // before 1 is settled many other nodes will be settled. however, find_max_weight already
         // returns even before node 1 is settled because the tentative weight is small enough
-        ws.init(3, 2);
-        assert_eq!(100, ws.find_max_weight(&g, 1, 200, usize::MAX));
+        ws.init(NodeId(3), NodeId(2));
+        assert_eq!(
+            Weight(100),
+            ws.find_max_weight(&g, NodeId(1), Weight(200), usize::MAX)
+        );
         // .. just two settled nodes. this is quite important for preparation speed when there are
         // large weight edges
         assert_eq!(2, ws.settled_nodes);